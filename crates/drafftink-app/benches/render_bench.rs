@@ -27,12 +27,47 @@ fn bench_build_scene(c: &mut Criterion) {
     let ctx = RenderContext::new(&canvas, size);
     renderer.build_scene(&ctx);
 
+    let (hits_before, misses_before) = renderer.cache_stats();
     c.bench_function("build_scene_warm", |b| {
         b.iter(|| {
             let ctx = RenderContext::new(black_box(&canvas), size);
             renderer.build_scene(&ctx);
         })
     });
+    let (hits, misses) = renderer.cache_stats();
+    println!(
+        "warm fragment cache: {} hits, {} misses",
+        hits - hits_before,
+        misses - misses_before
+    );
+
+    // Move one: nudge a single element per frame (only it is re-encoded)
+    let moved = canvas
+        .document
+        .shapes_ordered()
+        .next()
+        .expect("intro.json has shapes")
+        .id();
+    let mut step = 1.0;
+    let mut reencoded = 0;
+    let mut frames = 0;
+    c.bench_function("build_scene_move_one", |b| {
+        b.iter(|| {
+            if let Some(shape) = canvas.document.get_shape_mut(moved) {
+                shape.transform(kurbo::Affine::translate((step, 0.0)));
+            }
+            step = -step;
+            let ctx = RenderContext::new(black_box(&canvas), size);
+            renderer.build_scene(&ctx);
+            reencoded += renderer.last_dirty().len();
+            frames += 1;
+        })
+    });
+    println!(
+        "re-encoded per frame: cold {}, move one {:.2}",
+        canvas.document.len(),
+        reencoded as f64 / frames.max(1) as f64
+    );
 }
 
 criterion_group!(benches, bench_build_scene);
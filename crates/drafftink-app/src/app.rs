@@ -1,6 +1,6 @@
 //! Core application state and lifecycle.
 
-use drafftink_core::canvas::Canvas;
+use drafftink_core::canvas::{AlignMode, Canvas, ClipboardPayload};
 use drafftink_core::collaboration::CollaborationManager;
 use drafftink_core::input::InputState;
 use drafftink_core::shapes::Shape;
@@ -9,8 +9,8 @@ use drafftink_core::tools::ToolKind;
 #[cfg(not(target_arch = "wasm32"))]
 use drafftink_render::PngRenderResult;
 use drafftink_render::{
-    AngleSnapInfo, GridStyle, RenderContext, Renderer, TextEditResult, TextEditState, TextKey,
-    TextModifiers, VelloRenderer,
+    AngleSnapInfo, GridStyle, HIT_TOLERANCE_PX, RenderContext, Renderer, TextEditResult,
+    TextEditState, TextKey, TextModifiers, VelloRenderer,
 };
 use kurbo::{Point, Size, Vec2};
 use peniko::Color;
@@ -21,9 +21,9 @@ use vello::{AaConfig, RenderParams, RendererOptions, Scene};
 use winit::application::ApplicationHandler;
 #[cfg(not(target_arch = "wasm32"))]
 use winit::dpi::LogicalSize;
-use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::event::{ElementState, MouseButton, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, EventLoop};
-use winit::keyboard::{Key, NamedKey};
+use winit::keyboard::{Key, NamedKey, PhysicalKey};
 use winit::window::{CursorIcon, Window, WindowId};
 
 use crate::event_handler::EventHandler;
@@ -1018,116 +1018,18 @@ fn render_scene_to_png(
     width: u32,
     height: u32,
 ) -> Option<PngRenderResult> {
-    if width == 0 || height == 0 {
-        return None;
-    }
-
-    // Create offscreen texture for rendering
-    let texture = device.create_texture(&vello::wgpu::TextureDescriptor {
-        label: Some("png export texture"),
-        size: vello::wgpu::Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: vello::wgpu::TextureDimension::D2,
-        format: vello::wgpu::TextureFormat::Rgba8Unorm,
-        usage: vello::wgpu::TextureUsages::STORAGE_BINDING
-            | vello::wgpu::TextureUsages::COPY_SRC
-            | vello::wgpu::TextureUsages::TEXTURE_BINDING,
-        view_formats: &[],
-    });
-
-    let texture_view = texture.create_view(&vello::wgpu::TextureViewDescriptor::default());
-
-    // Render the scene
-    let params = RenderParams {
-        base_color: Color::WHITE,
-        width,
-        height,
-        antialiasing_method: AaConfig::Area,
-    };
-
-    if let Err(e) = vello_renderer.render_to_texture(device, queue, scene, &texture_view, &params) {
-        log::error!("Failed to render scene for PNG export: {:?}", e);
-        return None;
-    }
-
-    // Create buffer to read back pixels
-    let bytes_per_row = (width * 4).next_multiple_of(256); // wgpu alignment requirement
-    let buffer_size = (bytes_per_row * height) as u64;
-
-    let readback_buffer = device.create_buffer(&vello::wgpu::BufferDescriptor {
-        label: Some("png readback buffer"),
-        size: buffer_size,
-        usage: vello::wgpu::BufferUsages::COPY_DST | vello::wgpu::BufferUsages::MAP_READ,
-        mapped_at_creation: false,
-    });
-
-    // Copy texture to buffer
-    let mut encoder = device.create_command_encoder(&vello::wgpu::CommandEncoderDescriptor {
-        label: Some("png copy encoder"),
-    });
-
-    encoder.copy_texture_to_buffer(
-        vello::wgpu::TexelCopyTextureInfo {
-            texture: &texture,
-            mip_level: 0,
-            origin: vello::wgpu::Origin3d::ZERO,
-            aspect: vello::wgpu::TextureAspect::All,
-        },
-        vello::wgpu::TexelCopyBufferInfo {
-            buffer: &readback_buffer,
-            layout: vello::wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(bytes_per_row),
-                rows_per_image: Some(height),
-            },
-        },
-        vello::wgpu::Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        },
-    );
-
-    queue.submit(std::iter::once(encoder.finish()));
-
-    // Map buffer and read pixels
-    let buffer_slice = readback_buffer.slice(..);
-    let (tx, rx) = std::sync::mpsc::channel();
-    buffer_slice.map_async(vello::wgpu::MapMode::Read, move |result| {
-        tx.send(result).ok();
-    });
-
-    // Wait for GPU to finish (blocking - native only)
-    let _ = device.poll(vello::wgpu::PollType::wait_indefinitely());
-
-    if rx.recv().ok()?.is_err() {
-        log::error!("Failed to map buffer for PNG readback");
-        return None;
-    }
-
-    let data = buffer_slice.get_mapped_range();
-
-    // Remove row padding if any
-    let mut rgba_data = Vec::with_capacity((width * height * 4) as usize);
-    for row in 0..height {
-        let row_start = (row * bytes_per_row) as usize;
-        let row_end = row_start + (width * 4) as usize;
-        rgba_data.extend_from_slice(&data[row_start..row_end]);
-    }
-
-    drop(data);
-    readback_buffer.unmap();
-
-    Some(PngRenderResult {
-        rgba_data,
+    drafftink_render::render_scene_to_rgba(
+        device,
+        queue,
+        vello_renderer,
+        scene,
         width,
         height,
-    })
+        // Export scenes paint their own background, which may be transparent
+        Color::TRANSPARENT,
+    )
+    .map_err(|e| log::error!("Failed to render scene for PNG export: {}", e))
+    .ok()
 }
 
 /// Async PNG export for WASM - renders scene and triggers download when complete.
@@ -1182,8 +1084,9 @@ pub fn spawn_png_export_async(
     let texture_view = texture.create_view(&vello::wgpu::TextureViewDescriptor::default());
 
     // Render the scene
+    // Export scenes paint their own background, which may be transparent
     let params = RenderParams {
-        base_color: Color::WHITE,
+        base_color: Color::TRANSPARENT,
         width,
         height,
         antialiasing_method: AaConfig::Area,
@@ -2471,6 +2374,7 @@ impl ApplicationHandler for App {
                             UiAction::ToggleGridSnap => {
                                 state.ui_state.grid_snap_enabled =
                                     !state.ui_state.grid_snap_enabled;
+                                state.canvas.snap.enabled = state.ui_state.grid_snap_enabled;
                             }
                             UiAction::ToggleSmartSnap => {
                                 state.ui_state.smart_snap_enabled =
@@ -2518,10 +2422,13 @@ impl ApplicationHandler for App {
                                     _ => FontFamily::VanillaExtract,
                                 };
                                 for &shape_id in &state.canvas.selection.clone() {
-                                    if let Some(Shape::Text(text)) =
-                                        state.canvas.document.get_shape_mut(shape_id)
-                                    {
-                                        text.font_family = family;
+                                    match state.canvas.document.get_shape_mut(shape_id) {
+                                        Some(Shape::Text(text)) => text.font_family = family,
+                                        Some(Shape::Math(math)) => {
+                                            math.font_family = family;
+                                            math.invalidate_cache();
+                                        }
+                                        _ => {}
                                     }
                                 }
                             }
@@ -2635,6 +2542,7 @@ impl ApplicationHandler for App {
                                 let path_style = match level {
                                     0 => PathStyle::Direct,
                                     1 => PathStyle::Flowing,
+                                    3 => PathStyle::Curved,
                                     _ => PathStyle::Angular,
                                 };
                                 // Always update UI state for new shapes
@@ -2648,14 +2556,20 @@ impl ApplicationHandler for App {
                                         match shape {
                                             Shape::Line(line) => {
                                                 line.path_style = path_style;
-                                                // Angular recomputes path, clear intermediate points
-                                                if path_style == PathStyle::Angular {
+                                                // Angular and Curved compute the path, clear intermediate points
+                                                if matches!(
+                                                    path_style,
+                                                    PathStyle::Angular | PathStyle::Curved
+                                                ) {
                                                     line.intermediate_points.clear();
                                                 }
                                             }
                                             Shape::Arrow(arrow) => {
                                                 arrow.path_style = path_style;
-                                                if path_style == PathStyle::Angular {
+                                                if matches!(
+                                                    path_style,
+                                                    PathStyle::Angular | PathStyle::Curved
+                                                ) {
                                                     arrow.intermediate_points.clear();
                                                 }
                                             }
@@ -2677,11 +2591,7 @@ impl ApplicationHandler for App {
                             }
                             UiAction::SetStrokeStyle(level) => {
                                 use drafftink_core::shapes::StrokeStyle;
-                                let stroke_style = match level {
-                                    0 => StrokeStyle::Solid,
-                                    1 => StrokeStyle::Dashed,
-                                    _ => StrokeStyle::Dotted,
-                                };
+                                let stroke_style = StrokeStyle::from_index(level);
                                 let has_selection = !state.canvas.selection.is_empty();
                                 // Apply to selected lines/arrows
                                 for &shape_id in &state.canvas.selection.clone() {
@@ -2918,28 +2828,17 @@ impl ApplicationHandler for App {
                             }
                             UiAction::ZoomToFit => {
                                 // Fit to selection if any, otherwise fit to all shapes
-                                let bounds = if state.canvas.selection.is_empty() {
-                                    state.canvas.document.bounds()
-                                } else {
-                                    // Calculate bounds of selected shapes
-                                    let mut result: Option<kurbo::Rect> = None;
-                                    for &id in &state.canvas.selection {
-                                        if let Some(shape) = state.canvas.document.get_shape(id) {
-                                            let b = shape.bounds();
-                                            result = Some(match result {
-                                                Some(r) => r.union(b),
-                                                None => b,
-                                            });
-                                        }
-                                    }
-                                    result
-                                };
-                                if let Some(bounds) = bounds {
-                                    state.canvas.camera.fit_to_bounds(
-                                        bounds,
-                                        state.canvas.viewport_size,
-                                        50.0,
-                                    );
+                                let viewport = state.canvas.viewport_size;
+                                if !state.canvas.document.is_empty() {
+                                    let transform = if state.canvas.selection.is_empty() {
+                                        state.canvas.compute_fit_transform(viewport)
+                                    } else {
+                                        state.canvas.compute_fit_selection_transform(
+                                            &state.canvas.selection,
+                                            viewport,
+                                        )
+                                    };
+                                    state.canvas.camera.set_transform(transform, viewport);
                                 }
                             }
                             UiAction::Duplicate => {
@@ -2974,38 +2873,33 @@ impl ApplicationHandler for App {
                             }
                             UiAction::CopyShapes => {
                                 if !state.canvas.selection.is_empty() {
-                                    let shapes: Vec<Shape> = state
-                                        .canvas
-                                        .selection
-                                        .iter()
-                                        .filter_map(|&id| {
-                                            state.canvas.document.get_shape(id).cloned()
-                                        })
-                                        .collect();
-                                    if let Ok(json) = serde_json::to_string(&shapes) {
+                                    let payload =
+                                        state.canvas.document.copy(&state.canvas.selection);
+                                    if let Ok(json) = payload.to_json() {
                                         state.ui_state.clipboard_shapes = Some(json);
-                                        log::info!("Copied {} shapes to clipboard", shapes.len());
+                                        log::info!(
+                                            "Copied {} shapes to clipboard",
+                                            payload.shapes.len()
+                                        );
                                     }
                                 }
                             }
                             UiAction::CutShapes => {
                                 if !state.canvas.selection.is_empty() {
-                                    let shapes: Vec<Shape> = state
-                                        .canvas
-                                        .selection
-                                        .iter()
-                                        .filter_map(|&id| {
-                                            state.canvas.document.get_shape(id).cloned()
-                                        })
-                                        .collect();
-                                    if let Ok(json) = serde_json::to_string(&shapes) {
+                                    let payload =
+                                        state.canvas.document.copy(&state.canvas.selection);
+                                    if let Ok(json) = payload.to_json() {
                                         state.ui_state.clipboard_shapes = Some(json);
-                                        log::info!("Cut {} shapes to clipboard", shapes.len());
+                                        log::info!(
+                                            "Cut {} shapes to clipboard",
+                                            payload.shapes.len()
+                                        );
                                         // Delete the shapes
                                         state.canvas.document.push_undo();
                                         for &id in &state.canvas.selection.clone() {
-                                            state.canvas.document.remove_shape(id);
-                                            if state.collab.is_in_room() {
+                                            let removed =
+                                                state.canvas.document.remove_shape(id).is_some();
+                                            if removed && state.collab.is_in_room() {
                                                 let _ = state
                                                     .collab
                                                     .crdt_mut()
@@ -3018,177 +2912,48 @@ impl ApplicationHandler for App {
                             }
                             UiAction::PasteShapes => {
                                 if let Some(json) = &state.ui_state.clipboard_shapes.clone() {
-                                    if let Ok(shapes) = serde_json::from_str::<Vec<Shape>>(json) {
+                                    if let Ok(payload) = ClipboardPayload::from_json(json) {
                                         state.canvas.document.push_undo();
                                         state.canvas.clear_selection();
-                                        for shape in shapes {
-                                            // Create new shape with a new unique ID
-                                            let mut new_shape = shape.clone();
-                                            new_shape.regenerate_id();
-                                            // Offset slightly down-right
-                                            new_shape.transform(kurbo::Affine::translate(
-                                                kurbo::Vec2::new(20.0, 20.0),
-                                            ));
-                                            let new_id = new_shape.id();
-                                            state.canvas.document.add_shape(new_shape.clone());
+                                        // Offset slightly down-right
+                                        let new_ids = state
+                                            .canvas
+                                            .document
+                                            .paste(&payload, kurbo::Vec2::new(20.0, 20.0));
+                                        for new_id in new_ids {
                                             state.canvas.add_to_selection(new_id);
                                             if state.collab.is_in_room() {
-                                                let _ =
-                                                    state.collab.crdt_mut().add_shape(&new_shape);
+                                                if let Some(new_shape) =
+                                                    state.canvas.document.get_shape(new_id)
+                                                {
+                                                    let _ = state
+                                                        .collab
+                                                        .crdt_mut()
+                                                        .add_shape(new_shape);
+                                                }
                                             }
                                         }
                                         log::info!("Pasted shapes from clipboard");
                                     }
                                 }
                             }
-                            UiAction::AlignLeft => {
-                                if state.canvas.selection.len() >= 2 {
-                                    state.canvas.document.push_undo();
-                                    // Find leftmost x
-                                    let min_x = state
-                                        .canvas
-                                        .selection
-                                        .iter()
-                                        .filter_map(|&id| state.canvas.document.get_shape(id))
-                                        .map(|s| s.bounds().x0)
-                                        .fold(f64::INFINITY, f64::min);
-                                    // Align all shapes to left
-                                    for &id in &state.canvas.selection {
-                                        if let Some(shape) = state.canvas.document.get_shape_mut(id)
-                                        {
-                                            let bounds = shape.bounds();
-                                            let delta = min_x - bounds.x0;
-                                            shape.transform(kurbo::Affine::translate(
-                                                kurbo::Vec2::new(delta, 0.0),
-                                            ));
-                                        }
-                                    }
-                                }
-                            }
-                            UiAction::AlignRight => {
-                                if state.canvas.selection.len() >= 2 {
-                                    state.canvas.document.push_undo();
-                                    let max_x = state
-                                        .canvas
-                                        .selection
-                                        .iter()
-                                        .filter_map(|&id| state.canvas.document.get_shape(id))
-                                        .map(|s| s.bounds().x1)
-                                        .fold(f64::NEG_INFINITY, f64::max);
-                                    for &id in &state.canvas.selection {
-                                        if let Some(shape) = state.canvas.document.get_shape_mut(id)
-                                        {
-                                            let bounds = shape.bounds();
-                                            let delta = max_x - bounds.x1;
-                                            shape.transform(kurbo::Affine::translate(
-                                                kurbo::Vec2::new(delta, 0.0),
-                                            ));
-                                        }
-                                    }
-                                }
-                            }
-                            UiAction::AlignTop => {
-                                if state.canvas.selection.len() >= 2 {
-                                    state.canvas.document.push_undo();
-                                    let min_y = state
-                                        .canvas
-                                        .selection
-                                        .iter()
-                                        .filter_map(|&id| state.canvas.document.get_shape(id))
-                                        .map(|s| s.bounds().y0)
-                                        .fold(f64::INFINITY, f64::min);
-                                    for &id in &state.canvas.selection {
-                                        if let Some(shape) = state.canvas.document.get_shape_mut(id)
-                                        {
-                                            let bounds = shape.bounds();
-                                            let delta = min_y - bounds.y0;
-                                            shape.transform(kurbo::Affine::translate(
-                                                kurbo::Vec2::new(0.0, delta),
-                                            ));
-                                        }
-                                    }
-                                }
-                            }
-                            UiAction::AlignBottom => {
+                            UiAction::AlignLeft
+                            | UiAction::AlignRight
+                            | UiAction::AlignTop
+                            | UiAction::AlignBottom
+                            | UiAction::AlignCenterH
+                            | UiAction::AlignCenterV => {
                                 if state.canvas.selection.len() >= 2 {
-                                    state.canvas.document.push_undo();
-                                    let max_y = state
-                                        .canvas
-                                        .selection
-                                        .iter()
-                                        .filter_map(|&id| state.canvas.document.get_shape(id))
-                                        .map(|s| s.bounds().y1)
-                                        .fold(f64::NEG_INFINITY, f64::max);
-                                    for &id in &state.canvas.selection {
-                                        if let Some(shape) = state.canvas.document.get_shape_mut(id)
-                                        {
-                                            let bounds = shape.bounds();
-                                            let delta = max_y - bounds.y1;
-                                            shape.transform(kurbo::Affine::translate(
-                                                kurbo::Vec2::new(0.0, delta),
-                                            ));
-                                        }
-                                    }
-                                }
-                            }
-                            UiAction::AlignCenterH => {
-                                if state.canvas.selection.len() >= 2 {
-                                    state.canvas.document.push_undo();
-                                    // Calculate combined bounds center Y
-                                    let mut combined: Option<kurbo::Rect> = None;
-                                    for &id in &state.canvas.selection {
-                                        if let Some(shape) = state.canvas.document.get_shape(id) {
-                                            let b = shape.bounds();
-                                            combined = Some(match combined {
-                                                Some(r) => r.union(b),
-                                                None => b,
-                                            });
-                                        }
-                                    }
-                                    if let Some(bounds) = combined {
-                                        let center_y = bounds.center().y;
-                                        for &id in &state.canvas.selection {
-                                            if let Some(shape) =
-                                                state.canvas.document.get_shape_mut(id)
-                                            {
-                                                let shape_center_y = shape.bounds().center().y;
-                                                let delta = center_y - shape_center_y;
-                                                shape.transform(kurbo::Affine::translate(
-                                                    kurbo::Vec2::new(0.0, delta),
-                                                ));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            UiAction::AlignCenterV => {
-                                if state.canvas.selection.len() >= 2 {
-                                    state.canvas.document.push_undo();
-                                    // Calculate combined bounds center X
-                                    let mut combined: Option<kurbo::Rect> = None;
-                                    for &id in &state.canvas.selection {
-                                        if let Some(shape) = state.canvas.document.get_shape(id) {
-                                            let b = shape.bounds();
-                                            combined = Some(match combined {
-                                                Some(r) => r.union(b),
-                                                None => b,
-                                            });
-                                        }
-                                    }
-                                    if let Some(bounds) = combined {
-                                        let center_x = bounds.center().x;
-                                        for &id in &state.canvas.selection {
-                                            if let Some(shape) =
-                                                state.canvas.document.get_shape_mut(id)
-                                            {
-                                                let shape_center_x = shape.bounds().center().x;
-                                                let delta = center_x - shape_center_x;
-                                                shape.transform(kurbo::Affine::translate(
-                                                    kurbo::Vec2::new(delta, 0.0),
-                                                ));
-                                            }
-                                        }
-                                    }
+                                    let mode = match action {
+                                        UiAction::AlignLeft => AlignMode::Left,
+                                        UiAction::AlignRight => AlignMode::Right,
+                                        UiAction::AlignTop => AlignMode::Top,
+                                        UiAction::AlignBottom => AlignMode::Bottom,
+                                        UiAction::AlignCenterH => AlignMode::CenterY,
+                                        _ => AlignMode::CenterX,
+                                    };
+                                    state.canvas.document.align(&state.canvas.selection, mode);
+                                    state.collab.sync_to_crdt(&state.canvas.document);
                                 }
                             }
                             UiAction::ShowShortcuts => {
@@ -3213,15 +2978,15 @@ impl ApplicationHandler for App {
                             }
                             UiAction::FlipHorizontal => {
                                 if !state.canvas.selection.is_empty() {
-                                    state.canvas.document.push_undo();
                                     state.canvas.flip_selected_horizontal();
+                                    state.collab.sync_to_crdt(&state.canvas.document);
                                     log::info!("Flipped selection horizontally");
                                 }
                             }
                             UiAction::FlipVertical => {
                                 if !state.canvas.selection.is_empty() {
-                                    state.canvas.document.push_undo();
                                     state.canvas.flip_selected_vertical();
+                                    state.collab.sync_to_crdt(&state.canvas.document);
                                     log::info!("Flipped selection vertically");
                                 }
                             }
@@ -3231,7 +2996,7 @@ impl ApplicationHandler for App {
                                     for &id in &state.canvas.selection {
                                         if let Some(shape) = state.canvas.document.get_shape_mut(id)
                                         {
-                                            shape.style_mut().opacity = opacity as f64;
+                                            shape.style_mut().set_opacity(opacity as f64);
                                         }
                                     }
                                     log::info!("Set opacity to {}%", (opacity * 100.0) as i32);
@@ -3267,9 +3032,11 @@ impl ApplicationHandler for App {
                         match action {
                             UiAction::ExportPng => {
                                 // Build export scene (full document) with scale
-                                let (scene, bounds) = state
-                                    .shape_renderer
-                                    .build_export_scene(&state.canvas.document, export_scale);
+                                let (scene, bounds) = state.shape_renderer.build_export_scene(
+                                    &state.canvas.document,
+                                    None,
+                                    export_scale,
+                                );
                                 if let Some(bounds) = bounds {
                                     let width = bounds.width().ceil() as u32;
                                     let height = bounds.height().ceil() as u32;
@@ -3324,17 +3091,13 @@ impl ApplicationHandler for App {
                             }
                             UiAction::CopyPng => {
                                 // Build export scene (selection or full document) with scale
-                                let (scene, bounds) = if state.canvas.selection.is_empty() {
-                                    state
-                                        .shape_renderer
-                                        .build_export_scene(&state.canvas.document, export_scale)
-                                } else {
-                                    state.shape_renderer.build_export_scene_selection(
-                                        &state.canvas.document,
-                                        &state.canvas.selection,
-                                        export_scale,
-                                    )
-                                };
+                                let selection = (!state.canvas.selection.is_empty())
+                                    .then_some(state.canvas.selection.as_slice());
+                                let (scene, bounds) = state.shape_renderer.build_export_scene(
+                                    &state.canvas.document,
+                                    selection,
+                                    export_scale,
+                                );
 
                                 if let Some(bounds) = bounds {
                                     let width = bounds.width().ceil() as u32;
@@ -3850,10 +3613,10 @@ impl ApplicationHandler for App {
                             // Handle text editing cursor positioning
                             if let Some(text_id) = state.event_handler.editing_text {
                                 // Check if click is still on the text being edited
-                                let hits = state
-                                    .canvas
-                                    .document
-                                    .shapes_at_point(world_point, 5.0 / state.canvas.camera.zoom);
+                                let hits = state.canvas.document.shapes_at_point(
+                                    world_point,
+                                    HIT_TOLERANCE_PX / state.canvas.camera.zoom,
+                                );
                                 let clicked_on_editing =
                                     hits.first().map(|&id| id == text_id).unwrap_or(false);
 
@@ -4011,19 +3774,15 @@ impl ApplicationHandler for App {
                     return;
                 }
 
-                let scroll = match delta {
-                    MouseScrollDelta::LineDelta(x, y) => {
-                        Vec2::new(x as f64 * 20.0, y as f64 * 20.0)
-                    }
-                    MouseScrollDelta::PixelDelta(pos) => Vec2::new(pos.x, pos.y),
-                };
+                let scroll = state.input.normalize_scroll(delta);
 
                 let position = state.input.mouse_position();
 
                 if state.input.ctrl() {
                     // Ctrl/Cmd + scroll = zoom
                     let zoom_factor = if scroll.y > 0.0 { 1.1 } else { 0.9 };
-                    state.canvas.camera.zoom_at(position, zoom_factor);
+                    let viewport = state.canvas.viewport_size;
+                    state.canvas.zoom_at(position, zoom_factor, viewport);
                 } else {
                     // Normal scroll/trackpad = pan
                     state.canvas.camera.pan(scroll);
@@ -4042,11 +3801,9 @@ impl ApplicationHandler for App {
                 let pos = Point::new(touch.location.x, touch.location.y);
                 let world_point = state.canvas.camera.screen_to_world(pos);
 
-                // Track previous position for pan delta
-                let prev_pos = state.input.primary_touch();
-
-                // Process touch and get gesture result
-                let gesture = state.input.process_touch(&touch);
+                // Touch was already tracked by process_window_event above
+                let prev_pos = state.input.previous_primary_touch();
+                let gesture = state.input.touch_gesture();
                 let touch_count = state.input.touch_count();
 
                 if let Some((pan_delta, zoom_delta, zoom_center)) = gesture {
@@ -4290,6 +4047,10 @@ impl ApplicationHandler for App {
                     ElementState::Pressed => {
                         // Check for Ctrl/Cmd modifiers first for file operations
                         let has_modifier = state.input.ctrl();
+                        let shortcut_tool = match event.physical_key {
+                            PhysicalKey::Code(code) => ToolKind::from_shortcut(code),
+                            PhysicalKey::Unidentified(_) => None,
+                        };
 
                         if has_modifier {
                             let has_shift = state.input.shift();
@@ -4329,6 +4090,7 @@ impl ApplicationHandler for App {
                                         let (scene, bounds) =
                                             state.shape_renderer.build_export_scene(
                                                 &state.canvas.document,
+                                                None,
                                                 export_scale,
                                             );
                                         if let Some(bounds) = bounds {
@@ -4432,6 +4194,24 @@ impl ApplicationHandler for App {
                                         }
                                     }
                                 }
+                                "l" | "L" => {
+                                    if has_shift {
+                                        // Unlock everything, since locked shapes can't be selected.
+                                        // set_locked records its own undo entry.
+                                        let locked = state.canvas.document.locked_ids();
+                                        if state.canvas.document.set_locked(&locked, false) {
+                                            state.collab.sync_to_crdt(&state.canvas.document);
+                                            log::info!("Unlocked {} shapes", locked.len());
+                                        }
+                                    } else {
+                                        let selection = state.canvas.selection.clone();
+                                        state.canvas.clear_selection();
+                                        if state.canvas.document.set_locked(&selection, true) {
+                                            state.collab.sync_to_crdt(&state.canvas.document);
+                                            log::info!("Locked {} shapes", selection.len());
+                                        }
+                                    }
+                                }
                                 // Ctrl+Shift+C = Copy PNG
                                 "c" | "C" if has_shift => {
                                     if let Some(render_cx) = self.render_cx.as_ref() {
@@ -4441,18 +4221,14 @@ impl ApplicationHandler for App {
                                         let queue = &device_handle.queue;
                                         let export_scale = state.ui_state.export_scale as f64;
 
-                                        let (scene, bounds) = if state.canvas.selection.is_empty() {
+                                        let selection = (!state.canvas.selection.is_empty())
+                                            .then_some(state.canvas.selection.as_slice());
+                                        let (scene, bounds) =
                                             state.shape_renderer.build_export_scene(
                                                 &state.canvas.document,
+                                                selection,
                                                 export_scale,
-                                            )
-                                        } else {
-                                            state.shape_renderer.build_export_scene_selection(
-                                                &state.canvas.document,
-                                                &state.canvas.selection,
-                                                export_scale,
-                                            )
-                                        };
+                                            );
 
                                         if let Some(bounds) = bounds {
                                             let width = bounds.width().ceil() as u32;
@@ -4504,38 +4280,30 @@ impl ApplicationHandler for App {
                                 // Ctrl+C = Copy shapes (without shift)
                                 "c" | "C" => {
                                     if !state.canvas.selection.is_empty() {
-                                        let shapes: Vec<Shape> = state
-                                            .canvas
-                                            .selection
-                                            .iter()
-                                            .filter_map(|&id| {
-                                                state.canvas.document.get_shape(id).cloned()
-                                            })
-                                            .collect();
-                                        if let Ok(json) = serde_json::to_string(&shapes) {
+                                        let payload =
+                                            state.canvas.document.copy(&state.canvas.selection);
+                                        if let Ok(json) = payload.to_json() {
                                             state.ui_state.clipboard_shapes = Some(json);
-                                            log::info!("Copied {} shapes", shapes.len());
+                                            log::info!("Copied {} shapes", payload.shapes.len());
                                         }
                                     }
                                 }
                                 // Ctrl+X = Cut shapes
                                 "x" | "X" => {
                                     if !state.canvas.selection.is_empty() {
-                                        let shapes: Vec<Shape> = state
-                                            .canvas
-                                            .selection
-                                            .iter()
-                                            .filter_map(|&id| {
-                                                state.canvas.document.get_shape(id).cloned()
-                                            })
-                                            .collect();
-                                        if let Ok(json) = serde_json::to_string(&shapes) {
+                                        let payload =
+                                            state.canvas.document.copy(&state.canvas.selection);
+                                        if let Ok(json) = payload.to_json() {
                                             state.ui_state.clipboard_shapes = Some(json);
-                                            log::info!("Cut {} shapes", shapes.len());
+                                            log::info!("Cut {} shapes", payload.shapes.len());
                                             state.canvas.document.push_undo();
                                             for &id in &state.canvas.selection.clone() {
-                                                state.canvas.document.remove_shape(id);
-                                                if state.collab.is_in_room() {
+                                                let removed = state
+                                                    .canvas
+                                                    .document
+                                                    .remove_shape(id)
+                                                    .is_some();
+                                                if removed && state.collab.is_in_room() {
                                                     let _ = state
                                                         .collab
                                                         .crdt_mut()
@@ -4551,24 +4319,25 @@ impl ApplicationHandler for App {
                                     // First try to paste shapes from internal clipboard
                                     let mut pasted = false;
                                     if let Some(json) = &state.ui_state.clipboard_shapes.clone() {
-                                        if let Ok(shapes) = serde_json::from_str::<Vec<Shape>>(json)
-                                        {
+                                        if let Ok(payload) = ClipboardPayload::from_json(json) {
                                             state.canvas.document.push_undo();
                                             state.canvas.clear_selection();
-                                            for shape in shapes {
-                                                let mut new_shape = shape.clone();
-                                                new_shape.regenerate_id();
-                                                new_shape.transform(kurbo::Affine::translate(
-                                                    kurbo::Vec2::new(20.0, 20.0),
-                                                ));
-                                                let new_id = new_shape.id();
-                                                state.canvas.document.add_shape(new_shape.clone());
+                                            // Offset slightly down-right
+                                            let new_ids = state
+                                                .canvas
+                                                .document
+                                                .paste(&payload, kurbo::Vec2::new(20.0, 20.0));
+                                            for new_id in new_ids {
                                                 state.canvas.add_to_selection(new_id);
                                                 if state.collab.is_in_room() {
-                                                    let _ = state
-                                                        .collab
-                                                        .crdt_mut()
-                                                        .add_shape(&new_shape);
+                                                    if let Some(new_shape) =
+                                                        state.canvas.document.get_shape(new_id)
+                                                    {
+                                                        let _ = state
+                                                            .collab
+                                                            .crdt_mut()
+                                                            .add_shape(new_shape);
+                                                    }
                                                 }
                                             }
                                             log::info!("Pasted shapes");
@@ -4641,63 +4410,11 @@ impl ApplicationHandler for App {
                                 }
                                 _ => {}
                             }
+                        } else if let Some(tool) = shortcut_tool {
+                            state.canvas.set_tool(tool);
+                            log::info!("Tool: {:?}", tool);
                         } else {
                             match key_str {
-                                // Selection: V or 1
-                                "v" | "V" | "1" => {
-                                    state.canvas.set_tool(ToolKind::Select);
-                                    log::info!("Tool: Select");
-                                }
-                                // Pan: H
-                                "h" | "H" => {
-                                    state.canvas.set_tool(ToolKind::Pan);
-                                    log::info!("Tool: Pan");
-                                }
-                                // Rectangle: R or 2
-                                "r" | "R" | "2" => {
-                                    state.canvas.set_tool(ToolKind::Rectangle);
-                                    log::info!("Tool: Rectangle");
-                                }
-                                // Ellipse: O or 4
-                                "o" | "O" | "4" => {
-                                    state.canvas.set_tool(ToolKind::Ellipse);
-                                    log::info!("Tool: Ellipse");
-                                }
-                                // Arrow: A or 5
-                                "a" | "A" | "5" => {
-                                    state.canvas.set_tool(ToolKind::Arrow);
-                                    log::info!("Tool: Arrow");
-                                }
-                                // Line: L or 6
-                                "l" | "L" | "6" => {
-                                    state.canvas.set_tool(ToolKind::Line);
-                                    log::info!("Tool: Line");
-                                }
-                                // Draw/Pen: P or 7
-                                "p" | "P" | "7" => {
-                                    state.canvas.set_tool(ToolKind::Freehand);
-                                    log::info!("Tool: Pen");
-                                }
-                                // Text: T or 8
-                                "t" | "T" | "8" => {
-                                    state.canvas.set_tool(ToolKind::Text);
-                                    log::info!("Tool: Text");
-                                }
-                                // Math: M or 9
-                                "m" | "M" | "9" => {
-                                    state.canvas.set_tool(ToolKind::Math);
-                                    log::info!("Tool: Math");
-                                }
-                                // Eraser: E
-                                "e" | "E" => {
-                                    state.canvas.set_tool(ToolKind::Eraser);
-                                    log::info!("Tool: Eraser");
-                                }
-                                // Laser Pointer: Z
-                                "z" | "Z" => {
-                                    state.canvas.set_tool(ToolKind::LaserPointer);
-                                    log::info!("Tool: Laser Pointer");
-                                }
                                 "Delete" | "Backspace" => {
                                     if !state.canvas.selection.is_empty() {
                                         state.canvas.document.push_undo();
@@ -4736,26 +4453,17 @@ impl ApplicationHandler for App {
                                         state.ui_state.current_tool = ToolKind::Select;
                                     }
                                 }
-                                // Arrow keys: nudge selected shapes by grid size
+                                // Arrow keys: nudge selected shapes, further with Shift
                                 "ArrowUp" | "ArrowDown" | "ArrowLeft" | "ArrowRight" => {
-                                    if !state.canvas.selection.is_empty() {
-                                        use drafftink_core::GRID_SIZE;
-                                        let delta = match key_str {
-                                            "ArrowUp" => kurbo::Vec2::new(0.0, -GRID_SIZE),
-                                            "ArrowDown" => kurbo::Vec2::new(0.0, GRID_SIZE),
-                                            "ArrowLeft" => kurbo::Vec2::new(-GRID_SIZE, 0.0),
-                                            "ArrowRight" => kurbo::Vec2::new(GRID_SIZE, 0.0),
-                                            _ => return,
-                                        };
-                                        state.canvas.document.push_undo();
-                                        let translation = kurbo::Affine::translate(delta);
-                                        for &id in &state.canvas.selection {
-                                            if let Some(shape) =
-                                                state.canvas.document.get_shape_mut(id)
-                                            {
-                                                shape.transform(translation);
-                                            }
-                                        }
+                                    use drafftink_core::{NUDGE_LARGE, NUDGE_SMALL, nudge_delta};
+                                    if let Some(delta) =
+                                        nudge_delta(&state.input, NUDGE_SMALL, NUDGE_LARGE)
+                                    {
+                                        let selection = state.canvas.selection.clone();
+                                        state.canvas.document.transform_elements(
+                                            &selection,
+                                            kurbo::Affine::translate(delta),
+                                        );
                                     }
                                 }
                                 _ => {}
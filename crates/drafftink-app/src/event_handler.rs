@@ -4,16 +4,18 @@ use drafftink_core::canvas::Canvas;
 use drafftink_core::input::InputState;
 use drafftink_core::selection::{Corner, HandleKind};
 use drafftink_core::selection::{
-    HANDLE_HIT_TOLERANCE, ManipulationState, MultiMoveState, apply_manipulation, apply_rotation,
-    get_handles, get_manipulation_target_position, hit_test_boundary, hit_test_handles,
+    HANDLE_HIT_TOLERANCE, ManipulationState, MultiMoveState, SelectMode, apply_manipulation,
+    apply_rotation, elements_in_rect, get_handles, get_manipulation_target_position,
+    hit_test_boundary, hit_test_handles,
 };
 use drafftink_core::shapes::{Freehand, Math, Shape, ShapeId, ShapeStyle, ShapeTrait, Text};
 use drafftink_core::snap::{
-    AngleSnapResult, GRID_SIZE, SMART_GUIDE_THRESHOLD, SmartGuide, SnapResult, detect_smart_guides,
-    detect_smart_guides_for_point, snap_line_endpoint_isometric, snap_ray_to_smart_guides,
-    snap_to_grid,
+    AngleSnapResult, GRID_SIZE, SMART_GUIDE_THRESHOLD, SmartGuide, SnapConfig, SnapResult,
+    detect_smart_guides, detect_smart_guides_for_point, snap_bounds, snap_line_endpoint_isometric,
+    snap_ray_to_smart_guides, snap_to_grid,
 };
 use drafftink_core::tools::ToolKind;
+use drafftink_render::HIT_TOLERANCE_PX;
 use kurbo::{Point, Rect};
 
 /// Get the other endpoint of a line/arrow given the handle being manipulated.
@@ -258,7 +260,7 @@ impl EventHandler {
             ToolKind::Text => {
                 let hits = canvas
                     .document
-                    .shapes_at_point(world_point, 5.0 / canvas.camera.zoom);
+                    .shapes_at_point(world_point, HIT_TOLERANCE_PX / canvas.camera.zoom);
                 if let Some(&id) = hits.first() {
                     if let Some(shape @ Shape::Text(_)) = canvas.document.get_shape(id) {
                         if let Some(handle) = hit_test_handles(shape, world_point, handle_tolerance)
@@ -282,7 +284,7 @@ impl EventHandler {
                 }
                 let hits = canvas
                     .document
-                    .shapes_at_point(world_point, 5.0 / canvas.camera.zoom);
+                    .shapes_at_point(world_point, HIT_TOLERANCE_PX / canvas.camera.zoom);
                 if !hits.is_empty() {
                     return Some(None); // move
                 }
@@ -301,11 +303,13 @@ impl EventHandler {
         input: &InputState,
         grid_snap_enabled: bool,
     ) {
+        let grid_snap_enabled = grid_snap_enabled && !input.snap_override();
+
         // If we're editing text and click elsewhere, stop editing
         if self.editing_text.is_some() {
             let hits = canvas
                 .document
-                .shapes_at_point(world_point, 5.0 / canvas.camera.zoom);
+                .shapes_at_point(world_point, HIT_TOLERANCE_PX / canvas.camera.zoom);
             let clicked_on_editing = hits
                 .first()
                 .map(|&id| Some(id) == self.editing_text)
@@ -323,7 +327,7 @@ impl EventHandler {
                 // Text tool: check if clicking on existing text
                 let hits = canvas
                     .document
-                    .shapes_at_point(world_point, 5.0 / canvas.camera.zoom);
+                    .shapes_at_point(world_point, HIT_TOLERANCE_PX / canvas.camera.zoom);
                 if let Some(&id) = hits.first() {
                     if let Some(shape @ Shape::Text(_)) = canvas.document.get_shape(id) {
                         let boundary_tolerance = 8.0 / canvas.camera.zoom;
@@ -370,7 +374,7 @@ impl EventHandler {
                 if input.is_double_click() {
                     let hits = canvas
                         .document
-                        .shapes_at_point(world_point, 5.0 / canvas.camera.zoom);
+                        .shapes_at_point(world_point, HIT_TOLERANCE_PX / canvas.camera.zoom);
                     if let Some(&id) = hits.first() {
                         if let Some(Shape::Text(_)) = canvas.document.get_shape(id) {
                             // Double-click on text - enter edit mode
@@ -429,7 +433,7 @@ impl EventHandler {
                 // Check for shape hit (for selection or move)
                 let hits = canvas
                     .document
-                    .shapes_at_point(world_point, 5.0 / canvas.camera.zoom);
+                    .shapes_at_point(world_point, HIT_TOLERANCE_PX / canvas.camera.zoom);
                 if let Some(&id) = hits.first() {
                     if input.shift() {
                         // Add to/toggle selection
@@ -446,10 +450,15 @@ impl EventHandler {
                             canvas.select(id);
                         }
 
-                        // Start move - use MultiMoveState for all selected shapes
+                        // Start move - use MultiMoveState for all selected
+                        // shapes except locked ones, which stay put
                         let mut original_shapes = std::collections::HashMap::new();
                         for &shape_id in &canvas.selection {
-                            if let Some(shape) = canvas.document.get_shape(shape_id) {
+                            if let Some(shape) = canvas
+                                .document
+                                .get_shape(shape_id)
+                                .filter(|s| !s.is_locked())
+                            {
                                 original_shapes.insert(shape_id, shape.clone());
                             }
                         }
@@ -547,6 +556,8 @@ impl EventHandler {
         grid_snap_enabled: bool,
         angle_snap_enabled: bool,
     ) {
+        let grid_snap_enabled = grid_snap_enabled && !input.snap_override();
+
         // Clear rotation state
         self.rotation_state = None;
 
@@ -660,7 +671,8 @@ impl EventHandler {
             let rect = sel_rect.to_rect();
             // Only select if rectangle has meaningful size
             if rect.width() > 2.0 && rect.height() > 2.0 {
-                let shapes_in_rect = canvas.document.shapes_in_rect(rect);
+                let shapes_in_rect =
+                    elements_in_rect(&canvas.document, rect, SelectMode::Intersect);
                 if !input.shift() {
                     canvas.clear_selection();
                 }
@@ -812,6 +824,8 @@ impl EventHandler {
         smart_snap_enabled: bool,
         angle_snap_enabled: bool,
     ) {
+        let grid_snap_enabled = grid_snap_enabled && !input.snap_override();
+
         // Clear previous snap
         self.last_snap = None;
         self.last_angle_snap = None;
@@ -1015,10 +1029,13 @@ impl EventHandler {
             let movement_threshold = 2.0;
             let has_meaningful_movement = raw_delta.hypot() > movement_threshold;
 
-            // For multi-move, use the first shape's bounds as reference for snapping
-            let reference_shape = mm.original_shapes.values().next();
-            let snap_result = if let Some(ref_shape) = reference_shape {
-                let original_bounds = ref_shape.bounds();
+            // For multi-move, snap the combined bounds of all moved shapes
+            let reference_bounds = mm
+                .original_shapes
+                .values()
+                .map(|s| s.bounds())
+                .reduce(|a, b| a.union(b));
+            let snap_result = if let Some(original_bounds) = reference_bounds {
                 let target_bounds = Rect::new(
                     original_bounds.x0 + raw_delta.x,
                     original_bounds.y0 + raw_delta.y,
@@ -1054,14 +1071,15 @@ impl EventHandler {
                     }
 
                     // Grid snap (applied after smart guides)
-                    if grid_snap_enabled {
-                        let target_pos = Point::new(
-                            original_bounds.x0 + final_delta.x,
-                            original_bounds.y0 + final_delta.y,
-                        );
-                        let snap_result = snap_to_grid(target_pos, GRID_SIZE);
-                        final_delta.x = snap_result.point.x - original_bounds.x0;
-                        final_delta.y = snap_result.point.y - original_bounds.y0;
+                    // Aligns whichever bounding-box edge is nearest a gridline.
+                    let config = SnapConfig {
+                        enabled: grid_snap_enabled,
+                        ..canvas.snap
+                    };
+                    if config.enabled {
+                        let (offset, snap_result) =
+                            snap_bounds(original_bounds + final_delta, &config);
+                        final_delta += offset;
                         self.last_snap = Some(snap_result);
                     }
                 }
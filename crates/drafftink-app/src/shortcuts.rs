@@ -54,6 +54,8 @@ impl ShortcutRegistry {
             Shortcut::new("Y", true, false, "Redo"),
             Shortcut::new("G", true, false, "Group selected shapes"),
             Shortcut::new("G", true, true, "Ungroup selected shapes"),
+            Shortcut::new("L", true, false, "Lock selected shapes"),
+            Shortcut::new("L", true, true, "Unlock all shapes"),
             Shortcut::new("C", true, true, "Copy selection as PNG"),
             Shortcut::new("C", true, false, "Copy shapes"),
             Shortcut::new("X", true, false, "Cut shapes"),
@@ -114,7 +114,7 @@ impl SelectedShapeProps {
                 selection_count: count,
                 is_line: true,
                 path_style: line.path_style as u8,
-                stroke_style: line.stroke_style as u8,
+                stroke_style: line.stroke_style.index(),
                 sloppiness,
                 fill_pattern,
                 has_fill,
@@ -126,7 +126,7 @@ impl SelectedShapeProps {
                 selection_count: count,
                 is_arrow: true,
                 path_style: arrow.path_style as u8,
-                stroke_style: arrow.stroke_style as u8,
+                stroke_style: arrow.stroke_style.index(),
                 sloppiness,
                 fill_pattern,
                 has_fill,
@@ -148,6 +148,7 @@ impl SelectedShapeProps {
                 selection_count: count,
                 is_math: true,
                 font_size: math.font_size as f32,
+                font_family: math.font_family,
                 sloppiness,
                 fill_pattern,
                 has_fill,
@@ -457,7 +458,7 @@ pub enum UiAction {
     /// Set fill pattern for selected shapes.
     SetFillPattern(u8), // 0 = Solid, 1 = Hachure, 2 = ZigZag, 3 = CrossHatch, 4 = Dots, 5 = Dashed, 6 = ZigZagLine
     /// Set path style for selected lines/arrows.
-    SetPathStyle(u8), // 0 = Direct, 1 = Flowing, 2 = Angular
+    SetPathStyle(u8), // 0 = Direct, 1 = Flowing, 2 = Angular, 3 = Curved
     /// Set stroke style for selected lines/arrows.
     SetStrokeStyle(u8), // 0 = Solid, 1 = Dashed, 2 = Dotted
     /// Undo the last action.
@@ -1278,8 +1279,8 @@ fn render_right_panel(ctx: &Context, props: &SelectedShapeProps) -> Option<UiAct
                         );
                         ui.add_space(4.0);
 
-                        // Text-specific properties
-                        if props.is_text {
+                        // Font family applies to text and to letters in math
+                        if props.is_text || props.is_math {
                             // Font Family
                             ui.label(
                                 egui::RichText::new("Font Family")
@@ -1316,7 +1317,10 @@ fn render_right_panel(ctx: &Context, props: &SelectedShapeProps) -> Option<UiAct
                             });
 
                             ui.add_space(4.0);
+                        }
 
+                        // Text-specific properties
+                        if props.is_text {
                             // Font Weight
                             ui.label(
                                 egui::RichText::new("Font Weight")
@@ -1380,7 +1384,7 @@ fn render_right_panel(ctx: &Context, props: &SelectedShapeProps) -> Option<UiAct
                             });
                         }
 
-                        // Math-specific properties (font size only)
+                        // Math-specific properties
                         if props.is_math {
                             ui.label(
                                 egui::RichText::new("Font Size")
@@ -1528,6 +1532,11 @@ fn render_right_panel(ctx: &Context, props: &SelectedShapeProps) -> Option<UiAct
                                 {
                                     action = Some(UiAction::SetPathStyle(2));
                                 }
+
+                                let is_curved = props.path_style == 3;
+                                if ToggleButton::new("Curved", is_curved).show(ui) && !is_curved {
+                                    action = Some(UiAction::SetPathStyle(3));
+                                }
                             });
 
                             // Stroke style (solid/dashed/dotted)
@@ -94,21 +94,48 @@ impl Camera {
         self.zoom = BASE_ZOOM;
     }
 
+    /// Set zoom and offset from a world-to-screen transform.
+    ///
+    /// The transform is expected to be a uniform scale followed by a
+    /// translation, as returned by [`Camera::transform`]. If its scale is
+    /// outside the zoom limits, the clamped zoom keeps the world point at the
+    /// center of `viewport` where the transform would have put it.
+    pub fn set_transform(&mut self, transform: Affine, viewport: kurbo::Size) {
+        let [scale, _, _, _, x, y] = transform.as_coeffs();
+        let offset = Vec2::new(x, y);
+        self.zoom = scale.clamp(self.min_zoom, self.max_zoom);
+        self.offset = if self.zoom != scale && scale > 0.0 {
+            let center = Vec2::new(viewport.width / 2.0, viewport.height / 2.0);
+            let world_center = (center - offset) / scale;
+            center - world_center * self.zoom
+        } else {
+            offset
+        };
+    }
+
     /// Fit the camera to show the given bounding box.
+    ///
+    /// Bounds that are degenerate in one axis (e.g. a horizontal line) are
+    /// fitted along the other; a single point is centered at the default zoom.
     pub fn fit_to_bounds(&mut self, bounds: kurbo::Rect, viewport: kurbo::Size, padding: f64) {
-        if bounds.is_zero_area() {
-            self.reset();
-            return;
-        }
-
         let padded_viewport = kurbo::Size::new(
             (viewport.width - padding * 2.0).max(1.0),
             (viewport.height - padding * 2.0).max(1.0),
         );
 
-        let scale_x = padded_viewport.width / bounds.width();
-        let scale_y = padded_viewport.height / bounds.height();
-        self.zoom = scale_x.min(scale_y).clamp(self.min_zoom, self.max_zoom);
+        let scale_x = if bounds.width() > 0.0 {
+            padded_viewport.width / bounds.width()
+        } else {
+            f64::INFINITY
+        };
+        let scale_y = if bounds.height() > 0.0 {
+            padded_viewport.height / bounds.height()
+        } else {
+            f64::INFINITY
+        };
+        let scale = scale_x.min(scale_y);
+        let scale = if scale.is_finite() { scale } else { BASE_ZOOM };
+        self.zoom = scale.clamp(self.min_zoom, self.max_zoom);
 
         // Center the bounds in the viewport
         let bounds_center = bounds.center();
@@ -199,4 +226,44 @@ mod tests {
         assert!((camera.offset.x - 10.0).abs() < f64::EPSILON);
         assert!((camera.offset.y - 20.0).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn test_fit_to_bounds_degenerate() {
+        let mut camera = Camera::new();
+        let viewport = kurbo::Size::new(800.0, 600.0);
+
+        // Horizontal line: fitted along x, centered in the viewport
+        camera.fit_to_bounds(kurbo::Rect::new(0.0, 50.0, 100.0, 50.0), viewport, 50.0);
+        assert!((camera.zoom - 7.0).abs() < 1e-9);
+        let center = camera.world_to_screen(Point::new(50.0, 50.0));
+        assert!((center - Point::new(400.0, 300.0)).hypot() < 1e-9);
+
+        // Single point: default zoom, centered
+        camera.fit_to_bounds(kurbo::Rect::new(10.0, 10.0, 10.0, 10.0), viewport, 50.0);
+        assert!((camera.zoom - BASE_ZOOM).abs() < f64::EPSILON);
+        let center = camera.world_to_screen(Point::new(10.0, 10.0));
+        assert!((center - Point::new(400.0, 300.0)).hypot() < 1e-9);
+    }
+
+    #[test]
+    fn test_set_transform() {
+        let mut camera = Camera::new();
+        camera.zoom = 2.5;
+        camera.offset = Vec2::new(30.0, -20.0);
+        let transform = camera.transform();
+
+        let mut other = Camera::new();
+        let viewport = kurbo::Size::new(800.0, 600.0);
+        other.set_transform(transform, viewport);
+        assert!((other.zoom - 2.5).abs() < f64::EPSILON);
+        assert_eq!(other.offset, Vec2::new(30.0, -20.0));
+
+        // A clamped zoom stays centered on the same world point
+        camera.zoom = 50.0;
+        let center = Point::new(400.0, 300.0);
+        let world = camera.screen_to_world(center);
+        other.set_transform(camera.transform(), viewport);
+        assert_eq!(other.zoom, other.max_zoom);
+        assert!((other.screen_to_world(center) - world).hypot() < 1e-9);
+    }
 }
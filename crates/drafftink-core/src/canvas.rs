@@ -1,29 +1,120 @@
 //! Canvas document and state management.
 
 use crate::camera::Camera;
-use crate::shapes::{Group, Shape, ShapeId, ShapeTrait};
+use crate::oplog::{Op, OpLog, RecordedOp, apply_patch};
+use crate::schema::{self, SCHEMA_VERSION, SchemaError};
+use crate::selection::{self, HandleKind};
+use crate::shapes::{
+    ElementId, Group, SerializableColor, Shape, ShapeId, ShapeTrait, shape_content_hash,
+};
+use crate::snap::{self, PortId, SnapConfig};
 use crate::tools::{ToolKind, ToolManager};
 use crate::widget::{EditingKind, WidgetManager, WidgetState};
-use kurbo::{Point, Rect};
+use kurbo::{Affine, Point, Rect, Vec2};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
 use uuid::Uuid;
 
 /// Maximum number of undo states to keep.
 const MAX_UNDO_HISTORY: usize = 50;
 
-/// A snapshot of document state for undo/redo.
+/// Margin in screen pixels kept around content when fitting the view.
+pub const FIT_MARGIN: f64 = 50.0;
+
+/// Picking tolerance around strokes and shapes, in screen pixels.
+///
+/// Callers divide this by the camera zoom before passing it to
+/// [`CanvasDocument::shapes_at_point`], so thin lines stay equally easy to
+/// click when zoomed out.
+pub const HIT_TOLERANCE_PX: f64 = 6.0;
+
+/// Key holding the [`ViewState`] at the top level of a saved canvas.
+const KEY_VIEW: &str = "view";
+
+/// A snapshot of document state, for undo/redo and as the base of a
+/// [`DocumentDelta`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct DocumentSnapshot {
+pub struct DocumentSnapshot {
     /// All shapes in the snapshot.
     shapes: HashMap<ShapeId, Shape>,
     /// Z-order of shapes.
     z_order: Vec<ShapeId>,
 }
 
+/// Elements added, changed and removed since a [`DocumentSnapshot`], as
+/// produced by [`CanvasDocument::save_delta`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocumentDelta {
+    /// Elements not present in the snapshot.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added: Vec<Shape>,
+    /// Elements whose content hash differs from the snapshot.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changed: Vec<Shape>,
+    /// IDs of snapshot elements no longer in the document.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<ShapeId>,
+    /// New z-order, present only if it differs from the snapshot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub z_order: Option<Vec<ShapeId>>,
+}
+
+impl DocumentDelta {
+    /// Whether the delta records no changes.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.changed.is_empty()
+            && self.removed.is_empty()
+            && self.z_order.is_none()
+    }
+}
+
+/// A broken reference found and repaired while loading a document, as
+/// reported by [`CanvasDocument::from_json_with_warnings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadWarning {
+    /// An arrow end was bound to a missing element and is now a free point.
+    DanglingBinding { arrow: ShapeId, target: ShapeId },
+    /// The z-order listed a missing element; the entry was dropped.
+    MissingElement(ShapeId),
+    /// The z-order listed an element more than once; later entries were
+    /// dropped.
+    DuplicateOrder(ShapeId),
+    /// An element was not in the z-order and was placed on top.
+    UnorderedElement(ShapeId),
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DanglingBinding { arrow, target } => {
+                write!(f, "Arrow {arrow} was bound to missing element {target}")
+            }
+            Self::MissingElement(id) => write!(f, "Z-order lists missing element {id}"),
+            Self::DuplicateOrder(id) => write!(f, "Z-order lists element {id} more than once"),
+            Self::UnorderedElement(id) => write!(f, "Element {id} was missing from the z-order"),
+        }
+    }
+}
+
+/// Document background that rendering and export clear to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Background {
+    /// Solid color.
+    Color(SerializableColor),
+    /// No background: exports keep alpha 0 where nothing is drawn.
+    Transparent,
+}
+
 /// A canvas document containing all shapes and state.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CanvasDocument {
+    /// Schema version the document was written with (see [`crate::schema`]).
+    /// Missing in documents that predate versioning, which are v0.
+    #[serde(default)]
+    pub schema_version: u32,
     /// Unique document identifier.
     pub id: String,
     /// Document name.
@@ -32,12 +123,28 @@ pub struct CanvasDocument {
     pub shapes: HashMap<ShapeId, Shape>,
     /// Z-order of shapes (back to front).
     pub z_order: Vec<ShapeId>,
+    /// Document background. `None` leaves it to the viewer: the app's
+    /// background color on screen and white in exports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<Background>,
+    /// Counter for [`generate_id`](Self::generate_id); forms the high half
+    /// of generated IDs.
+    #[serde(default)]
+    next_id: u64,
+    /// Random low half of generated IDs, so documents edited in separate
+    /// sessions don't hand out the same IDs. Not persisted.
+    #[serde(skip, default = "random_id_salt")]
+    id_salt: u64,
     /// Undo history stack.
     #[serde(skip)]
     undo_stack: Vec<DocumentSnapshot>,
     /// Redo history stack.
     #[serde(skip)]
     redo_stack: Vec<DocumentSnapshot>,
+    /// Ops recorded since [`start_recording`](Self::start_recording), or
+    /// `None` when not recording.
+    #[serde(skip)]
+    op_log: Option<OpLog>,
 }
 
 impl Default for CanvasDocument {
@@ -50,17 +157,23 @@ impl CanvasDocument {
     /// Create a new empty document.
     pub fn new() -> Self {
         Self {
+            schema_version: SCHEMA_VERSION,
             id: Uuid::new_v4().to_string(),
             name: "Untitled".to_string(),
             shapes: HashMap::new(),
             z_order: Vec::new(),
+            background: None,
+            next_id: 0,
+            id_salt: random_id_salt(),
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            op_log: None,
         }
     }
 
-    /// Take a snapshot of the current document state for undo.
-    fn snapshot(&self) -> DocumentSnapshot {
+    /// Take a snapshot of the current document state, for undo or as the
+    /// base of a later [`save_delta`](Self::save_delta).
+    pub fn snapshot(&self) -> DocumentSnapshot {
         DocumentSnapshot {
             shapes: self.shapes.clone(),
             z_order: self.z_order.clone(),
@@ -82,16 +195,18 @@ impl CanvasDocument {
     }
 
     /// Undo the last change.
-    /// Returns true if undo was performed, false if nothing to undo.
+    /// Returns true if undo was performed, false if nothing to undo. Recorded.
     pub fn undo(&mut self) -> bool {
         if let Some(snapshot) = self.undo_stack.pop() {
             // Save current state to redo stack
             let current = self.snapshot();
+            let before = self.op_log.is_some().then(|| current.clone());
             self.redo_stack.push(current);
 
             // Restore the snapshot
             self.shapes = snapshot.shapes;
             self.z_order = snapshot.z_order;
+            self.record_since(before);
 
             true
         } else {
@@ -100,16 +215,18 @@ impl CanvasDocument {
     }
 
     /// Redo the last undone change.
-    /// Returns true if redo was performed, false if nothing to redo.
+    /// Returns true if redo was performed, false if nothing to redo. Recorded.
     pub fn redo(&mut self) -> bool {
         if let Some(snapshot) = self.redo_stack.pop() {
             // Save current state to undo stack
             let current = self.snapshot();
+            let before = self.op_log.is_some().then(|| current.clone());
             self.undo_stack.push(current);
 
             // Restore the snapshot
             self.shapes = snapshot.shapes;
             self.z_order = snapshot.z_order;
+            self.record_since(before);
 
             true
         } else {
@@ -127,21 +244,170 @@ impl CanvasDocument {
         !self.redo_stack.is_empty()
     }
 
-    /// Add a shape to the document.
+    /// Start recording element edits as [`Op`]s. Only the methods that say
+    /// so record, which includes undo, redo and [`apply_delta`]; writes
+    /// through [`get_shape_mut`] and the public fields do not. Does nothing
+    /// if already recording.
+    ///
+    /// [`apply_delta`]: Self::apply_delta
+    /// [`get_shape_mut`]: Self::get_shape_mut
+    pub fn start_recording(&mut self) {
+        self.op_log.get_or_insert_with(OpLog::default);
+    }
+
+    /// Stop recording and return the ops not yet taken.
+    pub fn stop_recording(&mut self) -> Vec<RecordedOp> {
+        self.op_log
+            .take()
+            .map(|mut log| log.take())
+            .unwrap_or_default()
+    }
+
+    /// Whether element edits are being recorded.
+    pub fn is_recording(&self) -> bool {
+        self.op_log.is_some()
+    }
+
+    /// Ops recorded and not yet taken. Empty when not recording.
+    pub fn recorded_ops(&self) -> &[RecordedOp] {
+        self.op_log.as_ref().map_or(&[], OpLog::ops)
+    }
+
+    /// Remove and return the recorded ops, continuing to record.
+    pub fn take_recorded_ops(&mut self) -> Vec<RecordedOp> {
+        self.op_log.as_mut().map(OpLog::take).unwrap_or_default()
+    }
+
+    /// Record the op built by `op`, if recording.
+    fn record(&mut self, op: impl FnOnce() -> Op) {
+        if let Some(log) = &mut self.op_log {
+            log.push(op());
+        }
+    }
+
+    /// Snapshot for [`record_since`](Self::record_since), taken only while
+    /// recording.
+    fn log_snapshot(&self) -> Option<DocumentSnapshot> {
+        self.op_log.is_some().then(|| self.snapshot())
+    }
+
+    /// Record the ops turning `before` into the current state: removals and
+    /// updates in the old z-order, additions in the new one, then a reorder
+    /// if replaying those would leave a different z-order.
+    fn record_since(&mut self, before: Option<DocumentSnapshot>) {
+        let (Some(before), Some(log)) = (before, &mut self.op_log) else {
+            return;
+        };
+        let mut replayed = Vec::with_capacity(self.z_order.len());
+        for &id in &before.z_order {
+            let Some(new) = self.shapes.get(&id) else {
+                log.push(Op::RemoveElement { id });
+                continue;
+            };
+            if let Some(op) = before.shapes.get(&id).and_then(|old| Op::update(old, new)) {
+                log.push(op);
+            }
+            replayed.push(id);
+        }
+        for &id in &self.z_order {
+            if before.shapes.contains_key(&id) {
+                continue;
+            }
+            if let Some(shape) = self.shapes.get(&id) {
+                log.push(Op::AddElement {
+                    element: shape.clone(),
+                });
+                replayed.push(id);
+            }
+        }
+        if replayed != self.z_order {
+            log.push(Op::Reorder {
+                z_order: self.z_order.clone(),
+            });
+        }
+    }
+
+    /// Remove a shape, locked or not, recording the removal.
+    fn remove_element(&mut self, id: ShapeId) -> Option<Shape> {
+        let removed = self.shapes.remove(&id)?;
+        self.z_order.retain(|&shape_id| shape_id != id);
+        self.record(|| Op::RemoveElement { id });
+        Some(removed)
+    }
+
+    /// Apply an op, as recorded by another document, recording it in turn.
+    ///
+    /// Returns false if it doesn't apply to this document: adding an ID
+    /// that exists, naming a missing element, a reorder that isn't a
+    /// permutation of the z-order, or a patch that doesn't fit the element.
+    pub fn apply_op(&mut self, op: &Op) -> bool {
+        match op {
+            Op::AddElement { element } => {
+                if self.shapes.contains_key(&element.id()) {
+                    return false;
+                }
+                self.add_shape(element.clone());
+                true
+            }
+            // Faithful replay: a lock only guards interactive edits
+            Op::RemoveElement { id } => self.remove_element(*id).is_some(),
+            Op::UpdateElement { id, patch } => {
+                let Some(patched) = self.shapes.get(id).and_then(|s| apply_patch(s, patch)) else {
+                    return false;
+                };
+                if patched.id() != *id {
+                    return false;
+                }
+                self.shapes.insert(*id, patched);
+                self.record(|| op.clone());
+                true
+            }
+            Op::Reorder { z_order } => {
+                let mut current = self.z_order.clone();
+                let mut proposed = z_order.clone();
+                current.sort_unstable();
+                proposed.sort_unstable();
+                if current != proposed {
+                    return false;
+                }
+                self.set_z_order(z_order.clone());
+                true
+            }
+            Op::Group { ids, group_id } => {
+                !self.shapes.contains_key(group_id)
+                    && self.group_shapes_with_id(ids, *group_id).is_some()
+            }
+            Op::Ungroup { group_id } => self.ungroup_shape(*group_id).is_some(),
+        }
+    }
+
+    /// Add a shape to the document. Recorded.
     pub fn add_shape(&mut self, shape: Shape) {
+        self.record(|| Op::AddElement {
+            element: shape.clone(),
+        });
         let id = shape.id();
         self.z_order.push(id);
         self.shapes.insert(id, shape);
     }
 
-    /// Remove a shape from the document.
+    /// Remove a shape from the document. Recorded.
+    ///
+    /// Locked shapes are refused: returns `None` and leaves them in place.
     pub fn remove_shape(&mut self, id: ShapeId) -> Option<Shape> {
-        self.z_order.retain(|&shape_id| shape_id != id);
-        self.shapes.remove(&id)
+        if self.shapes.get(&id).is_none_or(Shape::is_locked) {
+            return None;
+        }
+        self.remove_element(id)
     }
 
-    /// Clear all shapes from the document.
+    /// Clear all shapes from the document. Recorded.
     pub fn clear(&mut self) {
+        if self.op_log.is_some() {
+            for id in std::mem::take(&mut self.z_order) {
+                self.record(|| Op::RemoveElement { id });
+            }
+        }
         self.shapes.clear();
         self.z_order.clear();
     }
@@ -151,9 +417,84 @@ impl CanvasDocument {
         self.shapes.get(&id)
     }
 
+    /// Get a shape by ID unless it is locked.
+    fn unlocked_shape(&self, id: ShapeId) -> Option<&Shape> {
+        self.shapes.get(&id).filter(|s| !s.is_locked())
+    }
+
     /// Get a mutable reference to a shape by ID.
+    ///
+    /// Clears the shape's cached bounds, since callers may edit its geometry.
     pub fn get_shape_mut(&mut self, id: ShapeId) -> Option<&mut Shape> {
-        self.shapes.get_mut(&id)
+        let shape = self.shapes.get_mut(&id)?;
+        shape.invalidate_bounds();
+        Some(shape)
+    }
+
+    /// Find an element by ID, including shapes nested inside groups.
+    pub fn find_element(&self, id: ElementId) -> Option<&Shape> {
+        self.shapes.get(&id).or_else(|| {
+            self.shapes
+                .values()
+                .filter_map(Shape::as_group)
+                .find_map(|group| group.find_shape(id))
+        })
+    }
+
+    /// Edit a top-level shape in place. Recorded, as the fields `edit`
+    /// changed. Returns false if there is no such shape.
+    ///
+    /// `edit` must not change the shape's ID.
+    pub fn update_shape(&mut self, id: ShapeId, edit: impl FnOnce(&mut Shape)) -> bool {
+        let recording = self.op_log.is_some();
+        let Some(shape) = self.get_shape_mut(id) else {
+            return false;
+        };
+        let before = recording.then(|| shape.clone());
+        edit(shape);
+        if let Some(before) = before {
+            let after = shape.clone();
+            if let Some(op) = Op::update(&before, &after) {
+                self.record(|| op);
+            }
+        }
+        true
+    }
+
+    /// Mutable variant of [`find_element`](Self::find_element). Clears the
+    /// element's cached bounds like [`get_shape_mut`](Self::get_shape_mut).
+    pub fn find_element_mut(&mut self, id: ElementId) -> Option<&mut Shape> {
+        if self.shapes.contains_key(&id) {
+            return self.get_shape_mut(id);
+        }
+        let shape = self
+            .shapes
+            .values_mut()
+            .filter_map(Shape::as_group_mut)
+            .find_map(|group| group.find_shape_mut(id))?;
+        shape.invalidate_bounds();
+        Some(shape)
+    }
+
+    /// Generate an element ID not used anywhere in this document.
+    ///
+    /// IDs pair a counter, saved with the document, with a salt drawn at
+    /// random each time the document is created or loaded, so they never
+    /// repeat unless two salts collide. IDs from this generator increase,
+    /// but shapes made by constructors and tools carry random IDs, so ID
+    /// order is not creation order in general.
+    pub fn generate_id(&mut self) -> ElementId {
+        self.next_id = self.next_id.wrapping_add(1);
+        ElementId::from_u64_pair(self.next_id, self.id_salt)
+    }
+
+    /// Add a shape after giving it (and any group children) IDs from
+    /// [`generate_id`](Self::generate_id). Returns the shape's new ID.
+    pub fn add_shape_with_new_id(&mut self, mut shape: Shape) -> ElementId {
+        shape.regenerate_id_with(&mut || self.generate_id());
+        let id = shape.id();
+        self.add_shape(shape);
+        id
     }
 
     /// Get shapes in z-order (back to front).
@@ -161,36 +502,56 @@ impl CanvasDocument {
         self.z_order.iter().filter_map(|id| self.shapes.get(id))
     }
 
-    /// Bring a shape to the front (topmost).
+    /// Replace the z-order, recording a reorder if it changed.
+    fn set_z_order(&mut self, z_order: Vec<ShapeId>) {
+        if z_order != self.z_order {
+            self.z_order = z_order;
+            if let Some(log) = &mut self.op_log {
+                log.push(Op::Reorder {
+                    z_order: self.z_order.clone(),
+                });
+            }
+        }
+    }
+
+    /// Bring a shape to the front (topmost). Recorded.
     pub fn bring_to_front(&mut self, id: ShapeId) {
-        self.z_order.retain(|&shape_id| shape_id != id);
-        self.z_order.push(id);
+        let mut z_order = self.z_order.clone();
+        z_order.retain(|&shape_id| shape_id != id);
+        z_order.push(id);
+        self.set_z_order(z_order);
     }
 
-    /// Send a shape to the back (bottommost).
+    /// Send a shape to the back (bottommost). Recorded.
     pub fn send_to_back(&mut self, id: ShapeId) {
-        self.z_order.retain(|&shape_id| shape_id != id);
-        self.z_order.insert(0, id);
+        let mut z_order = self.z_order.clone();
+        z_order.retain(|&shape_id| shape_id != id);
+        z_order.insert(0, id);
+        self.set_z_order(z_order);
     }
 
-    /// Move a shape one layer forward (towards front).
+    /// Move a shape one layer forward (towards front). Recorded.
     /// Returns true if the shape was moved, false if already at front.
     pub fn bring_forward(&mut self, id: ShapeId) -> bool {
         if let Some(pos) = self.z_order.iter().position(|&shape_id| shape_id == id) {
             if pos < self.z_order.len() - 1 {
-                self.z_order.swap(pos, pos + 1);
+                let mut z_order = self.z_order.clone();
+                z_order.swap(pos, pos + 1);
+                self.set_z_order(z_order);
                 return true;
             }
         }
         false
     }
 
-    /// Move a shape one layer backward (towards back).
+    /// Move a shape one layer backward (towards back). Recorded.
     /// Returns true if the shape was moved, false if already at back.
     pub fn send_backward(&mut self, id: ShapeId) -> bool {
         if let Some(pos) = self.z_order.iter().position(|&shape_id| shape_id == id) {
             if pos > 0 {
-                self.z_order.swap(pos, pos - 1);
+                let mut z_order = self.z_order.clone();
+                z_order.swap(pos, pos - 1);
+                self.set_z_order(z_order);
                 return true;
             }
         }
@@ -210,8 +571,19 @@ impl CanvasDocument {
         result
     }
 
-    /// Find shapes at a point (in world coordinates).
+    /// Find unlocked shapes at a point (in world coordinates).
     pub fn shapes_at_point(&self, point: Point, tolerance: f64) -> Vec<ShapeId> {
+        self.shapes_at_point_with(point, tolerance, false)
+    }
+
+    /// Find shapes at a point (in world coordinates), skipping locked shapes
+    /// unless `include_locked` is set.
+    pub fn shapes_at_point_with(
+        &self,
+        point: Point,
+        tolerance: f64,
+        include_locked: bool,
+    ) -> Vec<ShapeId> {
         // Return in reverse z-order (front to back) for selection priority
         self.z_order
             .iter()
@@ -219,19 +591,21 @@ impl CanvasDocument {
             .filter_map(|&id| {
                 self.shapes
                     .get(&id)
+                    .filter(|s| include_locked || !s.is_locked())
                     .filter(|s| s.hit_test(point, tolerance))
                     .map(|_| id)
             })
             .collect()
     }
 
-    /// Find shapes that intersect or are contained within a rectangle.
+    /// Find unlocked shapes that intersect or are contained within a rectangle.
     pub fn shapes_in_rect(&self, rect: Rect) -> Vec<ShapeId> {
         self.z_order
             .iter()
             .filter_map(|&id| {
                 self.shapes
                     .get(&id)
+                    .filter(|s| !s.is_locked())
                     .filter(|s| {
                         let bounds = s.bounds();
                         // Check if shape bounds intersect with selection rect
@@ -242,6 +616,34 @@ impl CanvasDocument {
             .collect()
     }
 
+    /// Find text shapes, including those inside groups, whose content
+    /// contains `query`. Results are in z-order (back to front).
+    ///
+    /// Case-insensitive matching compares Unicode lowercase forms, so "É"
+    /// matches "é". An empty query matches nothing.
+    pub fn find_text(&self, query: &str, case_sensitive: bool) -> Vec<ShapeId> {
+        self.find_text_ranges(query, case_sensitive)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Like [`find_text`](Self::find_text), but also returns the byte ranges
+    /// of every occurrence within each shape's content for highlighting.
+    pub fn find_text_ranges(
+        &self,
+        query: &str,
+        case_sensitive: bool,
+    ) -> Vec<(ShapeId, Vec<Range<usize>>)> {
+        let mut matches = Vec::new();
+        if !query.is_empty() {
+            for shape in self.shapes_ordered() {
+                collect_text_matches(shape, query, case_sensitive, &mut matches);
+            }
+        }
+        matches
+    }
+
     /// Check if the document is empty.
     pub fn is_empty(&self) -> bool {
         self.shapes.is_empty()
@@ -257,214 +659,128 @@ impl CanvasDocument {
         serde_json::to_string_pretty(self)
     }
 
-    /// Deserialize a document from JSON.
-    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
-        serde_json::from_str(json)
-    }
-
-    /// Import from Excalidraw JSON format.
-    pub fn from_excalidraw(json: &str) -> Result<Self, String> {
-        use crate::shapes::{
-            Arrow, Ellipse, FillPattern, Freehand, Line, PathStyle, Rectangle, ShapeStyle,
-            Sloppiness, Text,
-        };
-
-        let data: serde_json::Value =
-            serde_json::from_str(json).map_err(|e| format!("Invalid JSON: {}", e))?;
-
-        let elements = data
-            .get("elements")
-            .and_then(|e| e.as_array())
-            .ok_or("Missing 'elements' array")?;
-
-        let mut doc = Self::new();
-
-        for elem in elements {
-            // Skip deleted elements
-            if elem
-                .get("isDeleted")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false)
-            {
-                continue;
-            }
-
-            let elem_type = elem.get("type").and_then(|t| t.as_str()).unwrap_or("");
-            let x = elem.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let y = elem.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0);
-
-            // Parse colors
-            let stroke_color = parse_excalidraw_color(
-                elem.get("strokeColor")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("#000000"),
-            );
-            let bg_color = elem
-                .get("backgroundColor")
-                .and_then(|v| v.as_str())
-                .unwrap_or("transparent");
-            let fill_color = if bg_color == "transparent" {
-                None
+    /// Deserialize a document from JSON, migrating older schema versions.
+    ///
+    /// Broken references are repaired and logged; see
+    /// [`from_json_with_warnings`](Self::from_json_with_warnings).
+    pub fn from_json(json: &str) -> Result<Self, SchemaError> {
+        let (document, warnings) = Self::from_json_with_warnings(json)?;
+        for warning in &warnings {
+            log::warn!("Loading document: {}", warning);
+        }
+        Ok(document)
+    }
+
+    /// Deserialize a document from JSON, migrating older schema versions and
+    /// repairing references to elements that do not exist.
+    ///
+    /// Hand-edited or corrupt files can still be opened: arrow ends bound to
+    /// missing elements become free points, and the z-order is made to list
+    /// every element exactly once. Each repair is reported as a warning.
+    pub fn from_json_with_warnings(json: &str) -> Result<(Self, Vec<LoadWarning>), SchemaError> {
+        let value = schema::migrate(serde_json::from_str(json)?)?;
+        let mut document: Self = serde_json::from_value(value)?;
+        let warnings = document.repair_references();
+        Ok((document, warnings))
+    }
+
+    /// Fix references to missing elements after deserializing.
+    fn repair_references(&mut self) -> Vec<LoadWarning> {
+        let mut warnings = Vec::new();
+        let mut listed = HashSet::new();
+        let shapes = &self.shapes;
+        self.z_order.retain(|&id| {
+            if !shapes.contains_key(&id) {
+                warnings.push(LoadWarning::MissingElement(id));
+                false
+            } else if !listed.insert(id) {
+                warnings.push(LoadWarning::DuplicateOrder(id));
+                false
             } else {
-                Some(parse_excalidraw_color(bg_color))
-            };
-
-            let stroke_width = elem
-                .get("strokeWidth")
-                .and_then(|v| v.as_f64())
-                .unwrap_or(2.0);
-            let roughness = elem.get("roughness").and_then(|v| v.as_i64()).unwrap_or(1);
-            let sloppiness = match roughness {
-                0 => Sloppiness::Architect,
-                1 => Sloppiness::Artist,
-                _ => Sloppiness::Cartoonist,
-            };
-
-            let style = ShapeStyle {
-                stroke_color,
-                stroke_width,
-                fill_color,
-                fill_pattern: FillPattern::default(),
-                sloppiness,
-                seed: elem.get("seed").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
-                opacity: elem.get("opacity").and_then(|v| v.as_f64()).unwrap_or(1.0),
-            };
+                true
+            }
+        });
+        // Sorted so the repaired order does not depend on map iteration.
+        let mut unordered: Vec<ShapeId> = shapes
+            .keys()
+            .copied()
+            .filter(|id| !listed.contains(id))
+            .collect();
+        unordered.sort();
+        for id in unordered {
+            warnings.push(LoadWarning::UnorderedElement(id));
+            self.z_order.push(id);
+        }
 
-            let shape: Option<Shape> = match elem_type {
-                "rectangle" | "diamond" => {
-                    let width = elem.get("width").and_then(|v| v.as_f64()).unwrap_or(100.0);
-                    let height = elem.get("height").and_then(|v| v.as_f64()).unwrap_or(100.0);
-                    let mut rect = Rectangle::new(Point::new(x, y), width, height);
-                    rect.style = style;
-                    // Handle roundness
-                    if elem.get("roundness").is_some() {
-                        rect.corner_radius = Rectangle::DEFAULT_ADAPTIVE_RADIUS
-                            .min(width / 4.0)
-                            .min(height / 4.0);
-                    }
-                    // Diamond is rendered as rotated rectangle (TODO: proper diamond shape)
-                    Some(Shape::Rectangle(rect))
-                }
-                "ellipse" => {
-                    let width = elem.get("width").and_then(|v| v.as_f64()).unwrap_or(100.0);
-                    let height = elem.get("height").and_then(|v| v.as_f64()).unwrap_or(100.0);
-                    let center = Point::new(x + width / 2.0, y + height / 2.0);
-                    let mut ellipse = Ellipse::new(center, width / 2.0, height / 2.0);
-                    ellipse.style = style;
-                    Some(Shape::Ellipse(ellipse))
-                }
-                "freedraw" => {
-                    let points = elem.get("points").and_then(|p| p.as_array());
-                    if let Some(pts) = points {
-                        let freehand_points: Vec<Point> = pts
-                            .iter()
-                            .filter_map(|p| p.as_array())
-                            .filter_map(|arr| {
-                                let px = arr.first().and_then(|v| v.as_f64())?;
-                                let py = arr.get(1).and_then(|v| v.as_f64())?;
-                                Some(Point::new(x + px, y + py))
-                            })
-                            .collect();
-                        if !freehand_points.is_empty() {
-                            let mut freehand = Freehand::from_points(freehand_points);
-                            freehand.style = style;
-                            Some(Shape::Freehand(freehand))
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                }
-                "line" => {
-                    let points = elem.get("points").and_then(|p| p.as_array());
-                    if let Some(pts) = points {
-                        let line_points: Vec<Point> = pts
-                            .iter()
-                            .filter_map(|p| p.as_array())
-                            .filter_map(|arr| {
-                                let px = arr.first().and_then(|v| v.as_f64())?;
-                                let py = arr.get(1).and_then(|v| v.as_f64())?;
-                                Some(Point::new(x + px, y + py))
-                            })
-                            .collect();
-                        if line_points.len() >= 2 {
-                            let path_style =
-                                if elem.get("roundness").map(|r| !r.is_null()).unwrap_or(false) {
-                                    PathStyle::Flowing
-                                } else {
-                                    PathStyle::Direct
-                                };
-                            let mut line = Line::from_points(line_points, path_style);
-                            line.style = style;
-                            Some(Shape::Line(line))
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                }
-                "arrow" => {
-                    let points = elem.get("points").and_then(|p| p.as_array());
-                    if let Some(pts) = points {
-                        let arrow_points: Vec<Point> = pts
-                            .iter()
-                            .filter_map(|p| p.as_array())
-                            .filter_map(|arr| {
-                                let px = arr.first().and_then(|v| v.as_f64())?;
-                                let py = arr.get(1).and_then(|v| v.as_f64())?;
-                                Some(Point::new(x + px, y + py))
-                            })
-                            .collect();
-                        if arrow_points.len() >= 2 {
-                            let elbowed = elem
-                                .get("elbowed")
-                                .and_then(|v| v.as_bool())
-                                .unwrap_or(false);
-                            let has_roundness =
-                                elem.get("roundness").map(|r| !r.is_null()).unwrap_or(false);
-                            let path_style = if elbowed {
-                                PathStyle::Angular
-                            } else if has_roundness {
-                                PathStyle::Flowing
-                            } else {
-                                PathStyle::Direct
-                            };
-                            let mut arrow = Arrow::from_points(arrow_points, path_style);
-                            arrow.style = style;
-                            Some(Shape::Arrow(arrow))
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
+        let known: HashSet<ShapeId> = self
+            .shapes
+            .values()
+            .flat_map(|shape| match shape {
+                Shape::Group(group) => group.all_shape_ids(),
+                _ => vec![shape.id()],
+            })
+            .collect();
+        for id in &self.z_order {
+            if let Some(shape) = self.shapes.get_mut(id) {
+                unbind_missing(shape, &known, &mut warnings);
+            }
+        }
+        warnings
+    }
+
+    /// Elements added, changed and removed since `since`.
+    ///
+    /// Changes are detected with [`shape_content_hash`], the same hash the
+    /// renderer caches on. Elements appear in the current z-order.
+    pub fn save_delta(&self, since: &DocumentSnapshot) -> DocumentDelta {
+        let mut delta = DocumentDelta::default();
+        for shape in self.shapes_ordered() {
+            match since.shapes.get(&shape.id()) {
+                None => delta.added.push(shape.clone()),
+                Some(old) => {
+                    if shape_content_hash(old) != shape_content_hash(shape) {
+                        delta.changed.push(shape.clone());
                     }
                 }
-                "text" => {
-                    let content = elem
-                        .get("text")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string();
-                    let font_size = elem
-                        .get("fontSize")
-                        .and_then(|v| v.as_f64())
-                        .unwrap_or(20.0);
-                    let mut text = Text::new(Point::new(x, y), content);
-                    text.font_size = font_size;
-                    text.style = style;
-                    Some(Shape::Text(text))
-                }
-                _ => None,
-            };
-
-            if let Some(s) = shape {
-                doc.add_shape(s);
             }
         }
+        delta.removed = since
+            .z_order
+            .iter()
+            .copied()
+            .filter(|id| !self.shapes.contains_key(id))
+            .collect();
+        if self.z_order != since.z_order {
+            delta.z_order = Some(self.z_order.clone());
+        }
+        delta
+    }
+
+    /// Apply a delta produced by [`save_delta`](Self::save_delta) to a
+    /// document in the snapshot's state. Does not touch the undo history.
+    /// Recorded.
+    pub fn apply_delta(&mut self, delta: DocumentDelta) {
+        let before = self.log_snapshot();
+        for id in &delta.removed {
+            self.shapes.remove(id);
+        }
+        for shape in delta.added.into_iter().chain(delta.changed) {
+            self.shapes.insert(shape.id(), shape);
+        }
+        match delta.z_order {
+            Some(z_order) => self.z_order = z_order,
+            None => self.z_order.retain(|id| !delta.removed.contains(id)),
+        }
+        self.record_since(before);
+    }
 
-        Ok(doc)
+    /// Import from Excalidraw JSON format, discarding import warnings.
+    pub fn from_excalidraw(json: &str) -> Result<Self, String> {
+        let import = import_excalidraw(json)?;
+        for warning in &import.warnings {
+            log::warn!("Excalidraw import: {}", warning);
+        }
+        Ok(import.document)
     }
 
     /// Export selected shapes to a new document.
@@ -478,10 +794,98 @@ impl CanvasDocument {
         doc
     }
 
+    /// Shapes to export, in z-order.
+    ///
+    /// `None` exports the whole document. With `Some(ids)`, only the listed
+    /// top-level elements are exported; unknown IDs are skipped. An arrow is
+    /// exported only if it is listed itself and every shape it is bound to is
+    /// listed too: a connector into a shape that is not exported would point
+    /// at nothing, so it is excluded rather than clipped.
+    pub fn export_shapes(&self, ids: Option<&[ElementId]>) -> Vec<&Shape> {
+        let Some(ids) = ids else {
+            return self.shapes_ordered().collect();
+        };
+        self.shapes_ordered()
+            .filter(|shape| ids.contains(&shape.id()))
+            .filter(|shape| match shape {
+                Shape::Arrow(arrow) => [arrow.start_binding, arrow.end_binding]
+                    .into_iter()
+                    .flatten()
+                    .all(|bound| ids.contains(&bound)),
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Union of the bounds of `shapes`, or `None` if there are none.
+    pub fn shapes_bounds<'a>(shapes: impl IntoIterator<Item = &'a Shape>) -> Option<Rect> {
+        shapes
+            .into_iter()
+            .map(|shape| shape.bounds())
+            .reduce(|a, b| a.union(b))
+    }
+
+    /// Copy shapes into a self-contained clipboard payload, in z-order.
+    /// Unknown IDs are skipped.
+    pub fn copy(&self, ids: &[ShapeId]) -> ClipboardPayload {
+        ClipboardPayload {
+            shapes: self
+                .z_order
+                .iter()
+                .filter(|id| ids.contains(id))
+                .filter_map(|id| self.shapes.get(id).cloned())
+                .collect(),
+        }
+    }
+
+    /// Paste clones of the payload's shapes with fresh IDs, moved by `offset`.
+    /// Returns the IDs of the new top-level shapes.
+    ///
+    /// Arrow ends bound to a shape in the payload are rebound to its pasted
+    /// copy; ends bound to anything else are freed.
+    pub fn paste(&mut self, payload: &ClipboardPayload, offset: Vec2) -> Vec<ShapeId> {
+        let mut new_ids = HashMap::new();
+        let mut shapes = Vec::with_capacity(payload.shapes.len());
+        for shape in &payload.shapes {
+            let mut shape = shape.clone();
+            shape.transform(Affine::translate(offset));
+            let mut old = Vec::new();
+            collect_ids_in_regeneration_order(&shape, &mut old);
+            let mut fresh = Vec::new();
+            shape.regenerate_id_with(&mut || {
+                let id = self.generate_id();
+                fresh.push(id);
+                id
+            });
+            new_ids.extend(old.into_iter().zip(fresh));
+            shapes.push(shape);
+        }
+        shapes
+            .into_iter()
+            .map(|mut shape| {
+                remap_bindings(&mut shape, &new_ids);
+                let id = shape.id();
+                self.add_shape(shape);
+                id
+            })
+            .collect()
+    }
+
     /// Group the given shapes into a single group.
     /// Returns the new group's ID, or None if less than 2 shapes were provided.
     /// The group will be placed at the position of the frontmost shape in z-order.
+    /// Recorded.
     pub fn group_shapes(&mut self, shape_ids: &[ShapeId]) -> Option<ShapeId> {
+        let group_id = self.generate_id();
+        self.group_shapes_with_id(shape_ids, group_id)
+    }
+
+    /// [`group_shapes`](Self::group_shapes), giving the group ID `group_id`.
+    fn group_shapes_with_id(
+        &mut self,
+        shape_ids: &[ShapeId],
+        group_id: ShapeId,
+    ) -> Option<ShapeId> {
         if shape_ids.len() < 2 {
             return None;
         }
@@ -507,8 +911,8 @@ impl CanvasDocument {
         let children: Vec<Shape> = shapes_to_group.into_iter().map(|(_, s)| s).collect();
 
         // Create the group
-        let group = Group::new(children);
-        let group_id = group.id();
+        let grouped_count = children.len();
+        let group = Group::reconstruct(group_id, children);
 
         // Remove the original shapes from the document
         for &id in shape_ids {
@@ -518,17 +922,21 @@ impl CanvasDocument {
 
         // Add the group at the position of the frontmost shape
         self.shapes.insert(group_id, Shape::Group(group));
-        // Insert at the max position (adjusted for removed items)
-        let insert_pos = max_z_idx
-            .saturating_sub(shape_ids.len() - 1)
-            .min(self.z_order.len());
+        // Insert at the max position, adjusted for the grouped shapes behind
+        // it (unknown or duplicate IDs in `shape_ids` removed nothing)
+        let insert_pos = (max_z_idx + 1 - grouped_count).min(self.z_order.len());
         self.z_order.insert(insert_pos, group_id);
+        self.record(|| Op::Group {
+            ids: shape_ids.to_vec(),
+            group_id,
+        });
 
         Some(group_id)
     }
 
     /// Ungroup a group shape, returning its children to the document.
     /// Returns the IDs of the ungrouped children, or None if the shape is not a group.
+    /// Recorded.
     pub fn ungroup_shape(&mut self, group_id: ShapeId) -> Option<Vec<ShapeId>> {
         // Check if the shape is a group
         let group = match self.shapes.get(&group_id) {
@@ -553,59 +961,593 @@ impl CanvasDocument {
             // Insert at the original position, maintaining child order
             self.z_order.insert(z_pos + i, child_id);
         }
+        self.record(|| Op::Ungroup { group_id });
 
         Some(child_ids)
     }
-}
 
-/// Runtime canvas state (not persisted).
-#[derive(Debug, Clone)]
-pub struct Canvas {
-    /// The document being edited.
-    pub document: CanvasDocument,
-    /// Camera for view transform.
-    pub camera: Camera,
-    /// Tool manager.
-    pub tool_manager: ToolManager,
-    /// Currently selected shape IDs.
-    /// NOTE: Prefer using widget_manager for selection state; this is kept for backward compatibility.
-    pub selection: Vec<ShapeId>,
-    /// Viewport size.
-    pub viewport_size: kurbo::Size,
-    /// Widget manager for UI state (selection, hover, editing).
-    pub widgets: WidgetManager,
-}
+    /// Align the bounding boxes of the given shapes to the selection's edge or center.
+    /// Groups move as a unit. Locked shapes are ignored. Does nothing for
+    /// fewer than 2 shapes.
+    ///
+    /// One undoable, recorded edit; connectors bound to moved shapes are
+    /// reflowed.
+    pub fn align(&mut self, ids: &[ShapeId], mode: AlignMode) {
+        let bounds: Vec<(ShapeId, Rect)> = ids
+            .iter()
+            .filter_map(|&id| self.unlocked_shape(id).map(|s| (id, s.bounds())))
+            .collect();
+        if bounds.len() < 2 {
+            return;
+        }
+        let combined = bounds
+            .iter()
+            .map(|(_, b)| *b)
+            .reduce(|a, b| a.union(b))
+            .unwrap();
+
+        let deltas: HashMap<ShapeId, Vec2> = bounds
+            .into_iter()
+            .map(|(id, b)| {
+                let delta = match mode {
+                    AlignMode::Left => Vec2::new(combined.x0 - b.x0, 0.0),
+                    AlignMode::CenterX => Vec2::new(combined.center().x - b.center().x, 0.0),
+                    AlignMode::Right => Vec2::new(combined.x1 - b.x1, 0.0),
+                    AlignMode::Top => Vec2::new(0.0, combined.y0 - b.y0),
+                    AlignMode::CenterY => Vec2::new(0.0, combined.center().y - b.center().y),
+                    AlignMode::Bottom => Vec2::new(0.0, combined.y1 - b.y1),
+                };
+                (id, delta)
+            })
+            .collect();
+        self.translate_elements(&deltas);
+    }
+
+    /// Space the given shapes so the gaps between their bounding boxes are equal
+    /// along `axis`. The first and last shapes along the axis stay in place.
+    /// Groups move as a unit. Locked shapes are ignored. Does nothing for
+    /// fewer than 3 shapes.
+    ///
+    /// One undoable, recorded edit; connectors bound to moved shapes are
+    /// reflowed.
+    pub fn distribute(&mut self, ids: &[ShapeId], axis: Axis) {
+        // (id, start, size) along the axis
+        let mut spans: Vec<(ShapeId, f64, f64)> = ids
+            .iter()
+            .filter_map(|&id| self.unlocked_shape(id).map(|s| (id, s.bounds())))
+            .map(|(id, b)| match axis {
+                Axis::Horizontal => (id, b.x0, b.width()),
+                Axis::Vertical => (id, b.y0, b.height()),
+            })
+            .collect();
+        if spans.len() < 3 {
+            return;
+        }
+        spans.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let (_, first_start, _) = spans[0];
+        let (_, last_start, last_size) = spans[spans.len() - 1];
+        let total_size: f64 = spans.iter().map(|(_, _, size)| size).sum();
+        let gap = (last_start + last_size - first_start - total_size) / (spans.len() - 1) as f64;
+
+        let mut cursor = first_start;
+        let mut deltas = HashMap::with_capacity(spans.len());
+        for &(id, start, size) in &spans {
+            let offset = cursor - start;
+            let delta = match axis {
+                Axis::Horizontal => Vec2::new(offset, 0.0),
+                Axis::Vertical => Vec2::new(0.0, offset),
+            };
+            deltas.insert(id, delta);
+            cursor += size + gap;
+        }
+        self.translate_elements(&deltas);
+    }
 
-impl Default for Canvas {
-    fn default() -> Self {
-        Self::new()
+    /// Move each shape by its delta as a single edit, like
+    /// [`transform_elements`](Self::transform_elements).
+    fn translate_elements(&mut self, deltas: &HashMap<ShapeId, Vec2>) -> bool {
+        let ids: Vec<ShapeId> = deltas.keys().copied().collect();
+        self.edit_elements(&ids, |id, shape| {
+            shape.transform(Affine::translate(deltas[&id]));
+        })
     }
 }
 
-impl Canvas {
-    /// Create a new canvas with an empty document.
-    pub fn new() -> Self {
-        Self {
-            document: CanvasDocument::new(),
-            camera: Camera::new(),
-            tool_manager: ToolManager::new(),
-            selection: Vec::new(),
-            viewport_size: kurbo::Size::new(800.0, 600.0),
-            widgets: WidgetManager::new(),
+impl CanvasDocument {
+    /// Mirror the given shapes left-to-right about the vertical center line of
+    /// their combined bounds. Groups flip as a unit and locked shapes are
+    /// ignored. One undoable, recorded edit; connectors bound to flipped
+    /// shapes are reflowed.
+    pub fn flip_horizontal(&mut self, ids: &[ShapeId]) {
+        self.flip(ids, Axis::Horizontal);
+    }
+
+    /// Mirror the given shapes top-to-bottom about the horizontal center line
+    /// of their combined bounds. Groups flip as a unit and locked shapes are
+    /// ignored. One undoable, recorded edit; connectors bound to flipped
+    /// shapes are reflowed.
+    pub fn flip_vertical(&mut self, ids: &[ShapeId]) {
+        self.flip(ids, Axis::Vertical);
+    }
+
+    /// Resize a shape by dragging one of its resize handles by `delta`.
+    ///
+    /// See [`selection::resize_bounds`] for how the handle and `lock_aspect`
+    /// shape the new box. Dragging past the opposite side flips the shape
+    /// instead of collapsing it. Returns false if the shape does not exist, is
+    /// locked, or `handle` is not a corner or edge handle. One undoable,
+    /// recorded edit; connectors bound to the shape are reflowed.
+    pub fn resize_element(
+        &mut self,
+        id: ShapeId,
+        handle: HandleKind,
+        delta: Vec2,
+        lock_aspect: bool,
+    ) -> bool {
+        let Some(old) = self.unlocked_shape(id).map(Shape::bounds) else {
+            return false;
+        };
+        let Some(new) = selection::resize_bounds(old, handle, delta, lock_aspect) else {
+            return false;
+        };
+
+        self.edit_elements(&[id], |_, shape| {
+            // Scale into the normalized box, then mirror for any negative side
+            let target = new.abs();
+            let scale = |new_size: f64, old_size: f64| {
+                if old_size > f64::EPSILON {
+                    new_size / old_size
+                } else {
+                    1.0
+                }
+            };
+            let sx = scale(target.width(), old.width());
+            let sy = scale(target.height(), old.height());
+            shape.transform(Affine::new([
+                sx,
+                0.0,
+                0.0,
+                sy,
+                target.x0 - old.x0 * sx,
+                target.y0 - old.y0 * sy,
+            ]));
+            let center = target.center();
+            if new.width() < 0.0 {
+                mirror_shape(shape, Axis::Horizontal, center.x);
+            }
+            if new.height() < 0.0 {
+                mirror_shape(shape, Axis::Vertical, center.y);
+            }
+        })
+    }
+
+    /// Apply one affine transform to several shapes as a single edit.
+    ///
+    /// Records one undo entry for the whole batch, then reflows connectors
+    /// bound to any transformed shape or group member once at the end. Text
+    /// moves and scales its font size but never shears. IDs that are not in
+    /// the document or are locked are ignored; returns false if none remain.
+    /// Recorded, including the reflowed connectors.
+    pub fn transform_elements(&mut self, ids: &[ElementId], transform: Affine) -> bool {
+        self.edit_elements(ids, |_, shape| shape.transform(transform))
+    }
+
+    /// Apply `edit` to several top-level shapes as a single edit.
+    ///
+    /// Shared by [`transform_elements`](Self::transform_elements) and the
+    /// other batch edits: one undo entry, then connectors bound to any edited
+    /// shape or group member reflowed once. IDs that are not in the document
+    /// or are locked are skipped; returns false if none remain.
+    fn edit_elements(
+        &mut self,
+        ids: &[ElementId],
+        mut edit: impl FnMut(ElementId, &mut Shape),
+    ) -> bool {
+        let targets: Vec<ElementId> = ids
+            .iter()
+            .copied()
+            .filter(|id| self.shapes.get(id).is_some_and(|s| !s.is_locked()))
+            .collect();
+        if targets.is_empty() {
+            return false;
         }
-    }
 
-    /// Create a canvas with an existing document.
-    pub fn with_document(document: CanvasDocument) -> Self {
-        Self {
-            document,
-            camera: Camera::new(),
-            tool_manager: ToolManager::new(),
-            selection: Vec::new(),
-            viewport_size: kurbo::Size::new(800.0, 600.0),
-            widgets: WidgetManager::new(),
+        self.push_undo();
+        let before = self.log_snapshot();
+        let mut moved = Vec::with_capacity(targets.len());
+        for id in targets {
+            let Some(shape) = self.shapes.get_mut(&id) else {
+                continue;
+            };
+            shape.invalidate_bounds();
+            edit(id, shape);
+            match shape {
+                Shape::Group(group) => moved.extend(group.all_shape_ids()),
+                _ => moved.push(id),
+            }
+        }
+        self.reflow_connectors(&moved);
+        self.record_since(before);
+        true
+    }
+
+    /// Lock or unlock the given shapes as a single undoable edit.
+    ///
+    /// Locked shapes are skipped by hit testing and refuse transforms,
+    /// resizes and deletion until unlocked. Returns false if none of the IDs
+    /// needed changing. Recorded.
+    pub fn set_locked(&mut self, ids: &[ShapeId], locked: bool) -> bool {
+        let targets: Vec<ShapeId> = ids
+            .iter()
+            .copied()
+            .filter(|id| self.shapes.get(id).is_some_and(|s| s.is_locked() != locked))
+            .collect();
+        if targets.is_empty() {
+            return false;
         }
-    }
+
+        self.push_undo();
+        let before = self.log_snapshot();
+        for id in targets {
+            if let Some(shape) = self.shapes.get_mut(&id) {
+                shape.set_locked(locked);
+            }
+        }
+        self.record_since(before);
+        true
+    }
+
+    /// IDs of all locked shapes, in z-order.
+    pub fn locked_ids(&self) -> Vec<ShapeId> {
+        self.z_order
+            .iter()
+            .copied()
+            .filter(|id| self.shapes.get(id).is_some_and(Shape::is_locked))
+            .collect()
+    }
+
+    /// Re-route arrows attached to any of the shapes in `moved_ids`.
+    ///
+    /// Arrows without waypoints get new ports from
+    /// [`compute_elbow_path_between`](crate::elbow::compute_elbow_path_between)
+    /// using the current bounds of both ends (a free end counts as a point).
+    /// Arrows with manual waypoints keep them; only an end attached to a moved
+    /// shape is moved onto that shape's boundary, nearest to the adjacent
+    /// waypoint. An end pinned to a [`PortId`] goes to that port instead, so it
+    /// stays on the same port through moves, resizes and rotations. Arrows not
+    /// attached to a moved shape are left untouched.
+    pub fn reflow_connectors(&mut self, moved_ids: &[ShapeId]) {
+        let moved = |binding: Option<ShapeId>| binding.is_some_and(|id| moved_ids.contains(&id));
+        let affected: Vec<ShapeId> = self
+            .z_order
+            .iter()
+            .copied()
+            .filter(|id| {
+                matches!(self.shapes.get(id), Some(Shape::Arrow(a))
+                    if moved(a.start_binding) || moved(a.end_binding))
+            })
+            .collect();
+
+        for id in affected {
+            let Some(Shape::Arrow(arrow)) = self.shapes.get(&id) else {
+                continue;
+            };
+            let bounds_of = |binding: Option<ShapeId>| Some(self.shapes.get(&binding?)?.bounds());
+            let port_of = |binding: Option<ShapeId>, port: Option<PortId>| {
+                let shape = self.shapes.get(&binding?)?;
+                Some(snap::port_position(shape.bounds(), shape.rotation(), port?))
+            };
+            let start_bounds = bounds_of(arrow.start_binding);
+            let end_bounds = bounds_of(arrow.end_binding);
+            let start_port = port_of(arrow.start_binding, arrow.start_port);
+            let end_port = port_of(arrow.end_binding, arrow.end_port);
+            let (start_moved, end_moved) = (moved(arrow.start_binding), moved(arrow.end_binding));
+
+            let Some(Shape::Arrow(arrow)) = self.get_shape_mut(id) else {
+                continue;
+            };
+            match (
+                arrow.intermediate_points.first().copied(),
+                arrow.intermediate_points.last().copied(),
+            ) {
+                (Some(first), Some(last)) => {
+                    if let Some(bounds) = start_bounds.filter(|_| start_moved) {
+                        arrow.start =
+                            start_port.unwrap_or_else(|| nearest_on_boundary(bounds, first));
+                    }
+                    if let Some(bounds) = end_bounds.filter(|_| end_moved) {
+                        arrow.end = end_port.unwrap_or_else(|| nearest_on_boundary(bounds, last));
+                    }
+                }
+                _ => {
+                    // A pinned port is a fixed point; otherwise the router
+                    // picks where to leave the shape
+                    let point = |p: Point| Rect::from_points(p, p);
+                    let from = start_port
+                        .map(point)
+                        .or(start_bounds)
+                        .unwrap_or_else(|| point(arrow.start));
+                    let to = end_port
+                        .map(point)
+                        .or(end_bounds)
+                        .unwrap_or_else(|| point(arrow.end));
+                    let route = crate::elbow::compute_elbow_path_between(from, to);
+                    if let (Some(&first), Some(&last)) = (route.first(), route.last()) {
+                        arrow.start = first;
+                        arrow.end = last;
+                    }
+                }
+            }
+        }
+    }
+
+    fn flip(&mut self, ids: &[ShapeId], axis: Axis) {
+        let Some(bounds) = ids
+            .iter()
+            .filter_map(|&id| self.unlocked_shape(id).map(|s| s.bounds()))
+            .reduce(|a, b| a.union(b))
+        else {
+            return;
+        };
+        let line = match axis {
+            Axis::Horizontal => bounds.center().x,
+            Axis::Vertical => bounds.center().y,
+        };
+        self.edit_elements(ids, |_, shape| mirror_shape(shape, axis, line));
+    }
+}
+
+/// Mirror a shape across the line at `line` perpendicular to `axis`.
+///
+/// Point-based shapes have their coordinates reflected. Box-based shapes
+/// (rectangles, text, images, ...) keep their orientation so text stays
+/// readable: only their position is mirrored and their rotation reversed.
+fn mirror_shape(shape: &mut Shape, axis: Axis, line: f64) {
+    match shape {
+        Shape::Group(group) => {
+            for child in group.children_mut() {
+                mirror_shape(child, axis, line);
+            }
+        }
+        Shape::Line(_) | Shape::Arrow(_) | Shape::Freehand(_) => {
+            let reflect = match axis {
+                Axis::Horizontal => Affine::new([-1.0, 0.0, 0.0, 1.0, 2.0 * line, 0.0]),
+                Axis::Vertical => Affine::new([1.0, 0.0, 0.0, -1.0, 0.0, 2.0 * line]),
+            };
+            shape.transform(reflect);
+        }
+        _ => {
+            let center = shape.bounds().center();
+            let offset = match axis {
+                Axis::Horizontal => Vec2::new(2.0 * (line - center.x), 0.0),
+                Axis::Vertical => Vec2::new(0.0, 2.0 * (line - center.y)),
+            };
+            shape.transform(Affine::translate(offset));
+            shape.set_rotation(-shape.rotation());
+        }
+    }
+}
+
+/// Append text matches in `shape` (recursing into groups) to `out`.
+fn collect_text_matches(
+    shape: &Shape,
+    query: &str,
+    case_sensitive: bool,
+    out: &mut Vec<(ShapeId, Vec<Range<usize>>)>,
+) {
+    match shape {
+        Shape::Group(group) => {
+            for child in group.children() {
+                collect_text_matches(child, query, case_sensitive, out);
+            }
+        }
+        Shape::Text(text) => {
+            let ranges = if case_sensitive {
+                text.content
+                    .match_indices(query)
+                    .map(|(start, m)| start..start + m.len())
+                    .collect()
+            } else {
+                find_folded(&text.content, query)
+            };
+            if !ranges.is_empty() {
+                out.push((shape.id(), ranges));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Case-insensitive occurrences of `query` in `haystack`, as byte ranges of
+/// the original `haystack`.
+///
+/// Lowercasing can change a character's byte length (and even expand it to
+/// several characters), so the search runs on a lowercased copy and maps
+/// each match back through the originating characters.
+fn find_folded(haystack: &str, query: &str) -> Vec<Range<usize>> {
+    let query = query.to_lowercase();
+    let mut folded = String::with_capacity(haystack.len());
+    // Original (start, end) of the character each folded byte came from
+    let mut origin = Vec::with_capacity(haystack.len());
+    for (start, ch) in haystack.char_indices() {
+        let end = start + ch.len_utf8();
+        for lower in ch.to_lowercase() {
+            folded.push(lower);
+            origin.extend(std::iter::repeat_n((start, end), lower.len_utf8()));
+        }
+    }
+    folded
+        .match_indices(query.as_str())
+        .map(|(start, m)| origin[start].0..origin[start + m.len() - 1].1)
+        .collect()
+}
+
+/// Which edge or center [`CanvasDocument::align`] lines shapes up on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignMode {
+    /// Left edges.
+    Left,
+    /// Horizontal centers (same x).
+    CenterX,
+    /// Right edges.
+    Right,
+    /// Top edges.
+    Top,
+    /// Vertical centers (same y).
+    CenterY,
+    /// Bottom edges.
+    Bottom,
+}
+
+/// Axis along which [`CanvasDocument::distribute`] spaces shapes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    /// Left to right.
+    Horizontal,
+    /// Top to bottom.
+    Vertical,
+}
+
+/// Shapes copied with [`CanvasDocument::copy`], ready to paste into any document.
+///
+/// The payload is self-contained: groups carry their children, and line and
+/// arrow endpoints are stored as positions. Arrow bindings refer to other
+/// shapes by ID; [`CanvasDocument::paste`] rebinds them to the pasted copies
+/// of shapes in the payload and frees the rest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClipboardPayload {
+    /// Copied shapes in z-order (back to front).
+    pub shapes: Vec<Shape>,
+}
+
+impl ClipboardPayload {
+    /// Check if the payload holds no shapes.
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// Serialize the payload to JSON (for the system clipboard).
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Deserialize a payload from JSON.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// Viewport saved alongside a document, separate from its elements.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ViewState {
+    /// World-to-screen transform, as returned by [`Camera::transform`].
+    pub transform: Affine,
+}
+
+/// Runtime canvas state (not persisted).
+///
+/// Only the document and the [`ViewState`] are saved, via
+/// [`Canvas::save_json`].
+#[derive(Debug, Clone)]
+pub struct Canvas {
+    /// The document being edited.
+    pub document: CanvasDocument,
+    /// Camera for view transform.
+    pub camera: Camera,
+    /// Tool manager.
+    pub tool_manager: ToolManager,
+    /// Currently selected shape IDs.
+    /// NOTE: Prefer using widget_manager for selection state; this is kept for backward compatibility.
+    pub selection: Vec<ShapeId>,
+    /// Viewport size.
+    pub viewport_size: kurbo::Size,
+    /// Widget manager for UI state (selection, hover, editing).
+    pub widgets: WidgetManager,
+    /// Grid snapping settings.
+    pub snap: SnapConfig,
+}
+
+impl Default for Canvas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Canvas {
+    /// Create a new canvas with an empty document.
+    pub fn new() -> Self {
+        Self {
+            document: CanvasDocument::new(),
+            camera: Camera::new(),
+            tool_manager: ToolManager::new(),
+            selection: Vec::new(),
+            viewport_size: kurbo::Size::new(800.0, 600.0),
+            widgets: WidgetManager::new(),
+            snap: SnapConfig::default(),
+        }
+    }
+
+    /// Create a canvas with an existing document.
+    pub fn with_document(document: CanvasDocument) -> Self {
+        Self {
+            document,
+            camera: Camera::new(),
+            tool_manager: ToolManager::new(),
+            selection: Vec::new(),
+            viewport_size: kurbo::Size::new(800.0, 600.0),
+            widgets: WidgetManager::new(),
+            snap: SnapConfig::default(),
+        }
+    }
+
+    /// Current pan and zoom, for saving.
+    pub fn view_state(&self) -> ViewState {
+        ViewState {
+            transform: self.camera.transform(),
+        }
+    }
+
+    /// Serialize the document together with the current view.
+    ///
+    /// Document fields are written exactly as by [`CanvasDocument::to_json`];
+    /// the view goes under a separate top-level `view` key, which
+    /// [`CanvasDocument::from_json`] ignores.
+    pub fn save_json(&self) -> Result<String, serde_json::Error> {
+        let mut value = serde_json::to_value(&self.document)?;
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert(
+                KEY_VIEW.to_string(),
+                serde_json::to_value(self.view_state())?,
+            );
+        }
+        serde_json::to_string_pretty(&value)
+    }
+
+    /// Load a canvas saved by [`Canvas::save_json`], or a bare document.
+    ///
+    /// The saved view is restored when present. Without one the view is
+    /// zoomed to fit the content in the default viewport size; callers that
+    /// know the real size should set it and call [`Canvas::fit_to_content`]
+    /// again.
+    pub fn from_json(json: &str) -> Result<Self, SchemaError> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        let view = value.as_object_mut().and_then(|obj| obj.remove(KEY_VIEW));
+        let mut document: CanvasDocument = serde_json::from_value(schema::migrate(value)?)?;
+        for warning in document.repair_references() {
+            log::warn!("Loading document: {}", warning);
+        }
+        let mut canvas = Self::with_document(document);
+        match view {
+            Some(view) => {
+                let view: ViewState = serde_json::from_value(view)?;
+                let viewport = canvas.viewport_size;
+                canvas.camera.set_transform(view.transform, viewport);
+            }
+            None => canvas.fit_to_content(),
+        }
+        Ok(canvas)
+    }
 
     /// Set the viewport size.
     pub fn set_viewport_size(&mut self, width: f64, height: f64) {
@@ -632,10 +1574,13 @@ impl Canvas {
         self.widgets.clear_selection();
     }
 
-    /// Select all shapes.
+    /// Select all unlocked shapes.
     pub fn select_all(&mut self) {
         self.clear_selection();
         for &id in &self.document.z_order {
+            if self.document.get_shape(id).is_none_or(Shape::is_locked) {
+                continue;
+            }
             self.selection.push(id);
             self.widgets.add_to_selection(id);
         }
@@ -676,16 +1621,74 @@ impl Canvas {
         self.tool_manager.set_tool(tool);
     }
 
+    /// Zoom the view by `factor` around the cursor, as for the scroll wheel.
+    ///
+    /// The world point under `cursor` (in screen coordinates) stays under it,
+    /// and the zoom is clamped to the camera's `min_zoom` and `max_zoom`. A
+    /// cursor outside `viewport` is moved to its nearest edge, so zooming
+    /// never pivots around an off-screen point. [`InputState::scroll_delta`]
+    /// is a distance, not a factor: callers pick one per scroll step from
+    /// its sign, and since factors multiply, steps can be applied one by one
+    /// or combined into one call.
+    ///
+    /// [`InputState::scroll_delta`]: crate::input::InputState::scroll_delta
+    pub fn zoom_at(&mut self, cursor: Point, factor: f64, viewport: kurbo::Size) {
+        let cursor = Point::new(
+            cursor.x.clamp(0.0, viewport.width.max(0.0)),
+            cursor.y.clamp(0.0, viewport.height.max(0.0)),
+        );
+        self.camera.zoom_at(cursor, factor);
+    }
+
     /// Fit the view to show all shapes.
     pub fn fit_to_content(&mut self) {
         if let Some(bounds) = self.document.bounds() {
-            self.camera.fit_to_bounds(bounds, self.viewport_size, 50.0);
+            self.camera
+                .fit_to_bounds(bounds, self.viewport_size, FIT_MARGIN);
         }
     }
 
-    /// Delete selected shapes.
+    /// View transform that frames the whole document in `viewport`.
+    ///
+    /// The content is centered with [`FIT_MARGIN`] screen pixels around it,
+    /// and the zoom is clamped to the camera's limits. An empty document
+    /// gives the identity transform.
+    pub fn compute_fit_transform(&self, viewport: kurbo::Size) -> Affine {
+        self.fit_transform(self.document.bounds(), viewport)
+    }
+
+    /// View transform that frames the given shapes in `viewport`.
+    ///
+    /// Like [`Canvas::compute_fit_transform`], but only for `ids`. Unknown IDs
+    /// are ignored; if none are found the identity transform is returned.
+    pub fn compute_fit_selection_transform(
+        &self,
+        ids: &[ShapeId],
+        viewport: kurbo::Size,
+    ) -> Affine {
+        let bounds = ids
+            .iter()
+            .filter_map(|&id| self.document.get_shape(id))
+            .map(|shape| shape.bounds())
+            .reduce(|a, b| a.union(b));
+        self.fit_transform(bounds, viewport)
+    }
+
+    fn fit_transform(&self, bounds: Option<Rect>, viewport: kurbo::Size) -> Affine {
+        let Some(bounds) = bounds else {
+            return Affine::IDENTITY;
+        };
+        let mut camera = self.camera.clone();
+        camera.fit_to_bounds(bounds, viewport, FIT_MARGIN);
+        camera.transform()
+    }
+
+    /// Delete selected shapes. Locked shapes are left in place.
     pub fn delete_selected(&mut self) {
         for id in self.selection.drain(..).collect::<Vec<_>>() {
+            if self.document.get_shape(id).is_some_and(Shape::is_locked) {
+                continue;
+            }
             self.document.remove_shape(id);
             self.widgets.remove(id);
         }
@@ -693,79 +1696,20 @@ impl Canvas {
 
     /// Flip selected shapes horizontally (mirror around vertical axis).
     pub fn flip_selected_horizontal(&mut self) {
-        if self.selection.is_empty() {
-            return;
-        }
-
-        // Get combined bounds of all selected shapes
-        let mut combined_bounds: Option<kurbo::Rect> = None;
-        for &id in &self.selection {
-            if let Some(shape) = self.document.get_shape(id) {
-                let bounds = shape.bounds();
-                combined_bounds = Some(match combined_bounds {
-                    Some(cb) => cb.union(bounds),
-                    None => bounds,
-                });
-            }
-        }
-
-        let Some(bounds) = combined_bounds else {
-            return;
-        };
-        let center_x = bounds.center().x;
-
-        // Flip each selected shape around the combined center
-        for &id in &self.selection {
-            if let Some(shape) = self.document.get_shape_mut(id) {
-                // Create flip transform: translate to origin, scale -1 on x, translate back
-                let flip = kurbo::Affine::translate(kurbo::Vec2::new(center_x, 0.0))
-                    * kurbo::Affine::scale_non_uniform(-1.0, 1.0)
-                    * kurbo::Affine::translate(kurbo::Vec2::new(-center_x, 0.0));
-                shape.transform(flip);
-            }
-        }
+        self.document.flip_horizontal(&self.selection);
     }
 
     /// Flip selected shapes vertically (mirror around horizontal axis).
     pub fn flip_selected_vertical(&mut self) {
-        if self.selection.is_empty() {
-            return;
-        }
-
-        // Get combined bounds of all selected shapes
-        let mut combined_bounds: Option<kurbo::Rect> = None;
-        for &id in &self.selection {
-            if let Some(shape) = self.document.get_shape(id) {
-                let bounds = shape.bounds();
-                combined_bounds = Some(match combined_bounds {
-                    Some(cb) => cb.union(bounds),
-                    None => bounds,
-                });
-            }
-        }
-
-        let Some(bounds) = combined_bounds else {
-            return;
-        };
-        let center_y = bounds.center().y;
-
-        // Flip each selected shape around the combined center
-        for &id in &self.selection {
-            if let Some(shape) = self.document.get_shape_mut(id) {
-                // Create flip transform: translate to origin, scale -1 on y, translate back
-                let flip = kurbo::Affine::translate(kurbo::Vec2::new(0.0, center_y))
-                    * kurbo::Affine::scale_non_uniform(1.0, -1.0)
-                    * kurbo::Affine::translate(kurbo::Vec2::new(0.0, -center_y));
-                shape.transform(flip);
-            }
-        }
+        self.document.flip_vertical(&self.selection);
     }
 
-    /// Remove a shape from the canvas.
+    /// Remove a shape from the canvas. Locked shapes are left in place.
     pub fn remove_shape(&mut self, id: ShapeId) {
-        self.selection.retain(|&s| s != id);
-        self.document.remove_shape(id);
-        self.widgets.remove(id);
+        if self.document.remove_shape(id).is_some() {
+            self.selection.retain(|&s| s != id);
+            self.widgets.remove(id);
+        }
     }
 
     /// Group the currently selected shapes.
@@ -843,88 +1787,763 @@ impl Canvas {
     }
 }
 
-/// Parse Excalidraw color string to SerializableColor.
-fn parse_excalidraw_color(color: &str) -> crate::shapes::SerializableColor {
-    use crate::shapes::SerializableColor;
-
-    if color == "transparent" {
-        return SerializableColor::transparent();
+/// IDs of `shape` and its group children, in the order
+/// [`Shape::regenerate_id_with`] replaces them.
+fn collect_ids_in_regeneration_order(shape: &Shape, ids: &mut Vec<ShapeId>) {
+    if let Shape::Group(group) = shape {
+        for child in group.children() {
+            collect_ids_in_regeneration_order(child, ids);
+        }
     }
+    ids.push(shape.id());
+}
 
-    // Handle hex colors (#rgb, #rrggbb, #rrggbbaa)
-    if let Some(hex) = color.strip_prefix('#') {
-        let hex = hex.trim();
-        match hex.len() {
-            3 => {
-                // #rgb -> #rrggbb
-                let r = u8::from_str_radix(&hex[0..1], 16).unwrap_or(0) * 17;
-                let g = u8::from_str_radix(&hex[1..2], 16).unwrap_or(0) * 17;
-                let b = u8::from_str_radix(&hex[2..3], 16).unwrap_or(0) * 17;
-                return SerializableColor::new(r, g, b, 255);
+/// Rebind the ends of arrows in `shape`, including inside groups, through
+/// `new_ids`, freeing ends bound to elements it doesn't map.
+fn remap_bindings(shape: &mut Shape, new_ids: &HashMap<ShapeId, ShapeId>) {
+    match shape {
+        Shape::Arrow(arrow) => {
+            arrow.start_binding = arrow.start_binding.and_then(|id| new_ids.get(&id).copied());
+            if arrow.start_binding.is_none() {
+                arrow.start_port = None;
             }
-            6 => {
-                let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
-                let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
-                let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
-                return SerializableColor::new(r, g, b, 255);
+            arrow.end_binding = arrow.end_binding.and_then(|id| new_ids.get(&id).copied());
+            if arrow.end_binding.is_none() {
+                arrow.end_port = None;
             }
-            8 => {
-                let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
-                let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
-                let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
-                let a = u8::from_str_radix(&hex[6..8], 16).unwrap_or(255);
-                return SerializableColor::new(r, g, b, a);
+        }
+        Shape::Group(group) => {
+            for child in &mut group.children {
+                remap_bindings(child, new_ids);
             }
-            _ => {}
         }
+        _ => {}
     }
-
-    // Default to black
-    SerializableColor::black()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::shapes::{Rectangle, ShapeTrait};
-
-    #[test]
-    fn test_document_creation() {
-        let doc = CanvasDocument::new();
-        assert!(doc.is_empty());
+/// Free the ends of arrows in `shape`, including inside groups, that are
+/// bound to elements not in `known`.
+fn unbind_missing(shape: &mut Shape, known: &HashSet<ShapeId>, warnings: &mut Vec<LoadWarning>) {
+    match shape {
+        Shape::Arrow(arrow) => {
+            let id = arrow.id();
+            if let Some(target) = arrow.start_binding.filter(|t| !known.contains(t)) {
+                arrow.start_binding = None;
+                arrow.start_port = None;
+                warnings.push(LoadWarning::DanglingBinding { arrow: id, target });
+            }
+            if let Some(target) = arrow.end_binding.filter(|t| !known.contains(t)) {
+                arrow.end_binding = None;
+                arrow.end_port = None;
+                warnings.push(LoadWarning::DanglingBinding { arrow: id, target });
+            }
+        }
+        Shape::Group(group) => {
+            for child in &mut group.children {
+                unbind_missing(child, known, warnings);
+            }
+        }
+        _ => {}
     }
+}
 
-    #[test]
-    fn test_add_shape() {
-        let mut doc = CanvasDocument::new();
-        let rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 100.0);
-        let id = rect.id();
+/// Result of [`import_excalidraw`].
+#[derive(Debug)]
+pub struct ExcalidrawImport {
+    /// The imported document.
+    pub document: CanvasDocument,
+    /// Elements that were skipped or only partially imported.
+    pub warnings: Vec<String>,
+}
 
-        doc.add_shape(Shape::Rectangle(rect));
-        assert_eq!(doc.len(), 1);
-        assert!(doc.get_shape(id).is_some());
-    }
+/// Import an Excalidraw scene.
+///
+/// Rectangles, diamonds, ellipses, lines, arrows, freehand strokes and text
+/// are converted to shapes. Arrow ends bound to an imported element are moved
+/// onto its boundary and stay attached to it; bindings to anything else keep
+/// the free endpoint. Other
+/// element types are skipped and reported in the warnings.
+pub fn import_excalidraw(json: &str) -> Result<ExcalidrawImport, String> {
+    use crate::shapes::{
+        Arrow, Ellipse, FillPattern, Freehand, Line, PathStyle, Rectangle, ShapeStyle, Sloppiness,
+        Text,
+    };
+
+    let data: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| format!("Invalid JSON: {}", e))?;
+
+    let elements = data
+        .get("elements")
+        .and_then(|e| e.as_array())
+        .ok_or("Missing 'elements' array")?;
+
+    let mut doc = CanvasDocument::new();
+    let mut warnings = Vec::new();
+    // Excalidraw element ID -> imported shape ID, for resolving arrow bindings
+    let mut imported: HashMap<String, ShapeId> = HashMap::new();
+    // Arrow shape ID with its start/end binding targets
+    let mut bindings: Vec<(ShapeId, Option<String>, Option<String>)> = Vec::new();
+
+    for elem in elements {
+        // Skip deleted elements
+        if elem
+            .get("isDeleted")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+        {
+            continue;
+        }
 
-    #[test]
-    fn test_remove_shape() {
-        let mut doc = CanvasDocument::new();
-        let rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 100.0);
-        let id = rect.id();
+        let elem_type = elem.get("type").and_then(|t| t.as_str()).unwrap_or("");
+        let x = elem.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        let y = elem.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0);
 
-        doc.add_shape(Shape::Rectangle(rect));
-        let removed = doc.remove_shape(id);
+        // Parse colors
+        let stroke_color = parse_excalidraw_color(
+            elem.get("strokeColor")
+                .and_then(|v| v.as_str())
+                .unwrap_or("#000000"),
+        );
+        let bg_color = elem
+            .get("backgroundColor")
+            .and_then(|v| v.as_str())
+            .unwrap_or("transparent");
+        let fill_color = if bg_color == "transparent" {
+            None
+        } else {
+            Some(parse_excalidraw_color(bg_color))
+        };
 
-        assert!(removed.is_some());
-        assert!(doc.is_empty());
-    }
+        let stroke_width = elem
+            .get("strokeWidth")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(2.0);
+        let roughness = elem.get("roughness").and_then(|v| v.as_i64()).unwrap_or(1);
+        let sloppiness = match roughness {
+            0 => Sloppiness::Architect,
+            1 => Sloppiness::Artist,
+            _ => Sloppiness::Cartoonist,
+        };
 
-    #[test]
-    fn test_z_order() {
-        let mut doc = CanvasDocument::new();
-        let rect1 = Rectangle::new(Point::new(0.0, 0.0), 100.0, 100.0);
-        let rect2 = Rectangle::new(Point::new(50.0, 50.0), 100.0, 100.0);
-        let id1 = rect1.id();
-        let id2 = rect2.id();
+        let style = ShapeStyle {
+            stroke_color,
+            stroke_width,
+            fill_color,
+            fill_pattern: FillPattern::default(),
+            fill: None,
+            shadow: None,
+            sloppiness,
+            seed: elem.get("seed").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+            // Excalidraw stores opacity as a percentage
+            opacity: elem
+                .get("opacity")
+                .and_then(|v| v.as_f64())
+                .map_or(1.0, |o| (o / 100.0).clamp(0.0, 1.0)),
+        };
+
+        let shape: Option<Shape> = match elem_type {
+            "rectangle" | "diamond" => {
+                let width = elem.get("width").and_then(|v| v.as_f64()).unwrap_or(100.0);
+                let height = elem.get("height").and_then(|v| v.as_f64()).unwrap_or(100.0);
+                let mut rect = Rectangle::new(Point::new(x, y), width, height);
+                rect.style = style;
+                // Handle roundness
+                if elem.get("roundness").is_some_and(|r| !r.is_null()) {
+                    rect.corner_radius = Rectangle::DEFAULT_ADAPTIVE_RADIUS
+                        .min(width / 4.0)
+                        .min(height / 4.0);
+                }
+                // Diamond is rendered as rotated rectangle (TODO: proper diamond shape)
+                Some(Shape::Rectangle(rect))
+            }
+            "ellipse" => {
+                let width = elem.get("width").and_then(|v| v.as_f64()).unwrap_or(100.0);
+                let height = elem.get("height").and_then(|v| v.as_f64()).unwrap_or(100.0);
+                let center = Point::new(x + width / 2.0, y + height / 2.0);
+                let mut ellipse = Ellipse::new(center, width / 2.0, height / 2.0);
+                ellipse.style = style;
+                Some(Shape::Ellipse(ellipse))
+            }
+            "freedraw" => {
+                let points = elem.get("points").and_then(|p| p.as_array());
+                if let Some(pts) = points {
+                    let freehand_points: Vec<Point> = pts
+                        .iter()
+                        .filter_map(|p| p.as_array())
+                        .filter_map(|arr| {
+                            let px = arr.first().and_then(|v| v.as_f64())?;
+                            let py = arr.get(1).and_then(|v| v.as_f64())?;
+                            Some(Point::new(x + px, y + py))
+                        })
+                        .collect();
+                    if !freehand_points.is_empty() {
+                        let mut freehand = Freehand::from_points(freehand_points);
+                        freehand.style = style;
+                        Some(Shape::Freehand(freehand))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            }
+            "line" => {
+                let points = elem.get("points").and_then(|p| p.as_array());
+                if let Some(pts) = points {
+                    let line_points: Vec<Point> = pts
+                        .iter()
+                        .filter_map(|p| p.as_array())
+                        .filter_map(|arr| {
+                            let px = arr.first().and_then(|v| v.as_f64())?;
+                            let py = arr.get(1).and_then(|v| v.as_f64())?;
+                            Some(Point::new(x + px, y + py))
+                        })
+                        .collect();
+                    if line_points.len() >= 2 {
+                        let path_style =
+                            if elem.get("roundness").map(|r| !r.is_null()).unwrap_or(false) {
+                                PathStyle::Flowing
+                            } else {
+                                PathStyle::Direct
+                            };
+                        let mut line = Line::from_points(line_points, path_style);
+                        line.style = style;
+                        Some(Shape::Line(line))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            }
+            "arrow" => {
+                let points = elem.get("points").and_then(|p| p.as_array());
+                if let Some(pts) = points {
+                    let arrow_points: Vec<Point> = pts
+                        .iter()
+                        .filter_map(|p| p.as_array())
+                        .filter_map(|arr| {
+                            let px = arr.first().and_then(|v| v.as_f64())?;
+                            let py = arr.get(1).and_then(|v| v.as_f64())?;
+                            Some(Point::new(x + px, y + py))
+                        })
+                        .collect();
+                    if arrow_points.len() >= 2 {
+                        let elbowed = elem
+                            .get("elbowed")
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(false);
+                        let has_roundness =
+                            elem.get("roundness").map(|r| !r.is_null()).unwrap_or(false);
+                        let path_style = if elbowed {
+                            PathStyle::Angular
+                        } else if has_roundness {
+                            PathStyle::Flowing
+                        } else {
+                            PathStyle::Direct
+                        };
+                        let mut arrow = Arrow::from_points(arrow_points, path_style);
+                        arrow.style = style;
+                        let binding_target = |key: &str| {
+                            elem.get(key)
+                                .and_then(|b| b.get("elementId"))
+                                .and_then(|v| v.as_str())
+                                .map(str::to_string)
+                        };
+                        let start = binding_target("startBinding");
+                        let end = binding_target("endBinding");
+                        if start.is_some() || end.is_some() {
+                            bindings.push((arrow.id(), start, end));
+                        }
+                        Some(Shape::Arrow(arrow))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            }
+            "text" => {
+                let content = elem
+                    .get("text")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string();
+                let font_size = elem
+                    .get("fontSize")
+                    .and_then(|v| v.as_f64())
+                    .unwrap_or(20.0);
+                let mut text = Text::new(Point::new(x, y), content);
+                text.font_size = font_size;
+                text.style = style;
+                Some(Shape::Text(text))
+            }
+            _ => {
+                warnings.push(format!("Skipped unsupported element type '{}'", elem_type));
+                continue;
+            }
+        };
+
+        match shape {
+            Some(s) => {
+                if let Some(elem_id) = elem.get("id").and_then(|v| v.as_str()) {
+                    imported.insert(elem_id.to_string(), s.id());
+                }
+                doc.add_shape(s);
+            }
+            None => warnings.push(format!("Skipped malformed '{}' element", elem_type)),
+        }
+    }
+
+    // Attach bound arrow ends to the boundary of their imported targets
+    for (arrow_id, start, end) in bindings {
+        let target_bounds = |target: &Option<String>, warnings: &mut Vec<String>| {
+            let target = target.as_ref()?;
+            let bounds = imported
+                .get(target)
+                .and_then(|id| doc.get_shape(*id))
+                .map(|s| (s.id(), s.bounds()));
+            if bounds.is_none() {
+                warnings.push(format!(
+                    "Arrow bound to missing element '{}'; keeping free endpoint",
+                    target
+                ));
+            }
+            bounds
+        };
+        let start_bounds = target_bounds(&start, &mut warnings);
+        let end_bounds = target_bounds(&end, &mut warnings);
+        if let Some(Shape::Arrow(arrow)) = doc.get_shape_mut(arrow_id) {
+            if let Some((target, bounds)) = start_bounds {
+                arrow.start = nearest_on_boundary(bounds, arrow.start);
+                arrow.start_binding = Some(target);
+            }
+            if let Some((target, bounds)) = end_bounds {
+                arrow.end = nearest_on_boundary(bounds, arrow.end);
+                arrow.end_binding = Some(target);
+            }
+        }
+    }
+
+    Ok(ExcalidrawImport {
+        document: doc,
+        warnings,
+    })
+}
+
+/// Export a document as an Excalidraw scene.
+///
+/// Per-element seeds and nonces are derived from shape IDs, so exporting the
+/// same document twice gives the same output. Group members are exported
+/// individually with the group ID in `groupIds`. Sticky notes become a
+/// rectangle with a bound, centered text element. Arrow bindings are kept on
+/// both sides, as Excalidraw expects: the arrow's `startBinding` and
+/// `endBinding` name the target with a focus of 0 (aimed at its center) and
+/// the endpoint's distance from its edge as the gap, and the target lists
+/// the arrow in `boundElements`. Images and math have no Excalidraw
+/// equivalent here (images would need the separate `files` table) and are
+/// omitted, along with bindings to them.
+pub fn export_excalidraw(doc: &CanvasDocument) -> String {
+    let mut bound_arrows = HashMap::new();
+    for shape in doc.shapes_ordered() {
+        collect_bound_arrows(doc, shape, &mut bound_arrows);
+    }
+    let mut elements = Vec::new();
+    for shape in doc.shapes_ordered() {
+        export_excalidraw_shape(doc, shape, &[], &bound_arrows, &mut elements);
+    }
+    let scene = serde_json::json!({
+        "type": "excalidraw",
+        "version": 2,
+        "source": "drafftink",
+        "elements": elements,
+        "appState": {
+            "viewBackgroundColor": "#ffffff",
+            "gridSize": null,
+        },
+        "files": {},
+    });
+    serde_json::to_string_pretty(&scene).unwrap_or_default()
+}
+
+/// Whether `shape` has an Excalidraw element of its own.
+fn exports_to_excalidraw(shape: &Shape) -> bool {
+    !matches!(shape, Shape::Image(_) | Shape::Math(_) | Shape::Group(_))
+}
+
+/// Map each exported binding target inside `shape` to the arrows bound to
+/// it, in document order.
+fn collect_bound_arrows(
+    doc: &CanvasDocument,
+    shape: &Shape,
+    bound_arrows: &mut HashMap<ShapeId, Vec<ShapeId>>,
+) {
+    match shape {
+        Shape::Group(group) => {
+            for child in group.children() {
+                collect_bound_arrows(doc, child, bound_arrows);
+            }
+        }
+        Shape::Arrow(arrow) => {
+            for target in [arrow.start_binding, arrow.end_binding]
+                .into_iter()
+                .flatten()
+            {
+                if doc.find_element(target).is_some_and(exports_to_excalidraw) {
+                    let arrows = bound_arrows.entry(target).or_default();
+                    if !arrows.contains(&arrow.id()) {
+                        arrows.push(arrow.id());
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Append the Excalidraw elements for one shape.
+fn export_excalidraw_shape(
+    doc: &CanvasDocument,
+    shape: &Shape,
+    group_ids: &[String],
+    bound_arrows: &HashMap<ShapeId, Vec<ShapeId>>,
+    out: &mut Vec<serde_json::Value>,
+) {
+    use crate::shapes::{ArrowheadStyle, PathStyle, StrokeStyle};
+    use serde_json::{Value, json};
+
+    fn hex(color: crate::shapes::SerializableColor) -> String {
+        if color.a == 255 {
+            format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+        } else {
+            format!(
+                "#{:02x}{:02x}{:02x}{:02x}",
+                color.r, color.g, color.b, color.a
+            )
+        }
+    }
+    fn arrowhead(style: ArrowheadStyle) -> Value {
+        match style {
+            ArrowheadStyle::None => Value::Null,
+            ArrowheadStyle::Triangle => "triangle".into(),
+            ArrowheadStyle::OpenV => "arrow".into(),
+            ArrowheadStyle::Diamond => "diamond".into(),
+            ArrowheadStyle::Dot => "dot".into(),
+        }
+    }
+    fn stroke_style_name(style: StrokeStyle) -> &'static str {
+        match style {
+            StrokeStyle::Solid => "solid",
+            StrokeStyle::Dashed { .. } => "dashed",
+            StrokeStyle::Dotted => "dotted",
+        }
+    }
+    /// Origin, relative points and extent for a linear element.
+    fn linear(points: &[Point]) -> (Point, Vec<[f64; 2]>, Rect) {
+        let origin = points.first().copied().unwrap_or(Point::ZERO);
+        let relative: Vec<[f64; 2]> = points
+            .iter()
+            .map(|p| [p.x - origin.x, p.y - origin.y])
+            .collect();
+        let extent = points
+            .iter()
+            .fold(Rect::from_points(origin, origin), |r, p| r.union_pt(*p));
+        (origin, relative, extent)
+    }
+    // Binding of an arrow end at `point` to `target`, if it is exported
+    let binding = |target: Option<ShapeId>, point: Point| -> Value {
+        let Some(target) = target
+            .and_then(|id| doc.find_element(id))
+            .filter(|target| exports_to_excalidraw(target))
+        else {
+            return Value::Null;
+        };
+        let gap = nearest_on_boundary(target.bounds(), point).distance(point);
+        json!({"elementId": target.id().to_string(), "focus": 0.0, "gap": gap})
+    };
+
+    if let Shape::Group(group) = shape {
+        let mut ids = group_ids.to_vec();
+        ids.insert(0, group.id().to_string());
+        for child in group.children() {
+            export_excalidraw_shape(doc, child, &ids, bound_arrows, out);
+        }
+        return;
+    }
+
+    let id = shape.id();
+    let style = shape.style();
+    let bits = id.as_u128();
+    let mut elem = json!({
+        "id": id.to_string(),
+        "angle": shape.rotation(),
+        "strokeColor": hex(style.stroke_color),
+        "backgroundColor": style.fill_color.map(hex).unwrap_or_else(|| "transparent".to_string()),
+        "fillStyle": "solid",
+        "strokeWidth": style.stroke_width,
+        "strokeStyle": "solid",
+        "roughness": (style.sloppiness as i64).min(2),
+        "opacity": (style.opacity * 100.0).round(),
+        "groupIds": group_ids,
+        "frameId": null,
+        "roundness": null,
+        "seed": (bits as u32) & 0x7fff_ffff,
+        "version": 1,
+        "versionNonce": ((bits >> 32) as u32) & 0x7fff_ffff,
+        "isDeleted": false,
+        "boundElements": bound_arrows.get(&id).map(|arrows| {
+            arrows
+                .iter()
+                .map(|arrow| json!({"id": arrow.to_string(), "type": "arrow"}))
+                .collect::<Vec<_>>()
+        }),
+        "updated": 1,
+        "link": null,
+        "locked": shape.is_locked(),
+    });
+    let fields = match shape {
+        Shape::Rectangle(rect) => json!({
+            "type": "rectangle",
+            "x": rect.position.x,
+            "y": rect.position.y,
+            "width": rect.width,
+            "height": rect.height,
+            "roundness": if rect.corner_radius > 0.0 { json!({"type": 3}) } else { Value::Null },
+        }),
+        Shape::Ellipse(ellipse) => json!({
+            "type": "ellipse",
+            "x": ellipse.center.x - ellipse.radius_x,
+            "y": ellipse.center.y - ellipse.radius_y,
+            "width": ellipse.radius_x * 2.0,
+            "height": ellipse.radius_y * 2.0,
+        }),
+        Shape::Line(line) => {
+            let (origin, points, extent) = linear(&line.all_points());
+            json!({
+                "type": "line",
+                "x": origin.x,
+                "y": origin.y,
+                "width": extent.width(),
+                "height": extent.height(),
+                "points": points,
+                "strokeStyle": stroke_style_name(line.stroke_style),
+                "roundness": if line.path_style == PathStyle::Flowing { json!({"type": 2}) } else { Value::Null },
+                "startBinding": null,
+                "endBinding": null,
+                "startArrowhead": null,
+                "endArrowhead": null,
+            })
+        }
+        Shape::Arrow(arrow) => {
+            let (origin, points, extent) = linear(&arrow.all_points());
+            json!({
+                "type": "arrow",
+                "x": origin.x,
+                "y": origin.y,
+                "width": extent.width(),
+                "height": extent.height(),
+                "points": points,
+                "strokeStyle": stroke_style_name(arrow.stroke_style),
+                "roundness": if arrow.path_style == PathStyle::Flowing { json!({"type": 2}) } else { Value::Null },
+                "elbowed": arrow.path_style == PathStyle::Angular,
+                "startBinding": binding(arrow.start_binding, arrow.start),
+                "endBinding": binding(arrow.end_binding, arrow.end),
+                "startArrowhead": arrowhead(arrow.start_head),
+                "endArrowhead": arrowhead(arrow.end_head),
+            })
+        }
+        Shape::Freehand(freehand) => {
+            let (origin, points, extent) = linear(&freehand.points);
+            json!({
+                "type": "freedraw",
+                "x": origin.x,
+                "y": origin.y,
+                "width": extent.width(),
+                "height": extent.height(),
+                "points": points,
+                "pressures": freehand.pressures,
+                "simulatePressure": !freehand.has_pressure(),
+            })
+        }
+        Shape::Text(text) => {
+            let bounds = text.bounds();
+            json!({
+                "type": "text",
+                "x": text.position.x,
+                "y": text.position.y,
+                "width": bounds.width(),
+                "height": bounds.height(),
+                "text": text.content,
+                "originalText": text.content,
+                "fontSize": text.font_size,
+                "fontFamily": 1,
+                "textAlign": "left",
+                "verticalAlign": "top",
+                "containerId": null,
+                "lineHeight": 1.25,
+                "autoResize": true,
+            })
+        }
+        Shape::StickyNote(note) => {
+            let background = &note.background;
+            json!({
+                "type": "rectangle",
+                "x": background.position.x,
+                "y": background.position.y,
+                "width": background.width,
+                "height": background.height,
+                "roundness": if background.corner_radius > 0.0 { json!({"type": 3}) } else { Value::Null },
+            })
+        }
+        Shape::Image(_) | Shape::Math(_) | Shape::Group(_) => return,
+    };
+
+    if let (Some(elem), Value::Object(fields)) = (elem.as_object_mut(), fields) {
+        elem.extend(fields);
+    }
+    let Shape::StickyNote(note) = shape else {
+        out.push(elem);
+        return;
+    };
+
+    // The note's text shares its ID, so the bound text element gets its own
+    let text_id = Uuid::from_u128(bits.rotate_left(64)).to_string();
+    let mut text = Vec::new();
+    export_excalidraw_shape(
+        doc,
+        &Shape::Text(note.text.clone()),
+        group_ids,
+        &HashMap::new(),
+        &mut text,
+    );
+    let Some(mut text) = text.pop() else {
+        out.push(elem);
+        return;
+    };
+    let mut bound = elem["boundElements"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    bound.push(json!({"id": text_id, "type": "text"}));
+    elem["boundElements"] = bound.into();
+    text["id"] = text_id.into();
+    text["seed"] = elem["versionNonce"].clone();
+    text["versionNonce"] = elem["seed"].clone();
+    text["containerId"] = id.to_string().into();
+    text["textAlign"] = "center".into();
+    text["verticalAlign"] = "middle".into();
+    text["locked"] = shape.is_locked().into();
+    out.push(elem);
+    out.push(text);
+}
+
+/// Random low half for generated element IDs.
+fn random_id_salt() -> u64 {
+    Uuid::new_v4().as_u64_pair().1
+}
+
+/// Closest point to `p` on the boundary of `rect`.
+fn nearest_on_boundary(rect: Rect, p: Point) -> Point {
+    let clamped = Point::new(p.x.clamp(rect.x0, rect.x1), p.y.clamp(rect.y0, rect.y1));
+    if clamped != p {
+        return clamped;
+    }
+    // Inside: push out through the nearest edge
+    let edges = [
+        (p.x - rect.x0, Point::new(rect.x0, p.y)),
+        (rect.x1 - p.x, Point::new(rect.x1, p.y)),
+        (p.y - rect.y0, Point::new(p.x, rect.y0)),
+        (rect.y1 - p.y, Point::new(p.x, rect.y1)),
+    ];
+    edges
+        .into_iter()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, point)| point)
+        .unwrap_or(p)
+}
+
+/// Parse Excalidraw color string to SerializableColor.
+fn parse_excalidraw_color(color: &str) -> crate::shapes::SerializableColor {
+    use crate::shapes::SerializableColor;
+
+    if color == "transparent" {
+        return SerializableColor::transparent();
+    }
+
+    // Handle hex colors (#rgb, #rrggbb, #rrggbbaa)
+    if let Some(hex) = color.strip_prefix('#') {
+        let hex = hex.trim();
+        match hex.len() {
+            3 => {
+                // #rgb -> #rrggbb
+                let r = u8::from_str_radix(&hex[0..1], 16).unwrap_or(0) * 17;
+                let g = u8::from_str_radix(&hex[1..2], 16).unwrap_or(0) * 17;
+                let b = u8::from_str_radix(&hex[2..3], 16).unwrap_or(0) * 17;
+                return SerializableColor::new(r, g, b, 255);
+            }
+            6 => {
+                let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
+                let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
+                let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
+                return SerializableColor::new(r, g, b, 255);
+            }
+            8 => {
+                let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
+                let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
+                let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
+                let a = u8::from_str_radix(&hex[6..8], 16).unwrap_or(255);
+                return SerializableColor::new(r, g, b, a);
+            }
+            _ => {}
+        }
+    }
+
+    // Default to black
+    SerializableColor::black()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::{Arrow, Rectangle, ShapeTrait};
+
+    #[test]
+    fn test_document_creation() {
+        let doc = CanvasDocument::new();
+        assert!(doc.is_empty());
+    }
+
+    #[test]
+    fn test_add_shape() {
+        let mut doc = CanvasDocument::new();
+        let rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 100.0);
+        let id = rect.id();
+
+        doc.add_shape(Shape::Rectangle(rect));
+        assert_eq!(doc.len(), 1);
+        assert!(doc.get_shape(id).is_some());
+    }
+
+    #[test]
+    fn test_remove_shape() {
+        let mut doc = CanvasDocument::new();
+        let rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 100.0);
+        let id = rect.id();
+
+        doc.add_shape(Shape::Rectangle(rect));
+        let removed = doc.remove_shape(id);
+
+        assert!(removed.is_some());
+        assert!(doc.is_empty());
+    }
+
+    #[test]
+    fn test_z_order() {
+        let mut doc = CanvasDocument::new();
+        let rect1 = Rectangle::new(Point::new(0.0, 0.0), 100.0, 100.0);
+        let rect2 = Rectangle::new(Point::new(50.0, 50.0), 100.0, 100.0);
+        let id1 = rect1.id();
+        let id2 = rect2.id();
 
         doc.add_shape(Shape::Rectangle(rect1));
         doc.add_shape(Shape::Rectangle(rect2));
@@ -1068,4 +2687,1296 @@ mod tests {
         assert!(!doc.can_redo());
         assert!(!doc.redo());
     }
+
+    #[test]
+    fn test_paste_assigns_fresh_ids() {
+        let mut doc = CanvasDocument::new();
+        let rect1 = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 50.0, 50.0));
+        let rect2 = Shape::Rectangle(Rectangle::new(Point::new(100.0, 0.0), 50.0, 50.0));
+        let group = Shape::Group(Group::new(vec![rect1, rect2]));
+        let arrow = Shape::Arrow(Arrow::new(Point::new(0.0, 100.0), Point::new(50.0, 100.0)));
+        let original: Vec<ShapeId> = vec![group.id(), arrow.id()];
+        let mut original_children = Vec::new();
+        if let Shape::Group(g) = &group {
+            original_children = g.all_shape_ids();
+        }
+        doc.add_shape(group);
+        doc.add_shape(arrow);
+
+        let payload = ClipboardPayload::from_json(&doc.copy(&original).to_json().unwrap()).unwrap();
+        let pasted = doc.paste(&payload, Vec2::ZERO);
+
+        assert_eq!(pasted.len(), 2);
+        assert_eq!(doc.len(), 4);
+        for id in &pasted {
+            assert!(!original.contains(id));
+        }
+        let Some(Shape::Group(group)) = doc.get_shape(pasted[0]) else {
+            panic!("expected a pasted group");
+        };
+        for id in group.all_shape_ids() {
+            assert!(!original_children.contains(&id));
+        }
+    }
+
+    #[test]
+    fn test_paste_rebinds_arrows_to_pasted_shapes() {
+        let mut doc = CanvasDocument::new();
+        let a = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 50.0, 50.0));
+        let b = Shape::Rectangle(Rectangle::new(Point::new(200.0, 0.0), 50.0, 50.0));
+        let (a_id, b_id) = (a.id(), b.id());
+        let mut arrow = Arrow::new(Point::new(50.0, 25.0), Point::new(200.0, 25.0));
+        arrow.start_binding = Some(a_id);
+        arrow.end_binding = Some(b_id);
+        arrow.end_port = Some(PortId::W);
+        let arrow_id = arrow.id();
+        doc.add_shape(a);
+        doc.add_shape(b);
+        doc.add_shape(Shape::Arrow(arrow));
+
+        // Both ends copied: the copy connects the copies
+        let payload = doc.copy(&[a_id, b_id, arrow_id]);
+        let pasted = doc.paste(&payload, Vec2::ZERO);
+        let Some(Shape::Arrow(copy)) = doc.get_shape(pasted[2]) else {
+            panic!("expected a pasted arrow");
+        };
+        assert_eq!(copy.start_binding, Some(pasted[0]));
+        assert_eq!(copy.end_binding, Some(pasted[1]));
+        assert_eq!(copy.end_port, Some(PortId::W));
+
+        // Only the start copied: the end is freed, here and in another document
+        let payload = doc.copy(&[a_id, arrow_id]);
+        let mut other = CanvasDocument::new();
+        for target in [&mut doc, &mut other] {
+            let pasted = target.paste(&payload, Vec2::ZERO);
+            let Some(Shape::Arrow(copy)) = target.get_shape(pasted[1]) else {
+                panic!("expected a pasted arrow");
+            };
+            assert_eq!(copy.start_binding, Some(pasted[0]));
+            assert_eq!(copy.end_binding, None);
+            assert_eq!(copy.end_port, None);
+        }
+
+        // Moving the original no longer drags the copies along
+        let before = doc.get_shape(pasted[2]).unwrap().bounds();
+        doc.get_shape_mut(b_id)
+            .unwrap()
+            .transform(Affine::translate(Vec2::new(100.0, 0.0)));
+        doc.reflow_connectors(&[b_id]);
+        assert_eq!(doc.get_shape(pasted[2]).unwrap().bounds(), before);
+    }
+
+    #[test]
+    fn test_paste_applies_offset() {
+        let mut doc = CanvasDocument::new();
+        let rect = Shape::Rectangle(Rectangle::new(Point::new(10.0, 10.0), 50.0, 50.0));
+        let arrow = Shape::Arrow(Arrow::new(Point::new(0.0, 100.0), Point::new(50.0, 100.0)));
+        let ids = [rect.id(), arrow.id()];
+        doc.add_shape(rect);
+        doc.add_shape(arrow);
+
+        let payload = doc.copy(&ids);
+        let pasted = doc.paste(&payload, Vec2::new(20.0, 30.0));
+
+        assert_eq!(
+            doc.get_shape(pasted[0]).unwrap().bounds().origin(),
+            Point::new(30.0, 40.0)
+        );
+        let Some(Shape::Arrow(arrow)) = doc.get_shape(pasted[1]) else {
+            panic!("expected a pasted arrow");
+        };
+        assert_eq!(arrow.start, Point::new(20.0, 130.0));
+        assert_eq!(arrow.end, Point::new(70.0, 130.0));
+    }
+
+    #[test]
+    fn test_group_moves_children_together() {
+        let mut doc = CanvasDocument::new();
+        let rect1 = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 50.0, 50.0));
+        let rect2 = Shape::Rectangle(Rectangle::new(Point::new(100.0, 0.0), 50.0, 50.0));
+        let ids = [rect1.id(), rect2.id()];
+        doc.add_shape(rect1);
+        doc.add_shape(rect2);
+
+        let group_id = doc.group_shapes(&ids).unwrap();
+        assert_eq!(doc.len(), 1);
+        // Hit-testing either child picks the group
+        assert_eq!(
+            doc.shapes_at_point(Point::new(125.0, 25.0), 0.0),
+            vec![group_id]
+        );
+
+        doc.get_shape_mut(group_id)
+            .unwrap()
+            .transform(Affine::translate((10.0, 20.0)));
+
+        let Some(Shape::Group(group)) = doc.get_shape(group_id) else {
+            panic!("expected a group");
+        };
+        let origins: Vec<Point> = group
+            .children()
+            .iter()
+            .map(|child| child.bounds().origin())
+            .collect();
+        assert_eq!(
+            origins,
+            vec![Point::new(10.0, 20.0), Point::new(110.0, 20.0)]
+        );
+    }
+
+    #[test]
+    fn test_group_json_roundtrip() {
+        let mut doc = CanvasDocument::new();
+        let rect1 = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 50.0, 50.0));
+        let rect2 = Shape::Rectangle(Rectangle::new(Point::new(100.0, 0.0), 50.0, 50.0));
+        let ids = [rect1.id(), rect2.id()];
+        doc.add_shape(rect1);
+        doc.add_shape(rect2);
+        let group_id = doc.group_shapes(&ids).unwrap();
+
+        let restored = CanvasDocument::from_json(&doc.to_json().unwrap()).unwrap();
+        let Some(Shape::Group(group)) = restored.get_shape(group_id) else {
+            panic!("expected a group");
+        };
+        let child_ids: Vec<ShapeId> = group.children().iter().map(|c| c.id()).collect();
+        assert_eq!(child_ids, ids);
+    }
+
+    #[test]
+    fn test_mutable_access_invalidates_cached_bounds() {
+        let fresh = |doc: &CanvasDocument, id| match doc.find_element(id) {
+            Some(Shape::Arrow(arrow)) => ShapeTrait::bounds(arrow),
+            _ => panic!("arrow missing"),
+        };
+        let mut doc = CanvasDocument::new();
+        let arrow = Shape::Arrow(Arrow::new(Point::ZERO, Point::new(100.0, 0.0)));
+        let id = arrow.id();
+        doc.add_shape(arrow);
+        let before = doc.bounds().unwrap();
+
+        if let Some(Shape::Arrow(arrow)) = doc.get_shape_mut(id) {
+            arrow.end = Point::new(300.0, 50.0);
+        }
+        assert_eq!(doc.bounds(), Some(fresh(&doc, id)));
+        assert!(doc.bounds().unwrap().x1 > before.x1);
+
+        // Editing a group member also refreshes the group's bounds
+        let (other_id, other) = rect_shape(0.0, 0.0, 10.0, 10.0);
+        doc.add_shape(other);
+        let group_id = doc.group_shapes(&[id, other_id]).unwrap();
+        let _ = doc.get_shape(group_id).unwrap().bounds();
+        if let Some(Shape::Arrow(arrow)) = doc.find_element_mut(id) {
+            arrow.start = Point::new(-50.0, -40.0);
+        }
+        let rect_bounds = doc.find_element(other_id).unwrap().bounds();
+        let expected = fresh(&doc, id).union(rect_bounds);
+        assert_eq!(doc.get_shape(group_id).unwrap().bounds(), expected);
+    }
+
+    #[test]
+    fn test_load_repairs_dangling_references() {
+        let mut doc = CanvasDocument::new();
+        let (rect_id, rect) = rect_shape(0.0, 0.0, 50.0, 50.0);
+        doc.add_shape(rect);
+        let missing = ShapeId::new_v4();
+        let mut arrow = Arrow::new(Point::new(50.0, 25.0), Point::new(200.0, 25.0));
+        arrow.start_binding = Some(rect_id);
+        arrow.end_binding = Some(missing);
+        arrow.end_port = Some(PortId::N);
+        let arrow_id = arrow.id();
+        doc.add_shape(Shape::Arrow(arrow));
+        doc.z_order.push(missing);
+
+        let (restored, warnings) =
+            CanvasDocument::from_json_with_warnings(&doc.to_json().unwrap()).unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                LoadWarning::MissingElement(missing),
+                LoadWarning::DanglingBinding {
+                    arrow: arrow_id,
+                    target: missing
+                },
+            ]
+        );
+        assert_eq!(restored.z_order, vec![rect_id, arrow_id]);
+        let Some(Shape::Arrow(arrow)) = restored.get_shape(arrow_id) else {
+            panic!("arrow missing");
+        };
+        assert_eq!(arrow.start_binding, Some(rect_id));
+        assert_eq!(arrow.end_binding, None);
+        assert_eq!(arrow.end_port, None);
+        assert_eq!(arrow.end, Point::new(200.0, 25.0));
+    }
+
+    #[test]
+    fn test_ungroup_nested_group_one_level() {
+        let mut doc = CanvasDocument::new();
+        let shapes: Vec<Shape> = (0..4)
+            .map(|i| {
+                Shape::Rectangle(Rectangle::new(
+                    Point::new(i as f64 * 100.0, 0.0),
+                    50.0,
+                    50.0,
+                ))
+            })
+            .collect();
+        let ids: Vec<ShapeId> = shapes.iter().map(|s| s.id()).collect();
+        for shape in shapes {
+            doc.add_shape(shape);
+        }
+
+        let inner = doc.group_shapes(&ids[1..3]).unwrap();
+        assert_eq!(doc.z_order, vec![ids[0], inner, ids[3]]);
+        // Unknown IDs are ignored and don't skew the z-order position
+        let outer = doc
+            .group_shapes(&[inner, ids[3], ShapeId::new_v4()])
+            .unwrap();
+        assert_eq!(doc.z_order, vec![ids[0], outer]);
+
+        // Ungrouping dissolves only the outer level; the inner group survives
+        let children = doc.ungroup_shape(outer).unwrap();
+        assert_eq!(children, vec![inner, ids[3]]);
+        assert_eq!(doc.z_order, vec![ids[0], inner, ids[3]]);
+        assert!(matches!(doc.get_shape(inner), Some(Shape::Group(_))));
+    }
+
+    fn rect_shape(x: f64, y: f64, w: f64, h: f64) -> (ShapeId, Shape) {
+        let rect = Rectangle::new(Point::new(x, y), w, h);
+        (rect.id(), Shape::Rectangle(rect))
+    }
+
+    #[test]
+    fn test_align_left() {
+        let mut doc = CanvasDocument::new();
+        let mut ids = Vec::new();
+        for (x, y) in [(30.0, 0.0), (10.0, 50.0), (70.0, 100.0)] {
+            let (id, shape) = rect_shape(x, y, 40.0, 20.0);
+            doc.add_shape(shape);
+            ids.push(id);
+        }
+
+        doc.align(&ids, AlignMode::Left);
+
+        for (id, y) in ids.iter().zip([0.0, 50.0, 100.0]) {
+            let bounds = doc.get_shape(*id).unwrap().bounds();
+            assert!((bounds.x0 - 10.0).abs() < 1e-9);
+            assert!((bounds.y0 - y).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_align_is_undoable_and_reflows_connectors() {
+        let mut doc = CanvasDocument::new();
+        let (a, shape) = rect_shape(0.0, 0.0, 40.0, 20.0);
+        doc.add_shape(shape);
+        let (b, shape) = rect_shape(100.0, 100.0, 40.0, 20.0);
+        doc.add_shape(shape);
+        let (c, shape) = rect_shape(300.0, 0.0, 40.0, 20.0);
+        doc.add_shape(shape);
+        let mut arrow = Arrow::new(Point::new(140.0, 110.0), Point::new(300.0, 10.0));
+        arrow.start_binding = Some(b);
+        arrow.end_binding = Some(c);
+        let arrow_id = arrow.id();
+        doc.add_shape(Shape::Arrow(arrow));
+
+        doc.align(&[a, b], AlignMode::Top);
+        let b_bounds = doc.get_shape(b).unwrap().bounds();
+        assert_eq!(b_bounds.y0, 0.0);
+        // The arrow still starts on b, now at its new position
+        let Some(Shape::Arrow(arrow)) = doc.get_shape(arrow_id) else {
+            panic!("arrow missing");
+        };
+        assert!(b_bounds.inflate(1e-9, 1e-9).contains(arrow.start));
+
+        assert!(doc.undo());
+        assert_eq!(doc.get_shape(b).unwrap().bounds().y0, 100.0);
+    }
+
+    #[test]
+    fn test_distribute_horizontal_equal_gaps() {
+        let mut doc = CanvasDocument::new();
+        let mut ids = Vec::new();
+        for (x, w) in [(0.0, 20.0), (200.0, 40.0), (30.0, 10.0), (300.0, 30.0)] {
+            let (id, shape) = rect_shape(x, 0.0, w, 20.0);
+            doc.add_shape(shape);
+            ids.push(id);
+        }
+
+        doc.distribute(&ids, Axis::Horizontal);
+
+        let mut bounds: Vec<Rect> = ids
+            .iter()
+            .map(|id| doc.get_shape(*id).unwrap().bounds())
+            .collect();
+        bounds.sort_by(|a, b| a.x0.total_cmp(&b.x0));
+
+        // Extremes stay put
+        assert!((bounds[0].x0 - 0.0).abs() < 1e-9);
+        assert!((bounds[3].x1 - 330.0).abs() < 1e-9);
+        let gaps: Vec<f64> = bounds.windows(2).map(|w| w[1].x0 - w[0].x1).collect();
+        for gap in &gaps {
+            assert!((gap - gaps[0]).abs() < 1e-9, "unequal gaps: {gaps:?}");
+        }
+        assert!((gaps[0] - 230.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_align_moves_group_as_unit() {
+        let mut doc = CanvasDocument::new();
+        let (a, shape_a) = rect_shape(50.0, 0.0, 20.0, 20.0);
+        let (b, shape_b) = rect_shape(80.0, 0.0, 20.0, 20.0);
+        let (c, shape_c) = rect_shape(10.0, 100.0, 20.0, 20.0);
+        doc.add_shape(shape_a);
+        doc.add_shape(shape_b);
+        doc.add_shape(shape_c);
+        let group = doc.group_shapes(&[a, b]).unwrap();
+
+        doc.align(&[group, c], AlignMode::Left);
+
+        let group_bounds = doc.get_shape(group).unwrap().bounds();
+        assert!((group_bounds.x0 - 10.0).abs() < 1e-9);
+        assert!((group_bounds.width() - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_flip_horizontal_swaps_order() {
+        let mut doc = CanvasDocument::new();
+        let (left, shape) = rect_shape(0.0, 0.0, 20.0, 20.0);
+        doc.add_shape(shape);
+        let (right, shape) = rect_shape(100.0, 30.0, 40.0, 10.0);
+        doc.add_shape(shape);
+
+        doc.flip_horizontal(&[left, right]);
+
+        let left_bounds = doc.get_shape(left).unwrap().bounds();
+        let right_bounds = doc.get_shape(right).unwrap().bounds();
+        assert!(left_bounds.x0 > right_bounds.x1);
+        assert_eq!(left_bounds, Rect::new(120.0, 0.0, 140.0, 20.0));
+        assert_eq!(right_bounds, Rect::new(0.0, 30.0, 40.0, 40.0));
+    }
+
+    #[test]
+    fn test_flip_is_undoable_and_reflows_connectors() {
+        let mut doc = CanvasDocument::new();
+        let (left, shape) = rect_shape(0.0, 0.0, 20.0, 20.0);
+        doc.add_shape(shape);
+        let (right, shape) = rect_shape(100.0, 0.0, 20.0, 20.0);
+        doc.add_shape(shape);
+        let (fixed, shape) = rect_shape(0.0, 200.0, 20.0, 20.0);
+        doc.add_shape(shape);
+        let mut arrow = Arrow::new(Point::new(10.0, 20.0), Point::new(10.0, 200.0));
+        arrow.start_binding = Some(left);
+        arrow.end_binding = Some(fixed);
+        let arrow_id = arrow.id();
+        doc.add_shape(Shape::Arrow(arrow));
+
+        doc.flip_horizontal(&[left, right]);
+        let left_bounds = doc.get_shape(left).unwrap().bounds();
+        assert_eq!(left_bounds.x0, 100.0);
+        let Some(Shape::Arrow(arrow)) = doc.get_shape(arrow_id) else {
+            panic!("arrow missing");
+        };
+        assert!(left_bounds.inflate(1e-9, 1e-9).contains(arrow.start));
+
+        assert!(doc.undo());
+        assert_eq!(doc.get_shape(left).unwrap().bounds().x0, 0.0);
+    }
+
+    #[test]
+    fn test_resize_element_corner_free() {
+        use crate::selection::Corner;
+
+        let mut doc = CanvasDocument::new();
+        let (id, shape) = rect_shape(0.0, 0.0, 100.0, 50.0);
+        doc.add_shape(shape);
+
+        let corner = HandleKind::Corner(Corner::BottomRight);
+        assert!(doc.resize_element(id, corner, Vec2::new(50.0, 10.0), false));
+        assert_eq!(
+            doc.get_shape(id).unwrap().bounds(),
+            Rect::new(0.0, 0.0, 150.0, 60.0)
+        );
+    }
+
+    #[test]
+    fn test_resize_element_corner_aspect_locked() {
+        use crate::selection::Corner;
+
+        let mut doc = CanvasDocument::new();
+        let (id, shape) = rect_shape(0.0, 0.0, 100.0, 50.0);
+        doc.add_shape(shape);
+
+        // x dominates, so the height follows the width at 2:1
+        let corner = HandleKind::Corner(Corner::TopLeft);
+        assert!(doc.resize_element(id, corner, Vec2::new(-100.0, 10.0), true));
+        assert_eq!(
+            doc.get_shape(id).unwrap().bounds(),
+            Rect::new(-100.0, -50.0, 100.0, 50.0)
+        );
+    }
+
+    #[test]
+    fn test_resize_element_past_anchor_flips() {
+        use crate::selection::Edge;
+
+        let mut doc = CanvasDocument::new();
+        let (id, shape) = rect_shape(0.0, 0.0, 100.0, 50.0);
+        doc.add_shape(shape);
+
+        let edge = HandleKind::Edge(Edge::Right);
+        assert!(doc.resize_element(id, edge, Vec2::new(-130.0, 0.0), false));
+        assert_eq!(
+            doc.get_shape(id).unwrap().bounds(),
+            Rect::new(-30.0, 0.0, 0.0, 50.0)
+        );
+        assert!(!doc.resize_element(id, HandleKind::Rotate, Vec2::ZERO, false));
+    }
+
+    #[test]
+    fn test_resize_element_is_undoable() {
+        use crate::selection::Corner;
+
+        let mut doc = CanvasDocument::new();
+        let (id, shape) = rect_shape(0.0, 0.0, 100.0, 50.0);
+        doc.add_shape(shape);
+
+        let corner = HandleKind::Corner(Corner::BottomRight);
+        assert!(doc.resize_element(id, corner, Vec2::new(50.0, 10.0), false));
+        assert!(doc.undo());
+        assert_eq!(
+            doc.get_shape(id).unwrap().bounds(),
+            Rect::new(0.0, 0.0, 100.0, 50.0)
+        );
+        assert!(!doc.can_undo());
+
+        // Refused resizes leave no undo step
+        doc.set_locked(&[id], true);
+        assert!(!doc.resize_element(id, corner, Vec2::new(50.0, 10.0), false));
+        assert!(doc.undo());
+        assert!(!doc.can_undo());
+    }
+
+    #[test]
+    fn test_reflow_connectors_only_touches_attached_arrows() {
+        let mut doc = CanvasDocument::new();
+        let (a, shape) = rect_shape(0.0, 0.0, 100.0, 50.0);
+        doc.add_shape(shape);
+        let (b, shape) = rect_shape(300.0, 0.0, 100.0, 50.0);
+        doc.add_shape(shape);
+
+        let mut connector = Arrow::new(Point::new(100.0, 25.0), Point::new(300.0, 25.0));
+        connector.path_style = crate::shapes::PathStyle::Angular;
+        connector.start_binding = Some(a);
+        connector.end_binding = Some(b);
+        let connector_id = connector.id();
+        doc.add_shape(Shape::Arrow(connector));
+        let unrelated = Arrow::new(Point::new(0.0, 200.0), Point::new(50.0, 250.0));
+        let unrelated_id = unrelated.id();
+        doc.add_shape(Shape::Arrow(unrelated));
+
+        let route = |doc: &CanvasDocument, id| match doc.get_shape(id) {
+            Some(Shape::Arrow(arrow)) => arrow.route_points(),
+            _ => panic!("arrow missing"),
+        };
+        let before = route(&doc, connector_id);
+        let unrelated_before = route(&doc, unrelated_id);
+
+        doc.get_shape_mut(b)
+            .unwrap()
+            .transform(Affine::translate(Vec2::new(0.0, 300.0)));
+        doc.reflow_connectors(&[b]);
+
+        let after = route(&doc, connector_id);
+        assert_ne!(after, before);
+        // The end now sits on the moved rect's boundary
+        let moved = doc.get_shape(b).unwrap().bounds();
+        let end = *after.last().unwrap();
+        assert!(moved.inflate(0.001, 0.001).contains(end));
+        assert_eq!(route(&doc, unrelated_id), unrelated_before);
+    }
+
+    #[test]
+    fn test_reflow_connectors_keeps_manual_waypoints() {
+        let mut doc = CanvasDocument::new();
+        let (a, shape) = rect_shape(0.0, 0.0, 100.0, 50.0);
+        doc.add_shape(shape);
+        let (b, shape) = rect_shape(300.0, 0.0, 100.0, 50.0);
+        doc.add_shape(shape);
+
+        let waypoints = vec![Point::new(200.0, -100.0)];
+        let mut arrow = Arrow::from_points(
+            vec![
+                Point::new(100.0, 25.0),
+                waypoints[0],
+                Point::new(300.0, 25.0),
+            ],
+            crate::shapes::PathStyle::Direct,
+        );
+        arrow.start_binding = Some(a);
+        arrow.end_binding = Some(b);
+        let id = arrow.id();
+        doc.add_shape(Shape::Arrow(arrow));
+
+        doc.get_shape_mut(b)
+            .unwrap()
+            .transform(Affine::translate(Vec2::new(100.0, 0.0)));
+        doc.reflow_connectors(&[b]);
+
+        let Some(Shape::Arrow(arrow)) = doc.get_shape(id) else {
+            panic!("arrow missing");
+        };
+        assert_eq!(arrow.intermediate_points, waypoints);
+        assert_eq!(arrow.start, Point::new(100.0, 25.0));
+        assert_eq!(arrow.end, Point::new(400.0, 0.0));
+    }
+
+    #[test]
+    fn test_port_binding_survives_resize() {
+        use crate::selection::Corner;
+
+        let mut doc = CanvasDocument::new();
+        let (a, shape) = rect_shape(0.0, 0.0, 100.0, 50.0);
+        doc.add_shape(shape);
+        let (b, shape) = rect_shape(300.0, 200.0, 100.0, 60.0);
+        doc.add_shape(shape);
+
+        // Drag the end near b's east edge: it snaps to the E port
+        let (port, pos) = snap::nearest_port(
+            doc.get_shape(b).unwrap(),
+            Point::new(404.0, 228.0),
+            snap::PORT_SNAP_THRESHOLD,
+        )
+        .unwrap();
+        assert_eq!(port, PortId::E);
+        let mut arrow = Arrow::new(Point::new(100.0, 25.0), pos);
+        arrow.start_binding = Some(a);
+        arrow.end_binding = Some(b);
+        arrow.end_port = Some(port);
+        let id = arrow.id();
+        doc.add_shape(Shape::Arrow(arrow));
+
+        assert!(doc.resize_element(
+            b,
+            HandleKind::Corner(Corner::BottomRight),
+            Vec2::new(50.0, 40.0),
+            false
+        ));
+
+        let resized = doc.get_shape(b).unwrap().bounds();
+        let Some(Shape::Arrow(arrow)) = doc.get_shape(id) else {
+            panic!("arrow missing");
+        };
+        assert_eq!(arrow.end, Point::new(resized.x1, resized.center().y));
+        assert_eq!(arrow.end, Point::new(450.0, 250.0));
+    }
+
+    #[test]
+    fn test_transform_elements_single_undo_entry() {
+        let mut doc = CanvasDocument::new();
+        let ids: Vec<ShapeId> = [(0.0, 0.0), (50.0, 20.0), (120.0, -30.0)]
+            .into_iter()
+            .map(|(x, y)| {
+                let (id, shape) = rect_shape(x, y, 10.0, 10.0);
+                doc.add_shape(shape);
+                id
+            })
+            .collect();
+        let origins = |doc: &CanvasDocument| -> Vec<Point> {
+            ids.iter()
+                .map(|&id| doc.get_shape(id).unwrap().bounds().origin())
+                .collect()
+        };
+        let before = origins(&doc);
+        let undo_depth = doc.undo_stack.len();
+
+        assert!(doc.transform_elements(&ids, Affine::translate(Vec2::new(15.0, -5.0))));
+        assert_eq!(doc.undo_stack.len(), undo_depth + 1);
+
+        let after = origins(&doc);
+        for (b, a) in before.iter().zip(&after) {
+            assert_eq!(*a - *b, Vec2::new(15.0, -5.0));
+        }
+        // Relative positions are preserved
+        assert_eq!(after[1] - after[0], before[1] - before[0]);
+        assert_eq!(after[2] - after[0], before[2] - before[0]);
+
+        assert!(doc.undo());
+        assert_eq!(origins(&doc), before);
+    }
+
+    #[test]
+    fn test_locked_shapes_ignore_hits_and_edits() {
+        use crate::selection::Corner;
+
+        let mut doc = CanvasDocument::new();
+        let (locked, shape) = rect_shape(0.0, 0.0, 10.0, 10.0);
+        doc.add_shape(shape);
+        let (free, shape) = rect_shape(20.0, 0.0, 10.0, 10.0);
+        doc.add_shape(shape);
+        assert!(doc.set_locked(&[locked], true));
+        assert_eq!(doc.locked_ids(), vec![locked]);
+
+        let center = Point::new(5.0, 5.0);
+        assert!(doc.shapes_at_point(center, 0.0).is_empty());
+        assert_eq!(doc.shapes_at_point_with(center, 0.0, true), vec![locked]);
+
+        let before = doc.get_shape(locked).unwrap().bounds();
+        assert!(doc.transform_elements(&[locked, free], Affine::translate((5.0, 0.0))));
+        assert_eq!(doc.get_shape(locked).unwrap().bounds(), before);
+        assert_eq!(doc.get_shape(free).unwrap().bounds().x0, 25.0);
+        assert!(!doc.transform_elements(&[locked], Affine::translate((5.0, 0.0))));
+        assert!(!doc.resize_element(
+            locked,
+            HandleKind::Corner(Corner::BottomRight),
+            Vec2::new(5.0, 5.0),
+            false
+        ));
+        assert_eq!(doc.get_shape(locked).unwrap().bounds(), before);
+
+        let mut canvas = Canvas::new();
+        canvas.document = doc;
+        canvas.select(locked);
+        canvas.delete_selected();
+        assert!(canvas.document.get_shape(locked).is_some());
+    }
+
+    #[test]
+    fn test_locked_shapes_refuse_align_flip_and_cut() {
+        let mut doc = CanvasDocument::new();
+        let (locked, shape) = rect_shape(0.0, 0.0, 10.0, 10.0);
+        doc.add_shape(shape);
+        let (a, shape) = rect_shape(50.0, 20.0, 10.0, 10.0);
+        doc.add_shape(shape);
+        let (b, shape) = rect_shape(100.0, 40.0, 10.0, 10.0);
+        doc.add_shape(shape);
+        assert!(doc.set_locked(&[locked], true));
+        let before = doc.get_shape(locked).unwrap().bounds();
+
+        // The locked shape neither moves nor anchors the others
+        doc.align(&[locked, a, b], AlignMode::Left);
+        assert_eq!(doc.get_shape(locked).unwrap().bounds(), before);
+        assert_eq!(doc.get_shape(b).unwrap().bounds().x0, 50.0);
+        doc.flip_horizontal(&[locked, a, b]);
+        doc.flip_vertical(&[locked]);
+        assert_eq!(doc.get_shape(locked).unwrap().bounds(), before);
+        doc.distribute(&[locked, a, b], Axis::Vertical);
+        assert_eq!(doc.get_shape(locked).unwrap().bounds(), before);
+        assert!(doc.remove_shape(locked).is_none());
+
+        // Select all then cut leaves the locked shape alone
+        let mut canvas = Canvas::with_document(doc);
+        canvas.select_all();
+        assert_eq!(canvas.selection.len(), 2);
+        assert!(!canvas.is_selected(locked));
+        let payload = canvas.document.copy(&canvas.selection);
+        assert_eq!(payload.shapes.len(), 2);
+        canvas.delete_selected();
+        canvas.remove_shape(locked);
+        assert_eq!(canvas.document.z_order, vec![locked]);
+    }
+
+    #[test]
+    fn test_transform_elements_reflows_and_keeps_text_unsheared() {
+        let mut doc = CanvasDocument::new();
+        let (a, shape) = rect_shape(0.0, 0.0, 100.0, 50.0);
+        doc.add_shape(shape);
+        let (b, shape) = rect_shape(300.0, 0.0, 100.0, 50.0);
+        doc.add_shape(shape);
+        let mut connector = Arrow::new(Point::new(100.0, 25.0), Point::new(300.0, 25.0));
+        connector.start_binding = Some(a);
+        connector.end_binding = Some(b);
+        let connector_id = connector.id();
+        doc.add_shape(Shape::Arrow(connector));
+        let (text_id, text) = text_shape(0.0, "label");
+        doc.add_shape(text);
+
+        assert!(!doc.transform_elements(&[ShapeId::new_v4()], Affine::scale(2.0)));
+        assert!(doc.transform_elements(&[b, text_id], Affine::scale_non_uniform(1.0, 3.0)));
+
+        let Some(Shape::Arrow(connector)) = doc.get_shape(connector_id) else {
+            panic!("arrow missing");
+        };
+        let moved = doc.get_shape(b).unwrap().bounds();
+        assert!(moved.inflate(0.001, 0.001).contains(connector.end));
+        let Some(Shape::Text(text)) = doc.get_shape(text_id) else {
+            panic!("text missing");
+        };
+        // Non-uniform scale averages into the font size, with no skew
+        assert_eq!(text.rotation, 0.0);
+        assert_eq!(text.font_size, crate::shapes::Text::DEFAULT_FONT_SIZE * 2.0);
+    }
+
+    #[test]
+    fn test_flip_twice_is_identity() {
+        let mut doc = CanvasDocument::new();
+        let (rect, shape) = rect_shape(10.0, 20.0, 30.0, 40.0);
+        doc.add_shape(shape);
+        let arrow = Arrow::new(Point::new(0.0, 0.0), Point::new(80.0, 15.0));
+        let arrow_id = arrow.id();
+        doc.add_shape(Shape::Arrow(arrow));
+        let mut text = crate::shapes::Text::new(Point::new(50.0, 60.0), "abc".to_string());
+        text.rotation = 0.3;
+        let text_id = text.id();
+        doc.add_shape(Shape::Text(text));
+        let ids = [rect, arrow_id, text_id];
+        let before: Vec<Rect> = ids
+            .iter()
+            .map(|id| doc.get_shape(*id).unwrap().bounds())
+            .collect();
+
+        for flip in [
+            CanvasDocument::flip_horizontal,
+            CanvasDocument::flip_vertical,
+        ] {
+            flip(&mut doc, &ids);
+            flip(&mut doc, &ids);
+            for (id, before) in ids.iter().zip(&before) {
+                let after = doc.get_shape(*id).unwrap().bounds();
+                assert!((after.origin() - before.origin()).hypot() < 1e-9);
+                assert!((after.size() - before.size()).to_vec2().hypot() < 1e-9);
+            }
+            let shape = doc.get_shape(text_id).unwrap();
+            assert!((shape.rotation() - 0.3).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_background_roundtrip() {
+        let mut doc = CanvasDocument::new();
+        assert!(!doc.to_json().unwrap().contains("\"background\""));
+
+        for background in [
+            Background::Transparent,
+            Background::Color(SerializableColor::new(10, 20, 30, 255)),
+        ] {
+            doc.background = Some(background);
+            let restored = CanvasDocument::from_json(&doc.to_json().unwrap()).unwrap();
+            assert_eq!(restored.background, Some(background));
+        }
+    }
+
+    #[test]
+    fn test_save_delta_single_move() {
+        let mut doc = CanvasDocument::new();
+        let mut ids = Vec::new();
+        for x in [0.0, 50.0, 100.0] {
+            let (id, shape) = rect_shape(x, 0.0, 20.0, 20.0);
+            doc.add_shape(shape);
+            ids.push(id);
+        }
+        let snapshot = doc.snapshot();
+        assert!(doc.save_delta(&snapshot).is_empty());
+
+        doc.transform_elements(&ids[1..2], Affine::translate((5.0, 5.0)));
+        let delta = doc.save_delta(&snapshot);
+        assert_eq!(delta.changed.len(), 1);
+        assert_eq!(delta.changed[0].id(), ids[1]);
+        assert!(delta.added.is_empty());
+        assert!(delta.removed.is_empty());
+        assert!(delta.z_order.is_none());
+    }
+
+    #[test]
+    fn test_save_delta_reports_image_lock() {
+        use crate::shapes::{Image, ImageFormat};
+
+        let mut doc = CanvasDocument::new();
+        let image = Image::new(Point::new(0.0, 0.0), &[0, 1, 2], 4, 4, ImageFormat::Png);
+        let id = image.id();
+        doc.add_shape(Shape::Image(image));
+        let snapshot = doc.snapshot();
+
+        assert!(doc.set_locked(&[id], true));
+        let delta = doc.save_delta(&snapshot);
+        assert_eq!(delta.changed.len(), 1);
+        assert_eq!(delta.changed[0].id(), id);
+        assert!(delta.changed[0].is_locked());
+    }
+
+    #[test]
+    fn test_save_delta_ignores_layout_caches() {
+        use crate::shapes::Math;
+
+        let mut doc = CanvasDocument::new();
+        let (text_id, shape) = text_shape(0.0, "hello");
+        doc.add_shape(shape);
+        let math = Math::new(Point::new(0.0, 50.0), "x^2".to_string());
+        let math_id = math.id();
+        doc.add_shape(Shape::Math(math));
+        let snapshot = doc.snapshot();
+
+        // As the renderer does on first layout
+        if let Some(Shape::Text(text)) = doc.get_shape(text_id) {
+            text.set_cached_size(42.0, 24.0);
+        }
+        if let Some(Shape::Math(math)) = doc.get_shape(math_id) {
+            math.set_cached_size(30.0, 20.0, 4.0);
+        }
+        assert!(doc.save_delta(&snapshot).is_empty());
+    }
+
+    #[test]
+    fn test_apply_delta_reproduces_document() {
+        let mut doc = CanvasDocument::new();
+        let mut ids = Vec::new();
+        for x in [0.0, 50.0, 100.0] {
+            let (id, shape) = rect_shape(x, 0.0, 20.0, 20.0);
+            doc.add_shape(shape);
+            ids.push(id);
+        }
+        let snapshot = doc.snapshot();
+        let mut base = doc.clone();
+
+        doc.transform_elements(&ids[..1], Affine::translate((5.0, 5.0)));
+        doc.remove_shape(ids[2]);
+        doc.add_shape(rect_shape(200.0, 0.0, 10.0, 10.0).1);
+        doc.send_to_back(ids[1]);
+
+        let json = serde_json::to_string(&doc.save_delta(&snapshot)).unwrap();
+        base.apply_delta(serde_json::from_str(&json).unwrap());
+
+        assert_eq!(base.z_order, doc.z_order);
+        assert_eq!(base.shapes.len(), doc.shapes.len());
+        for shape in doc.shapes_ordered() {
+            let restored = base.get_shape(shape.id()).unwrap();
+            assert_eq!(shape_content_hash(restored), shape_content_hash(shape));
+        }
+    }
+
+    #[test]
+    fn test_generate_id_unique_and_ordered() {
+        let mut doc = CanvasDocument::new();
+        let ids: Vec<ElementId> = (0..10_000).map(|_| doc.generate_id()).collect();
+
+        let unique: std::collections::HashSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_find_element_after_roundtrip() {
+        let mut doc = CanvasDocument::new();
+        let a = doc.add_shape_with_new_id(rect_shape(0.0, 0.0, 10.0, 10.0).1);
+        let b = doc.add_shape_with_new_id(rect_shape(50.0, 0.0, 10.0, 10.0).1);
+        let group = doc.group_shapes(&[a, b]).unwrap();
+        // Group IDs come from the same counter, in creation order
+        assert!(a < b && b < group);
+
+        let mut restored = CanvasDocument::from_json(&doc.to_json().unwrap()).unwrap();
+        assert_eq!(restored.find_element(group).unwrap().id(), group);
+        assert_eq!(
+            restored.find_element(b).unwrap().bounds(),
+            Rect::new(50.0, 0.0, 60.0, 10.0)
+        );
+        assert!(restored.find_element(ShapeId::new_v4()).is_none());
+
+        // The counter survives the round-trip, so new IDs keep sorting last
+        let next = restored.generate_id();
+        assert!(next > a && next > b);
+        assert!(restored.find_element_mut(a).is_some());
+    }
+
+    fn text_shape(x: f64, content: &str) -> (ShapeId, Shape) {
+        let text = crate::shapes::Text::new(Point::new(x, 0.0), content.to_string());
+        (text.id(), Shape::Text(text))
+    }
+
+    #[test]
+    fn test_find_text_folds_accents() {
+        let mut doc = CanvasDocument::new();
+        let (id, shape) = text_shape(0.0, "Café ÉCLAIR");
+        doc.add_shape(shape);
+
+        assert_eq!(doc.find_text("éclair", false), vec![id]);
+        assert!(doc.find_text("éclair", true).is_empty());
+
+        let ranges = doc.find_text_ranges("É", false);
+        assert_eq!(ranges.len(), 1);
+        let content = "Café ÉCLAIR";
+        let found: Vec<&str> = ranges[0].1.iter().map(|r| &content[r.clone()]).collect();
+        assert_eq!(found, vec!["é", "É"]);
+    }
+
+    #[test]
+    fn test_find_text_stable_order_with_groups() {
+        let mut doc = CanvasDocument::new();
+        let (first, shape) = text_shape(0.0, "todo: a");
+        doc.add_shape(shape);
+        let (grouped, shape) = text_shape(50.0, "TODO: b");
+        doc.add_shape(shape);
+        let (box_id, shape) = rect_shape(100.0, 0.0, 10.0, 10.0);
+        doc.add_shape(shape);
+        doc.group_shapes(&[grouped, box_id]).unwrap();
+        let (last, shape) = text_shape(200.0, "another todo");
+        doc.add_shape(shape);
+        doc.add_shape(text_shape(300.0, "nothing here").1);
+
+        let expected = vec![first, grouped, last];
+        assert_eq!(doc.find_text("todo", false), expected);
+        assert_eq!(doc.find_text("todo", false), expected);
+        assert!(doc.find_text("", false).is_empty());
+    }
+
+    #[test]
+    fn test_flip_group_as_unit() {
+        let mut doc = CanvasDocument::new();
+        let (a, shape) = rect_shape(0.0, 0.0, 10.0, 10.0);
+        doc.add_shape(shape);
+        let (b, shape) = rect_shape(50.0, 0.0, 30.0, 10.0);
+        doc.add_shape(shape);
+        let group = doc.group_shapes(&[a, b]).unwrap();
+
+        doc.flip_horizontal(&[group]);
+
+        let Some(Shape::Group(g)) = doc.get_shape(group) else {
+            panic!("group missing");
+        };
+        assert_eq!(g.bounds(), Rect::new(0.0, 0.0, 80.0, 10.0));
+        assert_eq!(g.children()[0].bounds(), Rect::new(70.0, 0.0, 80.0, 10.0));
+        assert_eq!(g.children()[1].bounds(), Rect::new(0.0, 0.0, 30.0, 10.0));
+    }
+
+    #[test]
+    fn test_import_excalidraw_fixture() {
+        let json = r##"{
+            "type": "excalidraw",
+            "elements": [
+                {"id": "r1", "type": "rectangle", "x": 10, "y": 20, "width": 100, "height": 50,
+                 "strokeColor": "#1e1e1e", "backgroundColor": "transparent", "strokeWidth": 2},
+                {"id": "e1", "type": "ellipse", "x": 300, "y": 0, "width": 80, "height": 40},
+                {"id": "t1", "type": "text", "x": 0, "y": 200, "text": "hello", "fontSize": 16},
+                {"id": "img", "type": "image", "x": 500, "y": 500, "width": 10, "height": 10},
+                {"id": "a1", "type": "arrow", "x": 150, "y": 45, "points": [[0, 0], [140, -25]],
+                 "startBinding": {"elementId": "r1", "focus": 0, "gap": 8},
+                 "endBinding": {"elementId": "img", "focus": 0, "gap": 8}},
+                {"id": "l1", "type": "line", "x": 0, "y": 0, "points": [[0, 0], [10, 10]]},
+                {"id": "gone", "type": "rectangle", "x": 0, "y": 0, "isDeleted": true}
+            ]
+        }"##;
+
+        let import = import_excalidraw(json).unwrap();
+        let doc = &import.document;
+        assert_eq!(doc.len(), 5);
+
+        let count = |pred: fn(&Shape) -> bool| doc.shapes_ordered().filter(|s| pred(s)).count();
+        assert_eq!(count(|s| matches!(s, Shape::Rectangle(_))), 1);
+        assert_eq!(count(|s| matches!(s, Shape::Ellipse(_))), 1);
+        assert_eq!(count(|s| matches!(s, Shape::Text(_))), 1);
+        assert_eq!(count(|s| matches!(s, Shape::Arrow(_))), 1);
+        assert_eq!(count(|s| matches!(s, Shape::Line(_))), 1);
+
+        let rect = doc
+            .shapes_ordered()
+            .find(|s| matches!(s, Shape::Rectangle(_)))
+            .unwrap();
+        assert_eq!(rect.bounds(), Rect::new(10.0, 20.0, 110.0, 70.0));
+
+        // Start snaps onto the rectangle; the end target was skipped
+        let Some(Shape::Arrow(arrow)) = doc.shapes_ordered().find(|s| matches!(s, Shape::Arrow(_)))
+        else {
+            panic!("arrow missing");
+        };
+        assert_eq!(arrow.start, Point::new(110.0, 45.0));
+        assert_eq!(arrow.end, Point::new(290.0, 20.0));
+
+        assert_eq!(import.warnings.len(), 2, "{:?}", import.warnings);
+        assert!(import.warnings.iter().any(|w| w.contains("'image'")));
+        assert!(import.warnings.iter().any(|w| w.contains("'img'")));
+    }
+
+    #[test]
+    fn test_export_excalidraw_roundtrip() {
+        let mut doc = CanvasDocument::new();
+        let mut rect = Rectangle::new(Point::new(10.0, 20.0), 100.0, 50.0);
+        rect.style.fill_color = Some(crate::shapes::SerializableColor::new(255, 0, 0, 255));
+        doc.add_shape(Shape::Rectangle(rect));
+        doc.add_shape(Shape::Arrow(Arrow::new(
+            Point::new(200.0, 100.0),
+            Point::new(260.0, 40.0),
+        )));
+        let (a, shape) = rect_shape(0.0, 300.0, 10.0, 10.0);
+        doc.add_shape(shape);
+        let (b, shape) = rect_shape(50.0, 300.0, 10.0, 10.0);
+        doc.add_shape(shape);
+        doc.group_shapes(&[a, b]);
+
+        let json = export_excalidraw(&doc);
+        assert_eq!(json, export_excalidraw(&doc));
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["type"], "excalidraw");
+        let arrow = value["elements"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["type"] == "arrow")
+            .unwrap();
+        assert_eq!(
+            arrow["points"],
+            serde_json::json!([[0.0, 0.0], [60.0, -60.0]])
+        );
+
+        let import = import_excalidraw(&json).unwrap();
+        assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+        let imported = &import.document;
+        // Groups are flattened into their members
+        assert_eq!(imported.len(), 4);
+        let rects: Vec<Rect> = imported
+            .shapes_ordered()
+            .filter(|s| matches!(s, Shape::Rectangle(_)))
+            .map(|s| s.bounds())
+            .collect();
+        assert_eq!(rects[0], Rect::new(10.0, 20.0, 110.0, 70.0));
+        assert_eq!(rects.len(), 3);
+        let Some(Shape::Rectangle(first)) = imported.shapes_ordered().next() else {
+            panic!("rectangle missing");
+        };
+        assert_eq!(first.style.fill_color.unwrap().r, 255);
+        assert_eq!(first.corner_radius, 0.0);
+        assert_eq!(first.style.opacity, 1.0);
+    }
+
+    #[test]
+    fn test_export_excalidraw_bindings_and_sticky_notes() {
+        use crate::shapes::StickyNote;
+
+        let mut doc = CanvasDocument::new();
+        let (a, shape) = rect_shape(0.0, 0.0, 40.0, 20.0);
+        doc.add_shape(shape);
+        let (b, shape) = rect_shape(200.0, 0.0, 40.0, 20.0);
+        doc.add_shape(shape);
+        let mut arrow = Arrow::new(Point::new(45.0, 10.0), Point::new(200.0, 10.0));
+        arrow.start_binding = Some(a);
+        arrow.end_binding = Some(b);
+        let arrow_id = arrow.id();
+        doc.add_shape(Shape::Arrow(arrow));
+        let note = StickyNote::new(Point::new(0.0, 100.0), "note".to_string());
+        let note_id = note.id();
+        doc.add_shape(Shape::StickyNote(note));
+
+        let json = export_excalidraw(&doc);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let elements = value["elements"].as_array().unwrap();
+        let element = |id: ShapeId| elements.iter().find(|e| e["id"] == id.to_string()).unwrap();
+        let exported = element(arrow_id);
+        assert_eq!(exported["startBinding"]["elementId"], a.to_string());
+        assert_eq!(exported["startBinding"]["focus"], 0.0);
+        assert_eq!(exported["startBinding"]["gap"], 5.0);
+        assert_eq!(exported["endBinding"]["elementId"], b.to_string());
+        assert_eq!(exported["endBinding"]["gap"], 0.0);
+        let arrow_ref = serde_json::json!([{"id": arrow_id.to_string(), "type": "arrow"}]);
+        assert_eq!(element(a)["boundElements"], arrow_ref);
+        assert_eq!(element(b)["boundElements"], arrow_ref);
+
+        // The note is a rectangle holding a bound text element
+        let rect = element(note_id);
+        assert_eq!(rect["type"], "rectangle");
+        let text_id = rect["boundElements"][0]["id"].as_str().unwrap();
+        assert_ne!(text_id, note_id.to_string());
+        let text = elements.iter().find(|e| e["id"] == text_id).unwrap();
+        assert_eq!(text["type"], "text");
+        assert_eq!(text["text"], "note");
+        assert_eq!(text["containerId"], note_id.to_string());
+
+        let imported = import_excalidraw(&json).unwrap().document;
+        assert_eq!(imported.len(), 5);
+        let Some(Shape::Arrow(arrow)) = imported
+            .shapes_ordered()
+            .find(|s| matches!(s, Shape::Arrow(_)))
+        else {
+            panic!("arrow missing");
+        };
+        assert!(arrow.start_binding.is_some());
+        assert!(arrow.end_binding.is_some());
+    }
+
+    #[test]
+    fn test_view_state_roundtrip() {
+        let mut canvas = Canvas::new();
+        let (id, shape) = rect_shape(10.0, 20.0, 30.0, 40.0);
+        canvas.document.add_shape(shape);
+        canvas.camera.zoom = 2.5;
+        canvas.camera.offset = Vec2::new(30.0, -40.0);
+        let transform = canvas.camera.transform();
+
+        let json = canvas.save_json().unwrap();
+        let loaded = Canvas::from_json(&json).unwrap();
+        assert_eq!(loaded.camera.transform(), transform);
+        assert_eq!(loaded.view_state(), canvas.view_state());
+        assert!(loaded.document.get_shape(id).is_some());
+
+        // Documents alone still load, and ignore the view
+        let doc = CanvasDocument::from_json(&json).unwrap();
+        assert_eq!(doc.len(), 1);
+    }
+
+    #[test]
+    fn test_missing_view_state_fits_content() {
+        let mut doc = CanvasDocument::new();
+        let (_, shape) = rect_shape(1000.0, 1000.0, 200.0, 100.0);
+        doc.add_shape(shape);
+
+        let canvas = Canvas::from_json(&doc.to_json().unwrap()).unwrap();
+        let screen = Rect::from_origin_size(Point::ZERO, canvas.viewport_size);
+        let on_screen = canvas
+            .camera
+            .transform()
+            .transform_rect_bbox(canvas.document.bounds().unwrap());
+        assert_eq!(screen.union(on_screen), screen);
+    }
+
+    #[test]
+    fn test_zoom_at_keeps_world_point_under_cursor() {
+        let mut canvas = Canvas::new();
+        let viewport = kurbo::Size::new(800.0, 600.0);
+        canvas.camera.pan(Vec2::new(-120.0, 45.0));
+        let cursor = Point::new(300.0, 200.0);
+        let world = canvas.camera.screen_to_world(cursor);
+
+        canvas.zoom_at(cursor, 1.5, viewport);
+        assert!((canvas.camera.zoom - crate::camera::BASE_ZOOM * 1.5).abs() < 1e-9);
+        assert!((canvas.camera.screen_to_world(cursor) - world).hypot() < 1e-9);
+
+        // Clamped at the limit, still pivoting on the cursor
+        canvas.zoom_at(cursor, 1000.0, viewport);
+        assert_eq!(canvas.camera.zoom, canvas.camera.max_zoom);
+        assert!((canvas.camera.screen_to_world(cursor) - world).hypot() < 1e-9);
+
+        // An off-screen cursor pivots on the nearest viewport edge
+        let edge = Point::new(800.0, 200.0);
+        let world = canvas.camera.screen_to_world(edge);
+        canvas.zoom_at(Point::new(950.0, 200.0), 0.5, viewport);
+        assert!((canvas.camera.screen_to_world(edge) - world).hypot() < 1e-9);
+    }
+
+    #[test]
+    fn test_compute_fit_transform() {
+        let mut canvas = Canvas::new();
+        let viewport = kurbo::Size::new(800.0, 600.0);
+        assert_eq!(canvas.compute_fit_transform(viewport), Affine::IDENTITY);
+
+        let (a, shape) = rect_shape(100.0, 100.0, 200.0, 100.0);
+        canvas.document.add_shape(shape);
+        let (b, shape) = rect_shape(-300.0, 0.0, 50.0, 50.0);
+        canvas.document.add_shape(shape);
+
+        let screen = Rect::from_origin_size(Point::ZERO, viewport);
+        let inner = screen.inset(-FIT_MARGIN);
+        let fitted = canvas
+            .compute_fit_transform(viewport)
+            .transform_rect_bbox(canvas.document.bounds().unwrap());
+        // Inside the margin, touching it along the limiting axis, centered
+        assert!(fitted.x0 >= inner.x0 - 1e-9 && fitted.x1 <= inner.x1 + 1e-9);
+        assert!(fitted.y0 >= inner.y0 - 1e-9 && fitted.y1 <= inner.y1 + 1e-9);
+        assert!((fitted.width() - inner.width()).abs() < 1e-9);
+        assert!((fitted.center() - screen.center()).hypot() < 1e-9);
+
+        let transform = canvas.compute_fit_selection_transform(&[a], viewport);
+        let fitted = transform.transform_rect_bbox(canvas.document.get_shape(a).unwrap().bounds());
+        assert!(fitted.x0 >= inner.x0 - 1e-9 && fitted.x1 <= inner.x1 + 1e-9);
+        assert!((fitted.center() - screen.center()).hypot() < 1e-9);
+        // The other shape is off screen at this zoom
+        let other = transform.transform_rect_bbox(canvas.document.get_shape(b).unwrap().bounds());
+        assert!(other.intersect(screen).is_zero_area());
+
+        // Tiny content is clamped to the maximum zoom
+        let mut tiny = Canvas::new();
+        tiny.document.add_shape(rect_shape(0.0, 0.0, 1.0, 1.0).1);
+        let scale = tiny.compute_fit_transform(viewport).as_coeffs()[0];
+        assert_eq!(scale, tiny.camera.max_zoom);
+        assert_eq!(
+            tiny.compute_fit_selection_transform(&[ShapeId::new_v4()], viewport),
+            Affine::IDENTITY
+        );
+    }
+
+    #[test]
+    fn test_recorded_ops_replay_onto_empty_document() {
+        let mut doc = CanvasDocument::new();
+        let (a, shape) = rect_shape(0.0, 0.0, 50.0, 50.0);
+        doc.add_shape(shape);
+        doc.start_recording();
+        let (b, shape) = rect_shape(100.0, 0.0, 50.0, 50.0);
+        doc.add_shape(shape);
+        let (c, shape) = rect_shape(200.0, 0.0, 50.0, 50.0);
+        doc.add_shape(shape);
+        let (d, shape) = rect_shape(300.0, 0.0, 50.0, 50.0);
+        doc.add_shape(shape);
+        assert!(doc.update_shape(b, |shape| {
+            shape.transform(Affine::translate((10.0, 20.0)));
+            shape.style_mut().fill_color = Some(SerializableColor::white());
+        }));
+        doc.send_to_back(d);
+        let group = doc.group_shapes(&[b, c]).unwrap();
+        doc.remove_shape(d);
+        doc.ungroup_shape(group);
+        doc.bring_forward(b);
+
+        let ops = doc.recorded_ops().to_vec();
+        assert!(ops.windows(2).all(|pair| pair[0].seq < pair[1].seq));
+        assert!(matches!(&ops[3].op, Op::UpdateElement { id, .. } if *id == b));
+
+        // Replay onto a document in the state recording started from
+        let mut replica = CanvasDocument::new();
+        replica.add_shape(doc.get_shape(a).unwrap().clone());
+        for recorded in &ops {
+            assert!(replica.apply_op(&recorded.op));
+        }
+        assert_eq!(replica.z_order, doc.z_order);
+        for (original, replayed) in doc.shapes_ordered().zip(replica.shapes_ordered()) {
+            assert_eq!(shape_content_hash(original), shape_content_hash(replayed));
+        }
+
+        // Ops travel as JSON, and stale ones are refused
+        let json = serde_json::to_string(&ops).unwrap();
+        let decoded: Vec<RecordedOp> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.len(), ops.len());
+        assert!(!replica.apply_op(&decoded[0].op));
+        assert!(!replica.apply_op(&Op::RemoveElement { id: d }));
+
+        assert_eq!(doc.take_recorded_ops().len(), ops.len());
+        let (_, shape) = rect_shape(0.0, 100.0, 10.0, 10.0);
+        doc.add_shape(shape);
+        let later = doc.stop_recording();
+        assert_eq!(later[0].seq, ops.last().unwrap().seq + 1);
+        assert!(!doc.is_recording());
+    }
+
+    #[test]
+    fn test_batch_edits_and_undo_replay_from_log() {
+        use crate::selection::Corner;
+
+        let mut doc = CanvasDocument::new();
+        let (a, shape) = rect_shape(0.0, 0.0, 40.0, 20.0);
+        doc.add_shape(shape);
+        let (b, shape) = rect_shape(100.0, 100.0, 40.0, 20.0);
+        doc.add_shape(shape);
+        let mut arrow = Arrow::new(Point::new(40.0, 10.0), Point::new(100.0, 110.0));
+        arrow.start_binding = Some(a);
+        arrow.end_binding = Some(b);
+        doc.add_shape(Shape::Arrow(arrow));
+        let mut replica = doc.clone();
+        doc.start_recording();
+
+        assert!(doc.transform_elements(&[a], Affine::translate((15.0, 25.0))));
+        doc.align(&[a, b], AlignMode::Left);
+        doc.flip_horizontal(&[b]);
+        let corner = HandleKind::Corner(Corner::BottomRight);
+        assert!(doc.resize_element(b, corner, Vec2::new(10.0, 10.0), false));
+        assert!(doc.set_locked(&[a], true));
+        assert!(doc.undo());
+        assert!(doc.undo());
+        assert!(doc.redo());
+
+        let ops = doc.recorded_ops().to_vec();
+        assert!(!ops.is_empty());
+        for recorded in &ops {
+            assert!(replica.apply_op(&recorded.op));
+        }
+        assert_eq!(replica.z_order, doc.z_order);
+        for (original, replayed) in doc.shapes_ordered().zip(replica.shapes_ordered()) {
+            assert_eq!(shape_content_hash(original), shape_content_hash(replayed));
+        }
+    }
 }
@@ -0,0 +1,297 @@
+//! System clipboard integration for copy and paste.
+//!
+//! Copied shapes travel through the clipboard as [`ClipboardPayload`] JSON.
+//! On paste the clipboard text is parsed as a payload first; other text
+//! becomes a text element, and with no text a clipboard image becomes an
+//! image element.
+//!
+//! Clipboard access goes through [`ClipboardBackend`]. Native builds use
+//! arboard via [`SystemClipboard`]. The browser clipboard is async, so WASM
+//! builds use [`copy_async`] and [`read_async`] instead.
+
+use crate::canvas::{CanvasDocument, ClipboardPayload};
+use crate::shapes::{Image, ImageFormat, Shape, ShapeId, Text};
+use kurbo::{Point, Vec2};
+use thiserror::Error;
+
+/// Largest width or height of a pasted image, in world units. Bigger images
+/// are scaled down to fit.
+pub const MAX_PASTED_IMAGE_SIZE: f64 = 800.0;
+
+/// Clipboard errors.
+#[derive(Debug, Error)]
+pub enum ClipboardError {
+    #[error("Clipboard unavailable: {0}")]
+    Unavailable(String),
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+    #[error("Image encoding error: {0}")]
+    Image(String),
+}
+
+/// An image read from the clipboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardImage {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// PNG-encoded pixel data.
+    pub png: Vec<u8>,
+}
+
+/// What a paste will insert.
+#[derive(Debug, Clone)]
+pub enum ClipboardContent {
+    /// Shapes copied from a DrafftInk document.
+    Shapes(ClipboardPayload),
+    /// Plain text from another application.
+    Text(String),
+    /// An image from another application.
+    Image(ClipboardImage),
+}
+
+impl ClipboardContent {
+    /// Interpret clipboard text: a non-empty payload if it parses as one,
+    /// plain text otherwise. Blank text is `None`.
+    pub fn from_text(text: String) -> Option<Self> {
+        if text.trim().is_empty() {
+            return None;
+        }
+        match ClipboardPayload::from_json(&text) {
+            Ok(payload) if !payload.is_empty() => Some(Self::Shapes(payload)),
+            _ => Some(Self::Text(text)),
+        }
+    }
+}
+
+/// Access to a clipboard holding text and images.
+pub trait ClipboardBackend {
+    /// Replace the clipboard contents with `text`.
+    fn set_text(&mut self, text: &str) -> Result<(), ClipboardError>;
+
+    /// Text on the clipboard, or `None` if it holds no text.
+    fn text(&mut self) -> Result<Option<String>, ClipboardError>;
+
+    /// Image on the clipboard, or `None` if it holds no image.
+    fn image(&mut self) -> Result<Option<ClipboardImage>, ClipboardError>;
+}
+
+/// Write `payload` to the clipboard as JSON.
+pub fn copy(
+    backend: &mut impl ClipboardBackend,
+    payload: &ClipboardPayload,
+) -> Result<(), ClipboardError> {
+    let json = payload
+        .to_json()
+        .map_err(|e| ClipboardError::Serialization(e.to_string()))?;
+    backend.set_text(&json)
+}
+
+/// Read the clipboard, preferring text over images. Returns `None` if it
+/// holds nothing pasteable.
+pub fn read(
+    backend: &mut impl ClipboardBackend,
+) -> Result<Option<ClipboardContent>, ClipboardError> {
+    if let Some(content) = backend.text()?.and_then(ClipboardContent::from_text) {
+        return Ok(Some(content));
+    }
+    Ok(backend.image()?.map(ClipboardContent::Image))
+}
+
+/// Insert `content` into `doc` at `at` (in world coordinates).
+///
+/// Shapes get fresh IDs and images become image elements, both centered on
+/// `at`; text becomes a text element starting there. Returns the
+/// IDs of the new top-level shapes. Does not record undo.
+pub fn paste(doc: &mut CanvasDocument, content: &ClipboardContent, at: Point) -> Vec<ShapeId> {
+    match content {
+        ClipboardContent::Shapes(payload) => {
+            let offset = CanvasDocument::shapes_bounds(&payload.shapes)
+                .map_or(Vec2::ZERO, |bounds| at - bounds.center());
+            doc.paste(payload, offset)
+        }
+        ClipboardContent::Text(text) => {
+            let text = Text::new(at, text.clone());
+            vec![doc.add_shape_with_new_id(Shape::Text(text))]
+        }
+        ClipboardContent::Image(image) => {
+            let mut element =
+                Image::new(at, &image.png, image.width, image.height, ImageFormat::Png);
+            if element.width > MAX_PASTED_IMAGE_SIZE || element.height > MAX_PASTED_IMAGE_SIZE {
+                element = element.fit_within(MAX_PASTED_IMAGE_SIZE, MAX_PASTED_IMAGE_SIZE);
+            }
+            element.position = at - Vec2::new(element.width / 2.0, element.height / 2.0);
+            vec![doc.add_shape_with_new_id(Shape::Image(element))]
+        }
+    }
+}
+
+/// The operating system clipboard, via arboard.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct SystemClipboard(arboard::Clipboard);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SystemClipboard {
+    /// Open the system clipboard.
+    pub fn new() -> Result<Self, ClipboardError> {
+        arboard::Clipboard::new()
+            .map(Self)
+            .map_err(|e| ClipboardError::Unavailable(e.to_string()))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ClipboardBackend for SystemClipboard {
+    fn set_text(&mut self, text: &str) -> Result<(), ClipboardError> {
+        self.0
+            .set_text(text)
+            .map_err(|e| ClipboardError::Unavailable(e.to_string()))
+    }
+
+    fn text(&mut self) -> Result<Option<String>, ClipboardError> {
+        match self.0.get_text() {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(ClipboardError::Unavailable(e.to_string())),
+        }
+    }
+
+    fn image(&mut self) -> Result<Option<ClipboardImage>, ClipboardError> {
+        let image = match self.0.get_image() {
+            Ok(image) => image,
+            Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+            Err(e) => return Err(ClipboardError::Unavailable(e.to_string())),
+        };
+        let (width, height) = (image.width as u32, image.height as u32);
+        let image_error = |e: png::EncodingError| ClipboardError::Image(e.to_string());
+
+        // arboard hands out raw RGBA; store it compactly as PNG
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(image_error)?;
+        writer.write_image_data(&image.bytes).map_err(image_error)?;
+        writer.finish().map_err(image_error)?;
+        Ok(Some(ClipboardImage { width, height, png }))
+    }
+}
+
+/// Write `payload` to the browser clipboard as JSON.
+#[cfg(target_arch = "wasm32")]
+pub async fn copy_async(payload: &ClipboardPayload) -> Result<(), ClipboardError> {
+    let json = payload
+        .to_json()
+        .map_err(|e| ClipboardError::Serialization(e.to_string()))?;
+    let promise = browser_clipboard()?.write_text(&json);
+    wasm_bindgen_futures::JsFuture::from(promise)
+        .await
+        .map(|_| ())
+        .map_err(|e| ClipboardError::Unavailable(format!("{e:?}")))
+}
+
+/// Read the browser clipboard. Only text is supported; images need the
+/// clipboard item API and a decoder.
+#[cfg(target_arch = "wasm32")]
+pub async fn read_async() -> Result<Option<ClipboardContent>, ClipboardError> {
+    let promise = browser_clipboard()?.read_text();
+    let text = wasm_bindgen_futures::JsFuture::from(promise)
+        .await
+        .map_err(|e| ClipboardError::Unavailable(format!("{e:?}")))?;
+    Ok(text.as_string().and_then(ClipboardContent::from_text))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn browser_clipboard() -> Result<web_sys::Clipboard, ClipboardError> {
+    let window =
+        web_sys::window().ok_or_else(|| ClipboardError::Unavailable("no window".to_string()))?;
+    Ok(window.navigator().clipboard())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::Rectangle;
+
+    /// In-memory clipboard standing in for the system one.
+    #[derive(Default)]
+    struct MockClipboard {
+        text: Option<String>,
+        image: Option<ClipboardImage>,
+    }
+
+    impl ClipboardBackend for MockClipboard {
+        fn set_text(&mut self, text: &str) -> Result<(), ClipboardError> {
+            self.text = Some(text.to_string());
+            self.image = None;
+            Ok(())
+        }
+
+        fn text(&mut self) -> Result<Option<String>, ClipboardError> {
+            Ok(self.text.clone())
+        }
+
+        fn image(&mut self) -> Result<Option<ClipboardImage>, ClipboardError> {
+            Ok(self.image.clone())
+        }
+    }
+
+    #[test]
+    fn test_payload_round_trips_through_clipboard() {
+        let mut source = CanvasDocument::new();
+        let rect = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 40.0, 20.0));
+        let id = rect.id();
+        source.add_shape(rect);
+        let payload = source.copy(&[id]);
+
+        let mut clipboard = MockClipboard::default();
+        copy(&mut clipboard, &payload).unwrap();
+        let Some(ClipboardContent::Shapes(read_back)) = read(&mut clipboard).unwrap() else {
+            panic!("expected shapes on the clipboard");
+        };
+        assert_eq!(read_back.shapes.len(), 1);
+        assert_eq!(read_back.shapes[0].id(), id);
+
+        let mut target = CanvasDocument::new();
+        let content = ClipboardContent::Shapes(read_back);
+        let pasted = paste(&mut target, &content, Point::new(100.0, 100.0));
+        assert_eq!(pasted.len(), 1);
+        assert_ne!(pasted[0], id);
+        let bounds = target.get_shape(pasted[0]).unwrap().bounds();
+        assert_eq!(bounds.center(), Point::new(100.0, 100.0));
+        assert_eq!(bounds.size(), kurbo::Size::new(40.0, 20.0));
+    }
+
+    #[test]
+    fn test_foreign_content_falls_back_to_text_then_image() {
+        let mut clipboard = MockClipboard {
+            text: Some("hello".to_string()),
+            image: None,
+        };
+        let content = read(&mut clipboard).unwrap().unwrap();
+        assert!(matches!(&content, ClipboardContent::Text(text) if text == "hello"));
+        let mut doc = CanvasDocument::new();
+        let ids = paste(&mut doc, &content, Point::ZERO);
+        assert!(matches!(doc.get_shape(ids[0]), Some(Shape::Text(_))));
+
+        let image = ClipboardImage {
+            width: 2000,
+            height: 1000,
+            png: vec![1, 2, 3],
+        };
+        clipboard.text = Some("   ".to_string());
+        clipboard.image = Some(image.clone());
+        let content = read(&mut clipboard).unwrap().unwrap();
+        assert!(matches!(&content, ClipboardContent::Image(read_image) if *read_image == image));
+        let ids = paste(&mut doc, &content, Point::ZERO);
+        let Some(Shape::Image(pasted)) = doc.get_shape(ids[0]) else {
+            panic!("expected an image element");
+        };
+        assert_eq!(pasted.width, MAX_PASTED_IMAGE_SIZE);
+        assert_eq!(pasted.position, Point::new(-400.0, -200.0));
+
+        clipboard.image = None;
+        assert!(read(&mut clipboard).unwrap().is_none());
+    }
+}
@@ -160,12 +160,9 @@ impl CollaborationManager {
     /// Update a shape, syncing to CRDT if enabled.
     pub fn update_shape(&mut self, doc: &mut CanvasDocument, shape: Shape) {
         let id = shape.id();
-        if let Some(existing) = doc.shapes.get_mut(&id) {
-            *existing = shape.clone();
-
-            if self.enabled {
-                let _ = self.crdt.update_shape(&shape);
-            }
+        let updated = doc.update_shape(id, |existing| *existing = shape.clone());
+        if updated && self.enabled {
+            let _ = self.crdt.update_shape(&shape);
         }
     }
 
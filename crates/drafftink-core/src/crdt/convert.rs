@@ -1,13 +1,13 @@
 //! Conversion between Shape types and Loro values.
 
 use crate::shapes::{
-    Arrow, Ellipse, FillPattern, FontFamily, FontWeight, Freehand, Group, Image, ImageFormat, Line,
-    Math, PathStyle, Rectangle, SerializableColor, Shape, ShapeStyle, ShapeTrait, Sloppiness,
-    StrokeStyle, Text,
+    Arrow, ArrowheadStyle, Ellipse, FillPattern, FontFamily, FontWeight, Freehand, Group, Image,
+    ImageFormat, Line, Math, PathStyle, Rectangle, SerializableColor, Shape, ShapeId, ShapeStyle,
+    ShapeTrait, Sloppiness, StickyNote, StrokeStyle, Text, TextAlign,
 };
+use crate::snap::PortId;
 use kurbo::Point;
 use loro::{LoroList, LoroMap, LoroMapValue, LoroResult, LoroValue};
-use uuid::Uuid;
 
 // Shape type identifiers
 const TYPE_RECTANGLE: &str = "rectangle";
@@ -18,6 +18,7 @@ const TYPE_FREEHAND: &str = "freehand";
 const TYPE_TEXT: &str = "text";
 const TYPE_GROUP: &str = "group";
 const TYPE_IMAGE: &str = "image";
+const TYPE_STICKY_NOTE: &str = "sticky_note";
 
 // Group keys
 const KEY_CHILDREN: &str = "children";
@@ -38,6 +39,10 @@ const KEY_FILL_B: &str = "fill_b";
 const KEY_FILL_A: &str = "fill_a";
 const KEY_HAS_FILL: &str = "has_fill";
 const KEY_FILL_PATTERN: &str = "fill_pattern";
+/// Gradient/solid fill, stored as JSON.
+const KEY_FILL: &str = "fill";
+/// Drop shadow, stored as JSON.
+const KEY_SHADOW: &str = "shadow";
 const KEY_SLOPPINESS: &str = "sloppiness";
 const KEY_SEED: &str = "seed";
 
@@ -56,7 +61,20 @@ const KEY_END_Y: &str = "end_y";
 const KEY_INTERMEDIATE_POINTS: &str = "intermediate_points";
 const KEY_PATH_STYLE: &str = "path_style";
 const KEY_STROKE_STYLE: &str = "stroke_style";
+const KEY_LINE_CAP: &str = "line_cap";
+const KEY_LINE_JOIN: &str = "line_join";
+const KEY_DASH: &str = "dash";
+const KEY_GAP: &str = "gap";
 const KEY_HEAD_SIZE: &str = "head_size";
+const KEY_START_HEAD: &str = "start_head";
+const KEY_END_HEAD: &str = "end_head";
+const KEY_START_BINDING: &str = "start_binding";
+const KEY_END_BINDING: &str = "end_binding";
+const KEY_START_PORT: &str = "start_port";
+const KEY_END_PORT: &str = "end_port";
+const KEY_LABEL: &str = "label";
+const KEY_LABEL_POSITION: &str = "label_position";
+const KEY_CURVATURE: &str = "curvature";
 
 // Freehand keys
 const KEY_POINTS: &str = "points";
@@ -68,6 +86,12 @@ const KEY_FONT_SIZE: &str = "font_size";
 const KEY_FONT_FAMILY: &str = "font_family";
 const KEY_FONT_WEIGHT: &str = "font_weight";
 const KEY_CHAR_COLORS: &str = "char_colors";
+const KEY_WRAP_WIDTH: &str = "wrap_width";
+const KEY_ALIGN: &str = "align";
+
+// Sticky note keys (nested shape maps)
+const KEY_BACKGROUND: &str = "background";
+const KEY_TEXT: &str = "text";
 
 // Image keys
 const KEY_SOURCE_WIDTH: &str = "source_width";
@@ -78,6 +102,9 @@ const KEY_DATA_BASE64: &str = "data_base64";
 // Rotation key (shared by Rectangle, Ellipse, Text, Image)
 const KEY_ROTATION: &str = "rotation";
 
+// Lock flag (shared by all shapes, absent when unlocked)
+const KEY_LOCKED: &str = "locked";
+
 // Helper functions to extract values from LoroMapValue
 fn get_double(map: &LoroMapValue, key: &str) -> Option<f64> {
     match map.get(key)? {
@@ -109,8 +136,8 @@ fn get_bool(map: &LoroMapValue, key: &str) -> Option<bool> {
     }
 }
 
-fn get_id(map: &LoroMapValue) -> Option<Uuid> {
-    Uuid::parse_str(&get_string(map, KEY_ID)?).ok()
+fn get_id(map: &LoroMapValue) -> Option<ShapeId> {
+    get_string(map, KEY_ID)?.parse().ok()
 }
 
 /// Convert a Shape to Loro map entries.
@@ -145,7 +172,9 @@ pub fn shape_to_loro(shape: &Shape, map: &LoroMap) -> LoroResult<()> {
             map.insert(KEY_END_X, line.end.x)?;
             map.insert(KEY_END_Y, line.end.y)?;
             map.insert(KEY_PATH_STYLE, path_style_to_i64(line.path_style))?;
-            map.insert(KEY_STROKE_STYLE, stroke_style_to_i64(line.stroke_style))?;
+            stroke_style_to_loro(line.stroke_style, map)?;
+            map.insert(KEY_LINE_CAP, line_cap_to_i64(line.line_cap))?;
+            map.insert(KEY_LINE_JOIN, line_join_to_i64(line.line_join))?;
             let pts_list = map.insert_container(KEY_INTERMEDIATE_POINTS, LoroList::new())?;
             for p in &line.intermediate_points {
                 let pt = pts_list.insert_container(pts_list.len(), LoroList::new())?;
@@ -162,8 +191,34 @@ pub fn shape_to_loro(shape: &Shape, map: &LoroMap) -> LoroResult<()> {
             map.insert(KEY_END_X, arrow.end.x)?;
             map.insert(KEY_END_Y, arrow.end.y)?;
             map.insert(KEY_HEAD_SIZE, arrow.head_size)?;
+            map.insert(KEY_START_HEAD, arrow.start_head.index() as i64)?;
+            map.insert(KEY_END_HEAD, arrow.end_head.index() as i64)?;
+            match arrow.start_binding {
+                Some(id) => map.insert(KEY_START_BINDING, id.to_string())?,
+                None => map.delete(KEY_START_BINDING)?,
+            }
+            match arrow.end_binding {
+                Some(id) => map.insert(KEY_END_BINDING, id.to_string())?,
+                None => map.delete(KEY_END_BINDING)?,
+            }
+            match arrow.start_port {
+                Some(port) => map.insert(KEY_START_PORT, port.index() as i64)?,
+                None => map.delete(KEY_START_PORT)?,
+            }
+            match arrow.end_port {
+                Some(port) => map.insert(KEY_END_PORT, port.index() as i64)?,
+                None => map.delete(KEY_END_PORT)?,
+            }
+            match &arrow.label {
+                Some(label) => map.insert(KEY_LABEL, label.clone())?,
+                None => map.delete(KEY_LABEL)?,
+            }
+            map.insert(KEY_LABEL_POSITION, arrow.label_position)?;
+            map.insert(KEY_CURVATURE, arrow.curvature)?;
             map.insert(KEY_PATH_STYLE, path_style_to_i64(arrow.path_style))?;
-            map.insert(KEY_STROKE_STYLE, stroke_style_to_i64(arrow.stroke_style))?;
+            stroke_style_to_loro(arrow.stroke_style, map)?;
+            map.insert(KEY_LINE_CAP, line_cap_to_i64(arrow.line_cap))?;
+            map.insert(KEY_LINE_JOIN, line_join_to_i64(arrow.line_join))?;
             let pts_list = map.insert_container(KEY_INTERMEDIATE_POINTS, LoroList::new())?;
             for p in &arrow.intermediate_points {
                 let pt = pts_list.insert_container(pts_list.len(), LoroList::new())?;
@@ -200,6 +255,11 @@ pub fn shape_to_loro(shape: &Shape, map: &LoroMap) -> LoroResult<()> {
             map.insert(KEY_FONT_FAMILY, font_family_to_i64(text.font_family))?;
             map.insert(KEY_FONT_WEIGHT, font_weight_to_i64(text.font_weight))?;
             map.insert(KEY_ROTATION, text.rotation)?;
+            match text.wrap_width {
+                Some(width) => map.insert(KEY_WRAP_WIDTH, width)?,
+                None => map.delete(KEY_WRAP_WIDTH)?,
+            }
+            map.insert(KEY_ALIGN, text_align_to_i64(text.align))?;
             // Serialize char_colors as list of [r,g,b,a] or null
             let colors_list = map.insert_container(KEY_CHAR_COLORS, LoroList::new())?;
             for color in &text.char_colors {
@@ -247,9 +307,23 @@ pub fn shape_to_loro(shape: &Shape, map: &LoroMap) -> LoroResult<()> {
             map.insert(KEY_Y, math.position.y)?;
             map.insert(KEY_CONTENT, math.latex.clone())?;
             map.insert(KEY_FONT_SIZE, math.font_size)?;
+            map.insert(KEY_FONT_FAMILY, font_family_to_i64(math.font_family))?;
             map.insert(KEY_ROTATION, math.rotation)?;
             style_to_loro(&math.style, map)?;
         }
+        Shape::StickyNote(note) => {
+            map.insert(KEY_TYPE, TYPE_STICKY_NOTE)?;
+            map.insert(KEY_ID, note.id().to_string())?;
+            let background = map.insert_container(KEY_BACKGROUND, LoroMap::new())?;
+            shape_to_loro(&Shape::Rectangle(note.background.clone()), &background)?;
+            let text = map.insert_container(KEY_TEXT, LoroMap::new())?;
+            shape_to_loro(&Shape::Text(note.text.clone()), &text)?;
+        }
+    }
+    if shape.is_locked() {
+        map.insert(KEY_LOCKED, true)?;
+    } else {
+        map.delete(KEY_LOCKED)?;
     }
     Ok(())
 }
@@ -276,6 +350,19 @@ fn style_to_loro(style: &ShapeStyle, map: &LoroMap) -> LoroResult<()> {
         map.insert(KEY_HAS_FILL, false)?;
     }
 
+    match style
+        .fill
+        .as_ref()
+        .and_then(|f| serde_json::to_string(f).ok())
+    {
+        Some(json) => map.insert(KEY_FILL, json)?,
+        None => map.delete(KEY_FILL)?,
+    }
+    match style.shadow.and_then(|s| serde_json::to_string(&s).ok()) {
+        Some(json) => map.insert(KEY_SHADOW, json)?,
+        None => map.delete(KEY_SHADOW)?,
+    }
+
     Ok(())
 }
 
@@ -283,7 +370,7 @@ fn style_to_loro(style: &ShapeStyle, map: &LoroMap) -> LoroResult<()> {
 pub fn shape_from_loro(map: &LoroMapValue) -> Option<Shape> {
     let shape_type = get_string(map, KEY_TYPE)?;
 
-    match shape_type.as_str() {
+    let mut shape = match shape_type.as_str() {
         TYPE_RECTANGLE => rectangle_from_loro(map),
         TYPE_ELLIPSE => ellipse_from_loro(map),
         TYPE_LINE => line_from_loro(map),
@@ -293,8 +380,11 @@ pub fn shape_from_loro(map: &LoroMapValue) -> Option<Shape> {
         TYPE_GROUP => group_from_loro(map),
         TYPE_IMAGE => image_from_loro(map),
         "math" => math_from_loro(map),
+        TYPE_STICKY_NOTE => sticky_note_from_loro(map),
         _ => None,
-    }
+    }?;
+    shape.set_locked(get_bool(map, KEY_LOCKED).unwrap_or(false));
+    Some(shape)
 }
 
 fn rectangle_from_loro(map: &LoroMapValue) -> Option<Shape> {
@@ -321,7 +411,7 @@ fn ellipse_from_loro(map: &LoroMapValue) -> Option<Shape> {
 }
 
 fn line_from_loro(map: &LoroMapValue) -> Option<Shape> {
-    Some(Shape::Line(Line::reconstruct(
+    let mut line = Line::reconstruct(
         get_id(map)?,
         Point::new(get_double(map, KEY_START_X)?, get_double(map, KEY_START_Y)?),
         Point::new(get_double(map, KEY_END_X)?, get_double(map, KEY_END_Y)?),
@@ -329,15 +419,15 @@ fn line_from_loro(map: &LoroMapValue) -> Option<Shape> {
         get_i64(map, KEY_PATH_STYLE)
             .map(i64_to_path_style)
             .unwrap_or_default(),
-        get_i64(map, KEY_STROKE_STYLE)
-            .map(i64_to_stroke_style)
-            .unwrap_or_default(),
+        stroke_style_from_loro(map),
         style_from_loro(map)?,
-    )))
+    );
+    (line.line_cap, line.line_join) = caps_from_loro(map);
+    Some(Shape::Line(line))
 }
 
 fn arrow_from_loro(map: &LoroMapValue) -> Option<Shape> {
-    Some(Shape::Arrow(Arrow::reconstruct(
+    let mut arrow = Arrow::reconstruct(
         get_id(map)?,
         Point::new(get_double(map, KEY_START_X)?, get_double(map, KEY_START_Y)?),
         Point::new(get_double(map, KEY_END_X)?, get_double(map, KEY_END_Y)?),
@@ -345,12 +435,31 @@ fn arrow_from_loro(map: &LoroMapValue) -> Option<Shape> {
         get_i64(map, KEY_PATH_STYLE)
             .map(i64_to_path_style)
             .unwrap_or_default(),
-        get_i64(map, KEY_STROKE_STYLE)
-            .map(i64_to_stroke_style)
-            .unwrap_or_default(),
+        stroke_style_from_loro(map),
         get_double(map, KEY_HEAD_SIZE).unwrap_or(15.0),
         style_from_loro(map)?,
-    )))
+    );
+    if let Some(v) = get_i64(map, KEY_START_HEAD) {
+        arrow.start_head = ArrowheadStyle::from_index(v.clamp(0, 4) as u8);
+    }
+    if let Some(v) = get_i64(map, KEY_END_HEAD) {
+        arrow.end_head = ArrowheadStyle::from_index(v.clamp(0, 4) as u8);
+    }
+    let binding = |key| get_string(map, key).and_then(|s| s.parse::<ShapeId>().ok());
+    arrow.start_binding = binding(KEY_START_BINDING);
+    arrow.end_binding = binding(KEY_END_BINDING);
+    let port = |key| get_i64(map, key).and_then(|v| PortId::from_index(u8::try_from(v).ok()?));
+    arrow.start_port = port(KEY_START_PORT);
+    arrow.end_port = port(KEY_END_PORT);
+    arrow.label = get_string(map, KEY_LABEL);
+    if let Some(t) = get_double(map, KEY_LABEL_POSITION) {
+        arrow.label_position = t;
+    }
+    (arrow.line_cap, arrow.line_join) = caps_from_loro(map);
+    if let Some(curvature) = get_double(map, KEY_CURVATURE) {
+        arrow.curvature = curvature;
+    }
+    Some(Shape::Arrow(arrow))
 }
 
 fn freehand_from_loro(map: &LoroMapValue) -> Option<Shape> {
@@ -379,6 +488,10 @@ fn text_from_loro(map: &LoroMapValue) -> Option<Shape> {
         get_double(map, KEY_ROTATION).unwrap_or(0.0),
         style_from_loro(map)?,
         char_colors,
+        get_double(map, KEY_WRAP_WIDTH),
+        get_i64(map, KEY_ALIGN)
+            .map(i64_to_text_align)
+            .unwrap_or_default(),
     )))
 }
 
@@ -402,6 +515,23 @@ fn group_from_loro(map: &LoroMapValue) -> Option<Shape> {
     Some(Shape::Group(Group::reconstruct(get_id(map)?, children)))
 }
 
+fn sticky_note_from_loro(map: &LoroMapValue) -> Option<Shape> {
+    let nested = |key: &str| match map.get(key)? {
+        LoroValue::Map(nested) => shape_from_loro(&nested.clone()),
+        _ => None,
+    };
+    let (Some(Shape::Rectangle(background)), Some(Shape::Text(text))) =
+        (nested(KEY_BACKGROUND), nested(KEY_TEXT))
+    else {
+        return None;
+    };
+    Some(Shape::StickyNote(StickyNote::reconstruct(
+        get_id(map)?,
+        background,
+        text,
+    )))
+}
+
 fn image_from_loro(map: &LoroMapValue) -> Option<Shape> {
     Some(Shape::Image(Image::reconstruct(
         get_id(map)?,
@@ -425,6 +555,9 @@ fn math_from_loro(map: &LoroMapValue) -> Option<Shape> {
         Point::new(get_double(map, KEY_X)?, get_double(map, KEY_Y)?),
         get_string(map, KEY_CONTENT)?,
         get_double(map, KEY_FONT_SIZE).unwrap_or(Math::DEFAULT_FONT_SIZE),
+        get_i64(map, KEY_FONT_FAMILY)
+            .map(i64_to_font_family)
+            .unwrap_or_default(),
         get_double(map, KEY_ROTATION).unwrap_or(0.0),
         style_from_loro(map)?,
     )))
@@ -535,14 +668,19 @@ fn style_from_loro(map: &LoroMapValue) -> Option<ShapeStyle> {
         None
     };
 
+    let fill = get_string(map, KEY_FILL).and_then(|json| serde_json::from_str(&json).ok());
+    let shadow = get_string(map, KEY_SHADOW).and_then(|json| serde_json::from_str(&json).ok());
+
     Some(ShapeStyle {
         stroke_color: SerializableColor::new(stroke_r, stroke_g, stroke_b, stroke_a),
         stroke_width,
         fill_color,
         fill_pattern,
+        fill,
+        shadow,
         sloppiness,
         seed,
-        opacity: get_double(map, "opacity").unwrap_or(1.0),
+        opacity: get_double(map, "opacity").map_or(1.0, |o| o.clamp(0.0, 1.0)),
     })
 }
 
@@ -571,6 +709,7 @@ fn path_style_to_i64(s: PathStyle) -> i64 {
         PathStyle::Direct => 0,
         PathStyle::Flowing => 1,
         PathStyle::Angular => 2,
+        PathStyle::Curved => 3,
     }
 }
 
@@ -578,23 +717,61 @@ fn i64_to_path_style(v: i64) -> PathStyle {
     match v {
         0 => PathStyle::Direct,
         1 => PathStyle::Flowing,
+        3 => PathStyle::Curved,
         _ => PathStyle::Angular,
     }
 }
 
-fn stroke_style_to_i64(s: StrokeStyle) -> i64 {
-    match s {
-        StrokeStyle::Solid => 0,
-        StrokeStyle::Dashed => 1,
-        StrokeStyle::Dotted => 2,
+fn line_cap_to_i64(cap: LineCap) -> i64 {
+    match cap {
+        LineCap::Butt => 0,
+        LineCap::Round => 1,
+        LineCap::Square => 2,
     }
 }
 
-fn i64_to_stroke_style(v: i64) -> StrokeStyle {
-    match v {
-        0 => StrokeStyle::Solid,
-        1 => StrokeStyle::Dashed,
-        _ => StrokeStyle::Dotted,
+fn line_join_to_i64(join: LineJoin) -> i64 {
+    match join {
+        LineJoin::Miter => 0,
+        LineJoin::Round => 1,
+        LineJoin::Bevel => 2,
+    }
+}
+
+/// Line cap and join of a line or arrow, defaulting when missing.
+fn caps_from_loro(map: &LoroMapValue) -> (LineCap, LineJoin) {
+    let cap = match get_i64(map, KEY_LINE_CAP) {
+        Some(0) => LineCap::Butt,
+        Some(2) => LineCap::Square,
+        _ => LineCap::Round,
+    };
+    let join = match get_i64(map, KEY_LINE_JOIN) {
+        Some(0) => LineJoin::Miter,
+        Some(2) => LineJoin::Bevel,
+        _ => LineJoin::Round,
+    };
+    (cap, join)
+}
+
+fn stroke_style_to_loro(s: StrokeStyle, map: &LoroMap) -> LoroResult<()> {
+    map.insert(KEY_STROKE_STYLE, s.index() as i64)?;
+    if let StrokeStyle::Dashed { dash, gap } = s {
+        map.insert(KEY_DASH, dash)?;
+        map.insert(KEY_GAP, gap)?;
+    }
+    Ok(())
+}
+
+fn stroke_style_from_loro(map: &LoroMapValue) -> StrokeStyle {
+    match get_i64(map, KEY_STROKE_STYLE) {
+        None => StrokeStyle::default(),
+        Some(v) => match StrokeStyle::from_index(v.clamp(0, 2) as u8) {
+            StrokeStyle::Dashed { dash, gap } => StrokeStyle::Dashed {
+                dash: get_double(map, KEY_DASH).unwrap_or(dash),
+                gap: get_double(map, KEY_GAP).unwrap_or(gap),
+            },
+            style => style,
+        },
     }
 }
 
@@ -632,6 +809,24 @@ fn i64_to_font_weight(v: i64) -> FontWeight {
     }
 }
 
+fn text_align_to_i64(a: TextAlign) -> i64 {
+    match a {
+        TextAlign::Left => 0,
+        TextAlign::Center => 1,
+        TextAlign::Right => 2,
+        TextAlign::Justify => 3,
+    }
+}
+
+fn i64_to_text_align(v: i64) -> TextAlign {
+    match v {
+        1 => TextAlign::Center,
+        2 => TextAlign::Right,
+        3 => TextAlign::Justify,
+        _ => TextAlign::Left,
+    }
+}
+
 fn image_format_to_i64(f: ImageFormat) -> i64 {
     match f {
         ImageFormat::Png => 0,
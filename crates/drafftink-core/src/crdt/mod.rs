@@ -30,9 +30,224 @@ pub use loro::{ExportMode, VersionVector};
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::shapes::{FillPattern, Rectangle, SerializableColor, Shape, ShapeStyle, Sloppiness};
+    use crate::shapes::{
+        FillPattern, Line, Rectangle, SerializableColor, Shape, ShapeStyle, Sloppiness, StrokeStyle,
+    };
     use kurbo::Point;
 
+    #[test]
+    fn test_roundtrip_gradient_fill() {
+        use crate::shapes::{Fill, GradientStop};
+
+        let mut doc = CrdtDocument::new();
+        let mut rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 50.0);
+        let gradient = Fill::LinearGradient {
+            stops: vec![
+                GradientStop::new(0.0, SerializableColor::new(255, 0, 0, 255)),
+                GradientStop::new(1.0, SerializableColor::new(0, 0, 255, 200)),
+            ],
+            angle: 0.5,
+        };
+        rect.style.fill = Some(gradient.clone());
+        let mut shape = Shape::Rectangle(rect);
+        let id = shape.id().to_string();
+        doc.add_shape(&shape).unwrap();
+        assert_eq!(doc.get_shape(&id).unwrap().style().fill, Some(gradient));
+
+        // Clearing the gradient removes it from the CRDT too
+        shape.style_mut().fill = None;
+        doc.update_shape(&shape).unwrap();
+        assert_eq!(doc.get_shape(&id).unwrap().style().fill, None);
+    }
+
+    #[test]
+    fn test_roundtrip_shadow() {
+        use crate::shapes::Shadow;
+
+        let mut doc = CrdtDocument::new();
+        let mut rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 50.0);
+        rect.style.shadow = Some(Shadow::default());
+        let mut shape = Shape::Rectangle(rect);
+        let id = shape.id().to_string();
+        doc.add_shape(&shape).unwrap();
+        assert_eq!(
+            doc.get_shape(&id).unwrap().style().shadow,
+            Some(Shadow::default())
+        );
+
+        shape.style_mut().shadow = None;
+        doc.update_shape(&shape).unwrap();
+        assert_eq!(doc.get_shape(&id).unwrap().style().shadow, None);
+    }
+
+    #[test]
+    fn test_roundtrip_math_font_family() {
+        use crate::shapes::{FontFamily, Math};
+
+        let mut doc = CrdtDocument::new();
+        let mut math = Math::new(Point::new(0.0, 0.0), "x^2".to_string());
+        math.font_family = FontFamily::VanillaExtract;
+        let shape = Shape::Math(math);
+        let id = shape.id().to_string();
+        doc.add_shape(&shape).unwrap();
+        let Some(Shape::Math(math)) = doc.get_shape(&id) else {
+            panic!("expected math shape");
+        };
+        assert_eq!(math.font_family, FontFamily::VanillaExtract);
+    }
+
+    #[test]
+    fn test_roundtrip_arrow_label() {
+        use crate::shapes::Arrow;
+
+        let mut doc = CrdtDocument::new();
+        let mut arrow = Arrow::new(Point::new(0.0, 0.0), Point::new(100.0, 0.0));
+        arrow.label = Some("yes".to_string());
+        arrow.label_position = 0.25;
+        let shape = Shape::Arrow(arrow);
+        let id = shape.id().to_string();
+        doc.add_shape(&shape).unwrap();
+        let Some(Shape::Arrow(restored)) = doc.get_shape(&id) else {
+            panic!("arrow missing");
+        };
+        assert_eq!(restored.label.as_deref(), Some("yes"));
+        assert_eq!(restored.label_position, 0.25);
+    }
+
+    #[test]
+    fn test_roundtrip_text_wrap_and_align() {
+        use crate::shapes::{Text, TextAlign};
+
+        let mut doc = CrdtDocument::new();
+        let text = Text::new(Point::new(0.0, 0.0), "wrapped".to_string())
+            .with_wrap_width(120.0)
+            .with_align(TextAlign::Right);
+        let shape = Shape::Text(text);
+        let id = shape.id().to_string();
+        doc.add_shape(&shape).unwrap();
+        let Some(Shape::Text(restored)) = doc.get_shape(&id) else {
+            panic!("text missing");
+        };
+        assert_eq!(restored.wrap_width, Some(120.0));
+        assert_eq!(restored.align, TextAlign::Right);
+
+        let mut unwrapped = restored.clone();
+        unwrapped.wrap_width = None;
+        doc.update_shape(&Shape::Text(unwrapped)).unwrap();
+        let Some(Shape::Text(restored)) = doc.get_shape(&id) else {
+            panic!("text missing");
+        };
+        assert_eq!(restored.wrap_width, None);
+    }
+
+    #[test]
+    fn test_roundtrip_sticky_note() {
+        use crate::shapes::{ShapeTrait, StickyNote, StickyNoteColor, TextAlign};
+
+        let mut doc = CrdtDocument::new();
+        let mut note = StickyNote::new(Point::new(10.0, 20.0), "remember".to_string())
+            .with_color(StickyNoteColor::Green);
+        note.background.width = 300.0;
+        note.fit_text();
+        let shape = Shape::StickyNote(note);
+        let id = shape.id().to_string();
+        doc.add_shape(&shape).unwrap();
+        let Some(Shape::StickyNote(restored)) = doc.get_shape(&id) else {
+            panic!("sticky note missing");
+        };
+        assert_eq!(restored.id().to_string(), id);
+        assert_eq!(restored.text.id(), restored.id());
+        assert_eq!(restored.content(), "remember");
+        assert_eq!(restored.bounds(), shape.bounds());
+        assert_eq!(restored.text.align, TextAlign::Center);
+        assert_eq!(
+            restored.text.wrap_width,
+            Some(300.0 - 2.0 * StickyNote::PADDING)
+        );
+        assert_eq!(
+            restored.style().fill_color,
+            Some(StickyNoteColor::Green.color())
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_curved_arrow() {
+        use crate::shapes::{Arrow, PathStyle};
+
+        let mut doc = CrdtDocument::new();
+        let mut arrow = Arrow::new(Point::new(0.0, 0.0), Point::new(100.0, 0.0));
+        arrow.path_style = PathStyle::Curved;
+        arrow.curvature = -0.4;
+        let shape = Shape::Arrow(arrow);
+        let id = shape.id().to_string();
+        doc.add_shape(&shape).unwrap();
+        let Some(Shape::Arrow(restored)) = doc.get_shape(&id) else {
+            panic!("arrow missing");
+        };
+        assert_eq!(restored.path_style, PathStyle::Curved);
+        assert_eq!(restored.curvature, -0.4);
+    }
+
+    #[test]
+    fn test_roundtrip_line_cap_and_join() {
+        use crate::shapes::{Line, LineCap, LineJoin};
+
+        let mut doc = CrdtDocument::new();
+        let mut line = Line::new(Point::new(0.0, 0.0), Point::new(100.0, 0.0));
+        line.line_cap = LineCap::Butt;
+        line.line_join = LineJoin::Bevel;
+        let shape = Shape::Line(line);
+        let id = shape.id().to_string();
+        doc.add_shape(&shape).unwrap();
+        let Some(Shape::Line(restored)) = doc.get_shape(&id) else {
+            panic!("line missing");
+        };
+        assert_eq!(restored.line_cap, LineCap::Butt);
+        assert_eq!(restored.line_join, LineJoin::Bevel);
+    }
+
+    #[test]
+    fn test_roundtrip_locked() {
+        use crate::shapes::Rectangle;
+
+        let mut doc = CrdtDocument::new();
+        let mut shape = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 10.0, 10.0));
+        shape.set_locked(true);
+        let id = shape.id().to_string();
+        doc.add_shape(&shape).unwrap();
+        assert!(doc.get_shape(&id).unwrap().is_locked());
+    }
+
+    #[test]
+    fn test_roundtrip_arrow_bindings() {
+        use crate::shapes::Arrow;
+
+        let mut doc = CrdtDocument::new();
+        let target = crate::shapes::ShapeId::new_v4();
+        let mut arrow = Arrow::new(Point::new(0.0, 0.0), Point::new(100.0, 0.0));
+        arrow.end_binding = Some(target);
+        arrow.end_port = Some(crate::snap::PortId::SW);
+        let mut shape = Shape::Arrow(arrow);
+        let id = shape.id().to_string();
+        doc.add_shape(&shape).unwrap();
+        let Some(Shape::Arrow(restored)) = doc.get_shape(&id) else {
+            panic!("arrow missing");
+        };
+        assert_eq!(restored.start_binding, None);
+        assert_eq!(restored.end_binding, Some(target));
+        assert_eq!(restored.start_port, None);
+        assert_eq!(restored.end_port, Some(crate::snap::PortId::SW));
+
+        if let Shape::Arrow(arrow) = &mut shape {
+            arrow.end_binding = None;
+        }
+        doc.update_shape(&shape).unwrap();
+        let Some(Shape::Arrow(restored)) = doc.get_shape(&id) else {
+            panic!("arrow missing");
+        };
+        assert_eq!(restored.end_binding, None);
+    }
+
     #[test]
     fn test_crdt_document_creation() {
         let doc = CrdtDocument::new();
@@ -63,6 +278,8 @@ mod tests {
             stroke_width: 3.0,
             fill_color: Some(SerializableColor::new(0, 255, 0, 128)),
             fill_pattern: FillPattern::default(),
+            fill: None,
+            shadow: None,
             sloppiness: Sloppiness::Artist,
             seed: 12345,
             opacity: 1.0,
@@ -92,6 +309,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_roundtrip_dashed_line() {
+        let mut doc = CrdtDocument::new();
+
+        let mut line = Line::new(Point::new(0.0, 0.0), Point::new(100.0, 0.0));
+        line.stroke_style = StrokeStyle::Dashed {
+            dash: 6.0,
+            gap: 3.0,
+        };
+        let original = Shape::Line(line);
+        doc.add_shape(&original).expect("Failed to add shape");
+
+        match doc.get_shape(&original.id().to_string()) {
+            Some(Shape::Line(l)) => assert_eq!(
+                l.stroke_style,
+                StrokeStyle::Dashed {
+                    dash: 6.0,
+                    gap: 3.0
+                }
+            ),
+            _ => panic!("Expected Line"),
+        }
+    }
+
     #[test]
     fn test_remove_shape() {
         let mut doc = CrdtDocument::new();
@@ -0,0 +1,93 @@
+//! Curved connector routing.
+//!
+//! A curved route is a single cubic Bézier that bows out to one side of the
+//! straight line between its endpoints, as an alternative to elbow routing.
+
+use kurbo::{BezPath, Point, Vec2};
+
+use crate::elbow::DEFAULT_LOOP_RADIUS;
+
+/// Default bow of a curved connector, as a fraction of its length.
+pub const DEFAULT_CURVATURE: f64 = 0.25;
+
+/// Compute a curved path from `start` to `end`.
+///
+/// Both control points are offset perpendicular to the straight line by
+/// `curvature` times its length, at one and two thirds of the way along, so
+/// the curve is symmetric and its midpoint lies `0.75 * curvature * length`
+/// off the line. Positive curvature bows to the left of the direction of
+/// travel in screen coordinates (y down), negative to the right, and 0 gives
+/// a straight line.
+///
+/// When `start` and `end` coincide, the path is a teardrop loop up and to
+/// the right of the point (down and to the left for negative curvature).
+pub fn compute_curved_path(start: Point, end: Point, curvature: f64) -> BezPath {
+    let mut path = BezPath::new();
+    path.move_to(start);
+
+    let delta = end - start;
+    let length = delta.hypot();
+    if length < f64::EPSILON {
+        let reach = 4.0 * DEFAULT_LOOP_RADIUS * if curvature < 0.0 { -1.0 } else { 1.0 };
+        path.curve_to(
+            start + Vec2::new(0.0, -reach),
+            start + Vec2::new(reach, 0.0),
+            end,
+        );
+        return path;
+    }
+
+    let offset = Vec2::new(delta.y, -delta.x) * curvature;
+    path.curve_to(
+        start + delta / 3.0 + offset,
+        start + delta * (2.0 / 3.0) + offset,
+        end,
+    );
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kurbo::{Line, ParamCurve, ParamCurveNearest, PathSeg};
+
+    fn only_segment(path: &BezPath) -> PathSeg {
+        let segments: Vec<_> = path.segments().collect();
+        assert_eq!(segments.len(), 1);
+        segments[0]
+    }
+
+    #[test]
+    fn test_zero_curvature_is_straight() {
+        let (start, end) = (Point::new(0.0, 0.0), Point::new(100.0, 50.0));
+        let seg = only_segment(&compute_curved_path(start, end, 0.0));
+        let line = Line::new(start, end);
+        for i in 0..=10 {
+            let p = seg.eval(i as f64 / 10.0);
+            assert!(line.nearest(p, 1e-9).distance_sq < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_positive_curvature_bows_midpoint() {
+        let (start, end) = (Point::new(0.0, 0.0), Point::new(100.0, 0.0));
+        let seg = only_segment(&compute_curved_path(start, end, 0.2));
+        let mid = seg.eval(0.5);
+        assert!((mid.x - 50.0).abs() < 1e-9);
+        // Bows up (left of travel) by 0.75 * curvature * length.
+        assert!((mid.y + 15.0).abs() < 1e-9);
+
+        let flipped = only_segment(&compute_curved_path(start, end, -0.2)).eval(0.5);
+        assert!((flipped.y - 15.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_coincident_points_loop() {
+        let point = Point::new(10.0, 10.0);
+        let seg = only_segment(&compute_curved_path(point, point, DEFAULT_CURVATURE));
+        assert_eq!(seg.eval(0.0), point);
+        assert_eq!(seg.eval(1.0), point);
+        let mid = seg.eval(0.5);
+        assert!(mid.x > point.x && mid.y < point.y);
+    }
+}
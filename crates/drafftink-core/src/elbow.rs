@@ -3,22 +3,131 @@
 //! Creates right-angle paths between two points with minimal turns.
 //! Uses departure/arrival waypoints to ensure clean entry/exit angles.
 
-use kurbo::Point;
+use std::collections::HashMap;
+
+use kurbo::{BezPath, Point, Rect, Vec2};
 use pathfinding::prelude::astar;
 
-const GRID_SIZE: f64 = 20.0;
+/// Default routing grid cell size in world units.
+pub const DEFAULT_GRID_SIZE: f64 = 20.0;
+
+/// Default exponent applied to the turn penalty.
+pub const DEFAULT_TURN_PENALTY_EXP: u32 = 3;
+
+/// Default radius of the loop drawn when start and end coincide.
+pub const DEFAULT_LOOP_RADIUS: f64 = 20.0;
+
+/// Default number of grid cells A* may expand before settling for a partial route.
+pub const DEFAULT_MAX_EXPANSIONS: usize = 50_000;
+
+/// Grid cells the A* search may stray beyond the endpoints and obstacles.
+const SEARCH_MARGIN_CELLS: i32 = 8;
+
+/// How strongly A* routing trades path length for fewer turns.
+///
+/// The bias scales the turn penalty exponent: each turn costs
+/// `base^(bias * turn_penalty_exp)` (see [`RoutingConfig`]).
+///
+/// - At [`TurnBias::MIN_TURNS`] (1.0, the default) a turn costs the full
+///   penalty, so routes take any detour that saves a bend.
+/// - At [`TurnBias::MIN_LENGTH`] (0.0) turns are free: the route is always a
+///   shortest path, but may bend more often than needed, e.g. as a staircase.
+///
+/// Values in between let dense diagrams accept some bends for compactness.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct TurnBias(f64);
+
+impl TurnBias {
+    /// Only path length counts.
+    pub const MIN_LENGTH: Self = Self(0.0);
+    /// Fewest turns dominates path length.
+    pub const MIN_TURNS: Self = Self(1.0);
+
+    /// Create a bias, clamped to `0.0..=1.0`. NaN becomes the default.
+    pub fn new(bias: f64) -> Self {
+        if bias.is_nan() {
+            Self::default()
+        } else {
+            Self(bias.clamp(0.0, 1.0))
+        }
+    }
+
+    /// The bias value in `0.0..=1.0`.
+    pub fn value(self) -> f64 {
+        self.0
+    }
+
+    /// Cost of one turn for the given penalty base and exponent.
+    fn turn_cost(self, base: u64, exp: u32) -> u64 {
+        if self.0 <= 0.0 {
+            0
+        } else if self.0 >= 1.0 {
+            base.saturating_pow(exp)
+        } else {
+            // Float to int casts saturate
+            (base as f64).powf(exp as f64 * self.0).round() as u64
+        }
+    }
+}
+
+impl Default for TurnBias {
+    fn default() -> Self {
+        Self::MIN_TURNS
+    }
+}
+
+/// Tuning parameters for elbow routing.
+///
+/// The turn penalty is the Manhattan distance between the departure and
+/// arrival waypoints measured in grid cells, raised to `turn_penalty_exp`
+/// (scaled by `turn_bias`; the rest of this describes the default bias).
+/// Because both the straight-line cost and the penalty base are counted in
+/// cells, their ratio stays the same when `grid_size` changes: a route of
+/// `d` cells costs roughly `d`, while a single turn costs `d^exp`. Any
+/// exponent of 2 or more therefore keeps "fewer turns" dominant over
+/// "shorter path" at every resolution. Halving `grid_size` doubles `d` and
+/// multiplies the turn cost by `2^exp`, so very fine grids on large
+/// diagrams grow the penalty quickly (it saturates instead of overflowing).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RoutingConfig {
+    /// Size of a routing grid cell in world units.
+    pub grid_size: f64,
+    /// Exponent applied to the turn penalty base (see type docs).
+    pub turn_penalty_exp: u32,
+    /// Trade-off between fewer turns and shorter routes.
+    pub turn_bias: TurnBias,
+    /// Half the side length of the self-loop emitted for coincident endpoints.
+    pub loop_radius: f64,
+    /// Most grid cells A* may expand. When the budget runs out the search
+    /// stops and the route is finished from the explored cell nearest the
+    /// target with a plain L, so dense obstacle fields stay responsive at the
+    /// cost of an optimal (or obstacle-free) route.
+    pub max_expansions: usize,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            grid_size: DEFAULT_GRID_SIZE,
+            turn_penalty_exp: DEFAULT_TURN_PENALTY_EXP,
+            turn_bias: TurnBias::default(),
+            loop_radius: DEFAULT_LOOP_RADIUS,
+            max_expansions: DEFAULT_MAX_EXPANSIONS,
+        }
+    }
+}
 
-fn to_grid(v: f64) -> i32 {
-    (v / GRID_SIZE).round() as i32
+fn to_grid(v: f64, grid_size: f64) -> i32 {
+    (v / grid_size).round() as i32
 }
 
-fn from_grid(v: i32) -> f64 {
-    v as f64 * GRID_SIZE
+fn from_grid(v: i32, grid_size: f64) -> f64 {
+    v as f64 * grid_size
 }
 
 /// Cardinal direction for orthogonal movement.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-enum Heading {
+pub enum Direction {
     Up,
     Down,
     Left,
@@ -26,14 +135,56 @@ enum Heading {
     None,
 }
 
-impl Heading {
-    fn reverse(&self) -> Heading {
+impl Direction {
+    /// Dominant direction of travel from `from` to `to` (`None` if coincident).
+    pub fn between(from: Point, to: Point) -> Direction {
+        let dx = to.x - from.x;
+        let dy = to.y - from.y;
+        if dx == 0.0 && dy == 0.0 {
+            Direction::None
+        } else if dx.abs() >= dy.abs() {
+            if dx > 0.0 {
+                Direction::Right
+            } else {
+                Direction::Left
+            }
+        } else if dy > 0.0 {
+            Direction::Down
+        } else {
+            Direction::Up
+        }
+    }
+
+    /// Unit vector in screen coordinates (y grows downward).
+    fn vector(&self) -> Vec2 {
         match self {
-            Heading::Up => Heading::Down,
-            Heading::Down => Heading::Up,
-            Heading::Left => Heading::Right,
-            Heading::Right => Heading::Left,
-            Heading::None => Heading::None,
+            Direction::Up => Vec2::new(0.0, -1.0),
+            Direction::Down => Vec2::new(0.0, 1.0),
+            Direction::Left => Vec2::new(-1.0, 0.0),
+            Direction::Right => Vec2::new(1.0, 0.0),
+            Direction::None => Vec2::ZERO,
+        }
+    }
+
+    /// Quarter turn counter-clockwise on screen (Right becomes Up).
+    fn rotate_ccw(&self) -> Direction {
+        match self {
+            Direction::Right => Direction::Up,
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::None => Direction::None,
+        }
+    }
+
+    /// The opposite direction.
+    pub fn reverse(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::None => Direction::None,
         }
     }
 }
@@ -43,142 +194,1301 @@ impl Heading {
 struct Cell {
     x: i32,
     y: i32,
-    heading: Heading,
+    heading: Direction,
 }
 
 impl Cell {
-    fn new(x: i32, y: i32, heading: Heading) -> Self {
+    fn new(x: i32, y: i32, heading: Direction) -> Self {
         Self { x, y, heading }
     }
 }
 
+/// Elbow route with the headings of its first and last segments.
+///
+/// Carries what editing widgets need to draw a handle per corner and orient
+/// the end caps without re-running the router.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElbowRoute {
+    /// Intermediate corner points (not including start and end).
+    pub corners: Vec<Point>,
+    /// Direction of the segment leaving the start point.
+    pub start_heading: Direction,
+    /// Direction of the segment arriving at the end point (arrowhead direction).
+    pub end_heading: Direction,
+    /// Length of the whole polyline from start through the corners to end.
+    pub total_length: f64,
+}
+
+impl ElbowRoute {
+    /// Build a route from its endpoints and corners, deriving the headings.
+    pub fn from_corners(start: Point, corners: Vec<Point>, end: Point) -> Self {
+        let mut points = Vec::with_capacity(corners.len() + 2);
+        points.push(start);
+        points.extend(corners.iter().copied());
+        points.push(end);
+
+        let start_heading = points
+            .windows(2)
+            .map(|w| Direction::between(w[0], w[1]))
+            .find(|h| *h != Direction::None)
+            .unwrap_or(Direction::None);
+        let end_heading = points
+            .windows(2)
+            .rev()
+            .map(|w| Direction::between(w[0], w[1]))
+            .find(|h| *h != Direction::None)
+            .unwrap_or(Direction::None);
+        let total_length = points.windows(2).map(|w| w[0].distance(w[1])).sum();
+
+        Self {
+            corners,
+            start_heading,
+            end_heading,
+            total_length,
+        }
+    }
+}
+
 fn manhattan(x1: i32, y1: i32, x2: i32, y2: i32) -> u64 {
     ((x1 - x2).abs() + (y1 - y2).abs()) as u64
 }
 
-/// Compute elbow path between two points.
+/// Compute elbow path between two points using the default [`RoutingConfig`].
 /// Returns intermediate corner points (not including start and end).
 pub fn compute_elbow_path(start: Point, end: Point) -> Vec<Point> {
+    compute_elbow_path_with(start, end, &RoutingConfig::default())
+}
+
+/// Compute elbow route (corners plus start/end headings) using the default config.
+pub fn compute_elbow_route(start: Point, end: Point) -> ElbowRoute {
+    compute_elbow_route_with(start, end, &RoutingConfig::default())
+}
+
+/// Compute elbow route (corners plus start/end headings) with explicit routing parameters.
+pub fn compute_elbow_route_with(start: Point, end: Point, config: &RoutingConfig) -> ElbowRoute {
+    ElbowRoute::from_corners(start, compute_elbow_path_with(start, end, config), end)
+}
+
+/// Compute elbow path between two points with explicit routing parameters.
+/// Returns intermediate corner points (not including start and end).
+pub fn compute_elbow_path_with(start: Point, end: Point, config: &RoutingConfig) -> Vec<Point> {
+    let grid_size = config.grid_size;
     let dx = end.x - start.x;
     let dy = end.y - start.y;
 
+    // Coincident endpoints - draw a self-loop instead of a zero-length line
+    if dx.abs() < grid_size && dy.abs() < grid_size {
+        return compute_self_loop(start, end, config.loop_radius, &[]);
+    }
+
     // Perfectly aligned - no intermediate points needed
-    if dx.abs() < GRID_SIZE {
+    if dx.abs() < grid_size {
         return vec![]; // Vertical line
     }
-    if dy.abs() < GRID_SIZE {
+    if dy.abs() < grid_size {
         return vec![]; // Horizontal line
     }
 
     // Departure heading: direction from start toward end (prefer horizontal)
-    let departure_heading = if dx.abs() >= dy.abs() {
-        if dx > 0.0 {
-            Heading::Right
+    let departure_heading = Direction::between(start, end);
+
+    // Without obstacles the optimal route is always a Z with its jog at the
+    // midpoint (like Excalidraw's dynamic bounds), so A* is not needed
+    direct_route(start, departure_heading, end, departure_heading, grid_size).unwrap_or_else(|| {
+        search(
+            start,
+            departure_heading,
+            end,
+            departure_heading,
+            config,
+            &[],
+        )
+    })
+}
+
+/// Compute elbow path between two points that steers around `obstacles`.
+///
+/// Uses A* on the routing grid and keeps the same departure and arrival axis
+/// as [`compute_elbow_path_with`], so arrowheads don't flip when an obstacle
+/// appears. Obstacles containing either endpoint are ignored (they are the
+/// shapes being connected). Falls back to the closed-form route when there is
+/// nothing to avoid.
+/// Returns intermediate corner points (not including start and end).
+pub fn compute_elbow_path_avoiding(
+    start: Point,
+    end: Point,
+    obstacles: &[Rect],
+    config: &RoutingConfig,
+) -> Vec<Point> {
+    let obstacles: Vec<Rect> = obstacles
+        .iter()
+        .filter(|r| !r.contains(start) && !r.contains(end))
+        .copied()
+        .collect();
+    if obstacles.is_empty() {
+        return compute_elbow_path_with(start, end, config);
+    }
+
+    let grid_size = config.grid_size;
+    if (end.x - start.x).abs() < grid_size && (end.y - start.y).abs() < grid_size {
+        return compute_self_loop(start, end, config.loop_radius, &obstacles);
+    }
+
+    let heading = Direction::between(start, end);
+    route_ports(start, heading, end, heading, config, &obstacles)
+}
+
+/// Compute elbow path from `start` to `end` passing through fixed `waypoints` in order.
+///
+/// Each leg between consecutive anchors is routed with the closed-form path
+/// when possible (falling back to A*), starting in the heading the previous leg
+/// arrived with. Among the closed-form shapes the one with the fewest corners
+/// wins, so a waypoint never forces an extra turn.
+/// Returns intermediate points (not including start and end). Waypoints are
+/// kept even when the route runs straight through them.
+pub fn compute_elbow_path_through(start: Point, waypoints: &[Point], end: Point) -> Vec<Point> {
+    compute_elbow_path_through_with(start, waypoints, end, &RoutingConfig::default())
+}
+
+/// Compute elbow path through fixed waypoints with explicit routing parameters.
+/// See [`compute_elbow_path_through`].
+pub fn compute_elbow_path_through_with(
+    start: Point,
+    waypoints: &[Point],
+    end: Point,
+    config: &RoutingConfig,
+) -> Vec<Point> {
+    if waypoints.is_empty() {
+        return compute_elbow_path_with(start, end, config);
+    }
+
+    let mut anchors = Vec::with_capacity(waypoints.len() + 2);
+    anchors.push(start);
+    anchors.extend_from_slice(waypoints);
+    anchors.push(end);
+
+    let mut points = vec![start];
+    let mut heading = Direction::None;
+    for leg in anchors.windows(2) {
+        let (from, to) = (leg[0], leg[1]);
+        if from == to {
+            continue;
+        }
+        if heading == Direction::None {
+            heading = Direction::between(from, to);
+        }
+
+        let corners = route_leg(from, heading, to, config);
+
+        // Carry the arrival heading into the next leg
+        let last = corners.last().copied().unwrap_or(from);
+        heading = if last == to {
+            heading
         } else {
-            Heading::Left
+            Direction::between(last, to)
+        };
+
+        for p in corners.into_iter().chain(std::iter::once(to)) {
+            if points.last() != Some(&p) {
+                points.push(p);
+            }
         }
-    } else if dy > 0.0 {
-        Heading::Down
+    }
+
+    // Every anchor coincides: no legs, so no corners
+    if points.len() < 2 {
+        return Vec::new();
+    }
+    points[1..points.len() - 1].to_vec()
+}
+
+/// Route a single leg already travelling along `heading`, preferring the
+/// closed-form shape with the fewest corners.
+fn route_leg(from: Point, heading: Direction, to: Point, config: &RoutingConfig) -> Vec<Point> {
+    [
+        heading,
+        heading.rotate_ccw(),
+        heading.rotate_ccw().reverse(),
+    ]
+    .into_iter()
+    .filter_map(|arrival| direct_route(from, heading, to, arrival, config.grid_size))
+    .min_by_key(|corners| corners.len())
+    .unwrap_or_else(|| search(from, heading, to, Direction::None, config, &[]))
+}
+
+/// Closed-form route for unobstructed cases needing at most two corners.
+///
+/// Handles a straight run or a midpoint Z when both headings agree, and a single
+/// L when they are perpendicular and the corner lies ahead of both. The Z's
+/// jog is snapped to `grid_size` like A* corners, unless no grid line lies
+/// strictly between the endpoints. Returns `None` when a detour is required,
+/// leaving those cases to A*.
+fn direct_route(
+    start: Point,
+    start_heading: Direction,
+    end: Point,
+    end_heading: Direction,
+    grid_size: f64,
+) -> Option<Vec<Point>> {
+    const EPS: f64 = 1e-9;
+    let out = start_heading.vector();
+    let delta = end - start;
+
+    if start_heading == Direction::None || end_heading == Direction::None {
+        return None;
+    }
+
+    if start_heading == end_heading {
+        let ahead = delta.dot(out);
+        let lateral = delta - out * ahead;
+        if lateral.hypot() < EPS {
+            return (ahead >= 0.0).then(Vec::new);
+        }
+        if ahead <= 0.0 {
+            return None;
+        }
+        let mut jog = start + out * (ahead / 2.0);
+        if grid_size > 0.0 {
+            let snap = |v: f64| from_grid(to_grid(v, grid_size), grid_size);
+            let between = |v: f64, a: f64, b: f64| v > a.min(b) + EPS && v < a.max(b) - EPS;
+            if out.x != 0.0 {
+                let x = snap(jog.x);
+                if between(x, start.x, end.x) {
+                    jog.x = x;
+                }
+            } else {
+                let y = snap(jog.y);
+                if between(y, start.y, end.y) {
+                    jog.y = y;
+                }
+            }
+        }
+        return Some(vec![jog, jog + lateral]);
+    }
+
+    if start_heading.reverse() == end_heading {
+        return None;
+    }
+
+    let corner = if out.x != 0.0 {
+        Point::new(end.x, start.y)
+    } else {
+        Point::new(start.x, end.y)
+    };
+    let leaves_forward = (corner - start).dot(out) > EPS;
+    let arrives_forward = (end - corner).dot(end_heading.vector()) > EPS;
+    (leaves_forward && arrives_forward).then(|| vec![corner])
+}
+
+/// A connection port on a shape boundary.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Port {
+    /// Attachment point on the boundary.
+    pub point: Point,
+    /// Outward normal of the boundary at the port (direction of travel when leaving).
+    pub heading: Direction,
+}
+
+/// Pick departure and arrival ports on the edge midpoints of two rects.
+///
+/// The facing edges are chosen from the relative position of the rect centers,
+/// preferring horizontal connections when the offsets are equal.
+pub fn connection_ports(from: Rect, to: Rect) -> (Port, Port) {
+    let from_center = from.center();
+    let to_center = to.center();
+    let side = Direction::between(from_center, to_center);
+    let side = if side == Direction::None {
+        Direction::Right
+    } else {
+        side
+    };
+
+    (
+        Port {
+            point: edge_midpoint(from, side),
+            heading: side,
+        },
+        Port {
+            point: edge_midpoint(to, side.reverse()),
+            heading: side.reverse(),
+        },
+    )
+}
+
+fn edge_midpoint(rect: Rect, side: Direction) -> Point {
+    let center = rect.center();
+    match side {
+        Direction::Up => Point::new(center.x, rect.y0),
+        Direction::Down => Point::new(center.x, rect.y1),
+        Direction::Left => Point::new(rect.x0, center.y),
+        Direction::Right | Direction::None => Point::new(rect.x1, center.y),
+    }
+}
+
+/// Compute elbow path connecting two shape bounds using the default config.
+/// Returns the full path including the departure and arrival ports.
+pub fn compute_elbow_path_between(from: Rect, to: Rect) -> Vec<Point> {
+    compute_elbow_path_between_with(from, to, &RoutingConfig::default())
+}
+
+/// Compute elbow path connecting two shape bounds with explicit routing parameters.
+///
+/// Ports are picked by [`connection_ports`]. The route first moves one grid cell
+/// along each port's outward heading, so it never doubles back into its own shape.
+/// Returns the full path including the departure and arrival ports.
+pub fn compute_elbow_path_between_with(from: Rect, to: Rect, config: &RoutingConfig) -> Vec<Point> {
+    let (out_port, in_port) = connection_ports(from, to);
+    let mut points = vec![out_port.point];
+    points.extend(route_ports(
+        out_port.point,
+        out_port.heading,
+        in_port.point,
+        in_port.heading.reverse(),
+        config,
+        &[],
+    ));
+    points.push(in_port.point);
+    points
+}
+
+/// Route from `start` leaving along `start_heading` to `end` arriving along `end_heading`.
+///
+/// The route always runs at least one grid cell straight out of `start` and
+/// straight into `end`. The closed-form route is used when there are no
+/// obstacles; otherwise A* finds a way around them.
+/// Returns intermediate corner points (not including start and end).
+fn route_ports(
+    start: Point,
+    start_heading: Direction,
+    end: Point,
+    end_heading: Direction,
+    config: &RoutingConfig,
+    obstacles: &[Rect],
+) -> Vec<Point> {
+    let departure = start + start_heading.vector() * config.grid_size;
+    let arrival = end - end_heading.vector() * config.grid_size;
+
+    let direct = if obstacles.is_empty() {
+        direct_route(
+            departure,
+            start_heading,
+            arrival,
+            end_heading,
+            config.grid_size,
+        )
     } else {
-        Heading::Up
+        None
     };
 
-    // Dongles at midpoint on the departure axis (like Excalidraw's dynamic bounds)
-    let mid_x = (start.x + end.x) / 2.0;
-    let mid_y = (start.y + end.y) / 2.0;
+    let mut points = vec![start, departure];
+    points.extend(direct.unwrap_or_else(|| {
+        search(
+            departure,
+            start_heading,
+            arrival,
+            end_heading,
+            config,
+            obstacles,
+        )
+    }));
+    points.push(arrival);
+    points.push(end);
+
+    let simplified = simplify(&points);
+    simplified[1..simplified.len() - 1].to_vec()
+}
+
+/// Run A* on the routing grid from `departure` (already moving along `heading`)
+/// to `arrival`. When `arrival_heading` is not `None` the final move must match it.
+/// Cells whose center lies inside one of `obstacles` are not entered.
+/// Returns the turn points in world coordinates, snapped onto the departure and
+/// arrival axes so all segments stay orthogonal.
+///
+/// The search is confined to the cells spanning both endpoints and all
+/// obstacles, plus [`SEARCH_MARGIN_CELLS`], so it cannot wander off across an
+/// unbounded grid. If no route exists within that box a plain L is returned.
+/// If the search expands more than [`RoutingConfig::max_expansions`] cells,
+/// the route follows the best path found to the explored cell nearest
+/// `arrival` and finishes with a plain L from there.
+fn search(
+    departure: Point,
+    heading: Direction,
+    arrival: Point,
+    arrival_heading: Direction,
+    config: &RoutingConfig,
+    obstacles: &[Rect],
+) -> Vec<Point> {
+    let grid_size = config.grid_size;
+    let sx = to_grid(departure.x, grid_size);
+    let sy = to_grid(departure.y, grid_size);
+    let ex = to_grid(arrival.x, grid_size);
+    let ey = to_grid(arrival.y, grid_size);
+
+    let turn_penalty = manhattan(sx, sy, ex, ey).max(1);
+    let turn_cost = config
+        .turn_bias
+        .turn_cost(turn_penalty, config.turn_penalty_exp);
+    let start_cell = Cell::new(sx, sy, heading);
 
-    let (departure, arrival) = match departure_heading {
-        Heading::Right | Heading::Left => {
-            // Horizontal departure: dongles at mid_x
-            (Point::new(mid_x, start.y), Point::new(mid_x, end.y))
+    let (mut min_x, mut max_x) = (sx.min(ex), sx.max(ex));
+    let (mut min_y, mut max_y) = (sy.min(ey), sy.max(ey));
+    for r in obstacles {
+        min_x = min_x.min(to_grid(r.x0, grid_size));
+        max_x = max_x.max(to_grid(r.x1, grid_size));
+        min_y = min_y.min(to_grid(r.y0, grid_size));
+        max_y = max_y.max(to_grid(r.y1, grid_size));
+    }
+    let (min_x, max_x) = (
+        min_x.saturating_sub(SEARCH_MARGIN_CELLS),
+        max_x.saturating_add(SEARCH_MARGIN_CELLS),
+    );
+    let (min_y, max_y) = (
+        min_y.saturating_sub(SEARCH_MARGIN_CELLS),
+        max_y.saturating_add(SEARCH_MARGIN_CELLS),
+    );
+
+    let blocked = |x: i32, y: i32| {
+        if (x, y) == (sx, sy) || (x, y) == (ex, ey) {
+            return false;
         }
-        _ => {
-            // Vertical departure: dongles at mid_y
-            (Point::new(start.x, mid_y), Point::new(end.x, mid_y))
+        if x < min_x || x > max_x || y < min_y || y > max_y {
+            return true;
         }
+        let center = Point::new(from_grid(x, grid_size), from_grid(y, grid_size));
+        obstacles
+            .iter()
+            .any(|r| r.x0 < center.x && center.x < r.x1 && r.y0 < center.y && center.y < r.y1)
     };
 
-    let sx = to_grid(departure.x);
-    let sy = to_grid(departure.y);
-    let ex = to_grid(arrival.x);
-    let ey = to_grid(arrival.y);
+    // Mirror the search's cost and parent bookkeeping so a partial route can
+    // be recovered if the expansion budget runs out
+    let budget = config.max_expansions;
+    let mut expanded = 0;
+    let mut costs = HashMap::from([(start_cell, 0)]);
+    let mut parents = HashMap::new();
+    let mut closest = start_cell;
+    let result = astar(
+        &start_cell,
+        |cell| {
+            if expanded >= budget {
+                return Vec::new();
+            }
+            expanded += 1;
+            if manhattan(cell.x, cell.y, ex, ey) < manhattan(closest.x, closest.y, ex, ey) {
+                closest = *cell;
+            }
+            let cost = costs.get(cell).copied().unwrap_or(0);
+            let next = neighbors(cell, turn_cost, blocked);
+            for &(neighbor, step) in &next {
+                let neighbor_cost = cost + step;
+                if costs
+                    .get(&neighbor)
+                    .is_none_or(|&known| neighbor_cost < known)
+                {
+                    costs.insert(neighbor, neighbor_cost);
+                    parents.insert(neighbor, *cell);
+                }
+            }
+            next
+        },
+        |cell| estimate(cell, ex, ey, arrival_heading, turn_cost),
+        |cell| {
+            cell.x == ex
+                && cell.y == ey
+                && (arrival_heading == Direction::None || cell.heading == arrival_heading)
+        },
+    );
+    let (path, complete) = match result {
+        Some((path, _)) => (path, true),
+        None if expanded >= budget => {
+            log::debug!(
+                "Elbow routing hit the budget of {budget} expanded cells; using a partial route"
+            );
+            let mut path = vec![closest];
+            while let Some(parent) = parents.get(path.last().unwrap()) {
+                path.push(*parent);
+            }
+            path.reverse();
+            (path, false)
+        }
+        None => return fallback_route(departure, heading, arrival),
+    };
 
-    // If waypoints are aligned, just return them
-    if sx == ex || sy == ey {
-        return vec![departure, arrival];
+    let snap_x = |x: i32| {
+        if x == ex {
+            arrival.x
+        } else if x == sx {
+            departure.x
+        } else {
+            from_grid(x, grid_size)
+        }
+    };
+    let snap_y = |y: i32| {
+        if y == ey {
+            arrival.y
+        } else if y == sy {
+            departure.y
+        } else {
+            from_grid(y, grid_size)
+        }
+    };
+
+    // A change of heading between consecutive cells means the turn happened
+    // at the earlier cell. Each corner keeps the cross-axis coordinate of the
+    // previous point so segments never drift off-axis.
+    let mut corners = Vec::new();
+    let mut prev = departure;
+    for pair in path.windows(2) {
+        let (turn, next) = (pair[0], pair[1]);
+        if turn.heading == next.heading || turn.heading == Direction::None {
+            continue;
+        }
+        let corner = match turn.heading {
+            Direction::Left | Direction::Right => Point::new(snap_x(turn.x), prev.y),
+            _ => Point::new(prev.x, snap_y(turn.y)),
+        };
+        corners.push(corner);
+        prev = corner;
     }
 
-    let turn_penalty = manhattan(sx, sy, ex, ey);
-    let start_cell = Cell::new(sx, sy, departure_heading);
+    if complete {
+        return corners;
+    }
 
-    let (path, _) = astar(
-        &start_cell,
-        |cell| neighbors(cell, turn_penalty),
-        |cell| estimate(cell, ex, ey, turn_penalty),
-        |cell| cell.x == ex && cell.y == ey,
-    )
-    .expect("A* always finds a path on unbounded grid");
+    // Run on to the last explored cell, then take an L to the arrival
+    let last = path[path.len() - 1];
+    let tail = match last.heading {
+        Direction::Left | Direction::Right => Point::new(snap_x(last.x), prev.y),
+        Direction::Up | Direction::Down => Point::new(prev.x, snap_y(last.y)),
+        Direction::None => prev,
+    };
+    let mut points = vec![departure];
+    points.extend(corners);
+    points.push(tail);
+    points.extend(fallback_route(tail, last.heading, arrival));
+    points.push(arrival);
+    let simplified = simplify(&points);
+    simplified[1..simplified.len() - 1].to_vec()
+}
+
+/// Single-corner route leaving along `heading`, used when A* finds nothing.
+fn fallback_route(departure: Point, heading: Direction, arrival: Point) -> Vec<Point> {
+    let corner = match heading {
+        Direction::Up | Direction::Down => Point::new(departure.x, arrival.y),
+        _ => Point::new(arrival.x, departure.y),
+    };
+    if corner == departure || corner == arrival {
+        Vec::new()
+    } else {
+        vec![corner]
+    }
+}
+
+/// Build a path through `points` (endpoints included) with each interior corner
+/// rounded by a quadratic arc of `radius`.
+///
+/// The radius is clamped per corner to half of each adjacent segment, so arcs on
+/// short segments never overshoot into each other.
+pub fn fillet_corners(points: &[Point], radius: f64) -> BezPath {
+    let mut path = BezPath::new();
+    let Some((&first, rest)) = points.split_first() else {
+        return path;
+    };
+    path.move_to(first);
+    if rest.is_empty() {
+        return path;
+    }
 
-    // Build result: departure + corners + arrival
-    let mut result = vec![departure];
-    result.extend(extract_corners(&path, departure, arrival));
-    result.push(arrival);
+    for i in 1..points.len() - 1 {
+        let (prev, corner, next) = (points[i - 1], points[i], points[i + 1]);
+        let incoming = corner - prev;
+        let outgoing = next - corner;
+        let len_in = incoming.hypot();
+        let len_out = outgoing.hypot();
+        let r = radius.min(len_in / 2.0).min(len_out / 2.0);
+
+        if r <= 0.0 {
+            path.line_to(corner);
+            continue;
+        }
+
+        path.line_to(corner - incoming * (r / len_in));
+        path.quad_to(corner, corner + outgoing * (r / len_out));
+    }
 
+    path.line_to(points[points.len() - 1]);
+    path
+}
+
+/// Drop duplicate points and interior points lying on a straight orthogonal run.
+fn simplify(points: &[Point]) -> Vec<Point> {
+    const EPS: f64 = 1e-9;
+    let mut result: Vec<Point> = Vec::with_capacity(points.len());
+    for &p in points {
+        if result.last().is_some_and(|last| last.distance(p) < EPS) {
+            continue;
+        }
+        if result.len() >= 2 {
+            let a = result[result.len() - 2];
+            let b = result[result.len() - 1];
+            let same_x = (a.x - b.x).abs() < EPS && (b.x - p.x).abs() < EPS;
+            let same_y = (a.y - b.y).abs() < EPS && (b.y - p.y).abs() < EPS;
+            if same_x || same_y {
+                result.pop();
+            }
+        }
+        result.push(p);
+    }
     result
 }
 
-fn neighbors(cell: &Cell, turn_penalty: u64) -> Vec<(Cell, u64)> {
+/// Compute a rectangular self-loop for (nearly) coincident start and end points.
+///
+/// The loop is a square of side `2 * radius` placed on the side of `start` with
+/// the most free space according to `obstacles`. It leaves `start` a quarter turn
+/// counter-clockwise from that side, travels around, and returns into `end`.
+/// Without obstacles the loop is routed up-and-right.
+/// Returns the four corner points (not including start and end).
+pub fn compute_self_loop(start: Point, end: Point, radius: f64, obstacles: &[Rect]) -> Vec<Point> {
+    // Candidate loop sides in order of preference (ties keep the earlier one)
+    let candidates = [
+        Direction::Right,
+        Direction::Up,
+        Direction::Left,
+        Direction::Down,
+    ];
+    let mut side = Direction::Right;
+    let mut best_space = f64::NEG_INFINITY;
+    for candidate in candidates {
+        let space = free_space(start, candidate, radius, obstacles);
+        if space > best_space {
+            best_space = space;
+            side = candidate;
+        }
+    }
+
+    let out = side.rotate_ccw().vector();
+    let across = side.vector();
+
+    let c1 = start + out * radius;
+    let c2 = c1 + across * (2.0 * radius);
+    let c4 = end - out * radius;
+    // c3 shares the far edge with c2 and the return edge with c4
+    let c3 = if across.x != 0.0 {
+        Point::new(c2.x, c4.y)
+    } else {
+        Point::new(c4.x, c2.y)
+    };
+
+    vec![c1, c2, c3, c4]
+}
+
+/// Distance from `point` to the nearest obstacle in direction `toward`, within
+/// a lateral band of `radius`. Infinite when nothing blocks that side.
+fn free_space(point: Point, toward: Direction, radius: f64, obstacles: &[Rect]) -> f64 {
+    let probe = point + toward.vector() * 1e-6;
+    obstacles
+        .iter()
+        .map(|r| {
+            // Stepping into an obstacle means this side has no room at all
+            if r.x0 < probe.x && probe.x < r.x1 && r.y0 < probe.y && probe.y < r.y1 {
+                return 0.0;
+            }
+            let in_x_band = r.x0 < point.x + radius && r.x1 > point.x - radius;
+            let in_y_band = r.y0 < point.y + radius && r.y1 > point.y - radius;
+            // Near and far edge distances along the direction of travel
+            let (near, far, in_band) = match toward {
+                Direction::Right => (r.x0 - point.x, r.x1 - point.x, in_y_band),
+                Direction::Left => (point.x - r.x1, point.x - r.x0, in_y_band),
+                Direction::Down => (r.y0 - point.y, r.y1 - point.y, in_x_band),
+                Direction::Up => (point.y - r.y1, point.y - r.y0, in_x_band),
+                Direction::None => (f64::INFINITY, f64::INFINITY, false),
+            };
+            if in_band && far > 0.0 {
+                near.max(0.0)
+            } else {
+                f64::INFINITY
+            }
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn neighbors(cell: &Cell, turn_cost: u64, blocked: impl Fn(i32, i32) -> bool) -> Vec<(Cell, u64)> {
     let moves = [
-        (0, -1, Heading::Up),
-        (0, 1, Heading::Down),
-        (-1, 0, Heading::Left),
-        (1, 0, Heading::Right),
+        (0, -1, Direction::Up),
+        (0, 1, Direction::Down),
+        (-1, 0, Direction::Left),
+        (1, 0, Direction::Right),
     ];
 
     moves
         .iter()
         .filter(|(_, _, h)| *h != cell.heading.reverse())
+        .filter(|(dx, dy, _)| !blocked(cell.x + dx, cell.y + dy))
         .map(|(dx, dy, h)| {
-            let cost = if cell.heading == Heading::None || cell.heading == *h {
+            let cost = if cell.heading == Direction::None || cell.heading == *h {
                 1
             } else {
-                1 + turn_penalty.pow(3)
+                1 + turn_cost
             };
             (Cell::new(cell.x + dx, cell.y + dy, *h), cost)
         })
         .collect()
 }
 
-fn estimate(cell: &Cell, ex: i32, ey: i32, turn_penalty: u64) -> u64 {
+/// Admissible A* estimate: remaining distance plus the cost of the fewest
+/// turns any unobstructed route from `cell` would need.
+fn estimate(cell: &Cell, ex: i32, ey: i32, arrival_heading: Direction, turn_cost: u64) -> u64 {
     let dist = manhattan(cell.x, cell.y, ex, ey);
-    let turns = if cell.x == ex || cell.y == ey { 0 } else { 1 };
-    dist + turns * turn_penalty.pow(2)
+    let turns = min_turns(cell.heading, ex - cell.x, ey - cell.y, arrival_heading);
+    dist.saturating_add(turns.saturating_mul(turn_cost))
 }
 
-fn extract_corners(path: &[Cell], start: Point, end: Point) -> Vec<Point> {
-    let mut corners = Vec::new();
+/// Fewest turns needed to travel `(dx, dy)` grid cells when currently moving
+/// along `heading`, optionally finishing with a move along `arrival_heading`.
+///
+/// Enumerates heading sequences of up to four turns (enough for any
+/// unobstructed case) and checks whether positive segment lengths can sum to
+/// the offset. Only the first segment may be empty, since the route can turn
+/// at the current cell but never reverses in place.
+fn min_turns(heading: Direction, dx: i32, dy: i32, arrival_heading: Direction) -> u64 {
+    const MAX_TURNS: u32 = 4;
 
-    for i in 1..path.len() - 1 {
-        if path[i - 1].heading != path[i].heading && path[i - 1].heading != Heading::None {
-            let mut corner = Point::new(from_grid(path[i].x), from_grid(path[i].y));
+    if heading == Direction::None {
+        return [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+        .into_iter()
+        .map(|h| min_turns(h, dx, dy, arrival_heading))
+        .min()
+        .unwrap_or(0);
+    }
 
-            // Snap to waypoint coordinates for cleaner lines
-            if path[i].x == path[0].x {
-                corner.x = start.x;
+    for turns in 0..=MAX_TURNS {
+        for choice in 0..(1u32 << turns) {
+            let mut sequence = vec![heading];
+            for i in 0..turns {
+                let last = *sequence.last().unwrap();
+                let next = if choice & (1 << i) == 0 {
+                    last.rotate_ccw()
+                } else {
+                    last.rotate_ccw().reverse()
+                };
+                sequence.push(next);
             }
-            if path[i].y == path[0].y {
-                corner.y = start.y;
+            if arrival_heading != Direction::None && *sequence.last().unwrap() != arrival_heading {
+                continue;
+            }
+            if reachable(&sequence, dx, dy) {
+                return turns as u64;
+            }
+        }
+    }
+
+    MAX_TURNS as u64
+}
+
+/// Whether segments along `sequence` (first may be empty, others at least one
+/// cell long) can add up to exactly `(dx, dy)`.
+fn reachable(sequence: &[Direction], dx: i32, dy: i32) -> bool {
+    // Per axis: (has positive segment, min positive sum, has negative segment, min negative sum)
+    let mut x = (false, 0, false, 0);
+    let mut y = (false, 0, false, 0);
+    for (i, heading) in sequence.iter().enumerate() {
+        let min_len = if i == 0 { 0 } else { 1 };
+        let axis = match heading {
+            Direction::Left | Direction::Right => &mut x,
+            Direction::Up | Direction::Down => &mut y,
+            Direction::None => continue,
+        };
+        if matches!(heading, Direction::Right | Direction::Down) {
+            axis.0 = true;
+            axis.1 += min_len;
+        } else {
+            axis.2 = true;
+            axis.3 += min_len;
+        }
+    }
+
+    fn axis_ok((has_pos, min_pos, has_neg, min_neg): (bool, i32, bool, i32), d: i32) -> bool {
+        match (has_pos, has_neg) {
+            (true, true) => true,
+            (true, false) => d >= min_pos,
+            (false, true) => -d >= min_neg,
+            (false, false) => d == 0,
+        }
+    }
+
+    axis_ok(x, dx) && axis_ok(y, dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_matches_wrapper() {
+        let start = Point::new(0.0, 0.0);
+        let end = Point::new(200.0, 100.0);
+        assert_eq!(
+            compute_elbow_path(start, end),
+            compute_elbow_path_with(start, end, &RoutingConfig::default())
+        );
+    }
+
+    #[test]
+    fn test_aligned_points_have_no_corners() {
+        assert!(compute_elbow_path(Point::new(0.0, 0.0), Point::new(200.0, 10.0)).is_empty());
+        assert!(compute_elbow_path(Point::new(0.0, 0.0), Point::new(10.0, 200.0)).is_empty());
+    }
+
+    #[test]
+    fn test_finer_grid_snaps_to_finer_resolution() {
+        let start = Point::new(0.0, 0.0);
+        let end = Point::new(100.0, 10.0);
+
+        // A 10px offset is below the default 20px resolution
+        assert!(compute_elbow_path(start, end).is_empty());
+
+        let fine = RoutingConfig {
+            grid_size: 5.0,
+            ..Default::default()
+        };
+        let corners = compute_elbow_path_with(start, end, &fine);
+        assert_eq!(corners.len(), 2);
+        for corner in &corners {
+            assert!((corner.x / fine.grid_size).fract().abs() < 1e-9);
+            assert!((corner.y / fine.grid_size).fract().abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_direct_jog_snaps_to_grid_between_off_grid_endpoints() {
+        let start = Point::new(0.0, 0.0);
+        let end = Point::new(103.0, 40.0);
+        let corners = compute_elbow_path(start, end);
+        assert_eq!(corners.len(), 2);
+        let grid = RoutingConfig::default().grid_size;
+        for corner in &corners {
+            assert!((corner.x / grid).fract().abs() < 1e-9);
+            assert!(corner.x > start.x && corner.x < end.x);
+        }
+        assert_eq!(corners[0].x, corners[1].x);
+
+        // No grid line strictly between: keep the raw midpoint
+        let fine = RoutingConfig {
+            grid_size: 5.0,
+            ..Default::default()
+        };
+        let corners = compute_elbow_path_with(start, Point::new(5.0, 5.0), &fine);
+        assert_eq!(corners, vec![Point::new(2.5, 0.0), Point::new(2.5, 5.0)]);
+    }
+
+    #[test]
+    fn test_coincident_points_form_loop() {
+        let p = Point::new(100.0, 100.0);
+        let corners = compute_elbow_path(p, p);
+        assert_eq!(corners.len(), 4);
+
+        // Default loop leaves upward and sits to the right of the point
+        let r = DEFAULT_LOOP_RADIUS;
+        assert_eq!(corners[0], Point::new(100.0, 100.0 - r));
+        assert_eq!(corners[1], Point::new(100.0 + 2.0 * r, 100.0 - r));
+        assert_eq!(corners[2], Point::new(100.0 + 2.0 * r, 100.0 + r));
+        assert_eq!(corners[3], Point::new(100.0, 100.0 + r));
+
+        // Every segment of start -> corners -> end is axis-aligned
+        let mut points = vec![p];
+        points.extend(&corners);
+        points.push(p);
+        for w in points.windows(2) {
+            assert!(w[0].x == w[1].x || w[0].y == w[1].y);
+        }
+    }
+
+    #[test]
+    fn test_self_loop_avoids_obstacle() {
+        // Point on the right edge of a node: the loop must not go left into it
+        let node = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let p = Point::new(100.0, 50.0);
+        let corners = compute_self_loop(p, p, 20.0, &[node]);
+        assert!(corners.iter().all(|c| c.x >= p.x));
+
+        // A blocker to the right pushes the loop to the open left side
+        let blocker = Rect::new(110.0, 0.0, 200.0, 100.0);
+        let corners = compute_self_loop(p, p, 20.0, &[blocker]);
+        assert!(corners.iter().all(|c| c.x <= p.x));
+    }
+
+    /// Assert every segment is axis-aligned and none passes through either rect.
+    fn assert_clean_connection(points: &[Point], from: Rect, to: Rect) {
+        for w in points.windows(2) {
+            assert!(w[0].x == w[1].x || w[0].y == w[1].y, "diagonal segment");
+            let mid = w[0].midpoint(w[1]);
+            for rect in [from, to] {
+                let inner = rect.inflate(-1.0, -1.0);
+                assert!(!inner.contains(mid), "segment crosses a shape");
             }
-            if path[i].x == path.last().unwrap().x {
-                corner.x = end.x;
+        }
+    }
+
+    #[test]
+    fn test_between_to_the_right() {
+        let from = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let to = Rect::new(300.0, 200.0, 400.0, 300.0);
+        let points = compute_elbow_path_between(from, to);
+        assert_eq!(points[0], Point::new(100.0, 50.0));
+        assert_eq!(*points.last().unwrap(), Point::new(300.0, 250.0));
+        assert!(points[1].x > from.x1);
+        assert_eq!(points.len(), 4);
+        assert_clean_connection(&points, from, to);
+    }
+
+    #[test]
+    fn test_between_to_the_left() {
+        let from = Rect::new(300.0, 0.0, 400.0, 100.0);
+        let to = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let points = compute_elbow_path_between(from, to);
+        assert_eq!(
+            points,
+            vec![Point::new(300.0, 50.0), Point::new(100.0, 50.0)]
+        );
+    }
+
+    #[test]
+    fn test_between_below() {
+        let from = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let to = Rect::new(60.0, 300.0, 160.0, 400.0);
+        let points = compute_elbow_path_between(from, to);
+        assert_eq!(points[0], Point::new(50.0, 100.0));
+        assert_eq!(*points.last().unwrap(), Point::new(110.0, 300.0));
+        assert!(points[1].y > from.y1);
+        assert_clean_connection(&points, from, to);
+    }
+
+    #[test]
+    fn test_between_above() {
+        let from = Rect::new(0.0, 300.0, 100.0, 400.0);
+        let to = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let points = compute_elbow_path_between(from, to);
+        assert_eq!(
+            points,
+            vec![Point::new(50.0, 300.0), Point::new(50.0, 100.0)]
+        );
+    }
+
+    #[test]
+    fn test_between_overlapping_rects_leave_outward() {
+        // Target starts right next to the source: the route must still step out first
+        let from = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let to = Rect::new(110.0, 150.0, 210.0, 250.0);
+        let points = compute_elbow_path_between(from, to);
+        let (out_port, _) = connection_ports(from, to);
+        assert_eq!(out_port.heading, Direction::Down);
+        assert!(points[1].y > from.y1);
+        assert_clean_connection(&points, from, to);
+    }
+
+    #[test]
+    fn test_fillet_two_corner_route() {
+        let start = Point::new(0.0, 0.0);
+        let end = Point::new(200.0, 100.0);
+        let mut points = vec![start];
+        points.extend(compute_elbow_path(start, end));
+        points.push(end);
+        assert_eq!(points.len(), 4);
+
+        let path = fillet_corners(&points, 10.0);
+        let arcs: Vec<_> = path
+            .elements()
+            .iter()
+            .filter_map(|el| match el {
+                kurbo::PathEl::QuadTo(ctrl, end) => Some((*ctrl, *end)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(arcs.len(), 2);
+        // Arc control points are the original corners
+        assert_eq!(arcs[0].0, points[1]);
+        assert_eq!(arcs[1].0, points[2]);
+    }
+
+    #[test]
+    fn test_fillet_clamps_to_short_segments() {
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(100.0, 0.0),
+            Point::new(100.0, 10.0),
+            Point::new(200.0, 10.0),
+        ];
+        let path = fillet_corners(&points, 50.0);
+        for el in path.elements() {
+            if let kurbo::PathEl::QuadTo(_, end) = el {
+                // Middle segment is 10 long, so each arc may use at most 5
+                assert!(end.y == 5.0 || end.x == 105.0);
             }
-            if path[i].y == path.last().unwrap().y {
-                corner.y = end.y;
+        }
+    }
+
+    #[test]
+    fn test_fast_path_matches_astar_corner_count() {
+        let start = Point::new(0.0, 0.0);
+        let end = Point::new(300.0, 180.0);
+        let config = RoutingConfig::default();
+
+        let fast = compute_elbow_path_with(start, end, &config);
+        // A distant obstacle forces the A* variant without affecting the route
+        let far = Rect::new(10_000.0, 10_000.0, 10_100.0, 10_100.0);
+        let searched = compute_elbow_path_avoiding(start, end, &[far], &config);
+
+        assert_eq!(fast.len(), 2);
+        assert_eq!(fast.len(), searched.len());
+    }
+
+    #[test]
+    fn test_avoiding_steers_around_obstacle() {
+        let start = Point::new(0.0, 0.0);
+        let end = Point::new(400.0, 200.0);
+        // Sits where the midpoint jog would run
+        let obstacle = Rect::new(150.0, 50.0, 250.0, 150.0);
+        let corners =
+            compute_elbow_path_avoiding(start, end, &[obstacle], &RoutingConfig::default());
+        assert_eq!(corners.len(), 2);
+
+        let mut points = vec![start];
+        points.extend(&corners);
+        points.push(end);
+        for w in points.windows(2) {
+            assert!(w[0].x == w[1].x || w[0].y == w[1].y);
+            for i in 0..=20 {
+                let p = w[0].lerp(w[1], i as f64 / 20.0);
+                assert!(!obstacle.contains(p), "route enters obstacle at {p:?}");
             }
+        }
+    }
 
-            corners.push(corner);
+    #[test]
+    fn test_through_central_waypoint() {
+        let start = Point::new(0.0, 0.0);
+        let waypoint = Point::new(100.0, 100.0);
+        let end = Point::new(200.0, 0.0);
+        let corners = compute_elbow_path_through(start, &[waypoint], end);
+        assert!(corners.contains(&waypoint));
+
+        let mut points = vec![start];
+        points.extend(&corners);
+        points.push(end);
+        for w in points.windows(2) {
+            assert!(w[0].x == w[1].x || w[0].y == w[1].y);
         }
     }
 
-    corners
+    #[test]
+    fn test_through_coincident_anchors() {
+        let p = Point::new(10.0, 20.0);
+        assert!(compute_elbow_path_through(p, &[p], p).is_empty());
+        assert!(compute_elbow_path_through(p, &[p, p], p).is_empty());
+    }
+
+    #[test]
+    fn test_through_carries_heading() {
+        // Collinear waypoint: the route keeps heading right and turns only once
+        let start = Point::new(0.0, 0.0);
+        let waypoint = Point::new(100.0, 0.0);
+        let end = Point::new(200.0, 100.0);
+        let corners = compute_elbow_path_through(start, &[waypoint], end);
+        assert_eq!(corners, vec![waypoint, Point::new(200.0, 0.0)]);
+    }
+
+    #[test]
+    fn test_route_end_heading_right() {
+        let route = compute_elbow_route(Point::new(0.0, 0.0), Point::new(200.0, 100.0));
+        assert_eq!(route.corners.len(), 2);
+        assert_eq!(route.start_heading, Direction::Right);
+        assert_eq!(route.end_heading, Direction::Right);
+    }
+
+    #[test]
+    fn test_route_vertical_headings() {
+        let route = compute_elbow_route(Point::new(0.0, 200.0), Point::new(60.0, 0.0));
+        assert_eq!(route.start_heading, Direction::Up);
+        assert_eq!(route.end_heading, Direction::Up);
+    }
+
+    #[test]
+    fn test_route_total_length_sums_segments() {
+        let start = Point::new(0.0, 0.0);
+        let end = Point::new(200.0, 140.0);
+        let route = compute_elbow_route(start, end);
+        assert!(!route.corners.is_empty());
+
+        let mut points = vec![start];
+        points.extend(route.corners.iter().copied());
+        points.push(end);
+        let sum: f64 = points.windows(2).map(|w| w[0].distance(w[1])).sum();
+        assert!((route.total_length - sum).abs() < 1e-9);
+        assert!(route.total_length >= start.distance(end));
+    }
+
+    #[test]
+    fn test_search_far_apart_completes() {
+        // Thousands of grid cells apart; the bounded search follows the L
+        let departure = Point::new(0.0, 0.0);
+        let arrival = Point::new(100_000.0, 60_000.0);
+        let corners = search(
+            departure,
+            Direction::Right,
+            arrival,
+            Direction::None,
+            &RoutingConfig::default(),
+            &[],
+        );
+        assert_eq!(corners, vec![Point::new(100_000.0, 0.0)]);
+    }
+
+    /// Route through two staggered bars from (0, 0) heading right to
+    /// (200, 200). A 2-turn route exists only as a long detour around the
+    /// right end of the lower bar; a 3-turn staircase between the bars is
+    /// as short as possible.
+    fn staggered_route(bias: TurnBias) -> (usize, f64) {
+        let departure = Point::new(0.0, 0.0);
+        let arrival = Point::new(200.0, 200.0);
+        let bars = [
+            Rect::new(-10.0, 130.0, 110.0, 150.0),
+            Rect::new(90.0, 50.0, 310.0, 70.0),
+        ];
+        let config = RoutingConfig {
+            turn_bias: bias,
+            ..Default::default()
+        };
+        let corners = search(
+            departure,
+            Direction::Right,
+            arrival,
+            Direction::None,
+            &config,
+            &bars,
+        );
+        let mut points = vec![departure];
+        points.extend(&corners);
+        points.push(arrival);
+        let length = points.windows(2).map(|w| w[0].distance(w[1])).sum();
+        (corners.len(), length)
+    }
+
+    #[test]
+    fn test_high_turn_bias_minimizes_corners() {
+        let (high_corners, _) = staggered_route(TurnBias::MIN_TURNS);
+        let (low_corners, _) = staggered_route(TurnBias::MIN_LENGTH);
+        assert_eq!(high_corners, 2);
+        assert!(high_corners < low_corners);
+    }
+
+    #[test]
+    fn test_low_turn_bias_minimizes_length() {
+        let (_, high_length) = staggered_route(TurnBias::MIN_TURNS);
+        let (_, low_length) = staggered_route(TurnBias::MIN_LENGTH);
+        assert!((low_length - 400.0).abs() < 1e-9);
+        assert!(low_length < high_length);
+    }
+
+    #[test]
+    fn test_turn_bias_clamps() {
+        assert_eq!(TurnBias::new(2.0), TurnBias::MIN_TURNS);
+        assert_eq!(TurnBias::new(-1.0), TurnBias::MIN_LENGTH);
+        assert_eq!(TurnBias::new(f64::NAN), TurnBias::default());
+        assert_eq!(TurnBias::new(0.5).turn_cost(10, 2), 10);
+    }
+
+    #[test]
+    fn test_search_falls_back_when_walled_in() {
+        // The goal is enclosed on all sides, so no route exists in the box
+        let arrival = Point::new(200.0, 200.0);
+        let walls = [
+            Rect::new(150.0, 150.0, 250.0, 185.0),
+            Rect::new(150.0, 215.0, 250.0, 250.0),
+            Rect::new(150.0, 150.0, 185.0, 250.0),
+            Rect::new(215.0, 150.0, 250.0, 250.0),
+        ];
+        let corners = search(
+            Point::new(0.0, 0.0),
+            Direction::Right,
+            arrival,
+            Direction::None,
+            &RoutingConfig::default(),
+            &walls,
+        );
+        assert_eq!(corners, vec![Point::new(200.0, 0.0)]);
+    }
+
+    /// Whether any segment of the polyline runs through the interior of a rect.
+    fn crosses_any(points: &[Point], rects: &[Rect]) -> bool {
+        points.windows(2).any(|w| {
+            let seg = Rect::from_points(w[0], w[1]);
+            rects.iter().any(|r| {
+                let overlaps = |lo: f64, hi: f64, r0: f64, r1: f64| {
+                    if (hi - lo).abs() < 1e-9 {
+                        r0 < lo && lo < r1
+                    } else {
+                        lo < r1 && r0 < hi
+                    }
+                };
+                overlaps(seg.x0, seg.x1, r.x0, r.x1) && overlaps(seg.y0, seg.y1, r.y0, r.y1)
+            })
+        })
+    }
+
+    #[test]
+    fn test_expansion_budget_returns_orthogonal_partial_route() {
+        // Staggered bars force a serpentine route that takes many expansions
+        let departure = Point::new(0.0, 0.0);
+        let arrival = Point::new(1000.0, 600.0);
+        let bars: Vec<Rect> = [200.0, 400.0, 600.0, 800.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let (y0, y1) = if i % 2 == 0 {
+                    (-200.0, 500.0)
+                } else {
+                    (100.0, 800.0)
+                };
+                Rect::new(x - 15.0, y0, x + 15.0, y1)
+            })
+            .collect();
+        let route = |max_expansions| {
+            let config = RoutingConfig {
+                max_expansions,
+                ..Default::default()
+            };
+            let corners = search(
+                departure,
+                Direction::Right,
+                arrival,
+                Direction::None,
+                &config,
+                &bars,
+            );
+            let mut points = vec![departure];
+            points.extend(corners);
+            points.push(arrival);
+            points
+        };
+
+        let full = route(DEFAULT_MAX_EXPANSIONS);
+        assert!(!crosses_any(&full, &bars));
+
+        // Out of budget, the route is finished with an L straight through the bars
+        let partial = route(40);
+        assert!(crosses_any(&partial, &bars));
+        for w in partial.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            assert!(
+                (a.x - b.x).abs() < 1e-9 || (a.y - b.y).abs() < 1e-9,
+                "diagonal segment {a:?} -> {b:?} in {partial:?}"
+            );
+        }
+    }
 }
@@ -1,7 +1,11 @@
 //! Input state management using winit_input_helper.
 
 use kurbo::{Point, Vec2};
-use winit::event::{DeviceEvent, MouseButton, Touch, TouchPhase, WindowEvent};
+use std::collections::{HashMap, VecDeque};
+use winit::event::{
+    DeviceEvent, ElementState, Force, Ime, KeyEvent, MouseButton, MouseScrollDelta, Touch,
+    TouchPhase, WindowEvent,
+};
 use winit::keyboard::KeyCode;
 use winit_input_helper::WinitInputHelper;
 
@@ -11,10 +15,90 @@ use std::time::Instant;
 #[cfg(target_arch = "wasm32")]
 use web_time::Instant;
 
-/// Double-click detection constants.
+/// Default distance the pointer must travel before a press becomes a drag.
+const DRAG_THRESHOLD: f64 = 4.0;
+
+/// Default double-click detection thresholds.
 const DOUBLE_CLICK_TIME_MS: u128 = 500;
 const DOUBLE_CLICK_DISTANCE: f64 = 5.0;
 
+/// Default fraction of scroll velocity kept per 60 Hz frame.
+const SCROLL_MOMENTUM_DECAY: f64 = 0.92;
+/// Speed (pixels per frame) below which momentum stops.
+const SCROLL_MOMENTUM_MIN_SPEED: f64 = 0.1;
+/// Number of recent scroll samples averaged into the fling velocity.
+const SCROLL_MOMENTUM_SAMPLES: usize = 4;
+/// Pixels scrolled per line of a [`MouseScrollDelta::LineDelta`].
+pub const LINE_SCROLL_PIXELS: f64 = 20.0;
+/// Arrow-key nudge distance in world units.
+pub const NUDGE_SMALL: f64 = 1.0;
+/// Arrow-key nudge distance with Shift held, in world units.
+pub const NUDGE_LARGE: f64 = 10.0;
+
+/// Continues scrolling after a touchpad flick, decaying exponentially.
+#[derive(Debug, Clone)]
+pub struct ScrollMomentum {
+    /// Recent per-frame scroll deltas.
+    samples: VecDeque<Vec2>,
+    /// Current velocity in pixels per 60 Hz frame.
+    velocity: Vec2,
+    /// Fraction of velocity kept per 60 Hz frame.
+    decay: f64,
+}
+
+impl Default for ScrollMomentum {
+    fn default() -> Self {
+        Self::new(SCROLL_MOMENTUM_DECAY)
+    }
+}
+
+impl ScrollMomentum {
+    pub fn new(decay: f64) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(SCROLL_MOMENTUM_SAMPLES),
+            velocity: Vec2::ZERO,
+            decay: decay.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Set the fraction of velocity kept per 60 Hz frame (`0.0..=1.0`).
+    pub fn set_decay(&mut self, decay: f64) {
+        self.decay = decay.clamp(0.0, 1.0);
+    }
+
+    /// Record a scroll delta from an active gesture.
+    pub fn record(&mut self, delta: Vec2) {
+        if self.samples.len() == SCROLL_MOMENTUM_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(delta);
+        let sum = self.samples.iter().fold(Vec2::ZERO, |acc, d| acc + *d);
+        self.velocity = sum / self.samples.len() as f64;
+    }
+
+    /// Advance by `dt` seconds with no new input and return the scroll to apply.
+    pub fn momentum_delta(&mut self, dt: f64) -> Vec2 {
+        self.samples.clear();
+        let frames = dt * 60.0;
+        self.velocity *= self.decay.powf(frames);
+        if self.velocity.hypot() < SCROLL_MOMENTUM_MIN_SPEED {
+            self.velocity = Vec2::ZERO;
+        }
+        self.velocity * frames
+    }
+
+    /// Cancel any remaining momentum.
+    pub fn stop(&mut self) {
+        self.samples.clear();
+        self.velocity = Vec2::ZERO;
+    }
+
+    /// Whether momentum is still producing movement.
+    pub fn is_active(&self) -> bool {
+        self.velocity != Vec2::ZERO
+    }
+}
+
 /// Touch state for a single finger.
 #[derive(Debug, Clone, Copy)]
 pub struct TouchState {
@@ -23,6 +107,138 @@ pub struct TouchState {
     pub phase: TouchPhase,
 }
 
+/// A key plus the exact set of modifiers that must be held with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    pub key: KeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    /// Super: Command on macOS, the Windows key elsewhere.
+    pub meta: bool,
+}
+
+impl Shortcut {
+    /// A shortcut for `key` with no modifiers.
+    pub const fn new(key: KeyCode) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+            meta: false,
+        }
+    }
+
+    /// Require Ctrl.
+    pub const fn ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    /// Require Shift.
+    pub const fn shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    /// Require Alt.
+    pub const fn alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    /// Require Super.
+    pub const fn meta(mut self) -> Self {
+        self.meta = true;
+        self
+    }
+
+    /// Whether pressing `key` with exactly these modifiers triggers this
+    /// shortcut. Extra modifiers do not match, so Ctrl+S ignores Ctrl+Shift+S.
+    pub fn matches(&self, key: KeyCode, ctrl: bool, shift: bool, alt: bool, meta: bool) -> bool {
+        self.key == key
+            && self.ctrl == ctrl
+            && self.shift == shift
+            && self.alt == alt
+            && self.meta == meta
+    }
+}
+
+/// Shortcut bindings to actions, checked in insertion order.
+#[derive(Debug, Clone)]
+pub struct ShortcutMap<T> {
+    bindings: Vec<(Shortcut, T)>,
+}
+
+impl<T> Default for ShortcutMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ShortcutMap<T> {
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Bind `shortcut` to `action`, returning the action it replaced.
+    pub fn bind(&mut self, shortcut: Shortcut, action: T) -> Option<T> {
+        match self.bindings.iter_mut().find(|(s, _)| *s == shortcut) {
+            Some((_, existing)) => Some(std::mem::replace(existing, action)),
+            None => {
+                self.bindings.push((shortcut, action));
+                None
+            }
+        }
+    }
+
+    /// Remove the binding for `shortcut`, returning its action.
+    pub fn unbind(&mut self, shortcut: &Shortcut) -> Option<T> {
+        let index = self.bindings.iter().position(|(s, _)| s == shortcut)?;
+        Some(self.bindings.remove(index).1)
+    }
+
+    /// The action bound to `key` with exactly these modifiers.
+    pub fn lookup(
+        &self,
+        key: KeyCode,
+        ctrl: bool,
+        shift: bool,
+        alt: bool,
+        meta: bool,
+    ) -> Option<&T> {
+        self.bindings
+            .iter()
+            .find(|(s, _)| s.matches(key, ctrl, shift, alt, meta))
+            .map(|(_, action)| action)
+    }
+
+    /// The first action whose shortcut was pressed this frame.
+    pub fn action(&self, input: &InputState) -> Option<&T> {
+        self.bindings
+            .iter()
+            .find(|(s, _)| input.matches_shortcut(s))
+            .map(|(_, action)| action)
+    }
+
+    /// All bindings, in the order they are checked.
+    pub fn iter(&self) -> impl Iterator<Item = &(Shortcut, T)> {
+        self.bindings.iter()
+    }
+}
+
+/// Press state for one mouse button.
+#[derive(Debug, Clone, Copy)]
+struct DragState {
+    /// Where the button was pressed.
+    start: Point,
+    /// Whether the pointer has left the drag threshold.
+    active: bool,
+}
+
 /// Tracks the current input state across frames using WinitInputHelper.
 pub struct InputState {
     helper: WinitInputHelper,
@@ -30,18 +246,66 @@ pub struct InputState {
     last_click_time: Option<Instant>,
     /// Last click position for double-click detection.
     last_click_position: Option<Point>,
+    /// Consecutive clicks within the double-click window.
+    click_count: u32,
     /// Whether a double-click was detected this frame.
     double_click_detected: bool,
-    /// Whether the pointer is currently dragging.
+    /// Whether a triple-click was detected this frame.
+    triple_click_detected: bool,
+    /// Maximum time between clicks of a double-click.
+    double_click_time_ms: u128,
+    /// Maximum pointer travel between clicks of a double-click.
+    double_click_distance: f64,
+    /// Whether a press was released without dragging this frame.
+    click_detected: bool,
+    /// Whether a right press was released without dragging this frame.
+    right_click_detected: bool,
+    /// Whether the pointer has moved past the drag threshold while pressed.
+    ///
+    /// Mirrors the left-button entry of the per-button drag state.
     pub is_dragging: bool,
-    /// Start position of current drag operation.
+    /// Press origin of the current left-button drag operation.
     pub drag_start: Option<Point>,
+    /// Whether the right button has moved past the drag threshold while pressed.
+    pub right_is_dragging: bool,
+    /// Press origin of the current right-button drag operation.
+    pub right_drag_start: Option<Point>,
+    /// Drag state for every held mouse button.
+    drags: HashMap<MouseButton, DragState>,
+    /// Last known pointer position.
+    pointer: Point,
+    /// Whether the pointer is inside the window.
+    pointer_inside: bool,
+    /// Whether the pointer entered the window this frame.
+    just_entered: bool,
+    /// Whether the pointer left the window this frame.
+    just_left: bool,
+    /// Distance the pointer must travel before a press becomes a drag.
+    drag_threshold: f64,
     /// Active touch points (up to 2 for pinch-zoom).
     touches: [Option<TouchState>; 2],
     /// Previous distance between two fingers (for pinch zoom).
     pinch_distance: Option<f64>,
     /// Previous center between two fingers (for pan during pinch).
-    pinch_center: Option<Point>,
+    pinch_anchor: Option<Point>,
+    /// Change in finger distance accumulated this frame.
+    frame_pinch_delta: Option<f64>,
+    /// Gesture produced by the most recent touch event.
+    touch_gesture: Option<(Vec2, f64, Point)>,
+    /// Primary touch position before the most recent touch event.
+    previous_primary_touch: Option<Point>,
+    /// Normalized stylus pressure seen this frame.
+    pen_pressure: Option<f64>,
+    /// Stylus (altitude, azimuth) in radians seen this frame.
+    pen_tilt: Option<(f64, f64)>,
+    /// Scroll momentum carried after a flick.
+    momentum: ScrollMomentum,
+    /// Wheel scroll accumulated this frame, in pixels, before inversion.
+    frame_scroll: Vec2,
+    /// Whether scroll direction is flipped on top of the platform setting.
+    invert_scroll: bool,
+    /// Text committed by key presses and the IME this frame.
+    frame_text: String,
 }
 
 impl Default for InputState {
@@ -56,12 +320,35 @@ impl InputState {
             helper: WinitInputHelper::new(),
             last_click_time: None,
             last_click_position: None,
+            click_count: 0,
             double_click_detected: false,
+            triple_click_detected: false,
+            double_click_time_ms: DOUBLE_CLICK_TIME_MS,
+            double_click_distance: DOUBLE_CLICK_DISTANCE,
+            click_detected: false,
+            right_click_detected: false,
             is_dragging: false,
             drag_start: None,
+            right_is_dragging: false,
+            right_drag_start: None,
+            drags: HashMap::new(),
+            pointer: Point::ZERO,
+            pointer_inside: false,
+            just_entered: false,
+            just_left: false,
+            drag_threshold: DRAG_THRESHOLD,
             touches: [None, None],
             pinch_distance: None,
-            pinch_center: None,
+            pinch_anchor: None,
+            frame_pinch_delta: None,
+            touch_gesture: None,
+            previous_primary_touch: None,
+            pen_pressure: None,
+            pen_tilt: None,
+            momentum: ScrollMomentum::default(),
+            frame_scroll: Vec2::ZERO,
+            invert_scroll: false,
+            frame_text: String::new(),
         }
     }
 
@@ -69,6 +356,15 @@ impl InputState {
     pub fn step(&mut self) {
         self.helper.step();
         self.double_click_detected = false;
+        self.triple_click_detected = false;
+        self.frame_pinch_delta = None;
+        self.click_detected = false;
+        self.right_click_detected = false;
+        self.just_entered = false;
+        self.just_left = false;
+        self.frame_scroll = Vec2::ZERO;
+        self.frame_text.clear();
+        self.clear_pen();
     }
 
     /// Call at the end of each frame.
@@ -80,41 +376,164 @@ impl InputState {
     pub fn process_window_event(&mut self, event: &WindowEvent) -> bool {
         let result = self.helper.process_window_event(event);
 
-        // Handle double-click and drag detection
-        if self.mouse_just_pressed(MouseButton::Left) {
-            let current_pos = self.mouse_position();
-            let now = Instant::now();
+        match event {
+            WindowEvent::Touch(touch) => {
+                self.previous_primary_touch = self.primary_touch();
+                self.touch_gesture = self.process_touch(touch);
+                self.record_force(touch.force);
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                // A mouse took over: callers should fall back to constant width.
+                self.clear_pen();
+                // Some platforms skip CursorEntered when the window opens
+                // under the pointer, so any motion counts as being inside.
+                self.pointer_inside = true;
+                self.on_pointer_moved(Point::new(position.x, position.y));
+            }
+            WindowEvent::CursorEntered { .. } => {
+                self.pointer_inside = true;
+                self.just_entered = true;
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.pointer_inside = false;
+                self.just_left = true;
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.frame_scroll += scroll_to_pixels(*delta);
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        text: Some(text),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                // Control characters (Backspace, Enter, ...) are keys, not text.
+                self.frame_text
+                    .extend(text.chars().filter(|c| !c.is_control()));
+            }
+            WindowEvent::Ime(Ime::Commit(text)) => self.frame_text.push_str(text),
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.clear_pen();
+                match state {
+                    ElementState::Pressed => self.on_press(*button, self.pointer, Instant::now()),
+                    ElementState::Released => self.on_release(*button),
+                }
+            }
+            _ => {}
+        }
 
-            if let (Some(last_time), Some(last_pos)) =
-                (self.last_click_time, self.last_click_position)
-            {
-                let elapsed = now.duration_since(last_time).as_millis();
-                let distance = current_pos.distance(last_pos);
+        result
+    }
 
-                if elapsed < DOUBLE_CLICK_TIME_MS && distance < DOUBLE_CLICK_DISTANCE {
-                    self.double_click_detected = true;
-                    self.last_click_time = None;
-                } else {
-                    self.last_click_time = Some(now);
-                    self.last_click_position = Some(current_pos);
-                }
-            } else {
-                self.last_click_time = Some(now);
-                self.last_click_position = Some(current_pos);
+    /// Start tracking a potential drag and register left clicks.
+    fn on_press(&mut self, button: MouseButton, pos: Point, now: Instant) {
+        if button == MouseButton::Left {
+            self.register_click(pos, now);
+        }
+        self.drags.insert(
+            button,
+            DragState {
+                start: pos,
+                active: false,
+            },
+        );
+        self.sync_drag_fields();
+    }
+
+    /// Promote presses to drags once the pointer leaves the threshold.
+    fn on_pointer_moved(&mut self, pos: Point) {
+        self.pointer = pos;
+        for drag in self.drags.values_mut() {
+            if !drag.active && drag.start.distance(pos) > self.drag_threshold {
+                drag.active = true;
             }
+        }
+        self.sync_drag_fields();
+    }
 
-            if !self.is_dragging {
-                self.is_dragging = true;
-                self.drag_start = Some(current_pos);
+    /// End a press; a left or right press counts as a click if it never
+    /// became a drag.
+    fn on_release(&mut self, button: MouseButton) {
+        let drag = self.drags.remove(&button);
+        if drag.is_some_and(|d| !d.active) {
+            match button {
+                MouseButton::Left => self.click_detected = true,
+                MouseButton::Right => self.right_click_detected = true,
+                _ => {}
             }
         }
+        self.sync_drag_fields();
+    }
+
+    /// Mirror the left- and right-button drags into the public fields.
+    fn sync_drag_fields(&mut self) {
+        let left = self.drags.get(&MouseButton::Left);
+        self.is_dragging = left.is_some_and(|d| d.active);
+        self.drag_start = left.map(|d| d.start);
+        let right = self.drags.get(&MouseButton::Right);
+        self.right_is_dragging = right.is_some_and(|d| d.active);
+        self.right_drag_start = right.map(|d| d.start);
+    }
+
+    /// Set how far (in pixels) the pointer must move before a press becomes a drag.
+    pub fn set_drag_threshold(&mut self, threshold: f64) {
+        self.drag_threshold = threshold;
+    }
 
-        if self.mouse_just_released(MouseButton::Left) {
-            self.is_dragging = false;
-            self.drag_start = None;
+    /// Record a left click for double- and triple-click detection.
+    fn register_click(&mut self, pos: Point, now: Instant) {
+        let chained = self
+            .last_click_time
+            .zip(self.last_click_position)
+            .is_some_and(|(last_time, last_pos)| {
+                now.duration_since(last_time).as_millis() < self.double_click_time_ms
+                    && pos.distance(last_pos) < self.double_click_distance
+            });
+
+        self.click_count = if chained { self.click_count + 1 } else { 1 };
+        self.last_click_time = Some(now);
+        self.last_click_position = Some(pos);
+
+        match self.click_count {
+            2 => self.double_click_detected = true,
+            3 => {
+                self.triple_click_detected = true;
+                self.click_count = 0;
+            }
+            _ => {}
         }
+    }
 
-        result
+    /// Set the maximum gap and pointer travel between clicks of a double-click.
+    ///
+    /// Takes effect on the next click.
+    pub fn set_double_click_threshold(&mut self, ms: u128, distance: f64) {
+        self.double_click_time_ms = ms;
+        self.double_click_distance = distance;
+    }
+
+    fn clear_pen(&mut self) {
+        self.pen_pressure = None;
+        self.pen_tilt = None;
+    }
+
+    /// Record stylus force and tilt from a touch event.
+    fn record_force(&mut self, force: Option<Force>) {
+        let Some(force) = force else {
+            return;
+        };
+        self.pen_pressure = Some(force.normalized().clamp(0.0, 1.0));
+        // winit only reports the altitude; azimuth is not available.
+        if let Force::Calibrated {
+            altitude_angle: Some(altitude),
+            ..
+        } = force
+        {
+            self.pen_tilt = Some((altitude, 0.0));
+        }
     }
 
     /// Process a device event.
@@ -129,6 +548,28 @@ impl InputState {
         Point::new(x as f64, y as f64)
     }
 
+    /// Last pointer position reported by the window, in screen coordinates.
+    pub fn pointer_position(&self) -> Point {
+        self.pointer
+    }
+
+    /// Pointer position for hover feedback: `None` while the pointer is
+    /// outside the window or any button is dragging.
+    pub fn hover_position(&self) -> Option<Point> {
+        let dragging = self.drags.values().any(|d| d.active);
+        (self.pointer_inside && !dragging).then_some(self.pointer)
+    }
+
+    /// Whether the pointer entered the window this frame.
+    pub fn just_entered(&self) -> bool {
+        self.just_entered
+    }
+
+    /// Whether the pointer left the window this frame.
+    pub fn just_left(&self) -> bool {
+        self.just_left
+    }
+
     pub fn is_button_pressed(&self, button: MouseButton) -> bool {
         self.helper.mouse_held(button)
     }
@@ -141,11 +582,66 @@ impl InputState {
         self.helper.mouse_released(button)
     }
 
+    /// Raw scroll this frame as summed by winit, mixing lines and pixels.
+    ///
+    /// Prefer [`scroll_pixels`](Self::scroll_pixels), which normalizes units.
     pub fn scroll_delta(&self) -> Vec2 {
         let (dx, dy) = self.helper.scroll_diff();
         Vec2::new(dx as f64, dy as f64)
     }
 
+    /// Scroll this frame in pixels, with [`invert_scroll`](Self::invert_scroll)
+    /// applied.
+    ///
+    /// Positive values move the content right and down, following winit.
+    /// Mouse wheels (Windows, X11, most Linux mice) report line deltas, which
+    /// are converted at [`LINE_SCROLL_PIXELS`] per line. Trackpads on macOS
+    /// and Wayland report pixel deltas, used as-is. Both already include the
+    /// OS "natural scrolling" setting, so the flag only adds a user override.
+    /// Browsers report pixel or line deltas depending on the device.
+    pub fn scroll_pixels(&self) -> Vec2 {
+        self.apply_scroll_direction(self.frame_scroll)
+    }
+
+    /// Convert one wheel event's delta like [`scroll_pixels`](Self::scroll_pixels)
+    /// does, for handling the event directly.
+    pub fn normalize_scroll(&self, delta: MouseScrollDelta) -> Vec2 {
+        self.apply_scroll_direction(scroll_to_pixels(delta))
+    }
+
+    /// Whether scrolling is inverted relative to the platform direction.
+    pub fn invert_scroll(&self) -> bool {
+        self.invert_scroll
+    }
+
+    /// Flip the scroll direction on top of the platform setting.
+    pub fn set_invert_scroll(&mut self, invert: bool) {
+        self.invert_scroll = invert;
+    }
+
+    fn apply_scroll_direction(&self, delta: Vec2) -> Vec2 {
+        if self.invert_scroll { -delta } else { delta }
+    }
+
+    /// Scroll to apply this frame from momentum after scrolling stops.
+    ///
+    /// Call once per frame; while the user is scrolling this records the
+    /// delta and returns zero.
+    pub fn momentum_delta(&mut self, dt: f64) -> Vec2 {
+        let delta = self.scroll_pixels();
+        if delta != Vec2::ZERO {
+            self.momentum.record(delta);
+            Vec2::ZERO
+        } else {
+            self.momentum.momentum_delta(dt)
+        }
+    }
+
+    /// Set the fraction of scroll velocity kept per 60 Hz frame.
+    pub fn set_momentum_decay(&mut self, decay: f64) {
+        self.momentum.set_decay(decay);
+    }
+
     pub fn cursor_diff(&self) -> Vec2 {
         let (dx, dy) = self.helper.cursor_diff();
         Vec2::new(dx as f64, dy as f64)
@@ -157,6 +653,12 @@ impl InputState {
         self.helper.key_held(key)
     }
 
+    /// Whether `key` is held down. Same as [`Self::is_key_pressed`], named
+    /// after the underlying `WinitInputHelper` query.
+    pub fn is_key_held(&self, key: KeyCode) -> bool {
+        self.is_key_pressed(key)
+    }
+
     pub fn is_key_just_pressed(&self, key: KeyCode) -> bool {
         self.helper.key_pressed(key)
     }
@@ -179,17 +681,105 @@ impl InputState {
         self.helper.held_alt()
     }
 
+    /// Whether either Super key (Command on macOS, Windows key elsewhere) is held.
+    pub fn meta(&self) -> bool {
+        self.is_key_pressed(KeyCode::SuperLeft) || self.is_key_pressed(KeyCode::SuperRight)
+    }
+
+    /// Alias for [`Self::meta`], matching the `held_*` modifier queries of
+    /// `WinitInputHelper`.
+    pub fn held_super(&self) -> bool {
+        self.meta()
+    }
+
+    // --- Text ---
+
+    /// Printable text typed or committed by the IME this frame, in order.
+    pub fn text_input(&self) -> &str {
+        &self.frame_text
+    }
+
+    /// Whether `shortcut`'s key was pressed this frame with exactly its
+    /// modifiers held.
+    pub fn matches_shortcut(&self, shortcut: &Shortcut) -> bool {
+        self.is_key_just_pressed(shortcut.key)
+            && shortcut.matches(
+                shortcut.key,
+                self.ctrl(),
+                self.shift(),
+                self.alt(),
+                self.meta(),
+            )
+    }
+
+    /// Whether the modifier that temporarily disables grid snapping (Ctrl) is held.
+    pub fn snap_override(&self) -> bool {
+        self.ctrl()
+    }
+
+    // --- Pen ---
+
+    /// Stylus pressure in `0.0..=1.0`, or `None` when the pointer is a mouse.
+    pub fn pen_pressure(&self) -> Option<f64> {
+        self.pen_pressure
+    }
+
+    /// Stylus `(altitude, azimuth)` in radians, or `None` when unavailable.
+    pub fn pen_tilt(&self) -> Option<(f64, f64)> {
+        self.pen_tilt
+    }
+
     // --- Custom logic ---
 
     pub fn is_double_click(&self) -> bool {
         self.double_click_detected
     }
 
+    /// Whether a third consecutive click landed this frame.
+    pub fn is_triple_click(&self) -> bool {
+        self.triple_click_detected
+    }
+
+    /// Whether a left press was released this frame without becoming a drag.
+    pub fn is_click(&self) -> bool {
+        self.click_detected
+    }
+
+    /// Whether a right press was released this frame without becoming a
+    /// drag, e.g. to open a context menu.
+    pub fn is_right_click(&self) -> bool {
+        self.right_click_detected
+    }
+
     pub fn drag_delta(&self) -> Option<Vec2> {
-        self.drag_start.map(|start| {
-            let pos = self.mouse_position();
-            Vec2::new(pos.x - start.x, pos.y - start.y)
-        })
+        self.drag_delta_for(MouseButton::Left)
+    }
+
+    /// Offset of the pointer from where `button` was pressed.
+    pub fn drag_delta_for(&self, button: MouseButton) -> Option<Vec2> {
+        self.drags
+            .get(&button)
+            .map(|drag| self.pointer - drag.start)
+    }
+
+    /// Press origin of `button`, if it is held.
+    pub fn drag_start_for(&self, button: MouseButton) -> Option<Point> {
+        self.drags.get(&button).map(|drag| drag.start)
+    }
+
+    /// Whether `button` has moved past the drag threshold while held.
+    pub fn is_dragging_with(&self, button: MouseButton) -> bool {
+        self.drags.get(&button).is_some_and(|drag| drag.active)
+    }
+
+    /// Offset of the pointer from where the right button was pressed.
+    pub fn right_drag_delta(&self) -> Option<Vec2> {
+        self.drag_delta_for(MouseButton::Right)
+    }
+
+    /// Middle-button drag delta, used for panning the canvas.
+    pub fn pan_delta(&self) -> Option<Vec2> {
+        self.drag_delta_for(MouseButton::Middle)
     }
 
     pub fn close_requested(&self) -> bool {
@@ -217,7 +807,7 @@ impl InputState {
                     // Initialize pinch state
                     if let (Some(t0), Some(t1)) = (self.touches[0], self.touches[1]) {
                         self.pinch_distance = Some(t0.position.distance(t1.position));
-                        self.pinch_center = Some(Point::new(
+                        self.pinch_anchor = Some(Point::new(
                             (t0.position.x + t1.position.x) / 2.0,
                             (t0.position.y + t1.position.y) / 2.0,
                         ));
@@ -253,14 +843,18 @@ impl InputState {
                         1.0
                     };
 
-                    let pan_delta = if let Some(old_center) = self.pinch_center {
+                    if let Some(old_dist) = self.pinch_distance {
+                        *self.frame_pinch_delta.get_or_insert(0.0) += new_dist - old_dist;
+                    }
+
+                    let pan_delta = if let Some(old_center) = self.pinch_anchor {
                         Vec2::new(new_center.x - old_center.x, new_center.y - old_center.y)
                     } else {
                         Vec2::ZERO
                     };
 
                     self.pinch_distance = Some(new_dist);
-                    self.pinch_center = Some(new_center);
+                    self.pinch_anchor = Some(new_center);
 
                     Some((pan_delta, zoom_delta, new_center))
                 } else {
@@ -276,13 +870,41 @@ impl InputState {
                         }
                     }
                 }
+                // Drop the baseline so a lifted finger can't cause a jump; a
+                // new pinch re-anchors when the next second finger lands.
                 self.pinch_distance = None;
-                self.pinch_center = None;
+                self.pinch_anchor = None;
                 None
             }
         }
     }
 
+    /// Gesture `(pan_delta, zoom_delta, zoom_center)` from the last touch event
+    /// passed through [`Self::process_window_event`].
+    pub fn touch_gesture(&self) -> Option<(Vec2, f64, Point)> {
+        self.touch_gesture
+    }
+
+    /// Change in distance between the first two touches this frame.
+    ///
+    /// Returns `None` when fewer than two fingers moved together this frame.
+    pub fn pinch_delta(&self) -> Option<f64> {
+        self.frame_pinch_delta
+    }
+
+    /// Centroid of the first two touches, if a pinch is in progress.
+    pub fn pinch_center(&self) -> Option<Point> {
+        match (self.touches[0], self.touches[1]) {
+            (Some(t0), Some(t1)) => Some(t0.position.midpoint(t1.position)),
+            _ => None,
+        }
+    }
+
+    /// Primary touch position before the most recent touch event.
+    pub fn previous_primary_touch(&self) -> Option<Point> {
+        self.previous_primary_touch
+    }
+
     /// Get the primary touch position (first finger).
     pub fn primary_touch(&self) -> Option<Point> {
         self.touches[0].map(|t| t.position)
@@ -310,3 +932,472 @@ impl InputState {
         self.touches[0].is_none() && self.touches[1].is_none()
     }
 }
+
+/// Movement for the arrow keys held right now: `small` per axis, or `large`
+/// with Shift held. Two perpendicular keys move diagonally and opposite keys
+/// cancel out. Returns `None` when no arrow key moves the selection.
+///
+/// Meant to be called once per arrow key press (including key repeats), with
+/// the result fed to
+/// [`CanvasDocument::transform_elements`](crate::canvas::CanvasDocument::transform_elements).
+pub fn nudge_delta(input: &InputState, small: f64, large: f64) -> Option<Vec2> {
+    arrow_keys_delta(|key| input.is_key_pressed(key), input.shift(), small, large)
+}
+
+/// [`nudge_delta`] for an arbitrary key state.
+fn arrow_keys_delta(
+    held: impl Fn(KeyCode) -> bool,
+    shift: bool,
+    small: f64,
+    large: f64,
+) -> Option<Vec2> {
+    let axis = |neg, pos| f64::from(u8::from(held(pos))) - f64::from(u8::from(held(neg)));
+    let x = axis(KeyCode::ArrowLeft, KeyCode::ArrowRight);
+    let y = axis(KeyCode::ArrowUp, KeyCode::ArrowDown);
+    if x == 0.0 && y == 0.0 {
+        return None;
+    }
+    let step = if shift { large } else { small };
+    Some(Vec2::new(x * step, y * step))
+}
+
+/// Convert a winit scroll delta to pixels.
+fn scroll_to_pixels(delta: MouseScrollDelta) -> Vec2 {
+    match delta {
+        MouseScrollDelta::LineDelta(x, y) => {
+            Vec2::new(x as f64 * LINE_SCROLL_PIXELS, y as f64 * LINE_SCROLL_PIXELS)
+        }
+        MouseScrollDelta::PixelDelta(pos) => Vec2::new(pos.x, pos.y),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+    use std::time::Duration;
+    use winit::dpi::PhysicalPosition;
+    use winit::event::DeviceId;
+
+    fn device() -> DeviceId {
+        // SAFETY: the dummy id is only compared, never dereferenced.
+        unsafe { DeviceId::dummy() }
+    }
+
+    fn touch(id: u64, phase: TouchPhase, x: f64, y: f64) -> WindowEvent {
+        WindowEvent::Touch(Touch {
+            device_id: device(),
+            phase,
+            location: PhysicalPosition::new(x, y),
+            force: None,
+            id,
+        })
+    }
+
+    fn wheel(delta: MouseScrollDelta) -> WindowEvent {
+        WindowEvent::MouseWheel {
+            device_id: device(),
+            delta,
+            phase: TouchPhase::Moved,
+        }
+    }
+
+    fn cursor_moved(x: f64, y: f64) -> WindowEvent {
+        WindowEvent::CursorMoved {
+            device_id: device(),
+            position: PhysicalPosition::new(x, y),
+        }
+    }
+
+    #[test]
+    fn test_shift_right_down_nudges_diagonally() {
+        let held = |keys: &'static [KeyCode]| move |key| keys.contains(&key);
+        let delta = arrow_keys_delta(
+            held(&[KeyCode::ArrowRight, KeyCode::ArrowDown]),
+            true,
+            NUDGE_SMALL,
+            NUDGE_LARGE,
+        );
+        assert_eq!(delta, Some(Vec2::new(NUDGE_LARGE, NUDGE_LARGE)));
+
+        let delta = arrow_keys_delta(held(&[KeyCode::ArrowLeft]), false, 1.0, 10.0);
+        assert_eq!(delta, Some(Vec2::new(-1.0, 0.0)));
+        assert_eq!(arrow_keys_delta(held(&[]), true, 1.0, 10.0), None);
+        assert_eq!(
+            arrow_keys_delta(
+                held(&[KeyCode::ArrowLeft, KeyCode::ArrowRight]),
+                false,
+                1.0,
+                10.0
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_ime_commit_surfaces_as_text_for_one_frame() {
+        let mut input = InputState::new();
+        input.step();
+        input.process_window_event(&WindowEvent::Ime(Ime::Commit("é".to_string())));
+        input.process_window_event(&WindowEvent::Ime(Ime::Commit("x".to_string())));
+        assert_eq!(input.text_input(), "éx");
+        assert!(!input.meta());
+        assert!(!input.held_super());
+        assert!(!input.is_key_held(KeyCode::SuperLeft));
+
+        input.step();
+        assert_eq!(input.text_input(), "");
+    }
+
+    #[test]
+    fn test_single_touch_is_not_pinch() {
+        let mut input = InputState::new();
+        input.step();
+        input.process_window_event(&touch(1, TouchPhase::Started, 10.0, 10.0));
+        input.process_window_event(&touch(1, TouchPhase::Moved, 50.0, 10.0));
+        assert_eq!(input.pinch_delta(), None);
+        assert_eq!(input.pinch_center(), None);
+    }
+
+    #[test]
+    fn test_two_finger_pinch_reports_delta_and_center() {
+        let mut input = InputState::new();
+        input.step();
+        input.process_window_event(&touch(1, TouchPhase::Started, 0.0, 0.0));
+        input.process_window_event(&touch(2, TouchPhase::Started, 100.0, 0.0));
+        assert_eq!(input.pinch_delta(), None);
+
+        input.step();
+        input.process_window_event(&touch(2, TouchPhase::Moved, 140.0, 0.0));
+        assert_eq!(input.pinch_delta(), Some(40.0));
+        assert_eq!(input.pinch_center(), Some(Point::new(70.0, 0.0)));
+
+        input.step();
+        assert_eq!(input.pinch_delta(), None);
+    }
+
+    #[test]
+    fn test_lifting_finger_does_not_jump() {
+        let mut input = InputState::new();
+        input.step();
+        input.process_window_event(&touch(1, TouchPhase::Started, 0.0, 0.0));
+        input.process_window_event(&touch(2, TouchPhase::Started, 100.0, 0.0));
+        input.process_window_event(&touch(2, TouchPhase::Ended, 100.0, 0.0));
+
+        input.step();
+        input.process_window_event(&touch(1, TouchPhase::Moved, 20.0, 0.0));
+        assert_eq!(input.pinch_delta(), None);
+
+        // A new second finger re-anchors instead of using the stale distance.
+        input.process_window_event(&touch(3, TouchPhase::Started, 300.0, 0.0));
+        input.process_window_event(&touch(3, TouchPhase::Moved, 310.0, 0.0));
+        assert_eq!(input.pinch_delta(), Some(10.0));
+    }
+
+    #[test]
+    fn test_pen_pressure_clears_on_mouse_move() {
+        let mut input = InputState::new();
+        input.step();
+        input.process_window_event(&WindowEvent::Touch(Touch {
+            device_id: device(),
+            phase: TouchPhase::Started,
+            location: PhysicalPosition::new(5.0, 5.0),
+            force: Some(Force::Calibrated {
+                force: 2.0,
+                max_possible_force: 4.0,
+                altitude_angle: Some(FRAC_PI_2),
+            }),
+            id: 1,
+        }));
+        assert_eq!(input.pen_pressure(), Some(0.5));
+        assert_eq!(input.pen_tilt(), Some((FRAC_PI_2, 0.0)));
+
+        input.process_window_event(&cursor_moved(6.0, 6.0));
+        assert_eq!(input.pen_pressure(), None);
+        assert_eq!(input.pen_tilt(), None);
+    }
+
+    #[test]
+    fn test_raised_double_click_threshold_accepts_slow_clicks() {
+        let mut input = InputState::new();
+        let pos = Point::new(10.0, 10.0);
+        let t0 = Instant::now();
+        let t1 = t0 + Duration::from_millis(700);
+
+        input.register_click(pos, t0);
+        input.register_click(pos, t1);
+        assert!(!input.is_double_click());
+
+        input.set_double_click_threshold(800, DOUBLE_CLICK_DISTANCE);
+        input.register_click(pos, t1 + Duration::from_millis(700));
+        assert!(input.is_double_click());
+    }
+
+    #[test]
+    fn test_small_move_stays_a_click() {
+        let mut input = InputState::new();
+        let origin = Point::new(100.0, 100.0);
+        input.on_press(MouseButton::Left, origin, Instant::now());
+        input.on_pointer_moved(Point::new(102.0, 100.0));
+        assert!(!input.is_dragging);
+        assert_eq!(input.drag_start, Some(origin));
+        input.on_release(MouseButton::Left);
+        assert!(!input.is_dragging);
+        assert!(input.is_click());
+    }
+
+    #[test]
+    fn test_move_past_threshold_starts_drag() {
+        let mut input = InputState::new();
+        input.set_drag_threshold(10.0);
+        input.on_press(MouseButton::Left, Point::ZERO, Instant::now());
+        input.on_pointer_moved(Point::new(8.0, 0.0));
+        assert!(!input.is_dragging);
+        input.on_pointer_moved(Point::new(12.0, 0.0));
+        assert!(input.is_dragging);
+        input.on_release(MouseButton::Left);
+        assert!(!input.is_click());
+    }
+
+    #[test]
+    fn test_triple_click_only_on_third() {
+        let mut input = InputState::new();
+        let pos = Point::new(10.0, 10.0);
+        let t0 = Instant::now();
+        let mut seen = Vec::new();
+        for i in 0..3 {
+            input.step();
+            input.register_click(pos, t0 + Duration::from_millis(100 * i));
+            seen.push((input.is_double_click(), input.is_triple_click()));
+        }
+        assert_eq!(seen, vec![(false, false), (true, false), (false, true)]);
+
+        // The counter restarts after a triple-click.
+        input.step();
+        input.register_click(pos, t0 + Duration::from_millis(300));
+        assert!(!input.is_double_click() && !input.is_triple_click());
+    }
+
+    #[test]
+    fn test_click_count_resets_when_pointer_moves_away() {
+        let mut input = InputState::new();
+        let t0 = Instant::now();
+        input.register_click(Point::ZERO, t0);
+        input.register_click(Point::ZERO, t0 + Duration::from_millis(50));
+        input.step();
+        input.register_click(Point::new(50.0, 0.0), t0 + Duration::from_millis(100));
+        assert!(!input.is_triple_click());
+    }
+
+    #[test]
+    fn test_middle_drag_reports_pan_without_touching_left() {
+        let mut input = InputState::new();
+        input.on_press(MouseButton::Middle, Point::new(10.0, 10.0), Instant::now());
+        input.on_pointer_moved(Point::new(40.0, 25.0));
+
+        assert_eq!(input.pan_delta(), Some(Vec2::new(30.0, 15.0)));
+        assert!(input.is_dragging_with(MouseButton::Middle));
+        assert!(!input.is_dragging);
+        assert_eq!(input.drag_start, None);
+        assert_eq!(input.drag_delta(), None);
+
+        input.on_release(MouseButton::Middle);
+        assert_eq!(input.pan_delta(), None);
+        assert!(!input.is_click());
+    }
+
+    #[test]
+    fn test_right_drag_is_separate_from_left() {
+        let mut input = InputState::new();
+        input.on_press(MouseButton::Right, Point::new(10.0, 10.0), Instant::now());
+        assert_eq!(input.right_drag_start, Some(Point::new(10.0, 10.0)));
+        assert!(!input.right_is_dragging);
+
+        input.on_pointer_moved(Point::new(60.0, 30.0));
+        assert!(input.right_is_dragging);
+        assert_eq!(input.right_drag_delta(), Some(Vec2::new(50.0, 20.0)));
+        assert!(!input.is_dragging);
+        assert_eq!(input.drag_start, None);
+        assert_eq!(input.drag_delta(), None);
+
+        input.on_release(MouseButton::Right);
+        assert!(!input.right_is_dragging);
+        assert_eq!(input.right_drag_start, None);
+        assert_eq!(input.right_drag_delta(), None);
+        assert!(!input.is_right_click());
+        assert!(!input.is_click());
+    }
+
+    #[test]
+    fn test_short_right_press_is_a_right_click() {
+        let mut input = InputState::new();
+        input.on_press(MouseButton::Right, Point::ZERO, Instant::now());
+        input.on_pointer_moved(Point::new(2.0, 0.0));
+        input.on_release(MouseButton::Right);
+        assert!(input.is_right_click());
+        assert!(!input.is_click());
+
+        input.step();
+        assert!(!input.is_right_click());
+    }
+
+    #[test]
+    fn test_momentum_decays_to_zero() {
+        let mut momentum = ScrollMomentum::new(0.8);
+        for _ in 0..4 {
+            momentum.record(Vec2::new(0.0, 20.0));
+        }
+
+        let mut last = f64::INFINITY;
+        let mut steps = 0;
+        loop {
+            let delta = momentum.momentum_delta(1.0 / 60.0).y;
+            if delta == 0.0 {
+                break;
+            }
+            assert!(delta > 0.0 && delta < last);
+            last = delta;
+            steps += 1;
+            assert!(steps < 1000, "momentum never settled");
+        }
+        assert!(steps > 1);
+        assert!(!momentum.is_active());
+    }
+
+    #[test]
+    fn test_cursor_left_clears_hover() {
+        let mut input = InputState::new();
+        input.step();
+        assert_eq!(input.hover_position(), None);
+
+        input.process_window_event(&WindowEvent::CursorEntered {
+            device_id: device(),
+        });
+        input.process_window_event(&cursor_moved(30.0, 40.0));
+        assert!(input.just_entered());
+        assert_eq!(input.hover_position(), Some(Point::new(30.0, 40.0)));
+
+        input.step();
+        assert!(!input.just_entered());
+        input.process_window_event(&WindowEvent::CursorLeft {
+            device_id: device(),
+        });
+        assert!(input.just_left());
+        assert_eq!(input.hover_position(), None);
+
+        input.step();
+        assert!(!input.just_left());
+        assert_eq!(input.hover_position(), None);
+    }
+
+    #[test]
+    fn test_dragging_hides_hover() {
+        let mut input = InputState::new();
+        input.process_window_event(&cursor_moved(0.0, 0.0));
+        input.on_press(MouseButton::Left, Point::ZERO, Instant::now());
+        assert_eq!(input.hover_position(), Some(Point::ZERO));
+        input.on_pointer_moved(Point::new(50.0, 0.0));
+        assert_eq!(input.hover_position(), None);
+        input.on_release(MouseButton::Left);
+        assert_eq!(input.hover_position(), Some(Point::new(50.0, 0.0)));
+    }
+
+    #[test]
+    fn test_shortcut_requires_exact_modifiers() {
+        let save = Shortcut::new(KeyCode::KeyS).ctrl();
+        assert!(save.matches(KeyCode::KeyS, true, false, false, false));
+        assert!(!save.matches(KeyCode::KeyS, true, true, false, false));
+        assert!(!save.matches(KeyCode::KeyS, true, false, true, false));
+        assert!(!save.matches(KeyCode::KeyS, false, false, false, false));
+        assert!(!save.matches(KeyCode::KeyA, true, false, false, false));
+    }
+
+    #[test]
+    fn test_shortcut_ignores_key_while_super_held() {
+        let select = Shortcut::new(KeyCode::KeyS);
+        assert!(select.matches(KeyCode::KeyS, false, false, false, false));
+        assert!(!select.matches(KeyCode::KeyS, false, false, false, true));
+
+        let mut map = ShortcutMap::new();
+        map.bind(select, "select");
+        map.bind(Shortcut::new(KeyCode::KeyS).meta(), "save");
+        assert_eq!(
+            map.lookup(KeyCode::KeyS, false, false, false, true),
+            Some(&"save")
+        );
+        assert_eq!(
+            map.lookup(KeyCode::KeyS, false, false, false, false),
+            Some(&"select")
+        );
+    }
+
+    #[test]
+    fn test_shortcut_map_discriminates_modifiers() {
+        let mut map = ShortcutMap::new();
+        map.bind(Shortcut::new(KeyCode::KeyS).ctrl(), "save");
+        map.bind(Shortcut::new(KeyCode::KeyS).ctrl().shift(), "save as");
+        map.bind(Shortcut::new(KeyCode::KeyS), "select");
+
+        assert_eq!(
+            map.lookup(KeyCode::KeyS, true, false, false, false),
+            Some(&"save")
+        );
+        assert_eq!(
+            map.lookup(KeyCode::KeyS, true, true, false, false),
+            Some(&"save as")
+        );
+        assert_eq!(
+            map.lookup(KeyCode::KeyS, false, false, false, false),
+            Some(&"select")
+        );
+        assert_eq!(map.lookup(KeyCode::KeyS, false, false, true, false), None);
+
+        // Rebinding replaces the old action instead of shadowing it
+        let previous = map.bind(Shortcut::new(KeyCode::KeyS).ctrl(), "export");
+        assert_eq!(previous, Some("save"));
+        assert_eq!(
+            map.lookup(KeyCode::KeyS, true, false, false, false),
+            Some(&"export")
+        );
+        assert_eq!(map.iter().count(), 3);
+
+        assert_eq!(map.unbind(&Shortcut::new(KeyCode::KeyS)), Some("select"));
+        assert_eq!(map.lookup(KeyCode::KeyS, false, false, false, false), None);
+    }
+
+    #[test]
+    fn test_shortcut_map_without_key_press_has_no_action() {
+        let mut map = ShortcutMap::new();
+        map.bind(Shortcut::new(KeyCode::KeyZ).ctrl(), 1);
+        let mut input = InputState::new();
+        input.step();
+        assert_eq!(map.action(&input), None);
+    }
+
+    #[test]
+    fn test_line_delta_scrolls_in_pixels() {
+        let mut input = InputState::new();
+        input.step();
+        input.process_window_event(&wheel(MouseScrollDelta::LineDelta(0.0, -3.0)));
+        assert_eq!(
+            input.scroll_pixels(),
+            Vec2::new(0.0, -3.0 * LINE_SCROLL_PIXELS)
+        );
+
+        // Pixel deltas add up with line deltas in the same frame
+        input.process_window_event(&wheel(MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+            5.0, 10.0,
+        ))));
+        assert_eq!(input.scroll_pixels(), Vec2::new(5.0, -50.0));
+
+        input.set_invert_scroll(true);
+        assert_eq!(input.scroll_pixels(), Vec2::new(-5.0, 50.0));
+        assert_eq!(
+            input.normalize_scroll(MouseScrollDelta::LineDelta(1.0, 0.0)),
+            Vec2::new(-LINE_SCROLL_PIXELS, 0.0)
+        );
+
+        input.step();
+        assert_eq!(input.scroll_pixels(), Vec2::ZERO);
+    }
+}
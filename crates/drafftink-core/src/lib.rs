@@ -4,10 +4,14 @@
 
 pub mod camera;
 pub mod canvas;
+pub mod clipboard;
 pub mod collaboration;
 pub mod crdt;
+pub mod curve;
 pub mod elbow;
 pub mod input;
+pub mod oplog;
+pub mod schema;
 pub mod selection;
 pub mod shapes;
 pub mod snap;
@@ -20,12 +24,16 @@ pub use camera::Camera;
 pub use canvas::Canvas;
 pub use collaboration::CollaborationManager;
 pub use crdt::CrdtDocument;
-pub use input::InputState;
+pub use input::{
+    InputState, LINE_SCROLL_PIXELS, NUDGE_LARGE, NUDGE_SMALL, ScrollMomentum, Shortcut,
+    ShortcutMap, nudge_delta,
+};
 pub use selection::{ManipulationState, MultiMoveState};
 pub use snap::{
-    GRID_SIZE, SMART_GUIDE_THRESHOLD, SmartGuide, SmartGuideKind, SmartGuideResult, SnapResult,
-    detect_smart_guides, detect_smart_guides_for_point, snap_point, snap_ray_to_smart_guides,
-    snap_to_grid,
+    ANGLE_SNAP_INCREMENT, GRID_SIZE, PORT_SNAP_THRESHOLD, PortId, SMART_GUIDE_THRESHOLD,
+    SmartGuide, SmartGuideKind, SmartGuideResult, SnapConfig, SnapResult, compute_alignment_guides,
+    constrain_angle, detect_smart_guides, detect_smart_guides_for_point, nearest_port, shape_ports,
+    snap_bounds, snap_point, snap_ray_to_smart_guides, snap_to_grid,
 };
 pub use sync::{ConnectionState, PlatformWebSocket, SyncEvent};
 pub use widget::{EditingKind, Handle, HandleKind, HandleShape, WidgetManager, WidgetState};
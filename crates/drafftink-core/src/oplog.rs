@@ -0,0 +1,174 @@
+//! Operation log for document edits.
+//!
+//! A [`CanvasDocument`](crate::canvas::CanvasDocument) that is recording
+//! appends an [`Op`] for each element edit made through its recording
+//! methods, numbered in order. Replaying the ops with
+//! [`apply_op`](crate::canvas::CanvasDocument::apply_op) onto a document in
+//! the starting state reproduces the edits. This is the local foundation for
+//! syncing edits between peers; ordering and conflict resolution between
+//! concurrent logs are left to the sync layer.
+//!
+//! Every `CanvasDocument` method that edits elements records, including
+//! batch transforms, align, distribute, flip, resize, locking, undo, redo
+//! and `apply_delta`. Writes through `get_shape_mut`, `find_element_mut` or
+//! the public `shapes` and `z_order` fields bypass the log; a replica only
+//! stays in step if such edits go through `update_shape` instead.
+
+use crate::shapes::{Shape, ShapeId};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// One edit to a document's elements.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Op {
+    /// Add an element in front of all others.
+    AddElement { element: Shape },
+    /// Remove a top-level element.
+    RemoveElement { id: ShapeId },
+    /// Change a top-level element. `patch` is a JSON merge patch
+    /// (RFC 7386) against the element's serialized form; see
+    /// [`Op::update`].
+    UpdateElement { id: ShapeId, patch: Value },
+    /// Replace the z-order (back to front) with a permutation of itself.
+    Reorder { z_order: Vec<ShapeId> },
+    /// Group the given elements into a new group with ID `group_id`.
+    Group {
+        ids: Vec<ShapeId>,
+        group_id: ShapeId,
+    },
+    /// Dissolve a group, returning its children to the document.
+    Ungroup { group_id: ShapeId },
+}
+
+impl Op {
+    /// An [`Op::UpdateElement`] turning `old` into `new`, or `None` if their
+    /// serialized forms are equal.
+    pub fn update(old: &Shape, new: &Shape) -> Option<Self> {
+        let old_value = serde_json::to_value(old).ok()?;
+        let new_value = serde_json::to_value(new).ok()?;
+        merge_diff(&old_value, &new_value).map(|patch| Op::UpdateElement {
+            id: old.id(),
+            patch,
+        })
+    }
+}
+
+/// Apply an [`Op::UpdateElement`] patch to `shape`. Returns `None` if the
+/// result is not a valid element.
+pub fn apply_patch(shape: &Shape, patch: &Value) -> Option<Shape> {
+    let mut value = serde_json::to_value(shape).ok()?;
+    merge_patch(&mut value, patch);
+    serde_json::from_value(value).ok()
+}
+
+/// An [`Op`] with its position in the log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedOp {
+    /// Sequence number; strictly increasing within a log.
+    pub seq: u64,
+    /// The edit.
+    pub op: Op,
+}
+
+/// Ops recorded by a document, in the order they were applied.
+#[derive(Debug, Clone, Default)]
+pub struct OpLog {
+    ops: Vec<RecordedOp>,
+    next_seq: u64,
+}
+
+impl OpLog {
+    /// Recorded ops not yet taken.
+    pub fn ops(&self) -> &[RecordedOp] {
+        &self.ops
+    }
+
+    /// Append `op` with the next sequence number, returning that number.
+    pub fn push(&mut self, op: Op) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.ops.push(RecordedOp { seq, op });
+        seq
+    }
+
+    /// Remove and return the recorded ops. Numbering continues after them.
+    pub fn take(&mut self) -> Vec<RecordedOp> {
+        std::mem::take(&mut self.ops)
+    }
+}
+
+/// JSON merge patch turning `old` into `new`, or `None` if they are equal.
+///
+/// Objects are diffed member by member; any other change replaces the value
+/// whole. Members missing from `new` become `null`, which removes them, so
+/// a `None` optional field is expressed by its absence.
+fn merge_diff(old: &Value, new: &Value) -> Option<Value> {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut patch = Map::new();
+            for (key, new_member) in new {
+                let member_patch = match old.get(key) {
+                    Some(old_member) => merge_diff(old_member, new_member),
+                    None => Some(new_member.clone()),
+                };
+                if let Some(member_patch) = member_patch {
+                    patch.insert(key.clone(), member_patch);
+                }
+            }
+            for key in old.keys().filter(|key| !new.contains_key(*key)) {
+                patch.insert(key.clone(), Value::Null);
+            }
+            (!patch.is_empty()).then_some(Value::Object(patch))
+        }
+        _ => (old != new).then(|| new.clone()),
+    }
+}
+
+/// Apply a JSON merge patch to `target` in place.
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(target) = target {
+        for (key, member) in patch {
+            if member.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.clone()).or_insert(Value::Null), member);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::{Rectangle, SerializableColor};
+    use kurbo::Point;
+
+    #[test]
+    fn test_patch_round_trips_changed_fields() {
+        let mut rect = Rectangle::new(Point::new(0.0, 0.0), 10.0, 10.0);
+        rect.style.fill_color = Some(SerializableColor::white());
+        let old = Shape::Rectangle(rect.clone());
+        rect.position = Point::new(5.0, 7.0);
+        rect.style.fill_color = None;
+        let new = Shape::Rectangle(rect);
+
+        let Some(Op::UpdateElement { id, patch }) = Op::update(&old, &new) else {
+            panic!("expected an update");
+        };
+        assert_eq!(id, old.id());
+        // Only the changed fields travel
+        assert!(patch.to_string().contains("position"));
+        assert!(!patch.to_string().contains("width"));
+        let patched = apply_patch(&old, &patch).unwrap();
+        assert_eq!(format!("{patched:?}"), format!("{new:?}"));
+
+        assert!(Op::update(&new, &new).is_none());
+    }
+}
@@ -0,0 +1,108 @@
+//! Document schema versioning and migration.
+//!
+//! Saved documents carry a top-level `schema_version`. [`migrate`] upgrades
+//! older JSON one version at a time before it is deserialized, so each step
+//! only needs to know about its immediate predecessor. Documents without a
+//! version predate versioning and are treated as v0.
+
+use serde_json::Value;
+use thiserror::Error;
+
+/// Current document schema version, written by `CanvasDocument::to_json`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Key holding the schema version at the top level of a document.
+const KEY_SCHEMA_VERSION: &str = "schema_version";
+
+/// Errors from loading a versioned document.
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("Document schema version {found} is newer than supported version {supported}")]
+    UnsupportedVersion { found: u64, supported: u32 },
+    #[error("Invalid schema version: {0}")]
+    InvalidVersion(Value),
+    #[error("Invalid document JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Upgrade a document's JSON to [`SCHEMA_VERSION`].
+pub fn migrate(mut doc: Value) -> Result<Value, SchemaError> {
+    let mut version = match doc.get(KEY_SCHEMA_VERSION) {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .ok_or_else(|| SchemaError::InvalidVersion(v.clone()))?,
+    };
+    if version > SCHEMA_VERSION as u64 {
+        return Err(SchemaError::UnsupportedVersion {
+            found: version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+
+    while version < SCHEMA_VERSION as u64 {
+        match version {
+            0 => v0_to_v1(&mut doc),
+            _ => unreachable!("missing migration from schema version {version}"),
+        }
+        version += 1;
+        if let Some(obj) = doc.as_object_mut() {
+            obj.insert(KEY_SCHEMA_VERSION.to_string(), Value::from(version));
+        }
+    }
+    Ok(doc)
+}
+
+/// v0 → v1: the version field itself was introduced. Shape data is unchanged;
+/// the legacy `"Dashed"` stroke style is still accepted by its deserializer.
+fn v0_to_v1(_doc: &mut Value) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::CanvasDocument;
+
+    const V0_FIXTURE: &str = r#"{
+        "id": "legacy",
+        "name": "Legacy",
+        "shapes": {},
+        "z_order": []
+    }"#;
+
+    #[test]
+    fn test_v0_document_upgrades_and_loads() {
+        let doc = CanvasDocument::from_json(V0_FIXTURE).unwrap();
+        assert_eq!(doc.schema_version, SCHEMA_VERSION);
+        assert_eq!(doc.name, "Legacy");
+        assert!(doc.is_empty());
+
+        let value = migrate(serde_json::from_str(V0_FIXTURE).unwrap()).unwrap();
+        assert_eq!(value[KEY_SCHEMA_VERSION], SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_intro_document_loads_as_v0() {
+        let doc = CanvasDocument::from_json(include_str!("../../drafftink-app/assets/intro.json"))
+            .unwrap();
+        assert!(!doc.is_empty());
+    }
+
+    #[test]
+    fn test_future_version_is_rejected() {
+        let json = r#"{"schema_version": 99, "id": "x", "name": "x", "shapes": {}, "z_order": []}"#;
+        let err = CanvasDocument::from_json(json).unwrap_err();
+        assert!(matches!(
+            err,
+            SchemaError::UnsupportedVersion { found: 99, .. }
+        ));
+    }
+
+    #[test]
+    fn test_current_version_roundtrip() {
+        let doc = CanvasDocument::new();
+        let json = doc.to_json().unwrap();
+        assert!(json.contains(r#""schema_version": 1"#));
+        let restored = CanvasDocument::from_json(&json).unwrap();
+        assert_eq!(restored.id, doc.id);
+    }
+}
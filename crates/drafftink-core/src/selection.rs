@@ -1,7 +1,8 @@
 //! Selection and manipulation handle system.
 
+use crate::canvas::CanvasDocument;
 use crate::shapes::{Shape, ShapeId, ShapeTrait};
-use kurbo::{Point, Rect};
+use kurbo::{Affine, Point, Rect, Shape as _};
 use serde::{Deserialize, Serialize};
 
 /// Handle size in screen pixels.
@@ -120,6 +121,10 @@ pub fn get_handles(shape: &Shape) -> Vec<Handle> {
             let rotation = shape.rotation();
             rotate_only_handle(bounds, rotation)
         }
+        Shape::StickyNote(_) => {
+            // Sticky notes resize from the corners but do not rotate
+            corner_handles(shape.bounds())
+        }
         Shape::Freehand(_) => {
             // Freehand uses bounding box corners (no rotation)
             let bounds = shape.bounds();
@@ -213,6 +218,150 @@ fn rotate_only_handle(bounds: Rect, rotation: f64) -> Vec<Handle> {
     )]
 }
 
+/// Axis-aligned bounds of a shape after applying its rotation about its center.
+pub fn rotated_bounds(shape: &Shape) -> Rect {
+    let bounds = shape.bounds();
+    let rotation = shape.rotation();
+    if rotation.abs() <= 0.001 {
+        return bounds;
+    }
+    let center = bounds.center().to_vec2();
+    let transform =
+        Affine::translate(center) * Affine::rotate(rotation) * Affine::translate(-center);
+    transform.transform_rect_bbox(bounds)
+}
+
+/// Union of the rotated bounds of the shapes in `ids`.
+///
+/// Ids that are not in the document are ignored. Returns `None` when none of
+/// them are.
+pub fn selection_bounds(doc: &CanvasDocument, ids: &[ShapeId]) -> Option<Rect> {
+    ids.iter()
+        .filter_map(|id| doc.get_shape(*id))
+        .map(rotated_bounds)
+        .reduce(|a, b| a.union(b))
+}
+
+/// How a marquee rectangle selects shapes in [`elements_in_rect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectMode {
+    /// Select anything touching the marquee.
+    #[default]
+    Intersect,
+    /// Select only shapes fully inside the marquee.
+    Contain,
+}
+
+/// Top-level shapes selected by a marquee `rect`, in z-order.
+///
+/// Lines and arrows are tested against their drawn path, so elbow arrows use
+/// their routed bounds; other shapes use their rotated bounds. Groups are
+/// tested as a whole, so their members are selected together. Locked shapes
+/// are never selected.
+pub fn elements_in_rect(doc: &CanvasDocument, rect: Rect, mode: SelectMode) -> Vec<ShapeId> {
+    doc.shapes_ordered()
+        .filter(|shape| !shape.is_locked())
+        .filter(|shape| {
+            let bounds = match shape {
+                Shape::Line(_) | Shape::Arrow(_) => shape.to_path().bounding_box(),
+                _ => rotated_bounds(shape),
+            };
+            match mode {
+                SelectMode::Intersect => {
+                    bounds.x0 <= rect.x1
+                        && rect.x0 <= bounds.x1
+                        && bounds.y0 <= rect.y1
+                        && rect.y0 <= bounds.y1
+                }
+                SelectMode::Contain => {
+                    bounds.x0 >= rect.x0
+                        && bounds.x1 <= rect.x1
+                        && bounds.y0 >= rect.y0
+                        && bounds.y1 <= rect.y1
+                }
+            }
+        })
+        .map(|shape| shape.id())
+        .collect()
+}
+
+/// Resize handle squares around `bounds`, plus the rotation handle position.
+///
+/// Squares are [`HANDLE_SIZE`] screen pixels wide at the given camera zoom,
+/// ordered top-left, top-right, bottom-left, bottom-right, then the top,
+/// right, bottom and left edge midpoints.
+pub fn handle_rects(bounds: Rect, zoom: f64) -> ([Rect; 8], Point) {
+    let half = HANDLE_SIZE / zoom.max(f64::EPSILON) / 2.0;
+    let center = bounds.center();
+    let square = |x: f64, y: f64| Rect::new(x - half, y - half, x + half, y + half);
+    let rects = [
+        square(bounds.x0, bounds.y0),
+        square(bounds.x1, bounds.y0),
+        square(bounds.x0, bounds.y1),
+        square(bounds.x1, bounds.y1),
+        square(center.x, bounds.y0),
+        square(bounds.x1, center.y),
+        square(center.x, bounds.y1),
+        square(bounds.x0, center.y),
+    ];
+    let rotate = Point::new(center.x, bounds.y0 - ROTATE_HANDLE_OFFSET);
+    (rects, rotate)
+}
+
+/// New bounding box after dragging a resize `handle` of `bounds` by `delta`.
+///
+/// Corner handles move two edges and edge handles move one; the opposite
+/// side stays anchored. With `lock_aspect`, corner drags scale uniformly,
+/// taking the scale from whichever axis dominates `delta`. The result is not
+/// normalized: a negative width or height means the drag crossed the anchor
+/// and the shape should be flipped. Returns `None` for non-resize handles.
+pub fn resize_bounds(
+    bounds: Rect,
+    handle: HandleKind,
+    delta: kurbo::Vec2,
+    lock_aspect: bool,
+) -> Option<Rect> {
+    let Rect {
+        mut x0,
+        mut y0,
+        mut x1,
+        mut y1,
+    } = bounds;
+    match handle {
+        HandleKind::Corner(corner) => {
+            match corner {
+                Corner::TopLeft => (x0, y0) = (x0 + delta.x, y0 + delta.y),
+                Corner::TopRight => (x1, y0) = (x1 + delta.x, y0 + delta.y),
+                Corner::BottomLeft => (x0, y1) = (x0 + delta.x, y1 + delta.y),
+                Corner::BottomRight => (x1, y1) = (x1 + delta.x, y1 + delta.y),
+            }
+            if lock_aspect && bounds.width() > 0.0 && bounds.height() > 0.0 {
+                let scale = if delta.x.abs() >= delta.y.abs() {
+                    (x1 - x0) / bounds.width()
+                } else {
+                    (y1 - y0) / bounds.height()
+                };
+                let width = bounds.width() * scale;
+                let height = bounds.height() * scale;
+                match corner {
+                    Corner::TopLeft => (x0, y0) = (x1 - width, y1 - height),
+                    Corner::TopRight => (x1, y0) = (x0 + width, y1 - height),
+                    Corner::BottomLeft => (x0, y1) = (x1 - width, y0 + height),
+                    Corner::BottomRight => (x1, y1) = (x0 + width, y0 + height),
+                }
+            }
+        }
+        HandleKind::Edge(edge) => match edge {
+            Edge::Top => y0 += delta.y,
+            Edge::Right => x1 += delta.x,
+            Edge::Bottom => y1 += delta.y,
+            Edge::Left => x0 += delta.x,
+        },
+        _ => return None,
+    }
+    Some(Rect::new(x0, y0, x1, y1))
+}
+
 /// Find which handle (if any) is hit at the given point.
 /// Returns the handle kind if hit.
 pub fn hit_test_handles(shape: &Shape, point: Point, tolerance: f64) -> Option<HandleKind> {
@@ -445,6 +594,8 @@ pub fn apply_manipulation(
     keep_aspect_ratio: bool,
 ) -> Shape {
     let mut shape = shape.clone();
+    // The clone carries the original's cached bounds
+    shape.invalidate_bounds();
 
     match handle {
         None => {
@@ -537,6 +688,15 @@ pub fn apply_manipulation(
                 Shape::Image(image) => {
                     apply_corner_resize_image(image, corner, delta, keep_aspect_ratio);
                 }
+                Shape::StickyNote(note) => {
+                    apply_corner_resize_rect(
+                        &mut note.background,
+                        corner,
+                        delta,
+                        keep_aspect_ratio,
+                    );
+                    note.fit_text();
+                }
                 _ => {}
             }
         }
@@ -867,6 +1027,129 @@ mod tests {
         assert!(matches!(handles[4].kind, HandleKind::Rotate));
     }
 
+    #[test]
+    fn test_selection_bounds_unions_rects() {
+        let mut doc = CanvasDocument::new();
+        let a = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 100.0, 50.0));
+        let b = Shape::Rectangle(Rectangle::new(Point::new(200.0, 100.0), 20.0, 20.0));
+        let ids = [a.id(), b.id()];
+        doc.add_shape(a);
+        doc.add_shape(b);
+
+        assert_eq!(
+            selection_bounds(&doc, &ids),
+            Some(Rect::new(0.0, 0.0, 220.0, 120.0))
+        );
+        assert_eq!(selection_bounds(&doc, &[]), None);
+    }
+
+    #[test]
+    fn test_selection_bounds_respects_rotation() {
+        let mut doc = CanvasDocument::new();
+        let mut rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 20.0);
+        rect.rotation = std::f64::consts::FRAC_PI_2;
+        let shape = Shape::Rectangle(rect);
+        let id = shape.id();
+        doc.add_shape(shape);
+
+        let bounds = selection_bounds(&doc, &[id]).unwrap();
+        assert!((bounds.width() - 20.0).abs() < 1e-9);
+        assert!((bounds.height() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_elements_in_rect_intersect_vs_contain() {
+        let mut doc = CanvasDocument::new();
+        let inside = Shape::Rectangle(Rectangle::new(Point::new(10.0, 10.0), 20.0, 20.0));
+        let partial = Shape::Rectangle(Rectangle::new(Point::new(80.0, 10.0), 50.0, 20.0));
+        let outside = Shape::Rectangle(Rectangle::new(Point::new(300.0, 300.0), 10.0, 10.0));
+        let (inside_id, partial_id) = (inside.id(), partial.id());
+        doc.add_shape(inside);
+        doc.add_shape(partial);
+        doc.add_shape(outside);
+
+        let marquee = Rect::new(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(
+            elements_in_rect(&doc, marquee, SelectMode::Intersect),
+            vec![inside_id, partial_id]
+        );
+        assert_eq!(
+            elements_in_rect(&doc, marquee, SelectMode::Contain),
+            vec![inside_id]
+        );
+        // Edges flush with the marquee still count as inside
+        let flush = Rect::new(10.0, 10.0, 30.0, 30.0);
+        assert_eq!(
+            elements_in_rect(&doc, flush, SelectMode::Contain),
+            vec![inside_id]
+        );
+    }
+
+    #[test]
+    fn test_elements_in_rect_skips_locked() {
+        let mut doc = CanvasDocument::new();
+        let locked = Shape::Rectangle(Rectangle::new(Point::new(10.0, 10.0), 20.0, 20.0));
+        let free = Shape::Rectangle(Rectangle::new(Point::new(40.0, 10.0), 20.0, 20.0));
+        let (locked_id, free_id) = (locked.id(), free.id());
+        doc.add_shape(locked);
+        doc.add_shape(free);
+        doc.set_locked(&[locked_id], true);
+
+        let marquee = Rect::new(0.0, 0.0, 100.0, 100.0);
+        for mode in [SelectMode::Intersect, SelectMode::Contain] {
+            assert_eq!(elements_in_rect(&doc, marquee, mode), vec![free_id]);
+        }
+    }
+
+    #[test]
+    fn test_elements_in_rect_selects_groups_atomically() {
+        let mut doc = CanvasDocument::new();
+        let a = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 10.0, 10.0));
+        let b = Shape::Rectangle(Rectangle::new(Point::new(200.0, 0.0), 10.0, 10.0));
+        let ids = [a.id(), b.id()];
+        doc.add_shape(a);
+        doc.add_shape(b);
+        let group = doc.group_shapes(&ids).unwrap();
+
+        let marquee = Rect::new(-5.0, -5.0, 20.0, 20.0);
+        assert_eq!(
+            elements_in_rect(&doc, marquee, SelectMode::Intersect),
+            vec![group]
+        );
+        assert!(elements_in_rect(&doc, marquee, SelectMode::Contain).is_empty());
+    }
+
+    #[test]
+    fn test_handle_rects_placement() {
+        let bounds = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let (rects, rotate) = handle_rects(bounds, 2.0);
+
+        // Corners and edge midpoints
+        assert_eq!(rects[0].center(), Point::new(0.0, 0.0));
+        assert_eq!(rects[3].center(), Point::new(100.0, 50.0));
+        assert_eq!(rects[4].center(), Point::new(50.0, 0.0));
+        assert_eq!(rects[7].center(), Point::new(0.0, 25.0));
+        // Constant screen size: half as large in world units at 2x zoom
+        assert_eq!(rects[0].width(), HANDLE_SIZE / 2.0);
+        assert_eq!(rotate, Point::new(50.0, -ROTATE_HANDLE_OFFSET));
+    }
+
+    #[test]
+    fn test_resize_bounds_edge_changes_one_dimension() {
+        let bounds = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let resized = resize_bounds(
+            bounds,
+            HandleKind::Edge(Edge::Right),
+            kurbo::Vec2::new(20.0, 30.0),
+            true,
+        );
+        assert_eq!(resized, Some(Rect::new(0.0, 0.0, 120.0, 50.0)));
+        assert_eq!(
+            resize_bounds(bounds, HandleKind::Rotate, kurbo::Vec2::ZERO, false),
+            None
+        );
+    }
+
     #[test]
     fn test_handle_hit_test() {
         let handle = Handle::new(Point::new(50.0, 50.0), HandleKind::Endpoint(0));
@@ -949,7 +1232,8 @@ mod tests {
         use crate::shapes::{Image, ImageFormat, ShapeStyle};
 
         let image = Image {
-            id: uuid::Uuid::new_v4(),
+            id: crate::shapes::ShapeId::new_v4(),
+            locked: false,
             position: Point::new(0.0, 0.0),
             width: 100.0,
             height: 100.0,
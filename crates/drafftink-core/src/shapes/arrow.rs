@@ -1,15 +1,151 @@
 //! Arrow shape.
 
-use super::line::PathStyle;
-use super::{ShapeId, ShapeStyle, ShapeTrait, StrokeStyle};
-use kurbo::{Affine, BezPath, Point, Rect, Vec2};
+use super::line::{LineCap, LineJoin, PathStyle};
+use super::{BoundsCache, ShapeId, ShapeStyle, ShapeTrait, StrokeStyle};
+use crate::curve::{DEFAULT_CURVATURE, compute_curved_path};
+use crate::snap::PortId;
+use kurbo::{
+    Affine, BezPath, Circle, ParamCurve, ParamCurveArclen, ParamCurveNearest, PathEl, Point, Rect,
+    Shape as _, Vec2,
+};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+
+/// Stroke width at which an arrowhead is drawn at exactly `head_size`.
+const REFERENCE_STROKE_WIDTH: f64 = 2.0;
+
+/// Default label position: the middle of the shaft.
+pub const DEFAULT_LABEL_POSITION: f64 = 0.5;
+
+/// Accuracy for arc-length measurements along the shaft.
+const ARCLEN_ACCURACY: f64 = 1e-3;
+
+/// Decoration drawn at an arrow endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ArrowheadStyle {
+    /// No head.
+    #[default]
+    None,
+    /// Filled triangle.
+    Triangle,
+    /// Two open strokes.
+    OpenV,
+    /// Filled diamond.
+    Diamond,
+    /// Filled circle.
+    Dot,
+}
+
+/// How a connector's shaft is routed between its endpoints.
+///
+/// A view onto [`PathStyle`] restricted to connector routings: arrows store
+/// only their path style, read and written through
+/// [`Arrow::route_style`] and [`Arrow::set_route_style`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RouteStyle {
+    /// Right-angle connector ([`PathStyle::Angular`]).
+    Elbow,
+    /// Single Bézier bowed by [`Arrow::curvature`] ([`PathStyle::Curved`]).
+    Curved,
+    /// Straight segments ([`PathStyle::Direct`]).
+    Straight,
+}
+
+impl From<RouteStyle> for PathStyle {
+    fn from(style: RouteStyle) -> Self {
+        match style {
+            RouteStyle::Elbow => PathStyle::Angular,
+            RouteStyle::Curved => PathStyle::Curved,
+            RouteStyle::Straight => PathStyle::Direct,
+        }
+    }
+}
+
+impl ArrowheadStyle {
+    /// Index of the style (0 = None, 1 = Triangle, 2 = OpenV, 3 = Diamond, 4 = Dot).
+    pub fn index(self) -> u8 {
+        match self {
+            ArrowheadStyle::None => 0,
+            ArrowheadStyle::Triangle => 1,
+            ArrowheadStyle::OpenV => 2,
+            ArrowheadStyle::Diamond => 3,
+            ArrowheadStyle::Dot => 4,
+        }
+    }
+
+    /// Style for an index.
+    pub fn from_index(index: u8) -> Self {
+        match index {
+            0 => ArrowheadStyle::None,
+            1 => ArrowheadStyle::Triangle,
+            2 => ArrowheadStyle::OpenV,
+            3 => ArrowheadStyle::Diamond,
+            _ => ArrowheadStyle::Dot,
+        }
+    }
+
+    /// Whether the head is filled (closed outline) rather than only stroked.
+    pub fn is_filled(self) -> bool {
+        matches!(
+            self,
+            ArrowheadStyle::Triangle | ArrowheadStyle::Diamond | ArrowheadStyle::Dot
+        )
+    }
+
+    /// Outline of the head with its tip at `tip`, pointing along the unit
+    /// vector `heading` and extending `size` back from the tip.
+    pub fn path(self, tip: Point, heading: Vec2, size: f64) -> Option<BezPath> {
+        let back = -heading * size;
+        let perp = Vec2::new(-heading.y, heading.x);
+        let mut path = BezPath::new();
+        match self {
+            ArrowheadStyle::None => return None,
+            ArrowheadStyle::OpenV => {
+                path.move_to(tip);
+                path.line_to(tip + back + perp * (size * 0.5));
+                path.move_to(tip);
+                path.line_to(tip + back - perp * (size * 0.5));
+            }
+            ArrowheadStyle::Triangle => {
+                path.move_to(tip);
+                path.line_to(tip + back + perp * (size * 0.5));
+                path.line_to(tip + back - perp * (size * 0.5));
+                path.close_path();
+            }
+            ArrowheadStyle::Diamond => {
+                path.move_to(tip);
+                path.line_to(tip + back * 0.5 + perp * (size * 0.35));
+                path.line_to(tip + back);
+                path.line_to(tip + back * 0.5 - perp * (size * 0.35));
+                path.close_path();
+            }
+            ArrowheadStyle::Dot => {
+                let radius = size / 3.0;
+                return Some(Circle::new(tip - heading * radius, radius).to_path(0.1));
+            }
+        }
+        Some(path)
+    }
+}
+
+fn default_label_position() -> f64 {
+    DEFAULT_LABEL_POSITION
+}
+
+fn default_curvature() -> f64 {
+    DEFAULT_CURVATURE
+}
+
+fn default_end_head() -> ArrowheadStyle {
+    ArrowheadStyle::OpenV
+}
 
 /// An arrow shape (line with arrowhead).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Arrow {
     pub(crate) id: ShapeId,
+    /// Locked elements ignore hit testing and refuse edits.
+    #[serde(default)]
+    pub locked: bool,
     /// Start point.
     pub start: Point,
     /// End point (where the arrowhead points).
@@ -17,30 +153,79 @@ pub struct Arrow {
     /// Intermediate points (for polylines/curves).
     #[serde(default)]
     pub intermediate_points: Vec<Point>,
-    /// Path style (Direct, Flowing, Angular).
+    /// Path style (Direct, Flowing, Angular, Curved).
     #[serde(default)]
     pub path_style: PathStyle,
+    /// How far a [`PathStyle::Curved`] shaft bows, as a fraction of its
+    /// length (see [`compute_curved_path`]).
+    #[serde(default = "default_curvature")]
+    pub curvature: f64,
     /// Stroke style (Solid, Dashed, Dotted).
     #[serde(default)]
     pub stroke_style: StrokeStyle,
+    /// Cap at the shaft ends and on open heads.
+    #[serde(default)]
+    pub line_cap: LineCap,
+    /// Join at shaft corners and head tips.
+    #[serde(default)]
+    pub line_join: LineJoin,
     /// Size of the arrowhead.
     pub head_size: f64,
+    /// Head drawn at the start point.
+    #[serde(default)]
+    pub start_head: ArrowheadStyle,
+    /// Head drawn at the end point.
+    #[serde(default = "default_end_head")]
+    pub end_head: ArrowheadStyle,
+    /// Shape the start point is attached to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_binding: Option<ShapeId>,
+    /// Shape the end point is attached to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_binding: Option<ShapeId>,
+    /// Port on the start shape the start point is pinned to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_port: Option<PortId>,
+    /// Port on the end shape the end point is pinned to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_port: Option<PortId>,
+    /// Text drawn on the shaft, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Where the label sits along the shaft, from 0 (start) to 1 (end).
+    #[serde(default = "default_label_position")]
+    pub label_position: f64,
     /// Style properties.
     pub style: ShapeStyle,
+    #[serde(skip)]
+    pub(crate) bounds_cache: BoundsCache,
 }
 
 impl Arrow {
     /// Create a new arrow.
     pub fn new(start: Point, end: Point) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             start,
             end,
             intermediate_points: Vec::new(),
             path_style: PathStyle::Direct,
+            curvature: DEFAULT_CURVATURE,
             stroke_style: StrokeStyle::default(),
+            line_cap: LineCap::default(),
+            line_join: LineJoin::default(),
             head_size: 15.0,
+            start_head: ArrowheadStyle::None,
+            end_head: default_end_head(),
+            start_binding: None,
+            end_binding: None,
+            start_port: None,
+            end_port: None,
+            label: None,
+            label_position: DEFAULT_LABEL_POSITION,
             style: ShapeStyle::default(),
+            bounds_cache: BoundsCache::default(),
         }
     }
 
@@ -57,13 +242,26 @@ impl Arrow {
     ) -> Self {
         Self {
             id,
+            locked: false,
             start,
             end,
             intermediate_points,
             path_style,
+            curvature: DEFAULT_CURVATURE,
             stroke_style,
+            line_cap: LineCap::default(),
+            line_join: LineJoin::default(),
             head_size,
+            start_head: ArrowheadStyle::None,
+            end_head: default_end_head(),
+            start_binding: None,
+            end_binding: None,
+            start_port: None,
+            end_port: None,
+            label: None,
+            label_position: DEFAULT_LABEL_POSITION,
             style,
+            bounds_cache: BoundsCache::default(),
         }
     }
 
@@ -77,14 +275,27 @@ impl Arrow {
             Vec::new()
         };
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             start,
             end,
             intermediate_points,
             path_style,
+            curvature: DEFAULT_CURVATURE,
             stroke_style: StrokeStyle::default(),
+            line_cap: LineCap::default(),
+            line_join: LineJoin::default(),
             head_size: 15.0,
+            start_head: ArrowheadStyle::None,
+            end_head: default_end_head(),
+            start_binding: None,
+            end_binding: None,
+            start_port: None,
+            end_port: None,
+            label: None,
+            label_position: DEFAULT_LABEL_POSITION,
             style: ShapeStyle::default(),
+            bounds_cache: BoundsCache::default(),
         }
     }
 
@@ -114,122 +325,145 @@ impl Arrow {
         let dy = self.end.y - self.start.y;
         (dx * dx + dy * dy).sqrt()
     }
-}
 
-impl ShapeTrait for Arrow {
-    fn id(&self) -> ShapeId {
-        self.id
+    /// Connector routing of the shaft, or `None` for a
+    /// [`PathStyle::Flowing`] shaft.
+    pub fn route_style(&self) -> Option<RouteStyle> {
+        match self.path_style {
+            PathStyle::Angular => Some(RouteStyle::Elbow),
+            PathStyle::Curved => Some(RouteStyle::Curved),
+            PathStyle::Direct => Some(RouteStyle::Straight),
+            PathStyle::Flowing => None,
+        }
     }
 
-    fn bounds(&self) -> Rect {
-        // Include all points and arrowhead in bounds
-        let dir = self.direction();
-        let perp = Vec2::new(-dir.y, dir.x);
-
-        let head_back = Point::new(
-            self.end.x - dir.x * self.head_size,
-            self.end.y - dir.y * self.head_size,
-        );
-        let head_left = Point::new(
-            head_back.x + perp.x * self.head_size * 0.5,
-            head_back.y + perp.y * self.head_size * 0.5,
-        );
-        let head_right = Point::new(
-            head_back.x - perp.x * self.head_size * 0.5,
-            head_back.y - perp.y * self.head_size * 0.5,
-        );
+    /// Route the shaft as `style`. A curved route bows by `curvature`, a
+    /// fraction of the shaft length (see [`compute_curved_path`]).
+    pub fn set_route_style(&mut self, style: RouteStyle, curvature: f64) {
+        self.path_style = style.into();
+        if style == RouteStyle::Curved {
+            self.curvature = curvature;
+        }
+        self.bounds_cache.clear();
+    }
 
-        let points = self.all_points();
-        let mut min_x = head_left.x.min(head_right.x);
-        let mut min_y = head_left.y.min(head_right.y);
-        let mut max_x = head_left.x.max(head_right.x);
-        let mut max_y = head_left.y.max(head_right.y);
-        for p in &points {
-            min_x = min_x.min(p.x);
-            min_y = min_y.min(p.y);
-            max_x = max_x.max(p.x);
-            max_y = max_y.max(p.y);
+    /// Points the shaft passes through, including a computed elbow route.
+    pub fn route_points(&self) -> Vec<Point> {
+        match self.path_style {
+            PathStyle::Angular if self.intermediate_points.is_empty() => {
+                // Compute elbow path dynamically
+                let elbow_pts = crate::elbow::compute_elbow_path(self.start, self.end);
+                let mut pts = vec![self.start];
+                pts.extend(elbow_pts);
+                pts.push(self.end);
+                pts
+            }
+            PathStyle::Curved => vec![self.start, self.end],
+            _ => self.all_points(),
         }
+    }
 
-        Rect::new(min_x, min_y, max_x, max_y)
+    /// Unit direction of travel into the end point, from the last routed segment.
+    pub fn end_heading(&self) -> Vec2 {
+        let points = self.heading_points();
+        heading(points.iter().rev().copied()).unwrap_or_else(|| self.direction())
     }
 
-    fn hit_test(&self, point: Point, tolerance: f64) -> bool {
-        // Check line segment
-        let line_vec = Vec2::new(self.end.x - self.start.x, self.end.y - self.start.y);
-        let point_vec = Vec2::new(point.x - self.start.x, point.y - self.start.y);
-
-        let line_len_sq = line_vec.hypot2();
-        if line_len_sq > f64::EPSILON {
-            let t = (point_vec.dot(line_vec) / line_len_sq).clamp(0.0, 1.0);
-            let projection =
-                Point::new(self.start.x + t * line_vec.x, self.start.y + t * line_vec.y);
-            let dist = ((point.x - projection.x).powi(2) + (point.y - projection.y).powi(2)).sqrt();
-            if dist <= tolerance + self.style.stroke_width / 2.0 {
-                return true;
+    /// Unit direction pointing out of the start point, from the first routed segment.
+    pub fn start_heading(&self) -> Vec2 {
+        let points = self.heading_points();
+        heading(points.iter().copied()).unwrap_or_else(|| -self.direction())
+    }
+
+    /// Points whose first and last segments give the shaft's end directions:
+    /// the route, or for a curved shaft its control polygon, which is
+    /// tangent to the curve at both ends.
+    fn heading_points(&self) -> Vec<Point> {
+        if self.path_style != PathStyle::Curved {
+            return self.route_points();
+        }
+        let mut points = Vec::new();
+        for el in self.shaft_path().elements() {
+            match *el {
+                PathEl::MoveTo(p) | PathEl::LineTo(p) => points.push(p),
+                PathEl::CurveTo(c1, c2, p) => points.extend([c1, c2, p]),
+                PathEl::QuadTo(c, p) => points.extend([c, p]),
+                PathEl::ClosePath => {}
             }
         }
+        points
+    }
 
-        // Check arrowhead triangle
-        let dir = self.direction();
-        let perp = Vec2::new(-dir.y, dir.x);
-        let head_back = Point::new(
-            self.end.x - dir.x * self.head_size,
-            self.end.y - dir.y * self.head_size,
-        );
-        let head_left = Point::new(
-            head_back.x + perp.x * self.head_size * 0.5,
-            head_back.y + perp.y * self.head_size * 0.5,
-        );
-        let head_right = Point::new(
-            head_back.x - perp.x * self.head_size * 0.5,
-            head_back.y - perp.y * self.head_size * 0.5,
-        );
+    /// Arrowhead length, growing with the stroke width so heads stay in
+    /// proportion. Never smaller than `head_size`.
+    pub fn head_length(&self) -> f64 {
+        self.head_size * (self.style.stroke_width / REFERENCE_STROKE_WIDTH).max(1.0)
+    }
 
-        // Point in triangle test
-        fn sign(p1: Point, p2: Point, p3: Point) -> f64 {
-            (p1.x - p3.x) * (p2.y - p3.y) - (p2.x - p3.x) * (p1.y - p3.y)
-        }
+    /// Head outlines for both ends, oriented by the routed segments.
+    pub fn head_paths(&self) -> Vec<(ArrowheadStyle, BezPath)> {
+        let size = self.head_length();
+        self.heads()
+            .into_iter()
+            .filter_map(|(style, tip, dir)| style.path(tip, dir, size).map(|path| (style, path)))
+            .collect()
+    }
 
-        let d1 = sign(point, self.end, head_left);
-        let d2 = sign(point, head_left, head_right);
-        let d3 = sign(point, head_right, self.end);
+    /// Style, tip and heading of the start and end heads.
+    fn heads(&self) -> [(ArrowheadStyle, Point, Vec2); 2] {
+        [
+            (self.start_head, self.start, self.start_heading()),
+            (self.end_head, self.end, self.end_heading()),
+        ]
+    }
 
-        let has_neg = (d1 < 0.0) || (d2 < 0.0) || (d3 < 0.0);
-        let has_pos = (d1 > 0.0) || (d2 > 0.0) || (d3 > 0.0);
+    /// Point at fraction `t` (0 to 1) of the shaft's length.
+    ///
+    /// Follows the routed shaft, so it moves with the arrow when it is
+    /// re-routed.
+    pub fn point_along(&self, t: f64) -> Point {
+        let path = self.shaft_path();
+        let segments: Vec<_> = path
+            .segments()
+            .map(|seg| (seg, seg.arclen(ARCLEN_ACCURACY)))
+            .collect();
+        let total: f64 = segments.iter().map(|(_, len)| len).sum();
+        let mut remaining = t.clamp(0.0, 1.0) * total;
+        for (seg, len) in segments {
+            if remaining <= len {
+                return seg.eval(seg.inv_arclen(remaining, ARCLEN_ACCURACY));
+            }
+            remaining -= len;
+        }
+        self.route_points().last().copied().unwrap_or(self.end)
+    }
 
-        !(has_neg && has_pos)
+    /// Center of the label, at `label_position` along the shaft.
+    pub fn label_anchor(&self) -> Point {
+        self.point_along(self.label_position)
     }
 
-    fn to_path(&self) -> BezPath {
+    /// The shaft without heads.
+    pub fn shaft_path(&self) -> BezPath {
+        if self.path_style == PathStyle::Curved {
+            return compute_curved_path(self.start, self.end, self.curvature);
+        }
+
         let mut path = BezPath::new();
 
         if self.start == self.end {
             return path;
         }
 
-        // Get points to draw
-        let points = match self.path_style {
-            PathStyle::Angular if self.intermediate_points.is_empty() => {
-                // Compute elbow path dynamically
-                let elbow_pts = crate::elbow::compute_elbow_path(self.start, self.end);
-                let mut pts = vec![self.start];
-                pts.extend(elbow_pts);
-                pts.push(self.end);
-                pts
-            }
-            _ => self.all_points(),
-        };
-
+        let points = self.route_points();
         if points.len() < 2 {
             return path;
         }
 
-        // Shaft
         path.move_to(points[0]);
 
         match self.path_style {
-            PathStyle::Direct | PathStyle::Angular => {
+            PathStyle::Direct | PathStyle::Angular | PathStyle::Curved => {
                 for p in &points[1..] {
                     path.line_to(*p);
                 }
@@ -259,38 +493,92 @@ impl ShapeTrait for Arrow {
                 }
             }
         }
+        path
+    }
+}
 
-        // Arrowhead - compute direction from last segment
-        let last_pt = points[points.len() - 1];
-        let prev_pt = points[points.len() - 2];
-        let dx = last_pt.x - prev_pt.x;
-        let dy = last_pt.y - prev_pt.y;
-        let len = (dx * dx + dy * dy).sqrt();
-        let dir = if len > f64::EPSILON {
-            Vec2::new(dx / len, dy / len)
-        } else {
-            self.direction()
-        };
-        let perp = Vec2::new(-dir.y, dir.x);
+/// Unit direction from the second distinct point to the first.
+fn heading(mut points: impl Iterator<Item = Point>) -> Option<Vec2> {
+    let tip = points.next()?;
+    points
+        .map(|p| tip - p)
+        .find(|v| v.hypot() > f64::EPSILON)
+        .map(|v| v.normalize())
+}
 
-        let head_back = Point::new(
-            self.end.x - dir.x * self.head_size,
-            self.end.y - dir.y * self.head_size,
-        );
-        let head_left = Point::new(
-            head_back.x + perp.x * self.head_size * 0.5,
-            head_back.y + perp.y * self.head_size * 0.5,
-        );
-        let head_right = Point::new(
-            head_back.x - perp.x * self.head_size * 0.5,
-            head_back.y - perp.y * self.head_size * 0.5,
-        );
+impl ShapeTrait for Arrow {
+    fn id(&self) -> ShapeId {
+        self.id
+    }
+
+    fn bounds(&self) -> Rect {
+        // Include the routed shaft and arrowheads in bounds
+        let points = self.route_points();
+        let mut bounds = Rect::from_points(points[0], points[0]);
+        for p in &points[1..] {
+            bounds = bounds.union_pt(*p);
+        }
+        if self.path_style == PathStyle::Curved {
+            bounds = bounds.union(self.shaft_path().bounding_box());
+        }
+        for (_, head) in self.head_paths() {
+            bounds = bounds.union(head.bounding_box());
+        }
+        bounds
+    }
 
-        path.move_to(self.end);
-        path.line_to(head_left);
-        path.move_to(self.end);
-        path.line_to(head_right);
+    fn hit_test(&self, point: Point, tolerance: f64) -> bool {
+        let reach = tolerance + self.style.stroke_width / 2.0;
+        if self.path_style == PathStyle::Curved {
+            // Check the curve itself
+            if self
+                .shaft_path()
+                .segments()
+                .any(|seg| seg.nearest(point, 1e-3).distance_sq <= reach * reach)
+            {
+                return true;
+            }
+        } else {
+            // Check line segment
+            let line_vec = Vec2::new(self.end.x - self.start.x, self.end.y - self.start.y);
+            let point_vec = Vec2::new(point.x - self.start.x, point.y - self.start.y);
+
+            let line_len_sq = line_vec.hypot2();
+            if line_len_sq > f64::EPSILON {
+                let t = (point_vec.dot(line_vec) / line_len_sq).clamp(0.0, 1.0);
+                let projection =
+                    Point::new(self.start.x + t * line_vec.x, self.start.y + t * line_vec.y);
+                let dist =
+                    ((point.x - projection.x).powi(2) + (point.y - projection.y).powi(2)).sqrt();
+                if dist <= reach {
+                    return true;
+                }
+            }
+        }
 
+        // Check arrowheads, treating an open V as the triangle it spans
+        let size = self.head_length();
+        self.heads()
+            .into_iter()
+            .filter_map(|(style, tip, dir)| {
+                let area = if style == ArrowheadStyle::OpenV {
+                    ArrowheadStyle::Triangle
+                } else {
+                    style
+                };
+                area.path(tip, dir, size)
+            })
+            .any(|head| head.contains(point))
+    }
+
+    fn to_path(&self) -> BezPath {
+        let mut path = self.shaft_path();
+        if path.is_empty() {
+            return path;
+        }
+        for (_, head) in self.head_paths() {
+            path.extend(head.elements().iter().copied());
+        }
         path
     }
 
@@ -299,10 +587,13 @@ impl ShapeTrait for Arrow {
     }
 
     fn style_mut(&mut self) -> &mut ShapeStyle {
+        // Heads scale with the stroke width
+        self.bounds_cache.clear();
         &mut self.style
     }
 
     fn transform(&mut self, affine: Affine) {
+        self.bounds_cache.clear();
         self.start = affine * self.start;
         self.end = affine * self.end;
         for p in &mut self.intermediate_points {
@@ -311,6 +602,10 @@ impl ShapeTrait for Arrow {
         // Scale head size based on transform
         let scale = affine.as_coeffs();
         self.head_size *= (scale[0].abs() + scale[3].abs()) / 2.0;
+        // A mirror swaps which side of the line a curve bows to
+        if affine.determinant() < 0.0 {
+            self.curvature = -self.curvature;
+        }
     }
 
     fn clone_box(&self) -> Box<dyn ShapeTrait + Send + Sync> {
@@ -321,6 +616,7 @@ impl ShapeTrait for Arrow {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kurbo::Shape as _;
 
     #[test]
     fn test_arrow_creation() {
@@ -347,4 +643,195 @@ mod tests {
         let arrow = Arrow::new(Point::new(0.0, 0.0), Point::new(100.0, 0.0));
         assert!(arrow.hit_test(Point::new(100.0, 0.0), 1.0));
     }
+
+    #[test]
+    fn test_heads_follow_final_segment() {
+        let targets = [
+            Point::new(100.0, 0.0),
+            Point::new(-100.0, 0.0),
+            Point::new(0.0, 100.0),
+            Point::new(0.0, -100.0),
+        ];
+        let styles = [
+            ArrowheadStyle::Triangle,
+            ArrowheadStyle::OpenV,
+            ArrowheadStyle::Diamond,
+            ArrowheadStyle::Dot,
+        ];
+        for end in targets {
+            for style in styles {
+                let mut arrow = Arrow::new(Point::ZERO, end);
+                arrow.end_head = style;
+                let heading = arrow.end_heading();
+                assert!((heading - (end - Point::ZERO).normalize()).hypot() < 1e-9);
+
+                let heads = arrow.head_paths();
+                assert_eq!(heads.len(), 1);
+                let bbox = heads[0].1.bounding_box();
+                // The head sits behind the tip, centered on the shaft
+                let center = bbox.center() - end;
+                assert!(center.dot(heading) < 0.0, "{style:?} toward {end:?}");
+                assert!(
+                    center.cross(heading).abs() < 1e-9,
+                    "{style:?} toward {end:?}"
+                );
+                assert!(bbox.inflate(1e-9, 1e-9).contains(end));
+            }
+        }
+    }
+
+    #[test]
+    fn test_routed_heading_uses_last_segment() {
+        let mut arrow = Arrow::from_points(
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(50.0, 0.0),
+                Point::new(50.0, 60.0),
+                Point::new(50.0, 60.0),
+                Point::new(100.0, 60.0),
+            ],
+            PathStyle::Angular,
+        );
+        arrow.start_head = ArrowheadStyle::Triangle;
+        assert!((arrow.end_heading() - Vec2::new(1.0, 0.0)).hypot() < 1e-9);
+        assert!((arrow.start_heading() - Vec2::new(-1.0, 0.0)).hypot() < 1e-9);
+
+        // Start head points back out of the start point
+        let heads = arrow.head_paths();
+        assert_eq!(heads.len(), 2);
+        let start_center = heads[0].1.bounding_box().center();
+        assert!(start_center.x > 0.0);
+        assert!(start_center.y.abs() < 1e-9);
+        let end_center = heads[1].1.bounding_box().center();
+        assert!(end_center.x < 100.0);
+        assert!((end_center.y - 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_head_scales_with_stroke_width() {
+        let mut arrow = Arrow::new(Point::ZERO, Point::new(100.0, 0.0));
+        let thin = arrow.head_length();
+        arrow.style.stroke_width = REFERENCE_STROKE_WIDTH * 3.0;
+        assert!((arrow.head_length() - thin * 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_label_anchor_follows_route() {
+        use kurbo::ParamCurveNearest;
+
+        let mut arrow = Arrow::new(Point::new(0.0, 0.0), Point::new(200.0, 100.0));
+        arrow.path_style = PathStyle::Angular;
+        let route = arrow.route_points();
+        assert_eq!(route.len(), 4, "expected a Z-shaped route: {route:?}");
+        let (a, b) = (route[1], route[2]);
+
+        let anchor = arrow.label_anchor();
+        let on_middle = kurbo::Line::new(a, b).nearest(anchor, 1e-9).distance_sq;
+        assert!(on_middle < 1e-6, "{anchor:?} not on {a:?}-{b:?}");
+
+        // Known polyline: three 100-long legs, so the midpoint is the
+        // middle of the second leg
+        let arrow = Arrow::from_points(
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(100.0, 0.0),
+                Point::new(100.0, 100.0),
+                Point::new(200.0, 100.0),
+            ],
+            PathStyle::Direct,
+        );
+        let anchor = arrow.label_anchor();
+        assert!((anchor - Point::new(100.0, 50.0)).hypot() < 1e-6);
+        assert_eq!(arrow.point_along(0.0), Point::new(0.0, 0.0));
+        assert!((arrow.point_along(1.0) - Point::new(200.0, 100.0)).hypot() < 1e-6);
+    }
+
+    #[test]
+    fn test_legacy_arrow_defaults_to_open_head() {
+        let arrow = Arrow::new(Point::ZERO, Point::new(100.0, 0.0));
+        let mut json = serde_json::to_value(&arrow).unwrap();
+        let obj = json.as_object_mut().unwrap();
+        obj.remove("start_head");
+        obj.remove("end_head");
+        let restored: Arrow = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.start_head, ArrowheadStyle::None);
+        assert_eq!(restored.end_head, ArrowheadStyle::OpenV);
+    }
+
+    #[test]
+    fn test_curved_arrow_follows_curve() {
+        let mut arrow = Arrow::new(Point::ZERO, Point::new(100.0, 0.0));
+        arrow.path_style = PathStyle::Curved;
+        arrow.curvature = 0.2;
+
+        let mid = arrow.point_along(0.5);
+        assert!((mid - Point::new(50.0, -15.0)).hypot() < 1e-6);
+        assert!(arrow.hit_test(mid, 1.0));
+        assert!(!arrow.hit_test(Point::new(50.0, 0.0), 1.0));
+        assert!(arrow.bounds().y0 <= -15.0);
+        // The head follows the curve's tangent, not the chord.
+        assert!(arrow.end_heading().y > 0.1);
+
+        arrow.transform(Affine::FLIP_Y);
+        assert_eq!(arrow.curvature, -0.2);
+        assert!((arrow.point_along(0.5) - Point::new(50.0, 15.0)).hypot() < 1e-6);
+    }
+
+    #[test]
+    fn test_route_style_selects_path_style() {
+        let mut arrow = Arrow::new(Point::ZERO, Point::new(100.0, 0.0));
+        assert_eq!(arrow.route_style(), Some(RouteStyle::Straight));
+
+        arrow.set_route_style(RouteStyle::Curved, 0.4);
+        assert_eq!(arrow.path_style, PathStyle::Curved);
+        assert_eq!(arrow.curvature, 0.4);
+        assert!((arrow.point_along(0.5) - Point::new(50.0, -30.0)).hypot() < 1e-6);
+
+        // Other routes keep the curvature for switching back
+        arrow.set_route_style(RouteStyle::Elbow, 0.1);
+        assert_eq!(arrow.route_style(), Some(RouteStyle::Elbow));
+        assert_eq!(arrow.curvature, 0.4);
+
+        arrow.path_style = PathStyle::Flowing;
+        assert_eq!(arrow.route_style(), None);
+    }
+
+    #[test]
+    fn test_cached_bounds_follow_elbow_reroute() {
+        use crate::shapes::Shape;
+
+        let fresh = |shape: &Shape| match shape {
+            Shape::Arrow(arrow) => ShapeTrait::bounds(arrow),
+            _ => unreachable!(),
+        };
+        let cached = |shape: &Shape| match shape {
+            Shape::Arrow(arrow) => arrow.bounds_cache.get(),
+            _ => unreachable!(),
+        };
+        let mut arrow = Arrow::new(Point::ZERO, Point::new(100.0, 60.0));
+        arrow.path_style = PathStyle::Angular;
+        let mut shape = Shape::Arrow(arrow);
+
+        let bounds = shape.bounding_box();
+        assert_eq!(bounds, fresh(&shape));
+        assert_eq!(cached(&shape), Some(bounds));
+        if let Shape::Arrow(arrow) = &shape {
+            for point in arrow.route_points() {
+                assert!(bounds.inflate(1e-9, 1e-9).contains(point));
+            }
+        }
+
+        shape.transform(Affine::translate((50.0, 0.0)));
+        assert_eq!(cached(&shape), None);
+        assert_eq!(shape.bounding_box(), fresh(&shape));
+        assert_eq!(shape.bounding_box(), bounds + kurbo::Vec2::new(50.0, 0.0));
+
+        // Moving an endpoint re-routes the elbow
+        if let Shape::Arrow(arrow) = &mut shape {
+            arrow.end = Point::new(-80.0, 200.0);
+        }
+        shape.invalidate_bounds();
+        assert_eq!(shape.bounding_box(), fresh(&shape));
+        assert!(shape.bounding_box().y1 >= 200.0);
+    }
 }
@@ -3,12 +3,14 @@
 use super::{ShapeId, ShapeStyle, ShapeTrait};
 use kurbo::{Affine, BezPath, Ellipse as KurboEllipse, Point, Rect, Shape as KurboShape};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 /// An ellipse shape.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ellipse {
     pub(crate) id: ShapeId,
+    /// Locked elements ignore hit testing and refuse edits.
+    #[serde(default)]
+    pub locked: bool,
     /// Center point.
     pub center: Point,
     /// Horizontal radius.
@@ -26,7 +28,8 @@ impl Ellipse {
     /// Create a new ellipse.
     pub fn new(center: Point, radius_x: f64, radius_y: f64) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             center,
             radius_x,
             radius_y,
@@ -46,6 +49,7 @@ impl Ellipse {
     ) -> Self {
         Self {
             id,
+            locked: false,
             center,
             radius_x,
             radius_y,
@@ -145,6 +149,30 @@ mod tests {
         assert!(!circle.hit_test(Point::new(15.0, 0.0), 0.0));
     }
 
+    #[test]
+    fn test_hit_test_bounding_box_corner_misses() {
+        let ellipse = Ellipse::new(Point::new(50.0, 50.0), 30.0, 20.0);
+        // Inside the bounding box, outside the ellipse
+        let corner = Point::new(78.0, 68.0);
+        assert!(ellipse.bounds().contains(corner));
+        assert!(!ellipse.hit_test(corner, 0.0));
+        // On the ellipse edge along each axis
+        assert!(ellipse.hit_test(Point::new(80.0, 50.0), 0.0));
+        assert!(ellipse.hit_test(Point::new(50.0, 30.0), 0.0));
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let mut ellipse = Ellipse::new(Point::new(50.0, 50.0), 30.0, 20.0);
+        ellipse.rotation = 0.5;
+        let json = serde_json::to_string(&ellipse).unwrap();
+        let restored: Ellipse = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.id(), ellipse.id());
+        assert_eq!(restored.center, ellipse.center);
+        assert_eq!((restored.radius_x, restored.radius_y), (30.0, 20.0));
+        assert_eq!(restored.rotation, 0.5);
+    }
+
     #[test]
     fn test_bounds() {
         let ellipse = Ellipse::new(Point::new(50.0, 50.0), 30.0, 20.0);
@@ -1,14 +1,16 @@
 //! Freehand drawing shape.
 
-use super::{ShapeId, ShapeStyle, ShapeTrait};
-use kurbo::{Affine, BezPath, Point, Rect};
+use super::{BoundsCache, ShapeId, ShapeStyle, ShapeTrait};
+use kurbo::{Affine, BezPath, Line, ParamCurveNearest, Point, Rect};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 /// A freehand drawing (series of points with optional pressure).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Freehand {
     pub(crate) id: ShapeId,
+    /// Locked elements ignore hit testing and refuse edits.
+    #[serde(default)]
+    pub locked: bool,
     /// Points in the freehand path.
     pub points: Vec<Point>,
     /// Pressure values per point (0.0-1.0). If empty, uniform pressure is assumed.
@@ -16,36 +18,44 @@ pub struct Freehand {
     pub pressures: Vec<f64>,
     /// Style properties.
     pub style: ShapeStyle,
+    #[serde(skip)]
+    pub(crate) bounds_cache: BoundsCache,
 }
 
 impl Freehand {
     /// Create a new empty freehand shape.
     pub fn new() -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             points: Vec::new(),
             pressures: Vec::new(),
             style: ShapeStyle::default(),
+            bounds_cache: BoundsCache::default(),
         }
     }
 
     /// Create from existing points.
     pub fn from_points(points: Vec<Point>) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             points,
             pressures: Vec::new(),
             style: ShapeStyle::default(),
+            bounds_cache: BoundsCache::default(),
         }
     }
 
     /// Create from points with pressure values.
     pub fn from_points_with_pressure(points: Vec<Point>, pressures: Vec<f64>) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             points,
             pressures,
             style: ShapeStyle::default(),
+            bounds_cache: BoundsCache::default(),
         }
     }
 
@@ -58,19 +68,23 @@ impl Freehand {
     ) -> Self {
         Self {
             id,
+            locked: false,
             points,
             pressures,
             style,
+            bounds_cache: BoundsCache::default(),
         }
     }
 
     /// Add a point to the path.
     pub fn add_point(&mut self, point: Point) {
+        self.bounds_cache.clear();
         self.points.push(point);
     }
 
     /// Add a point with pressure value.
     pub fn add_point_with_pressure(&mut self, point: Point, pressure: f64) {
+        self.bounds_cache.clear();
         self.points.push(point);
         self.pressures.push(pressure.clamp(0.0, 1.0));
     }
@@ -101,11 +115,13 @@ impl Freehand {
             return;
         }
 
+        self.bounds_cache.clear();
         // Ramer-Douglas-Peucker algorithm with pressure preservation
-        let (new_points, new_pressures) =
-            rdp_simplify_with_pressure(&self.points, &self.pressures, tolerance);
-        self.points = new_points;
-        self.pressures = new_pressures;
+        let keep = rdp_keep(&self.points, tolerance);
+        if self.has_pressure() {
+            self.pressures = keep.iter().map(|&i| self.pressure_at(i)).collect();
+        }
+        self.points = keep.iter().map(|&i| self.points[i]).collect();
     }
 }
 
@@ -115,66 +131,55 @@ impl Default for Freehand {
     }
 }
 
-/// Ramer-Douglas-Peucker line simplification with pressure preservation.
-fn rdp_simplify_with_pressure(
-    points: &[Point],
-    pressures: &[f64],
-    tolerance: f64,
-) -> (Vec<Point>, Vec<f64>) {
+/// Simplify a polyline with the Ramer-Douglas-Peucker algorithm.
+///
+/// Points within `epsilon` of the simplified line are dropped. The first and
+/// last points are always kept, and runs of identical points collapse.
+pub fn simplify_points(points: &[Point], epsilon: f64) -> Vec<Point> {
+    rdp_keep(points, epsilon)
+        .into_iter()
+        .map(|i| points[i])
+        .collect()
+}
+
+/// Indices of the points kept by Ramer-Douglas-Peucker, in order.
+fn rdp_keep(points: &[Point], epsilon: f64) -> Vec<usize> {
     if points.len() < 3 {
-        let p = if pressures.len() == points.len() {
-            pressures.to_vec()
-        } else {
-            vec![1.0; points.len()]
-        };
-        return (points.to_vec(), p);
-    }
-
-    // Find point with maximum distance from line between first and last
-    let first = points[0];
-    let last = points[points.len() - 1];
-
-    let mut max_dist = 0.0;
-    let mut max_index = 0;
-
-    for (i, point) in points.iter().enumerate().skip(1).take(points.len() - 2) {
-        let dist = perpendicular_distance(*point, first, last);
-        if dist > max_dist {
-            max_dist = dist;
-            max_index = i;
-        }
+        return (0..points.len()).collect();
     }
 
-    if max_dist > tolerance {
-        // Recursively simplify
-        let left_pressures = if pressures.len() > max_index {
-            &pressures[..=max_index]
-        } else {
-            &[]
-        };
-        let right_pressures = if pressures.len() > max_index {
-            &pressures[max_index..]
-        } else {
-            &[]
-        };
-
-        let (mut left_pts, mut left_press) =
-            rdp_simplify_with_pressure(&points[..=max_index], left_pressures, tolerance);
-        let (right_pts, right_press) =
-            rdp_simplify_with_pressure(&points[max_index..], right_pressures, tolerance);
-
-        // Combine, removing duplicate point at junction
-        left_pts.pop();
-        left_press.pop();
-        left_pts.extend(right_pts);
-        left_press.extend(right_press);
-        (left_pts, left_press)
-    } else {
-        // All points between first and last can be removed
-        let first_pressure = pressures.first().copied().unwrap_or(1.0);
-        let last_pressure = pressures.last().copied().unwrap_or(1.0);
-        (vec![first, last], vec![first_pressure, last_pressure])
+    let last = points.len() - 1;
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[last] = true;
+
+    // Explicit stack so long strokes can't overflow the call stack
+    let mut stack = vec![(0, last)];
+    while let Some((start, end)) = stack.pop() {
+        // Strict comparison keeps the earliest of equally distant points,
+        // so nearly-collinear runs simplify deterministically
+        let mut max_dist = 0.0;
+        let mut max_index = start;
+        for (i, point) in points.iter().enumerate().take(end).skip(start + 1) {
+            let dist = perpendicular_distance(*point, points[start], points[end]);
+            if dist > max_dist {
+                max_dist = dist;
+                max_index = i;
+            }
+        }
+
+        if max_dist > epsilon {
+            keep[max_index] = true;
+            stack.push((start, max_index));
+            stack.push((max_index, end));
+        }
     }
+
+    keep.iter()
+        .enumerate()
+        .filter(|(_, kept)| **kept)
+        .map(|(i, _)| i)
+        .collect()
 }
 
 /// Calculate perpendicular distance from point to line.
@@ -195,6 +200,70 @@ fn perpendicular_distance(point: Point, line_start: Point, line_end: Point) -> f
     area2 / line_len_sq.sqrt()
 }
 
+/// Erase the parts of a stroke within `radius` of an eraser path.
+///
+/// Every stroke point within `radius` of the eraser polyline is removed, and
+/// the stroke is split wherever points were removed. Returns the remaining
+/// sub-strokes in order; pieces left with a single point are dropped, since
+/// they no longer draw a line. An empty result means the whole stroke was
+/// erased. A one-point eraser path erases around that point; an empty one
+/// leaves the stroke untouched.
+pub fn erase_stroke(
+    stroke_points: &[Point],
+    eraser_path: &[Point],
+    radius: f64,
+) -> Vec<Vec<Point>> {
+    let radius_sq = radius * radius;
+    let erased = |point: Point| match eraser_path {
+        [] => false,
+        [only] => point.distance_squared(*only) <= radius_sq,
+        _ => eraser_path
+            .windows(2)
+            .any(|pair| Line::new(pair[0], pair[1]).nearest(point, 1e-9).distance_sq <= radius_sq),
+    };
+
+    stroke_points
+        .split(|point| erased(*point))
+        .filter(|piece| piece.len() >= 2)
+        .map(<[Point]>::to_vec)
+        .collect()
+}
+
+/// Fit a Catmull-Rom spline through raw stroke samples, as cubic Béziers.
+///
+/// The curve passes through every input point, so the first and last points
+/// are preserved exactly. `tension` scales the tangents: 0.5 gives a uniform
+/// Catmull-Rom spline and 0.0 collapses to straight segments. Fewer than three
+/// points produce straight segments.
+pub fn smooth_stroke(points: &[Point], tension: f64) -> BezPath {
+    let mut path = BezPath::new();
+    let Some(&first) = points.first() else {
+        return path;
+    };
+    path.move_to(first);
+
+    if points.len() < 3 {
+        for &p in &points[1..] {
+            path.line_to(p);
+        }
+        return path;
+    }
+
+    let last = points.len() - 1;
+    for i in 0..last {
+        // Endpoints are duplicated so the spline starts and ends on them
+        let p0 = points[i.saturating_sub(1)];
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = points[(i + 2).min(last)];
+
+        let cp1 = p1 + (p2 - p0) * (tension / 3.0);
+        let cp2 = p2 - (p3 - p1) * (tension / 3.0);
+        path.curve_to(cp1, cp2, p2);
+    }
+    path
+}
+
 impl ShapeTrait for Freehand {
     fn id(&self) -> ShapeId {
         self.id
@@ -279,6 +348,7 @@ impl ShapeTrait for Freehand {
     }
 
     fn transform(&mut self, affine: Affine) {
+        self.bounds_cache.clear();
         for point in &mut self.points {
             *point = affine * *point;
         }
@@ -292,6 +362,52 @@ impl ShapeTrait for Freehand {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kurbo::ParamCurve;
+
+    #[test]
+    fn test_smooth_stroke_straight_line() {
+        let points: Vec<Point> = (0..10)
+            .map(|i| Point::new(i as f64 * 10.0, i as f64 * 5.0))
+            .collect();
+        let path = smooth_stroke(&points, 0.5);
+
+        let start = points[0];
+        let end = points[points.len() - 1];
+        for seg in path.segments() {
+            for t in 0..=10 {
+                let p = seg.eval(t as f64 / 10.0);
+                assert!(perpendicular_distance(p, start, end) < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_smooth_stroke_preserves_endpoints() {
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 20.0),
+            Point::new(30.0, 5.0),
+            Point::new(50.0, 25.0),
+        ];
+        let path = smooth_stroke(&points, 0.5);
+        let segments: Vec<_> = path.segments().collect();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].start(), points[0]);
+        assert_eq!(segments[2].end(), points[3]);
+        // Interior samples are interpolated, not approximated
+        assert_eq!(segments[1].start(), points[1]);
+        assert_eq!(segments[1].end(), points[2]);
+    }
+
+    #[test]
+    fn test_smooth_stroke_few_points() {
+        assert!(smooth_stroke(&[], 0.5).is_empty());
+
+        let path = smooth_stroke(&[Point::new(0.0, 0.0), Point::new(10.0, 0.0)], 0.5);
+        let segments: Vec<_> = path.segments().collect();
+        assert_eq!(segments.len(), 1);
+        assert!(matches!(segments[0], kurbo::PathSeg::Line(_)));
+    }
 
     #[test]
     fn test_freehand_creation() {
@@ -336,6 +452,50 @@ mod tests {
         assert!(freehand.len() < 5);
     }
 
+    #[test]
+    fn test_simplify_points_zigzag() {
+        let zigzag: Vec<Point> = (0..8)
+            .map(|i| Point::new(i as f64 * 10.0, if i % 2 == 0 { 0.0 } else { 4.0 }))
+            .collect();
+
+        assert_eq!(simplify_points(&zigzag, 1.0), zigzag);
+        assert_eq!(simplify_points(&zigzag, 10.0), vec![zigzag[0], zigzag[7]]);
+    }
+
+    #[test]
+    fn test_simplify_points_identical() {
+        let points = vec![Point::new(5.0, 5.0); 6];
+        assert_eq!(
+            simplify_points(&points, 0.5),
+            vec![Point::new(5.0, 5.0), Point::new(5.0, 5.0)]
+        );
+        assert_eq!(simplify_points(&points[..1], 0.5).len(), 1);
+    }
+
+    #[test]
+    fn test_simplify_keeps_pressure_aligned() {
+        let mut freehand = Freehand::from_points_with_pressure(
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(5.0, 0.0),
+                Point::new(10.0, 10.0),
+            ],
+            vec![0.1, 0.5, 0.9],
+        );
+        freehand.simplify(0.5);
+        assert_eq!(freehand.len(), 3);
+        assert_eq!(freehand.pressures, vec![0.1, 0.5, 0.9]);
+
+        let mut no_pressure = Freehand::from_points(vec![
+            Point::new(0.0, 0.0),
+            Point::new(5.0, 0.0),
+            Point::new(10.0, 0.0),
+        ]);
+        no_pressure.simplify(0.5);
+        assert_eq!(no_pressure.len(), 2);
+        assert!(!no_pressure.has_pressure());
+    }
+
     #[test]
     fn test_hit_test() {
         let freehand = Freehand::from_points(vec![Point::new(0.0, 0.0), Point::new(100.0, 0.0)]);
@@ -343,4 +503,33 @@ mod tests {
         assert!(freehand.hit_test(Point::new(50.0, 0.0), 5.0));
         assert!(!freehand.hit_test(Point::new(50.0, 20.0), 5.0));
     }
+
+    fn horizontal_stroke() -> Vec<Point> {
+        (0..=10).map(|i| Point::new(i as f64 * 10.0, 0.0)).collect()
+    }
+
+    #[test]
+    fn test_erase_stroke_middle_splits() {
+        let stroke = horizontal_stroke();
+        let eraser = [Point::new(50.0, -20.0), Point::new(50.0, 20.0)];
+        let pieces = erase_stroke(&stroke, &eraser, 12.0);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0], stroke[..4]);
+        assert_eq!(pieces[1], stroke[7..]);
+    }
+
+    #[test]
+    fn test_erase_stroke_end_shortens() {
+        let stroke = horizontal_stroke();
+        let pieces = erase_stroke(&stroke, &[Point::new(100.0, 0.0)], 15.0);
+        assert_eq!(pieces, vec![stroke[..9].to_vec()]);
+    }
+
+    #[test]
+    fn test_erase_stroke_whole() {
+        let stroke = horizontal_stroke();
+        let eraser = [Point::new(-10.0, 0.0), Point::new(110.0, 0.0)];
+        assert!(erase_stroke(&stroke, &eraser, 1.0).is_empty());
+        assert_eq!(erase_stroke(&stroke, &[], 1.0), vec![stroke]);
+    }
 }
@@ -3,13 +3,15 @@
 use super::{Shape, ShapeId, ShapeStyle, ShapeTrait};
 use kurbo::{Affine, BezPath, Point, Rect};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 /// A group of shapes that can be manipulated as a single unit.
 /// Groups can contain other groups, enabling nested hierarchies.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Group {
     pub(crate) id: ShapeId,
+    /// Locked elements ignore hit testing and refuse edits.
+    #[serde(default)]
+    pub locked: bool,
     /// Child shapes in this group.
     pub children: Vec<Shape>,
     /// Style properties (not directly used, but kept for consistency).
@@ -20,7 +22,8 @@ impl Group {
     /// Create a new group from a list of shapes.
     pub fn new(children: Vec<Shape>) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             children,
             style: ShapeStyle::default(),
         }
@@ -30,6 +33,7 @@ impl Group {
     pub(crate) fn reconstruct(id: ShapeId, children: Vec<Shape>) -> Self {
         Self {
             id,
+            locked: false,
             children,
             style: ShapeStyle::default(),
         }
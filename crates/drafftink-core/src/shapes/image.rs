@@ -3,7 +3,6 @@
 use super::{ShapeId, ShapeStyle, ShapeTrait};
 use kurbo::{Affine, BezPath, Point, Rect, Shape as KurboShape};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 /// Image format for stored image data.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -65,6 +64,9 @@ impl ImageFormat {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Image {
     pub(crate) id: ShapeId,
+    /// Locked elements ignore hit testing and refuse edits.
+    #[serde(default)]
+    pub locked: bool,
     /// Top-left corner position.
     pub position: Point,
     /// Display width.
@@ -106,7 +108,8 @@ impl Image {
         use base64::{Engine, engine::general_purpose::STANDARD};
 
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             position,
             width: source_width as f64,
             height: source_height as f64,
@@ -135,6 +138,7 @@ impl Image {
     ) -> Self {
         Self {
             id,
+            locked: false,
             position,
             width,
             height,
@@ -188,6 +192,15 @@ impl Image {
         )
     }
 
+    /// Hash of the encoded image data. Shapes showing the same image share a
+    /// hash, so renderers can decode and upload it once.
+    pub fn content_hash(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.data_base64.hash(&mut hasher);
+        hasher.finish()
+    }
+
     /// Get the size of the image data in bytes.
     pub fn data_size(&self) -> usize {
         // Base64 is ~4/3 the size of raw data
@@ -283,4 +296,26 @@ mod tests {
         assert!((bounds.x1 - 110.0).abs() < f64::EPSILON);
         assert!((bounds.y1 - 70.0).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn test_content_hash_follows_data() {
+        let a = Image::new(Point::ZERO, &[1, 2, 3], 1, 1, ImageFormat::Png);
+        let b = Image::new(Point::new(50.0, 50.0), &[1, 2, 3], 1, 1, ImageFormat::Png);
+        let c = Image::new(Point::ZERO, &[4, 5, 6], 1, 1, ImageFormat::Png);
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert_ne!(a.content_hash(), c.content_hash());
+    }
+
+    #[test]
+    fn test_json_roundtrip_bounds() {
+        let data = [0x89, 0x50, 0x4E, 0x47, 1, 2, 3];
+        let img = Image::new(Point::new(10.0, 20.0), &data, 100, 50, ImageFormat::Png)
+            .with_size(40.0, 30.0);
+        let json = serde_json::to_string(&img).unwrap();
+        let restored: Image = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.id(), img.id());
+        assert_eq!(restored.bounds(), Rect::new(10.0, 20.0, 50.0, 50.0));
+        assert_eq!(restored.data().unwrap(), data);
+        assert_eq!(restored.content_hash(), img.content_hash());
+    }
 }
@@ -1,9 +1,9 @@
 //! Line shape.
 
-use super::{ShapeId, ShapeStyle, ShapeTrait, StrokeStyle};
-use kurbo::{Affine, BezPath, Line as KurboLine, Point, Rect};
+use super::{BoundsCache, ShapeId, ShapeStyle, ShapeTrait, StrokeStyle};
+use crate::curve::DEFAULT_CURVATURE;
+use kurbo::{Affine, BezPath, Line as KurboLine, Point, Rect, Shape as _};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 /// Path style for lines and arrows.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -15,12 +15,43 @@ pub enum PathStyle {
     Flowing,
     /// Right-angle connectors (elbow).
     Angular,
+    /// A single Bézier between the endpoints, bowed to one side (see
+    /// [`compute_curved_path`](crate::curve::compute_curved_path)).
+    /// Intermediate points are ignored.
+    Curved,
+}
+
+/// Shape drawn at the open ends of a stroke.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineCap {
+    /// Flat end exactly at the endpoint.
+    Butt,
+    /// Half-circle past the endpoint.
+    #[default]
+    Round,
+    /// Half-square past the endpoint.
+    Square,
+}
+
+/// Shape drawn where stroke segments meet, e.g. at elbow corners.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineJoin {
+    /// Sharp corner, beveled when it would be very long.
+    Miter,
+    /// Rounded corner.
+    #[default]
+    Round,
+    /// Corner cut off flat.
+    Bevel,
 }
 
 /// A line segment or polyline with optional bezier smoothing.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Line {
     pub(crate) id: ShapeId,
+    /// Locked elements ignore hit testing and refuse edits.
+    #[serde(default)]
+    pub locked: bool,
     /// Start point.
     pub start: Point,
     /// End point.
@@ -28,27 +59,40 @@ pub struct Line {
     /// Intermediate points (for polylines/curves).
     #[serde(default)]
     pub intermediate_points: Vec<Point>,
-    /// Path style (Direct, Flowing, Angular).
+    /// Path style (Direct, Flowing, Angular, Curved). Curved lines bow by
+    /// [`DEFAULT_CURVATURE`].
     #[serde(default)]
     pub path_style: PathStyle,
     /// Stroke style (Solid, Dashed, Dotted).
     #[serde(default)]
     pub stroke_style: StrokeStyle,
+    /// Cap at both ends of the stroke.
+    #[serde(default)]
+    pub line_cap: LineCap,
+    /// Join at every corner of the stroke.
+    #[serde(default)]
+    pub line_join: LineJoin,
     /// Style properties.
     pub style: ShapeStyle,
+    #[serde(skip)]
+    pub(crate) bounds_cache: BoundsCache,
 }
 
 impl Line {
     /// Create a new line.
     pub fn new(start: Point, end: Point) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             start,
             end,
             intermediate_points: Vec::new(),
             path_style: PathStyle::Direct,
             stroke_style: StrokeStyle::default(),
+            line_cap: LineCap::default(),
+            line_join: LineJoin::default(),
             style: ShapeStyle::default(),
+            bounds_cache: BoundsCache::default(),
         }
     }
 
@@ -64,12 +108,16 @@ impl Line {
     ) -> Self {
         Self {
             id,
+            locked: false,
             start,
             end,
             intermediate_points,
             path_style,
             stroke_style,
+            line_cap: LineCap::default(),
+            line_join: LineJoin::default(),
             style,
+            bounds_cache: BoundsCache::default(),
         }
     }
 
@@ -83,13 +131,17 @@ impl Line {
             Vec::new()
         };
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             start,
             end,
             intermediate_points,
             path_style,
             stroke_style: StrokeStyle::default(),
+            line_cap: LineCap::default(),
+            line_join: LineJoin::default(),
             style: ShapeStyle::default(),
+            bounds_cache: BoundsCache::default(),
         }
     }
 
@@ -135,7 +187,12 @@ impl ShapeTrait for Line {
         let (min_y, max_y) = points.iter().fold((f64::MAX, f64::MIN), |(mn, mx), p| {
             (mn.min(p.y), mx.max(p.y))
         });
-        Rect::new(min_x, min_y, max_x, max_y)
+        let bounds = Rect::new(min_x, min_y, max_x, max_y);
+        if self.path_style == PathStyle::Curved {
+            bounds.union(self.to_path().bounding_box())
+        } else {
+            bounds
+        }
     }
 
     fn hit_test(&self, point: Point, tolerance: f64) -> bool {
@@ -165,6 +222,10 @@ impl ShapeTrait for Line {
             return path;
         }
 
+        if self.path_style == PathStyle::Curved {
+            return crate::curve::compute_curved_path(self.start, self.end, DEFAULT_CURVATURE);
+        }
+
         // Get points to draw
         let points = match self.path_style {
             PathStyle::Angular if self.intermediate_points.is_empty() => {
@@ -185,7 +246,7 @@ impl ShapeTrait for Line {
         path.move_to(points[0]);
 
         match self.path_style {
-            PathStyle::Direct | PathStyle::Angular => {
+            PathStyle::Direct | PathStyle::Angular | PathStyle::Curved => {
                 for p in &points[1..] {
                     path.line_to(*p);
                 }
@@ -227,6 +288,7 @@ impl ShapeTrait for Line {
     }
 
     fn transform(&mut self, affine: Affine) {
+        self.bounds_cache.clear();
         self.start = affine * self.start;
         self.end = affine * self.end;
         for p in &mut self.intermediate_points {
@@ -243,6 +305,42 @@ impl ShapeTrait for Line {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_legacy_dashed_stroke_style() {
+        let legacy: StrokeStyle = serde_json::from_str(r#""Dashed""#).unwrap();
+        assert_eq!(legacy, StrokeStyle::DASHED);
+
+        let custom = StrokeStyle::Dashed {
+            dash: 6.0,
+            gap: 1.0,
+        };
+        let json = serde_json::to_string(&custom).unwrap();
+        assert_eq!(serde_json::from_str::<StrokeStyle>(&json).unwrap(), custom);
+        assert_eq!(
+            serde_json::from_str::<StrokeStyle>(r#""Dotted""#).unwrap(),
+            StrokeStyle::Dotted
+        );
+    }
+
+    #[test]
+    fn test_cap_and_join_roundtrip_and_defaults() {
+        let mut line = Line::new(Point::new(0.0, 0.0), Point::new(100.0, 0.0));
+        line.line_cap = LineCap::Square;
+        line.line_join = LineJoin::Miter;
+        let json = serde_json::to_value(&line).unwrap();
+        let restored: Line = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(restored.line_cap, LineCap::Square);
+        assert_eq!(restored.line_join, LineJoin::Miter);
+
+        let mut legacy = json;
+        let obj = legacy.as_object_mut().unwrap();
+        obj.remove("line_cap");
+        obj.remove("line_join");
+        let restored: Line = serde_json::from_value(legacy).unwrap();
+        assert_eq!(restored.line_cap, LineCap::Round);
+        assert_eq!(restored.line_join, LineJoin::Round);
+    }
+
     #[test]
     fn test_line_creation() {
         let line = Line::new(Point::new(0.0, 0.0), Point::new(100.0, 0.0));
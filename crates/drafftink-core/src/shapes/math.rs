@@ -1,21 +1,27 @@
 //! Math shape for LaTeX equations.
 
-use super::{ShapeId, ShapeStyle, ShapeTrait};
+use super::{FontFamily, ShapeId, ShapeStyle, ShapeTrait};
 use kurbo::{Affine, BezPath, Point, Rect};
 use serde::{Deserialize, Serialize};
 use std::sync::RwLock;
-use uuid::Uuid;
 
 /// A math equation shape (LaTeX).
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Math {
     pub(crate) id: ShapeId,
+    /// Locked elements ignore hit testing and refuse edits.
+    #[serde(default)]
+    pub locked: bool,
     /// Position (baseline origin).
     pub position: Point,
     /// LaTeX source.
     pub latex: String,
     /// Font size in pixels.
     pub font_size: f64,
+    /// Font family for letters and digits; symbols always come from the math
+    /// font.
+    #[serde(default)]
+    pub font_family: FontFamily,
     /// Rotation angle in radians (around center).
     #[serde(default)]
     pub rotation: f64,
@@ -30,9 +36,11 @@ impl Clone for Math {
     fn clone(&self) -> Self {
         Self {
             id: self.id,
+            locked: self.locked,
             position: self.position,
             latex: self.latex.clone(),
             font_size: self.font_size,
+            font_family: self.font_family,
             rotation: self.rotation,
             style: self.style.clone(),
             cached_size: RwLock::new(self.cached_size.read().ok().and_then(|g| *g)),
@@ -45,10 +53,12 @@ impl Math {
 
     pub fn new(position: Point, latex: String) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             position,
             latex,
             font_size: Self::DEFAULT_FONT_SIZE,
+            font_family: FontFamily::default(),
             rotation: 0.0,
             style: ShapeStyle::default(),
             cached_size: RwLock::new(None),
@@ -60,14 +70,17 @@ impl Math {
         position: Point,
         latex: String,
         font_size: f64,
+        font_family: FontFamily,
         rotation: f64,
         style: ShapeStyle,
     ) -> Self {
         Self {
             id,
+            locked: false,
             position,
             latex,
             font_size,
+            font_family,
             rotation,
             style,
             cached_size: RwLock::new(None),
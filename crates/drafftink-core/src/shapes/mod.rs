@@ -8,21 +8,24 @@ mod image;
 mod line;
 mod math;
 mod rectangle;
+mod sticky_note;
 mod text;
 
-pub use arrow::Arrow;
+pub use arrow::{Arrow, ArrowheadStyle, RouteStyle};
 pub use ellipse::Ellipse;
-pub use freehand::Freehand;
+pub use freehand::{Freehand, erase_stroke, simplify_points, smooth_stroke};
 pub use group::Group;
 pub use image::{Image, ImageFormat};
-pub use line::{Line, PathStyle};
+pub use line::{Line, LineCap, LineJoin, PathStyle};
 pub use math::Math;
 pub use rectangle::Rectangle;
-pub use text::{FontFamily, FontWeight, Text};
+pub use sticky_note::{StickyNote, StickyNoteColor};
+pub use text::{FontFamily, FontWeight, Text, TextAlign, wrap_lines};
 
 use kurbo::{Affine, BezPath, Point, Rect};
 use peniko::Color;
 use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
 use uuid::Uuid;
 
 /// Serializable color representation (RGBA8).
@@ -143,23 +146,171 @@ impl FillPattern {
 }
 
 /// Stroke style for lines and arrows.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+///
+/// Dash and gap lengths are multiples of the stroke width, so the pattern
+/// scales with the line.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
 pub enum StrokeStyle {
     #[default]
     Solid,
-    Dashed,
+    Dashed {
+        dash: f64,
+        gap: f64,
+    },
     Dotted,
 }
 
 impl StrokeStyle {
+    /// Default dashed style (dash 4x, gap 2x the stroke width).
+    pub const DASHED: StrokeStyle = StrokeStyle::Dashed {
+        dash: 4.0,
+        gap: 2.0,
+    };
+
     /// Cycle to the next stroke style.
     pub fn next(self) -> Self {
         match self {
-            StrokeStyle::Solid => StrokeStyle::Dashed,
-            StrokeStyle::Dashed => StrokeStyle::Dotted,
+            StrokeStyle::Solid => StrokeStyle::DASHED,
+            StrokeStyle::Dashed { .. } => StrokeStyle::Dotted,
             StrokeStyle::Dotted => StrokeStyle::Solid,
         }
     }
+
+    /// Index of the style kind (0 = Solid, 1 = Dashed, 2 = Dotted).
+    pub fn index(self) -> u8 {
+        match self {
+            StrokeStyle::Solid => 0,
+            StrokeStyle::Dashed { .. } => 1,
+            StrokeStyle::Dotted => 2,
+        }
+    }
+
+    /// Style for a kind index, using the default dash lengths.
+    pub fn from_index(index: u8) -> Self {
+        match index {
+            0 => StrokeStyle::Solid,
+            1 => StrokeStyle::DASHED,
+            _ => StrokeStyle::Dotted,
+        }
+    }
+
+    /// Dash pattern in absolute units for the given stroke width, or `None`
+    /// for solid strokes.
+    pub fn dash_pattern(self, stroke_width: f64) -> Option<[f64; 2]> {
+        match self {
+            StrokeStyle::Solid => None,
+            StrokeStyle::Dashed { dash, gap } => Some([dash * stroke_width, gap * stroke_width]),
+            StrokeStyle::Dotted => Some([stroke_width, stroke_width * 2.0]),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for StrokeStyle {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        enum Tagged {
+            Solid,
+            Dashed { dash: f64, gap: f64 },
+            Dotted,
+        }
+
+        /// Documents saved before dash lengths existed store `"Dashed"`.
+        #[derive(Deserialize)]
+        enum Legacy {
+            Dashed,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Tagged(Tagged),
+            Legacy(Legacy),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Tagged(Tagged::Solid) => StrokeStyle::Solid,
+            Repr::Tagged(Tagged::Dashed { dash, gap }) => StrokeStyle::Dashed { dash, gap },
+            Repr::Tagged(Tagged::Dotted) => StrokeStyle::Dotted,
+            Repr::Legacy(Legacy::Dashed) => StrokeStyle::DASHED,
+        })
+    }
+}
+
+/// A color stop of a gradient fill.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    /// Position along the gradient, from 0.0 to 1.0.
+    pub offset: f64,
+    pub color: SerializableColor,
+}
+
+impl GradientStop {
+    pub fn new(offset: f64, color: SerializableColor) -> Self {
+        Self { offset, color }
+    }
+}
+
+/// Paint for a shape's interior.
+///
+/// Gradients are laid out in the shape's unrotated bounds, so they move,
+/// scale and rotate with the shape.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Fill {
+    Solid(SerializableColor),
+    /// Runs across the bounds through their center, in direction `angle`
+    /// (radians, 0 = left to right, y down).
+    LinearGradient {
+        stops: Vec<GradientStop>,
+        angle: f64,
+    },
+    /// `center` is relative to the bounds ((0.5, 0.5) is the middle) and
+    /// `radius` relative to their larger side.
+    RadialGradient {
+        stops: Vec<GradientStop>,
+        center: Point,
+        radius: f64,
+    },
+}
+
+impl Fill {
+    /// Start and end points of a linear gradient at `angle` spanning `bounds`.
+    pub fn linear_endpoints(angle: f64, bounds: Rect) -> (Point, Point) {
+        let dir = kurbo::Vec2::from_angle(angle);
+        let half = (bounds.width() * dir.x.abs() + bounds.height() * dir.y.abs()) / 2.0;
+        let center = bounds.center();
+        (center - dir * half, center + dir * half)
+    }
+
+    /// Center and radius of a radial gradient in `bounds`.
+    pub fn radial_geometry(center: Point, radius: f64, bounds: Rect) -> (Point, f64) {
+        (
+            Point::new(
+                bounds.x0 + center.x * bounds.width(),
+                bounds.y0 + center.y * bounds.height(),
+            ),
+            radius * bounds.width().max(bounds.height()),
+        )
+    }
+}
+
+/// Drop shadow drawn behind a shape.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Shadow {
+    /// Offset from the shape in world units (not rotated with the shape).
+    pub offset: kurbo::Vec2,
+    /// Blur standard deviation in world units, so it scales with zoom.
+    pub blur: f64,
+    pub color: SerializableColor,
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Self {
+            offset: kurbo::Vec2::new(4.0, 4.0),
+            blur: 4.0,
+            color: SerializableColor::new(0, 0, 0, 64),
+        }
+    }
 }
 
 /// Style properties for shapes.
@@ -174,13 +325,19 @@ pub struct ShapeStyle {
     /// Fill pattern style.
     #[serde(default)]
     pub fill_pattern: FillPattern,
+    /// Gradient or solid paint that overrides `fill_color` when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fill: Option<Fill>,
+    /// Optional drop shadow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<Shadow>,
     /// Sloppiness level for hand-drawn effect.
     pub sloppiness: Sloppiness,
     /// Random seed for hand-drawn effect (ensures consistent rendering across transforms).
     #[serde(default = "generate_seed")]
     pub seed: u32,
     /// Overall opacity (0.0 = fully transparent, 1.0 = fully opaque).
-    #[serde(default = "default_opacity")]
+    #[serde(default = "default_opacity", deserialize_with = "deserialize_opacity")]
     pub opacity: f64,
 }
 
@@ -188,6 +345,19 @@ fn default_opacity() -> f64 {
     1.0
 }
 
+/// Opacity clamped into `0.0..=1.0`; NaN becomes fully opaque.
+fn clamp_opacity(opacity: f64) -> f64 {
+    if opacity.is_nan() {
+        1.0
+    } else {
+        opacity.clamp(0.0, 1.0)
+    }
+}
+
+fn deserialize_opacity<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    f64::deserialize(deserializer).map(clamp_opacity)
+}
+
 /// Generate a random seed for new shapes.
 /// Uses a simple counter + hash approach that works on all platforms including WASM.
 fn generate_seed() -> u32 {
@@ -218,10 +388,7 @@ impl ShapeStyle {
 
     /// Get the stroke color with opacity applied.
     pub fn stroke_with_opacity(&self) -> Color {
-        let color: Color = self.stroke_color.into();
-        let rgba = color.to_rgba8();
-        let alpha = (rgba.a as f64 * self.opacity) as u8;
-        Color::from_rgba8(rgba.r, rgba.g, rgba.b, alpha)
+        self.apply_opacity(self.stroke_color.into())
     }
 
     /// Get the fill color as a peniko Color.
@@ -231,12 +398,26 @@ impl ShapeStyle {
 
     /// Get the fill color with opacity applied.
     pub fn fill_with_opacity(&self) -> Option<Color> {
-        self.fill_color.map(|c| {
-            let color: Color = c.into();
-            let rgba = color.to_rgba8();
-            let alpha = (rgba.a as f64 * self.opacity) as u8;
-            Color::from_rgba8(rgba.r, rgba.g, rgba.b, alpha)
-        })
+        self.fill_color.map(|c| self.apply_opacity(c.into()))
+    }
+
+    /// The interior paint: `fill` if set, otherwise a solid `fill_color`.
+    pub fn effective_fill(&self) -> Option<Fill> {
+        self.fill
+            .clone()
+            .or_else(|| self.fill_color.map(Fill::Solid))
+    }
+
+    /// Set the overall opacity, clamped into `0.0..=1.0`.
+    pub fn set_opacity(&mut self, opacity: f64) {
+        self.opacity = clamp_opacity(opacity);
+    }
+
+    /// Scale a color's alpha by the overall opacity.
+    pub fn apply_opacity(&self, color: Color) -> Color {
+        let rgba = color.to_rgba8();
+        let alpha = (rgba.a as f64 * self.opacity) as u8;
+        Color::from_rgba8(rgba.r, rgba.g, rgba.b, alpha)
     }
 
     /// Set the stroke color from a peniko Color.
@@ -257,6 +438,8 @@ impl Default for ShapeStyle {
             stroke_width: 2.0,
             fill_color: None,
             fill_pattern: FillPattern::default(),
+            fill: None,
+            shadow: None,
             sloppiness: Sloppiness::default(),
             seed: generate_seed(),
             opacity: 1.0,
@@ -264,8 +447,68 @@ impl Default for ShapeStyle {
     }
 }
 
+/// Identifier of a document element.
+///
+/// A UUID in its own type, so element IDs can't be confused with other UUIDs
+/// such as document or room IDs. Serializes as the hyphenated string form.
+/// Shape constructors assign random (v4) IDs;
+/// [`CanvasDocument::generate_id`](crate::canvas::CanvasDocument::generate_id)
+/// hands out IDs unique within the document.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ElementId(Uuid);
+
+impl ElementId {
+    /// A random ID.
+    pub fn new_v4() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    /// The ID whose high and low 64 bits are `high` and `low`.
+    pub fn from_u64_pair(high: u64, low: u64) -> Self {
+        Self(Uuid::from_u64_pair(high, low))
+    }
+
+    /// The ID's 128 bits.
+    pub fn as_u128(&self) -> u128 {
+        self.0.as_u128()
+    }
+}
+
+impl From<Uuid> for ElementId {
+    fn from(uuid: Uuid) -> Self {
+        Self(uuid)
+    }
+}
+
+impl From<ElementId> for Uuid {
+    fn from(id: ElementId) -> Self {
+        id.0
+    }
+}
+
+impl std::fmt::Debug for ElementId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl std::fmt::Display for ElementId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::str::FromStr for ElementId {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(s).map(Self)
+    }
+}
+
 /// Unique identifier for shapes.
-pub type ShapeId = Uuid;
+pub type ShapeId = ElementId;
 
 /// Common trait for all shapes.
 pub trait ShapeTrait {
@@ -294,6 +537,62 @@ pub trait ShapeTrait {
     fn clone_box(&self) -> Box<dyn ShapeTrait + Send + Sync>;
 }
 
+/// Lazily computed bounding box of a path-based shape.
+///
+/// Lines, arrows and freehand strokes derive their bounds from their whole
+/// path (for angular arrows, the routed elbow path), so the result is kept
+/// until the geometry changes. The owning shape clears it in every method
+/// that moves points; code that writes geometry fields directly must call
+/// [`Shape::invalidate_bounds`], which [`CanvasDocument::get_shape_mut`]
+/// and [`find_element_mut`] do on every mutable access.
+///
+/// Not serialized, and left out of `Debug` output so [`shape_content_hash`]
+/// does not depend on whether the bounds were computed yet. Clones carry the
+/// cached value.
+///
+/// [`CanvasDocument::get_shape_mut`]: crate::canvas::CanvasDocument::get_shape_mut
+/// [`find_element_mut`]: crate::canvas::CanvasDocument::find_element_mut
+#[derive(Default)]
+pub(crate) struct BoundsCache(RwLock<Option<Rect>>);
+
+impl Clone for BoundsCache {
+    fn clone(&self) -> Self {
+        Self(RwLock::new(self.get()))
+    }
+}
+
+impl std::fmt::Debug for BoundsCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoundsCache").finish_non_exhaustive()
+    }
+}
+
+impl BoundsCache {
+    /// The cached bounds, if still valid.
+    pub(crate) fn get(&self) -> Option<Rect> {
+        self.0.read().ok().and_then(|guard| *guard)
+    }
+
+    /// The cached bounds, computing and storing them on a miss.
+    pub(crate) fn get_or_compute(&self, compute: impl FnOnce() -> Rect) -> Rect {
+        if let Some(bounds) = self.get() {
+            return bounds;
+        }
+        let bounds = compute();
+        if let Ok(mut cache) = self.0.write() {
+            *cache = Some(bounds);
+        }
+        bounds
+    }
+
+    /// Drop the cached bounds after a geometry change.
+    pub(crate) fn clear(&mut self) {
+        if let Ok(cache) = self.0.get_mut() {
+            *cache = None;
+        }
+    }
+}
+
 /// Enum wrapper for all shape types (for serialization).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Shape {
@@ -306,6 +605,7 @@ pub enum Shape {
     Group(Group),
     Image(Image),
     Math(Math),
+    StickyNote(StickyNote),
 }
 
 impl Shape {
@@ -320,20 +620,53 @@ impl Shape {
             Shape::Group(s) => s.id(),
             Shape::Image(s) => s.id(),
             Shape::Math(s) => s.id(),
+            Shape::StickyNote(s) => s.id(),
         }
     }
 
+    /// Bounding box in world coordinates. Same as
+    /// [`bounding_box`](Self::bounding_box).
     pub fn bounds(&self) -> Rect {
+        self.bounding_box()
+    }
+
+    /// Bounding box in world coordinates.
+    ///
+    /// Path-based shapes (lines, arrows, freehand) compute it once and reuse
+    /// it until their geometry changes; box-based shapes are cheap to compute
+    /// directly, and groups union their children's cached bounds.
+    pub fn bounding_box(&self) -> Rect {
         match self {
             Shape::Rectangle(s) => s.bounds(),
             Shape::Ellipse(s) => s.bounds(),
-            Shape::Line(s) => s.bounds(),
-            Shape::Arrow(s) => s.bounds(),
-            Shape::Freehand(s) => s.bounds(),
+            Shape::Line(s) => s.bounds_cache.get_or_compute(|| s.bounds()),
+            Shape::Arrow(s) => s.bounds_cache.get_or_compute(|| s.bounds()),
+            Shape::Freehand(s) => s.bounds_cache.get_or_compute(|| s.bounds()),
             Shape::Text(s) => s.bounds(),
             Shape::Group(s) => s.bounds(),
             Shape::Image(s) => s.bounds(),
             Shape::Math(s) => s.bounds(),
+            Shape::StickyNote(s) => s.bounds(),
+        }
+    }
+
+    /// Drop cached bounds after editing geometry fields directly, including
+    /// those of any group members.
+    pub fn invalidate_bounds(&mut self) {
+        match self {
+            Shape::Line(s) => s.bounds_cache.clear(),
+            Shape::Arrow(s) => s.bounds_cache.clear(),
+            Shape::Freehand(s) => s.bounds_cache.clear(),
+            Shape::Group(s) => s
+                .children_mut()
+                .iter_mut()
+                .for_each(Shape::invalidate_bounds),
+            Shape::Rectangle(_)
+            | Shape::Ellipse(_)
+            | Shape::Text(_)
+            | Shape::Image(_)
+            | Shape::Math(_)
+            | Shape::StickyNote(_) => {}
         }
     }
 
@@ -348,6 +681,7 @@ impl Shape {
             Shape::Group(s) => s.hit_test(point, tolerance),
             Shape::Image(s) => s.hit_test(point, tolerance),
             Shape::Math(s) => s.hit_test(point, tolerance),
+            Shape::StickyNote(s) => s.hit_test(point, tolerance),
         }
     }
 
@@ -362,6 +696,7 @@ impl Shape {
             Shape::Group(s) => s.to_path(),
             Shape::Image(s) => s.to_path(),
             Shape::Math(s) => s.to_path(),
+            Shape::StickyNote(s) => s.to_path(),
         }
     }
 
@@ -376,6 +711,7 @@ impl Shape {
             Shape::Group(s) => s.style(),
             Shape::Image(s) => s.style(),
             Shape::Math(s) => s.style(),
+            Shape::StickyNote(s) => s.style(),
         }
     }
 
@@ -390,6 +726,7 @@ impl Shape {
             Shape::Group(s) => s.style_mut(),
             Shape::Image(s) => s.style_mut(),
             Shape::Math(s) => s.style_mut(),
+            Shape::StickyNote(s) => s.style_mut(),
         }
     }
 
@@ -404,6 +741,7 @@ impl Shape {
             Shape::Group(s) => s.transform(affine),
             Shape::Image(s) => s.transform(affine),
             Shape::Math(s) => s.transform(affine),
+            Shape::StickyNote(s) => s.transform(affine),
         }
     }
 
@@ -430,8 +768,20 @@ impl Shape {
 
     /// Regenerate the shape's ID with a new unique identifier.
     /// This is used when duplicating or pasting shapes to ensure they have unique IDs.
+    /// Group children (including nested groups) get fresh IDs too.
     pub fn regenerate_id(&mut self) {
-        let new_id = Uuid::new_v4();
+        self.regenerate_id_with(&mut ShapeId::new_v4);
+    }
+
+    /// Like [`regenerate_id`](Self::regenerate_id), taking IDs from `next_id`.
+    /// Children are renumbered before their group.
+    pub fn regenerate_id_with(&mut self, next_id: &mut impl FnMut() -> ShapeId) {
+        if let Shape::Group(group) = self {
+            for child in group.children_mut() {
+                child.regenerate_id_with(next_id);
+            }
+        }
+        let new_id = next_id();
         match self {
             Shape::Rectangle(s) => s.id = new_id,
             Shape::Ellipse(s) => s.id = new_id,
@@ -442,6 +792,7 @@ impl Shape {
             Shape::Group(s) => s.id = new_id,
             Shape::Image(s) => s.id = new_id,
             Shape::Math(s) => s.id = new_id,
+            Shape::StickyNote(s) => s.set_id(new_id),
         }
     }
 
@@ -482,6 +833,38 @@ impl Shape {
         }
     }
 
+    /// Whether this shape is locked against selection and edits.
+    pub fn is_locked(&self) -> bool {
+        match self {
+            Shape::Rectangle(s) => s.locked,
+            Shape::Ellipse(s) => s.locked,
+            Shape::Line(s) => s.locked,
+            Shape::Arrow(s) => s.locked,
+            Shape::Freehand(s) => s.locked,
+            Shape::Text(s) => s.locked,
+            Shape::Group(s) => s.locked,
+            Shape::Image(s) => s.locked,
+            Shape::Math(s) => s.locked,
+            Shape::StickyNote(s) => s.locked,
+        }
+    }
+
+    /// Lock or unlock this shape.
+    pub fn set_locked(&mut self, locked: bool) {
+        match self {
+            Shape::Rectangle(s) => s.locked = locked,
+            Shape::Ellipse(s) => s.locked = locked,
+            Shape::Line(s) => s.locked = locked,
+            Shape::Arrow(s) => s.locked = locked,
+            Shape::Freehand(s) => s.locked = locked,
+            Shape::Text(s) => s.locked = locked,
+            Shape::Group(s) => s.locked = locked,
+            Shape::Image(s) => s.locked = locked,
+            Shape::Math(s) => s.locked = locked,
+            Shape::StickyNote(s) => s.locked = locked,
+        }
+    }
+
     /// Check if this shape supports rotation.
     pub fn supports_rotation(&self) -> bool {
         matches!(
@@ -494,3 +877,52 @@ impl Shape {
         )
     }
 }
+
+/// Hash of everything that affects how a shape is drawn.
+///
+/// The renderer keys its fragment cache on this, and
+/// [`CanvasDocument::save_delta`](crate::canvas::CanvasDocument::save_delta)
+/// uses it to detect changed elements.
+///
+/// Hashes the serialized state, so caches the renderer fills in (layout
+/// sizes, bounds) never count as changes.
+pub fn shape_content_hash(shape: &Shape) -> u64 {
+    use std::hash::Hasher;
+
+    /// Feeds serialized output straight into a hasher without allocating.
+    struct HashWriter<'h>(&'h mut std::collections::hash_map::DefaultHasher);
+
+    impl std::io::Write for HashWriter<'_> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Hasher::write(self.0, buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    let writer = HashWriter(&mut hasher);
+    let _ = match shape {
+        // Image pixels never change for a given ID, so skip hashing the data.
+        Shape::Image(image) => serde_json::to_writer(
+            writer,
+            &(
+                shape.id(),
+                image.locked,
+                image.position,
+                image.width,
+                image.height,
+                image.source_width,
+                image.source_height,
+                image.format,
+                image.rotation,
+                &image.style,
+            ),
+        ),
+        _ => serde_json::to_writer(writer, shape),
+    };
+    hasher.finish()
+}
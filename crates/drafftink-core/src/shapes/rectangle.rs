@@ -3,19 +3,22 @@
 use super::{ShapeId, ShapeStyle, ShapeTrait};
 use kurbo::{Affine, BezPath, Point, Rect, RoundedRect, Shape as KurboShape};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 /// A rectangle shape with optional rounded corners.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Rectangle {
     pub(crate) id: ShapeId,
+    /// Locked elements ignore hit testing and refuse edits.
+    #[serde(default)]
+    pub locked: bool,
     /// Top-left corner position.
     pub position: Point,
     /// Width of the rectangle.
     pub width: f64,
     /// Height of the rectangle.
     pub height: f64,
-    /// Corner radius (0 = sharp corners).
+    /// Corner radius (0 = sharp corners). Drawn clamped to half the
+    /// smaller side; see [`Rectangle::effective_corner_radius`].
     pub corner_radius: f64,
     /// Rotation angle in radians (around center).
     #[serde(default)]
@@ -36,7 +39,8 @@ impl Rectangle {
     /// Create a new rectangle.
     pub fn new(position: Point, width: f64, height: f64) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             position,
             width,
             height,
@@ -58,6 +62,7 @@ impl Rectangle {
     ) -> Self {
         Self {
             id,
+            locked: false,
             position,
             width,
             height,
@@ -86,6 +91,18 @@ impl Rectangle {
             self.position.y + self.height,
         )
     }
+
+    /// Corner radius as drawn: `corner_radius` clamped to half the smaller
+    /// side, so opposite corners never overlap.
+    pub fn effective_corner_radius(&self) -> f64 {
+        let max = self.width.abs().min(self.height.abs()) / 2.0;
+        self.corner_radius.max(0.0).min(max)
+    }
+
+    /// Get the rectangle as a kurbo RoundedRect with the effective radius.
+    pub fn as_rounded_rect(&self) -> RoundedRect {
+        RoundedRect::from_rect(self.as_rect(), self.effective_corner_radius())
+    }
 }
 
 impl ShapeTrait for Rectangle {
@@ -99,13 +116,18 @@ impl ShapeTrait for Rectangle {
 
     fn hit_test(&self, point: Point, tolerance: f64) -> bool {
         let rect = self.as_rect().inflate(tolerance, tolerance);
-        rect.contains(point)
+        let radius = self.effective_corner_radius();
+        if radius > 0.0 {
+            // Points cut off by a rounded corner miss
+            RoundedRect::from_rect(rect, radius + tolerance).contains(point)
+        } else {
+            rect.contains(point)
+        }
     }
 
     fn to_path(&self) -> BezPath {
-        if self.corner_radius > 0.0 {
-            let rounded = RoundedRect::from_rect(self.as_rect(), self.corner_radius);
-            rounded.to_path(0.1)
+        if self.effective_corner_radius() > 0.0 {
+            self.as_rounded_rect().to_path(0.1)
         } else {
             self.as_rect().to_path(0.1)
         }
@@ -162,6 +184,32 @@ mod tests {
         assert!(rect.hit_test(Point::new(105.0, 50.0), 10.0)); // Within tolerance
     }
 
+    #[test]
+    fn test_corner_radius_clamps_to_half_smaller_side() {
+        let mut rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 40.0);
+        rect.corner_radius = 75.0;
+        assert_eq!(rect.effective_corner_radius(), 20.0);
+        rect.corner_radius = -5.0;
+        assert_eq!(rect.effective_corner_radius(), 0.0);
+        rect.corner_radius = 8.0;
+        assert_eq!(rect.effective_corner_radius(), 8.0);
+    }
+
+    #[test]
+    fn test_rounded_hit_test_misses_cut_corners() {
+        let mut rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 100.0);
+        rect.corner_radius = 30.0;
+        // Inside the bounding box but outside the rounded corner
+        assert!(!rect.hit_test(Point::new(2.0, 2.0), 0.0));
+        assert!(rect.hit_test(Point::new(50.0, 1.0), 0.0));
+        assert!(rect.hit_test(Point::new(50.0, 50.0), 0.0));
+
+        let json = serde_json::to_string(&rect).unwrap();
+        let restored: Rectangle = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.corner_radius, 30.0);
+        assert_eq!(restored.as_rounded_rect(), rect.as_rounded_rect());
+    }
+
     #[test]
     fn test_bounds() {
         let rect = Rectangle::new(Point::new(10.0, 20.0), 100.0, 50.0);
@@ -171,4 +219,84 @@ mod tests {
         assert!((bounds.x1 - 110.0).abs() < f64::EPSILON);
         assert!((bounds.y1 - 70.0).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn test_gradient_fill_json_roundtrip() {
+        use crate::shapes::{Fill, GradientStop, SerializableColor};
+
+        let mut rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 50.0);
+        rect.style.fill = Some(Fill::RadialGradient {
+            stops: vec![
+                GradientStop::new(0.0, SerializableColor::white()),
+                GradientStop::new(0.6, SerializableColor::new(10, 20, 30, 128)),
+            ],
+            center: Point::new(0.5, 0.25),
+            radius: 0.75,
+        });
+        let json = serde_json::to_string(&rect).unwrap();
+        let restored: Rectangle = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.style.fill, rect.style.fill);
+
+        // Shapes without a gradient keep their old JSON shape
+        rect.style.fill = None;
+        assert!(!serde_json::to_string(&rect).unwrap().contains("\"fill\""));
+    }
+
+    #[test]
+    fn test_shadow_json_roundtrip() {
+        use crate::shapes::{SerializableColor, Shadow};
+
+        let mut rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 50.0);
+        assert!(!serde_json::to_string(&rect).unwrap().contains("\"shadow\""));
+
+        rect.style.shadow = Some(Shadow {
+            offset: kurbo::Vec2::new(6.0, -2.0),
+            blur: 8.0,
+            color: SerializableColor::new(0, 0, 0, 100),
+        });
+        let json = serde_json::to_string(&rect).unwrap();
+        let restored: Rectangle = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.style.shadow, rect.style.shadow);
+    }
+
+    #[test]
+    fn test_linear_gradient_spans_bounds() {
+        use crate::shapes::Fill;
+
+        let bounds = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let (start, end) = Fill::linear_endpoints(0.0, bounds);
+        assert!((start - Point::new(0.0, 25.0)).hypot() < 1e-9);
+        assert!((end - Point::new(100.0, 25.0)).hypot() < 1e-9);
+        let (start, end) = Fill::linear_endpoints(std::f64::consts::FRAC_PI_2, bounds);
+        assert!((start - Point::new(50.0, 0.0)).hypot() < 1e-9);
+        assert!((end - Point::new(50.0, 50.0)).hypot() < 1e-9);
+    }
+
+    #[test]
+    fn test_opacity_scales_fill_alpha() {
+        use crate::shapes::SerializableColor;
+
+        let mut rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 50.0);
+        rect.style.fill_color = Some(SerializableColor::new(255, 0, 0, 255));
+        rect.style.set_opacity(0.5);
+        let fill = rect.style.fill_with_opacity().unwrap().to_rgba8();
+        assert_eq!((fill.r, fill.a), (255, 127));
+        assert_eq!(rect.style.stroke_with_opacity().to_rgba8().a, 127);
+
+        rect.style.set_opacity(3.0);
+        assert_eq!(rect.style.opacity, 1.0);
+    }
+
+    #[test]
+    fn test_opacity_json_roundtrip_clamps() {
+        let mut rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 50.0);
+        rect.style.set_opacity(0.25);
+        let json = serde_json::to_string(&rect).unwrap();
+        let restored: Rectangle = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.style.opacity, 0.25);
+
+        let json = json.replace("\"opacity\":0.25", "\"opacity\":-2.0");
+        let restored: Rectangle = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.style.opacity, 0.0);
+    }
 }
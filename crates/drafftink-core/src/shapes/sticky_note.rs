@@ -0,0 +1,257 @@
+//! Sticky note shape.
+
+use super::{Rectangle, SerializableColor, ShapeId, ShapeStyle, ShapeTrait, Text, TextAlign};
+use kurbo::{Affine, BezPath, Point, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Background color presets for sticky notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum StickyNoteColor {
+    /// Pale yellow (default).
+    #[default]
+    Yellow,
+    /// Pale pink.
+    Pink,
+    /// Pale blue.
+    Blue,
+    /// Pale green.
+    Green,
+    /// Pale orange.
+    Orange,
+    /// Pale purple.
+    Purple,
+}
+
+impl StickyNoteColor {
+    /// Get the background color.
+    pub fn color(&self) -> SerializableColor {
+        match self {
+            StickyNoteColor::Yellow => SerializableColor::new(254, 240, 138, 255),
+            StickyNoteColor::Pink => SerializableColor::new(251, 207, 232, 255),
+            StickyNoteColor::Blue => SerializableColor::new(191, 219, 254, 255),
+            StickyNoteColor::Green => SerializableColor::new(187, 247, 208, 255),
+            StickyNoteColor::Orange => SerializableColor::new(254, 215, 170, 255),
+            StickyNoteColor::Purple => SerializableColor::new(233, 213, 255, 255),
+        }
+    }
+
+    /// Get display name for UI.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            StickyNoteColor::Yellow => "Yellow",
+            StickyNoteColor::Pink => "Pink",
+            StickyNoteColor::Blue => "Blue",
+            StickyNoteColor::Green => "Green",
+            StickyNoteColor::Orange => "Orange",
+            StickyNoteColor::Purple => "Purple",
+        }
+    }
+
+    /// Get all available presets.
+    pub fn all() -> &'static [StickyNoteColor] {
+        &[
+            StickyNoteColor::Yellow,
+            StickyNoteColor::Pink,
+            StickyNoteColor::Blue,
+            StickyNoteColor::Green,
+            StickyNoteColor::Orange,
+            StickyNoteColor::Purple,
+        ]
+    }
+}
+
+/// A sticky note: a rounded, colored background with centered, wrapped text.
+///
+/// The text wraps to the background width less [`StickyNote::PADDING`] on
+/// each side and is centered vertically. When it no longer fits, the note
+/// grows taller; it never shrinks on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StickyNote {
+    pub(crate) id: ShapeId,
+    /// Locked elements ignore hit testing and refuse edits.
+    #[serde(default)]
+    pub locked: bool,
+    /// Background. Its position and size are the note's, and its style is
+    /// the note's style.
+    pub background: Rectangle,
+    /// Text block, laid out by [`StickyNote::fit_text`].
+    pub text: Text,
+}
+
+impl StickyNote {
+    /// Default width and height of a new note.
+    pub const DEFAULT_SIZE: f64 = 200.0;
+    /// Default corner radius of the background.
+    pub const DEFAULT_CORNER_RADIUS: f64 = 8.0;
+    /// Space between the background edge and the text.
+    pub const PADDING: f64 = 12.0;
+
+    /// Create a new note of the default size with its top-left corner at
+    /// `position`.
+    pub fn new(position: Point, content: String) -> Self {
+        let id = ShapeId::new_v4();
+        let mut background = Rectangle::new(position, Self::DEFAULT_SIZE, Self::DEFAULT_SIZE);
+        background.id = id;
+        background.corner_radius = Self::DEFAULT_CORNER_RADIUS;
+        let mut text = Text::new(position, content).with_align(TextAlign::Center);
+        text.id = id;
+        let mut note = Self {
+            id,
+            locked: false,
+            background,
+            text,
+        };
+        note.set_color(StickyNoteColor::default());
+        note.fit_text();
+        note
+    }
+
+    /// Reconstruct a note with a specific ID (for CRDT/storage).
+    pub(crate) fn reconstruct(id: ShapeId, background: Rectangle, text: Text) -> Self {
+        let mut note = Self {
+            id,
+            locked: false,
+            background,
+            text,
+        };
+        note.set_id(id);
+        note.fit_text();
+        note
+    }
+
+    /// Set the background color preset.
+    pub fn with_color(mut self, color: StickyNoteColor) -> Self {
+        self.set_color(color);
+        self
+    }
+
+    /// Fill the background with a color preset. The border takes the same
+    /// color so it does not show.
+    pub fn set_color(&mut self, color: StickyNoteColor) {
+        let color = color.color();
+        self.background.style.fill_color = Some(color);
+        self.background.style.stroke_color = color;
+    }
+
+    /// Replace the text, growing the note if it no longer fits.
+    pub fn set_content(&mut self, content: String) {
+        self.text.set_content(content);
+        self.text.sync_char_colors_with_content();
+        self.fit_text();
+    }
+
+    /// Get the text content.
+    pub fn content(&self) -> &str {
+        self.text.content()
+    }
+
+    /// Give the note and its parts a new ID.
+    pub(crate) fn set_id(&mut self, id: ShapeId) {
+        self.id = id;
+        self.background.id = id;
+        self.text.id = id;
+    }
+
+    /// Re-wrap the text to the background width, grow the background if the
+    /// text overflows it, and center the text vertically.
+    pub fn fit_text(&mut self) {
+        let wrap_width = (self.background.width - 2.0 * Self::PADDING).max(1.0);
+        if self.text.wrap_width != Some(wrap_width) {
+            self.text.wrap_width = Some(wrap_width);
+            // A measured size is only valid for the old wrap width
+            self.text.invalidate_cache();
+        }
+        let text_height = self.text.bounds().height();
+        self.background.height = self
+            .background
+            .height
+            .max(text_height + 2.0 * Self::PADDING);
+        self.text.position = self.background.position
+            + Vec2::new(Self::PADDING, (self.background.height - text_height) / 2.0);
+    }
+}
+
+impl ShapeTrait for StickyNote {
+    fn id(&self) -> ShapeId {
+        self.id
+    }
+
+    fn bounds(&self) -> Rect {
+        self.background.bounds()
+    }
+
+    fn hit_test(&self, point: Point, tolerance: f64) -> bool {
+        self.background.hit_test(point, tolerance)
+    }
+
+    fn to_path(&self) -> BezPath {
+        self.background.to_path()
+    }
+
+    fn style(&self) -> &ShapeStyle {
+        &self.background.style
+    }
+
+    fn style_mut(&mut self) -> &mut ShapeStyle {
+        &mut self.background.style
+    }
+
+    fn transform(&mut self, affine: Affine) {
+        // Scaling resizes the background and re-wraps the text at its font
+        // size rather than scaling the text
+        self.background.transform(affine);
+        self.fit_text();
+    }
+
+    fn clone_box(&self) -> Box<dyn ShapeTrait + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overflowing_text_grows_note() {
+        let mut note = StickyNote::new(Point::new(0.0, 0.0), "short".to_string());
+        assert_eq!(note.bounds().height(), StickyNote::DEFAULT_SIZE);
+        assert_eq!(
+            note.text.wrap_width,
+            Some(StickyNote::DEFAULT_SIZE - 2.0 * StickyNote::PADDING)
+        );
+
+        note.set_content("word ".repeat(60));
+        let bounds = note.bounds();
+        assert!(bounds.height() > StickyNote::DEFAULT_SIZE);
+        assert_eq!(bounds.width(), StickyNote::DEFAULT_SIZE);
+        // The grown note holds the text with padding to spare
+        let text = note.text.bounds();
+        assert_eq!(text.y0 - bounds.y0, StickyNote::PADDING);
+        assert_eq!(bounds.y1 - text.y1, StickyNote::PADDING);
+
+        // Shortening the text keeps the size
+        note.set_content("short".to_string());
+        assert_eq!(note.bounds(), bounds);
+    }
+
+    #[test]
+    fn test_background_and_text_move_together() {
+        let mut note = StickyNote::new(Point::new(10.0, 20.0), "hello".to_string())
+            .with_color(StickyNoteColor::Blue);
+        let offset = note.text.position - note.background.position;
+        note.transform(Affine::translate((100.0, 50.0)));
+        assert_eq!(note.background.position, Point::new(110.0, 70.0));
+        assert_eq!(note.text.position - note.background.position, offset);
+        assert_eq!(note.style().fill_color, Some(StickyNoteColor::Blue.color()));
+
+        // Widening re-wraps the text to the new width
+        note.transform(Affine::scale_non_uniform(2.0, 1.0));
+        assert_eq!(
+            note.text.wrap_width,
+            Some(2.0 * StickyNote::DEFAULT_SIZE - 2.0 * StickyNote::PADDING)
+        );
+        assert_eq!(note.text.font_size, Text::DEFAULT_FONT_SIZE);
+        assert!(note.bounds().contains(note.text.position));
+    }
+}
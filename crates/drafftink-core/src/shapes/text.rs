@@ -4,7 +4,6 @@ use super::{ShapeId, ShapeStyle, ShapeTrait};
 use kurbo::{Affine, BezPath, Point, Rect};
 use serde::{Deserialize, Serialize};
 use std::sync::RwLock;
-use uuid::Uuid;
 
 /// Font family options.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -80,10 +79,93 @@ impl FontWeight {
     }
 }
 
+/// Horizontal alignment of lines within a text box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum TextAlign {
+    /// Lines start at the left edge (default).
+    #[default]
+    Left,
+    /// Lines are centered.
+    Center,
+    /// Lines end at the right edge.
+    Right,
+    /// Lines are stretched to fill the box, except the last line of each
+    /// paragraph, which is left-aligned.
+    Justify,
+}
+
+impl TextAlign {
+    /// Get display name for UI.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            TextAlign::Left => "Left",
+            TextAlign::Center => "Center",
+            TextAlign::Right => "Right",
+            TextAlign::Justify => "Justify",
+        }
+    }
+
+    /// Get all available alignments.
+    pub fn all() -> &'static [TextAlign] {
+        &[
+            TextAlign::Left,
+            TextAlign::Center,
+            TextAlign::Right,
+            TextAlign::Justify,
+        ]
+    }
+
+    /// Horizontal offset of a line `line_width` wide within a box
+    /// `box_width` wide. Justified lines start at the left edge.
+    pub fn line_offset(&self, line_width: f64, box_width: f64) -> f64 {
+        let slack = box_width - line_width;
+        match self {
+            TextAlign::Left | TextAlign::Justify => 0.0,
+            TextAlign::Center => slack / 2.0,
+            TextAlign::Right => slack,
+        }
+    }
+}
+
+/// Break `text` into lines no wider than `max_width`.
+///
+/// Explicit newlines always break. Within a paragraph, lines break at
+/// whitespace, greedily filling each line; the whitespace at a break is
+/// dropped, leading indentation is kept. A word wider than `max_width` gets a line of its own and
+/// overflows it rather than being split. `measure` gives the width of a
+/// piece of text.
+pub fn wrap_lines<'a>(
+    text: &'a str,
+    max_width: f64,
+    measure: impl Fn(&str) -> f64,
+) -> Vec<&'a str> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut start = 0;
+        let mut end = 0;
+        for (word_start, word) in paragraph.split_whitespace().map(|word| {
+            let offset = word.as_ptr() as usize - paragraph.as_ptr() as usize;
+            (offset, word)
+        }) {
+            let word_end = word_start + word.len();
+            if end > start && measure(&paragraph[start..word_end]) > max_width {
+                lines.push(&paragraph[start..end]);
+                start = word_start;
+            }
+            end = word_end;
+        }
+        lines.push(&paragraph[start..end]);
+    }
+    lines
+}
+
 /// A text shape.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Text {
     pub(crate) id: ShapeId,
+    /// Locked elements ignore hit testing and refuse edits.
+    #[serde(default)]
+    pub locked: bool,
     /// Position (top-left corner of text bounding box).
     pub position: Point,
     /// The text content.
@@ -102,6 +184,13 @@ pub struct Text {
     /// Per-character colors (one per char, None = use default style color).
     #[serde(default)]
     pub char_colors: Vec<Option<super::SerializableColor>>,
+    /// Width at which lines wrap at word boundaries. `None` only breaks at
+    /// explicit newlines.
+    #[serde(default)]
+    pub wrap_width: Option<f64>,
+    /// Horizontal alignment of lines within the wrap width.
+    #[serde(default)]
+    pub align: TextAlign,
     /// Cached layout size (width, height) computed by the renderer.
     /// This is set after text layout and provides accurate bounds.
     /// Uses RwLock for thread-safe interior mutability.
@@ -114,6 +203,7 @@ impl Clone for Text {
     fn clone(&self) -> Self {
         Self {
             id: self.id,
+            locked: self.locked,
             position: self.position,
             content: self.content.clone(),
             font_size: self.font_size,
@@ -122,6 +212,8 @@ impl Clone for Text {
             rotation: self.rotation,
             style: self.style.clone(),
             char_colors: self.char_colors.clone(),
+            wrap_width: self.wrap_width,
+            align: self.align,
             // Clone the cached size value, not the lock
             cached_size: RwLock::new(self.cached_size.read().ok().and_then(|guard| *guard)),
         }
@@ -136,7 +228,8 @@ impl Text {
     pub fn new(position: Point, content: String) -> Self {
         let char_count = content.chars().count();
         Self {
-            id: Uuid::new_v4(),
+            id: ShapeId::new_v4(),
+            locked: false,
             position,
             content,
             font_size: Self::DEFAULT_FONT_SIZE,
@@ -145,6 +238,8 @@ impl Text {
             rotation: 0.0,
             style: ShapeStyle::default(),
             char_colors: vec![None; char_count],
+            wrap_width: None,
+            align: TextAlign::default(),
             cached_size: RwLock::new(None),
         }
     }
@@ -232,9 +327,12 @@ impl Text {
         rotation: f64,
         style: ShapeStyle,
         char_colors: Vec<Option<super::SerializableColor>>,
+        wrap_width: Option<f64>,
+        align: TextAlign,
     ) -> Self {
         Self {
             id,
+            locked: false,
             position,
             content,
             font_size,
@@ -243,6 +341,8 @@ impl Text {
             rotation,
             style,
             char_colors,
+            wrap_width,
+            align,
             cached_size: RwLock::new(None),
         }
     }
@@ -265,6 +365,18 @@ impl Text {
         self
     }
 
+    /// Wrap lines at `width`.
+    pub fn with_wrap_width(mut self, width: f64) -> Self {
+        self.wrap_width = Some(width);
+        self
+    }
+
+    /// Set the line alignment.
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
     /// Set the text content.
     pub fn set_content(&mut self, content: String) {
         self.content = content;
@@ -276,9 +388,27 @@ impl Text {
         &self.content
     }
 
+    /// Lines as laid out, wrapping at [`Self::wrap_width`] with approximate
+    /// character widths.
+    pub fn approximate_lines(&self) -> Vec<&str> {
+        match self.wrap_width {
+            Some(width) => {
+                let char_width = self.font_size * self.char_width_factor();
+                wrap_lines(&self.content, width, |line| {
+                    line.chars().count() as f64 * char_width
+                })
+            }
+            None => self.content.lines().collect(),
+        }
+    }
+
     /// Approximate width based on character count and font size.
     /// This is a rough estimate; actual width depends on the font.
+    /// Wrapped text is as wide as its wrap width.
     fn approximate_width(&self) -> f64 {
+        if let Some(width) = self.wrap_width {
+            return width;
+        }
         // For multi-line text, find the widest line
         let max_line_len = self
             .content
@@ -287,9 +417,14 @@ impl Text {
             .max()
             .unwrap_or(0);
 
+        max_line_len as f64 * self.font_size * self.char_width_factor()
+    }
+
+    /// Average character width as a fraction of the font size.
+    fn char_width_factor(&self) -> f64 {
         // Average character width varies by font family and weight
         // These values are empirically determined approximations
-        let char_width_factor = match (&self.font_family, &self.font_weight) {
+        match (&self.font_family, &self.font_weight) {
             // GelPen is a handwritten-style font with medium width
             (FontFamily::GelPen, FontWeight::Light) => 0.50,
             (FontFamily::GelPen, FontWeight::Regular) => 0.55,
@@ -304,13 +439,14 @@ impl Text {
             (FontFamily::GelPenSerif, FontWeight::Heavy) => 0.60,
             // Vanilla Extract is a handwritten font
             (FontFamily::VanillaExtract, _) => 0.50,
-        };
-
-        max_line_len as f64 * self.font_size * char_width_factor
+        }
     }
 
     /// Approximate height based on font size and number of lines.
     fn approximate_height(&self) -> f64 {
+        if self.wrap_width.is_some() {
+            return self.approximate_lines().len() as f64 * self.font_size * 1.2;
+        }
         // Count lines (empty content = 1 line)
         let line_count = self.content.lines().count().max(1);
         // Add 1 if content ends with newline (lines() doesn't count trailing empty line)
@@ -419,6 +555,35 @@ mod tests {
         assert!(!text.hit_test(Point::new(0.0, 0.0), 0.0));
     }
 
+    #[test]
+    fn test_wrap_sentence_to_two_lines() {
+        // 10 units per character: "the quick brown" is 150 wide
+        let measure = |s: &str| s.chars().count() as f64 * 10.0;
+        let lines = wrap_lines("the quick brown fox jumps", 150.0, measure);
+        assert_eq!(lines, ["the quick brown", "fox jumps"]);
+
+        // Unbreakable words overflow on a line of their own
+        let lines = wrap_lines("a supercalifragilistic b", 50.0, measure);
+        assert_eq!(lines, ["a", "supercalifragilistic", "b"]);
+        // Explicit newlines still break, keeping empty lines
+        assert_eq!(wrap_lines("a\n\nb", 50.0, measure), ["a", "", "b"]);
+
+        let text =
+            Text::new(Point::ZERO, "the quick brown fox jumps".to_string()).with_wrap_width(200.0);
+        let bounds = text.bounds();
+        assert_eq!(bounds.width(), 200.0);
+        assert_eq!(bounds.height(), 2.0 * text.font_size * 1.2);
+    }
+
+    #[test]
+    fn test_right_alignment_offset() {
+        assert_eq!(TextAlign::Right.line_offset(60.0, 200.0), 140.0);
+        assert_eq!(TextAlign::Center.line_offset(60.0, 200.0), 70.0);
+        assert_eq!(TextAlign::Left.line_offset(60.0, 200.0), 0.0);
+        // Overflowing lines shift left past the box start
+        assert_eq!(TextAlign::Right.line_offset(250.0, 200.0), -50.0);
+    }
+
     #[test]
     fn test_bounds() {
         let text = Text::new(Point::new(100.0, 100.0), "Hi".to_string());
@@ -1,6 +1,9 @@
 //! Snap functionality for aligning points to grid and smart guides.
 
-use kurbo::{Point, Rect};
+use crate::canvas::CanvasDocument;
+use crate::shapes::{Shape, ShapeId};
+use kurbo::{Affine, Point, Rect, Vec2};
+use serde::{Deserialize, Serialize};
 
 /// Grid size for snapping (matches the visual grid).
 pub const GRID_SIZE: f64 = 20.0;
@@ -341,6 +344,39 @@ pub fn detect_smart_guides(
     result
 }
 
+/// Alignment guides for the shape `moving_id` at its current position
+/// against every other shape in `doc`.
+///
+/// Runs [`detect_smart_guides`] on the shape's bounds, so its left, center
+/// and right (top, middle and bottom) line up with the same lines of other
+/// shapes within `threshold`. Returns `(offset, guides)`: the offset is the
+/// snap delta the drag applies to the shape, and each [`SmartGuide`] is a
+/// horizontal or vertical guide line to draw (its `kind`). Both are empty if
+/// nothing is in range.
+///
+/// `moving_id` must be a top-level shape; for anything else, such as a
+/// member of a group, nothing snaps. Every other top-level shape is a
+/// target, locked ones included, since they still make useful references.
+/// A group counts once, by its overall bounds, so members of a group are
+/// not targets on their own.
+pub fn compute_alignment_guides(
+    doc: &CanvasDocument,
+    moving_id: ShapeId,
+    threshold: f64,
+) -> (Vec2, Vec<SmartGuide>) {
+    let Some(moving) = doc.get_shape(moving_id) else {
+        return (Vec2::ZERO, Vec::new());
+    };
+    let bounds = moving.bounds();
+    let others: Vec<Rect> = doc
+        .shapes_ordered()
+        .filter(|shape| shape.id() != moving_id)
+        .map(|shape| shape.bounds())
+        .collect();
+    let result = detect_smart_guides(bounds, &others, threshold);
+    (result.point - bounds.origin(), result.guides)
+}
+
 /// Detect smart guides for a single point (e.g., line/arrow endpoint) against shape bounds.
 pub fn detect_smart_guides_for_point(
     point: Point,
@@ -619,6 +655,34 @@ pub fn snap_angle(angle_degrees: f64, increment: f64) -> f64 {
     }
 }
 
+/// Constrain `end` to the nearest multiple of `step_deg` degrees around
+/// `start`, keeping its distance from `start`.
+///
+/// Axis-aligned results are exact, so a snapped horizontal line has exactly
+/// the start's y. Returns `end` unchanged for zero-length drags or a
+/// non-positive step. Tools call this with [`ANGLE_SNAP_INCREMENT`] while
+/// Shift is held.
+pub fn constrain_angle(start: Point, end: Point, step_deg: f64) -> Point {
+    let delta = end - start;
+    let distance = delta.hypot();
+    if distance < 0.001 || step_deg <= 0.0 {
+        return end;
+    }
+    let angle = snap_angle(delta.y.atan2(delta.x).to_degrees(), step_deg);
+    let quarter = angle / 90.0;
+    let (sin, cos) = if quarter.fract() == 0.0 {
+        match quarter as i32 {
+            0 | 4 => (0.0, 1.0),
+            1 => (1.0, 0.0),
+            2 => (0.0, -1.0),
+            _ => (-1.0, 0.0),
+        }
+    } else {
+        angle.to_radians().sin_cos()
+    };
+    start + Vec2::new(distance * cos, distance * sin)
+}
+
 /// Snap a line endpoint to angle increments from a start point.
 /// This snaps the angle while preserving the distance from start.
 pub fn snap_line_endpoint(start: Point, end: Point, angle_snap_enabled: bool) -> AngleSnapResult {
@@ -655,15 +719,9 @@ pub fn snap_line_endpoint(start: Point, end: Point, angle_snap_enabled: bool) ->
         };
     }
 
-    // Snap to nearest 15° increment
+    // Snap to nearest 15° increment, keeping the distance
     let snapped_angle = snap_angle(original_angle_normalized, ANGLE_SNAP_INCREMENT);
-    let snapped_radians = snapped_angle.to_radians();
-
-    // Calculate new endpoint at snapped angle with same distance
-    let snapped_point = Point::new(
-        start.x + distance * snapped_radians.cos(),
-        start.y + distance * snapped_radians.sin(),
-    );
+    let snapped_point = constrain_angle(start, end, ANGLE_SNAP_INCREMENT);
 
     // Check if we actually snapped (angle changed meaningfully)
     let angle_diff = (snapped_angle - original_angle_normalized).abs();
@@ -690,15 +748,76 @@ pub fn snap_to_grid(point: Point, grid_size: f64) -> SnapResult {
     }
 }
 
-/// Snap a point to grid if enabled.
-pub fn snap_point(point: Point, grid_enabled: bool, grid_size: f64) -> SnapResult {
-    if grid_enabled {
-        snap_to_grid(point, grid_size)
+/// Grid snapping settings for element placement and movement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnapConfig {
+    /// Whether grid snapping is active.
+    pub enabled: bool,
+    /// Grid cell size in world units.
+    pub size: f64,
+}
+
+impl Default for SnapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            size: GRID_SIZE,
+        }
+    }
+}
+
+impl SnapConfig {
+    /// Apply a held override modifier, which temporarily disables snapping.
+    pub fn with_override(self, override_held: bool) -> Self {
+        Self {
+            enabled: self.enabled && !override_held,
+            ..self
+        }
+    }
+}
+
+/// Snap a point to the nearest grid multiple if snapping is enabled.
+pub fn snap_point(point: Point, config: &SnapConfig) -> SnapResult {
+    if config.enabled {
+        snap_to_grid(point, config.size)
     } else {
         SnapResult::none(point)
     }
 }
 
+/// Snap a bounding box so that its edges land on gridlines.
+///
+/// On each axis the edge closest to a gridline wins, so a box can align by
+/// either its left or right (top or bottom) side. Returns the offset to apply
+/// to the box together with the snapped corner, for drawing snap guides.
+pub fn snap_bounds(bounds: Rect, config: &SnapConfig) -> (Vec2, SnapResult) {
+    if !config.enabled {
+        return (Vec2::ZERO, SnapResult::none(bounds.origin()));
+    }
+    let (dx, x) = snap_edges(bounds.x0, bounds.x1, config.size);
+    let (dy, y) = snap_edges(bounds.y0, bounds.y1, config.size);
+    (
+        Vec2::new(dx, dy),
+        SnapResult {
+            point: Point::new(x, y),
+            snapped_x: true,
+            snapped_y: true,
+        },
+    )
+}
+
+/// Pick whichever of two edges is nearer a gridline.
+/// Returns the offset to reach it and the gridline position.
+fn snap_edges(lo: f64, hi: f64, grid_size: f64) -> (f64, f64) {
+    let d_lo = (lo / grid_size).round() * grid_size - lo;
+    let d_hi = (hi / grid_size).round() * grid_size - hi;
+    if d_lo.abs() <= d_hi.abs() {
+        (d_lo, lo + d_lo)
+    } else {
+        (d_hi, hi + d_hi)
+    }
+}
+
 /// Find where a ray from origin at a given angle intersects with grid lines.
 /// Returns the nearest intersection point to the target point.
 ///
@@ -948,9 +1067,130 @@ pub fn snap_line_endpoint_isometric(
     }
 }
 
+/// Distance (in world units) within which an arrow endpoint snaps to a port.
+pub const PORT_SNAP_THRESHOLD: f64 = 12.0;
+
+/// A connection port on a shape: an edge midpoint or a corner of its box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PortId {
+    /// Top edge midpoint.
+    N,
+    /// Top-right corner.
+    NE,
+    /// Right edge midpoint.
+    E,
+    /// Bottom-right corner.
+    SE,
+    /// Bottom edge midpoint.
+    S,
+    /// Bottom-left corner.
+    SW,
+    /// Left edge midpoint.
+    W,
+    /// Top-left corner.
+    NW,
+}
+
+impl PortId {
+    /// All ports, clockwise from the top edge.
+    pub const ALL: [PortId; 8] = [
+        PortId::N,
+        PortId::NE,
+        PortId::E,
+        PortId::SE,
+        PortId::S,
+        PortId::SW,
+        PortId::W,
+        PortId::NW,
+    ];
+
+    /// Index into [`PortId::ALL`] (for compact storage).
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+
+    /// Port for an index from [`PortId::index`].
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// Position within the box, as fractions of its width and height.
+    fn fraction(self) -> (f64, f64) {
+        match self {
+            PortId::N => (0.5, 0.0),
+            PortId::NE => (1.0, 0.0),
+            PortId::E => (1.0, 0.5),
+            PortId::SE => (1.0, 1.0),
+            PortId::S => (0.5, 1.0),
+            PortId::SW => (0.0, 1.0),
+            PortId::W => (0.0, 0.5),
+            PortId::NW => (0.0, 0.0),
+        }
+    }
+}
+
+/// Position of `port` on `rect` rotated by `rotation` radians about its
+/// center.
+pub fn port_position(rect: Rect, rotation: f64, port: PortId) -> Point {
+    let (fx, fy) = port.fraction();
+    let local = Point::new(rect.x0 + rect.width() * fx, rect.y0 + rect.height() * fy);
+    Affine::rotate_about(rotation, rect.center()) * local
+}
+
+/// Connection ports of `rect` rotated by `rotation` radians about its center,
+/// in [`PortId::ALL`] order.
+pub fn shape_ports(rect: Rect, rotation: f64) -> Vec<(PortId, Point)> {
+    PortId::ALL
+        .iter()
+        .map(|&port| (port, port_position(rect, rotation, port)))
+        .collect()
+}
+
+/// The port of `shape` closest to `point`, if within `threshold`.
+///
+/// Ports follow the shape's rotation. Lines, arrows and freehand strokes
+/// have no ports.
+pub fn nearest_port(shape: &Shape, point: Point, threshold: f64) -> Option<(PortId, Point)> {
+    if matches!(shape, Shape::Line(_) | Shape::Arrow(_) | Shape::Freehand(_)) {
+        return None;
+    }
+    shape_ports(shape.bounds(), shape.rotation())
+        .into_iter()
+        .map(|(port, pos)| (port, pos, pos.distance(point)))
+        .filter(|&(_, _, distance)| distance <= threshold)
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(port, pos, _)| (port, pos))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::shapes::Rectangle;
+
+    #[test]
+    fn test_alignment_guides_center_to_center() {
+        let mut doc = CanvasDocument::new();
+        doc.add_shape(Shape::Rectangle(Rectangle::new(
+            Point::new(0.0, 0.0),
+            100.0,
+            50.0,
+        )));
+        // Center x is 53, three units right of the other shape's center.
+        let moving = Shape::Rectangle(Rectangle::new(Point::new(23.0, 200.0), 60.0, 30.0));
+        let moving_id = moving.id();
+        doc.add_shape(moving);
+
+        let (offset, guides) = compute_alignment_guides(&doc, moving_id, SMART_GUIDE_THRESHOLD);
+        assert_eq!(offset, Vec2::new(-3.0, 0.0));
+        assert_eq!(guides.len(), 1);
+        assert_eq!(guides[0].kind, SmartGuideKind::Vertical);
+        assert_eq!(guides[0].position, 50.0);
+        assert_eq!((guides[0].start, guides[0].end), (0.0, 230.0));
+
+        let (offset, guides) = compute_alignment_guides(&doc, moving_id, 2.0);
+        assert_eq!(offset, Vec2::ZERO);
+        assert!(guides.is_empty());
+    }
 
     #[test]
     fn test_snap_to_grid() {
@@ -972,6 +1212,76 @@ mod tests {
         assert_eq!(result.point, Point::new(40.0, 60.0));
     }
 
+    #[test]
+    fn test_snap_point_corner() {
+        let config = SnapConfig {
+            enabled: true,
+            size: 10.0,
+        };
+        let result = snap_point(Point::new(14.0, 26.0), &config);
+        assert_eq!(result.point, Point::new(10.0, 30.0));
+
+        let disabled = SnapConfig::default();
+        let result = snap_point(Point::new(14.0, 26.0), &disabled);
+        assert_eq!(result.point, Point::new(14.0, 26.0));
+        assert!(!result.is_snapped());
+    }
+
+    #[test]
+    fn test_snap_bounds_left_edge() {
+        let config = SnapConfig {
+            enabled: true,
+            size: 20.0,
+        };
+        // Left edge is 3 away from x=40, right edge 7 away from x=80.
+        let bounds = Rect::new(43.0, 20.0, 73.0, 50.0);
+        let (offset, result) = snap_bounds(bounds, &config);
+        let snapped = bounds + offset;
+        assert_eq!(snapped.x0, 40.0);
+        assert_eq!(snapped.y0, 20.0);
+        assert_eq!(result.point, Point::new(40.0, 20.0));
+    }
+
+    #[test]
+    fn test_snap_bounds_far_edge() {
+        let config = SnapConfig {
+            enabled: true,
+            size: 20.0,
+        };
+        // Bottom-right corner is nearest the grid.
+        let bounds = Rect::new(5.0, 5.0, 59.0, 41.0);
+        let (offset, result) = snap_bounds(bounds, &config);
+        assert_eq!(offset, Vec2::new(1.0, -1.0));
+        assert_eq!(result.point, Point::new(60.0, 40.0));
+    }
+
+    #[test]
+    fn test_snap_override_modifier() {
+        let config = SnapConfig {
+            enabled: true,
+            size: 20.0,
+        };
+        let bounds = Rect::new(43.0, 21.0, 73.0, 50.0);
+
+        let (offset, _) = snap_bounds(bounds, &config.with_override(false));
+        assert_ne!(offset, Vec2::ZERO);
+
+        let (offset, result) = snap_bounds(bounds, &config.with_override(true));
+        assert_eq!(offset, Vec2::ZERO);
+        assert!(!result.is_snapped());
+
+        // Releasing the modifier restores the configured state.
+        assert!(!SnapConfig::default().with_override(false).enabled);
+    }
+
+    #[test]
+    fn test_snap_bounds_disabled() {
+        let bounds = Rect::new(43.0, 21.0, 73.0, 50.0);
+        let (offset, result) = snap_bounds(bounds, &SnapConfig::default());
+        assert_eq!(offset, Vec2::ZERO);
+        assert!(!result.is_snapped());
+    }
+
     #[test]
     fn test_snap_angle() {
         // Test snapping to 15° increments
@@ -987,6 +1297,34 @@ mod tests {
         assert!((snap_angle(359.0, 15.0) - 0.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_constrain_angle_near_horizontal_is_exact() {
+        let start = Point::new(10.0, 20.0);
+        let end = Point::new(110.0, 23.0);
+        let constrained = constrain_angle(start, end, ANGLE_SNAP_INCREMENT);
+        assert_eq!(constrained.y, start.y);
+        assert!((constrained.distance(start) - end.distance(start)).abs() < 1e-9);
+
+        // Leftward drags snap to exactly 180°
+        let constrained = constrain_angle(start, Point::new(-90.0, 18.0), 15.0);
+        assert_eq!(constrained.y, start.y);
+        assert!(constrained.x < start.x);
+    }
+
+    #[test]
+    fn test_constrain_angle_44_snaps_to_45() {
+        let start = Point::new(0.0, 0.0);
+        let angle = 44.0_f64.to_radians();
+        let end = Point::new(100.0 * angle.cos(), 100.0 * angle.sin());
+        let constrained = constrain_angle(start, end, 15.0);
+        assert!((constrained.x - constrained.y).abs() < 1e-9);
+        assert!((constrained.distance(start) - 100.0).abs() < 1e-9);
+
+        // Configurable step: 44° is kept at a 1° step
+        let fine = constrain_angle(start, end, 1.0);
+        assert!((fine - end).hypot() < 1e-9);
+    }
+
     #[test]
     fn test_snap_line_endpoint_horizontal() {
         let start = Point::new(0.0, 0.0);
@@ -1111,4 +1449,32 @@ mod tests {
             (result.point.y % 20.0).abs() < 0.01 || (result.point.y % 20.0 - 20.0).abs() < 0.01;
         assert!(on_x_grid || on_y_grid);
     }
+
+    #[test]
+    fn test_nearest_port_snaps_to_east() {
+        use crate::shapes::Rectangle;
+
+        let shape = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 100.0, 60.0));
+        let (port, pos) =
+            nearest_port(&shape, Point::new(106.0, 33.0), PORT_SNAP_THRESHOLD).unwrap();
+        assert_eq!(port, PortId::E);
+        assert_eq!(pos, Point::new(100.0, 30.0));
+        assert_eq!(
+            nearest_port(&shape, Point::new(150.0, 30.0), PORT_SNAP_THRESHOLD),
+            None
+        );
+    }
+
+    #[test]
+    fn test_ports_rotate_with_shape() {
+        let rect = Rect::new(0.0, 0.0, 100.0, 60.0);
+        let ports = shape_ports(rect, std::f64::consts::FRAC_PI_2);
+        assert_eq!(ports.len(), 8);
+        // A quarter turn about (50, 30) carries the east port to the bottom
+        let (_, east) = ports[2];
+        assert!((east - Point::new(50.0, 80.0)).hypot() < 1e-9);
+        for port in PortId::ALL {
+            assert_eq!(PortId::from_index(port.index()), Some(port));
+        }
+    }
 }
@@ -0,0 +1,277 @@
+//! Pointer and keyboard dispatch for tools.
+//!
+//! [`Tool`] turns [`InputState`] events into canvas edits, so tool behavior
+//! can be driven and tested without a window. [`ToolKind`] implements it for
+//! the built-in tools; their interaction state lives in the canvas's
+//! [`ToolManager`](super::ToolManager).
+
+use super::ToolKind;
+use crate::canvas::{Canvas, HIT_TOLERANCE_PX};
+use crate::input::InputState;
+use kurbo::Point;
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+/// Behavior of a tool in response to pointer and keyboard input.
+///
+/// The pointer position is read from `input` in screen coordinates.
+pub trait Tool {
+    /// The primary button went down.
+    fn on_pointer_down(&mut self, input: &InputState, canvas: &mut Canvas);
+    /// The pointer moved, with or without a button held.
+    fn on_pointer_move(&mut self, input: &InputState, canvas: &mut Canvas);
+    /// The primary button was released.
+    fn on_pointer_up(&mut self, input: &InputState, canvas: &mut Canvas);
+    /// `key` was pressed. Returns whether the tool handled it.
+    fn on_key(&mut self, input: &InputState, canvas: &mut Canvas, key: KeyCode) -> bool;
+}
+
+impl ToolKind {
+    /// Tool selected by pressing `key` without modifiers.
+    pub fn from_shortcut(key: KeyCode) -> Option<Self> {
+        Some(match key {
+            KeyCode::KeyV | KeyCode::Digit1 => Self::Select,
+            KeyCode::KeyH => Self::Pan,
+            KeyCode::KeyR | KeyCode::Digit2 => Self::Rectangle,
+            KeyCode::KeyO | KeyCode::Digit4 => Self::Ellipse,
+            KeyCode::KeyA | KeyCode::Digit5 => Self::Arrow,
+            KeyCode::KeyL | KeyCode::Digit6 => Self::Line,
+            KeyCode::KeyP | KeyCode::Digit7 => Self::Freehand,
+            KeyCode::KeyT | KeyCode::Digit8 => Self::Text,
+            KeyCode::KeyM | KeyCode::Digit9 => Self::Math,
+            KeyCode::KeyE => Self::Eraser,
+            KeyCode::KeyZ => Self::LaserPointer,
+            _ => return None,
+        })
+    }
+
+    /// Whether this tool draws a new shape by dragging.
+    fn creates_shapes(self) -> bool {
+        matches!(
+            self,
+            Self::Rectangle
+                | Self::Ellipse
+                | Self::Line
+                | Self::Arrow
+                | Self::Freehand
+                | Self::Highlighter
+                | Self::Text
+                | Self::Math
+        )
+    }
+}
+
+/// Pointer position in world coordinates.
+fn world_pointer(input: &InputState, canvas: &Canvas) -> Point {
+    canvas.camera.screen_to_world(input.pointer_position())
+}
+
+/// Remove the unlocked shapes under the pointer.
+///
+/// The first hit of a stroke pushes one undo step for the whole stroke;
+/// strokes that erase nothing leave the history alone.
+fn erase_at_pointer(input: &InputState, canvas: &mut Canvas) {
+    let point = world_pointer(input, canvas);
+    let tolerance = HIT_TOLERANCE_PX / canvas.camera.zoom;
+    let hits = canvas.document.shapes_at_point(point, tolerance);
+    if hits.is_empty() {
+        return;
+    }
+    if !canvas.tool_manager.is_active() {
+        canvas.document.push_undo();
+        canvas.tool_manager.begin(point);
+    }
+    for id in hits {
+        canvas.remove_shape(id);
+    }
+}
+
+impl Tool for ToolKind {
+    fn on_pointer_down(&mut self, input: &InputState, canvas: &mut Canvas) {
+        match *self {
+            Self::Select => {
+                let point = world_pointer(input, canvas);
+                let tolerance = HIT_TOLERANCE_PX / canvas.camera.zoom;
+                match canvas.document.shapes_at_point(point, tolerance).first() {
+                    Some(&id) if input.shift() => canvas.add_to_selection(id),
+                    Some(&id) => canvas.select(id),
+                    None if input.shift() => {}
+                    None => canvas.clear_selection(),
+                }
+            }
+            // Panning tracks the pointer in screen space
+            Self::Pan => canvas.tool_manager.begin(input.pointer_position()),
+            Self::Eraser => erase_at_pointer(input, canvas),
+            Self::LaserPointer => {}
+            kind if kind.creates_shapes() => {
+                let point = world_pointer(input, canvas);
+                canvas.tool_manager.begin(point);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_pointer_move(&mut self, input: &InputState, canvas: &mut Canvas) {
+        match *self {
+            Self::Pan => {
+                if let super::ToolState::Active { current, .. } = canvas.tool_manager.state {
+                    let screen = input.pointer_position();
+                    canvas.camera.pan(screen - current);
+                    canvas.tool_manager.update(screen);
+                }
+            }
+            Self::Eraser => {
+                if input.is_button_pressed(MouseButton::Left) {
+                    erase_at_pointer(input, canvas);
+                }
+            }
+            kind if kind.creates_shapes() => {
+                if canvas.tool_manager.is_active() {
+                    let point = world_pointer(input, canvas);
+                    canvas.tool_manager.update(point);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_pointer_up(&mut self, input: &InputState, canvas: &mut Canvas) {
+        match *self {
+            Self::Pan | Self::Eraser => canvas.tool_manager.cancel(),
+            kind if kind.creates_shapes() => {
+                let point = world_pointer(input, canvas);
+                if let Some(shape) = canvas.tool_manager.end(point) {
+                    canvas.document.push_undo();
+                    canvas.document.add_shape(shape);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_key(&mut self, input: &InputState, canvas: &mut Canvas, key: KeyCode) -> bool {
+        if input.ctrl() || input.alt() || input.meta() {
+            return false;
+        }
+        match key {
+            KeyCode::Escape => {
+                canvas.tool_manager.cancel();
+                canvas.clear_selection();
+                true
+            }
+            KeyCode::Delete | KeyCode::Backspace if *self == Self::Select => {
+                if !canvas.selection.is_empty() {
+                    canvas.document.push_undo();
+                    canvas.delete_selected();
+                }
+                true
+            }
+            _ => match Self::from_shortcut(key) {
+                Some(tool) => {
+                    canvas.set_tool(tool);
+                    *self = tool;
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::Shape;
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{DeviceId, ElementState, WindowEvent};
+
+    fn device() -> DeviceId {
+        // SAFETY: the dummy id is only compared, never dereferenced.
+        unsafe { DeviceId::dummy() }
+    }
+
+    fn move_to(input: &mut InputState, x: f64, y: f64) {
+        input.process_window_event(&WindowEvent::CursorMoved {
+            device_id: device(),
+            position: PhysicalPosition::new(x, y),
+        });
+    }
+
+    fn button(input: &mut InputState, state: ElementState) {
+        input.process_window_event(&WindowEvent::MouseInput {
+            device_id: device(),
+            state,
+            button: MouseButton::Left,
+        });
+    }
+
+    #[test]
+    fn test_rectangle_tool_drag_adds_one_rectangle() {
+        let mut canvas = Canvas::new();
+        let mut input = InputState::new();
+        let mut tool = ToolKind::Rectangle;
+        canvas.set_tool(tool);
+
+        move_to(&mut input, 10.0, 20.0);
+        button(&mut input, ElementState::Pressed);
+        tool.on_pointer_down(&input, &mut canvas);
+        move_to(&mut input, 60.0, 50.0);
+        tool.on_pointer_move(&input, &mut canvas);
+        move_to(&mut input, 110.0, 80.0);
+        tool.on_pointer_move(&input, &mut canvas);
+        button(&mut input, ElementState::Released);
+        tool.on_pointer_up(&input, &mut canvas);
+
+        assert_eq!(canvas.document.len(), 1);
+        let shape = canvas.document.shapes_ordered().next().unwrap();
+        assert!(matches!(shape, Shape::Rectangle(_)));
+        let expected = kurbo::Rect::from_points(
+            canvas.camera.screen_to_world(Point::new(10.0, 20.0)),
+            canvas.camera.screen_to_world(Point::new(110.0, 80.0)),
+        );
+        assert_eq!(shape.bounds(), expected);
+        assert!(!canvas.tool_manager.is_active());
+    }
+
+    #[test]
+    fn test_eraser_pushes_undo_only_on_hit() {
+        let mut canvas = Canvas::new();
+        let mut input = InputState::new();
+        let mut tool = ToolKind::Eraser;
+        canvas.set_tool(tool);
+        let corner = canvas.camera.screen_to_world(Point::new(100.0, 100.0));
+        let rect = crate::shapes::Rectangle::new(corner, 50.0, 50.0);
+        canvas.document.add_shape(Shape::Rectangle(rect));
+
+        // A click on empty canvas erases nothing and leaves no undo step
+        move_to(&mut input, 400.0, 400.0);
+        button(&mut input, ElementState::Pressed);
+        tool.on_pointer_down(&input, &mut canvas);
+        button(&mut input, ElementState::Released);
+        tool.on_pointer_up(&input, &mut canvas);
+        assert!(!canvas.document.can_undo());
+
+        move_to(&mut input, 100.0, 100.0);
+        button(&mut input, ElementState::Pressed);
+        tool.on_pointer_down(&input, &mut canvas);
+        button(&mut input, ElementState::Released);
+        tool.on_pointer_up(&input, &mut canvas);
+        assert_eq!(canvas.document.len(), 0);
+        assert!(!canvas.tool_manager.is_active());
+
+        assert!(canvas.document.undo());
+        assert_eq!(canvas.document.len(), 1);
+        assert!(!canvas.document.can_undo());
+    }
+
+    #[test]
+    fn test_shortcut_switches_tool() {
+        let mut canvas = Canvas::new();
+        let input = InputState::new();
+        let mut tool = ToolKind::Select;
+        assert!(tool.on_key(&input, &mut canvas, KeyCode::KeyO));
+        assert_eq!(tool, ToolKind::Ellipse);
+        assert_eq!(canvas.tool_manager.current_tool, ToolKind::Ellipse);
+        assert!(!tool.on_key(&input, &mut canvas, KeyCode::KeyQ));
+    }
+}
@@ -10,6 +10,10 @@ use std::time::Instant;
 #[cfg(target_arch = "wasm32")]
 use web_time::Instant;
 
+mod dispatch;
+
+pub use dispatch::Tool;
+
 /// Generate a random seed for new tool interactions.
 /// Uses a simple counter + hash approach that works on all platforms including WASM.
 fn generate_tool_seed() -> u32 {
@@ -0,0 +1,136 @@
+//! Font registry mapping family names to embedded font faces.
+//!
+//! Each family has one primary face. On-screen text registers the primary
+//! faces with Parley, and math rendering hands the face of the shape's family
+//! to ReX so letters in formulas match the surrounding text.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use drafftink_core::shapes::FontFamily;
+use ttf_parser::{Face, FaceParsingError};
+
+pub(crate) static GELPEN_REGULAR: &[u8] = include_bytes!("../assets/GelPen.ttf");
+pub(crate) static GELPEN_SERIF_MEDIUM: &[u8] = include_bytes!("../assets/GelPenSerifMedium.ttf");
+pub(crate) static NOTO_SANS: &[u8] = include_bytes!("../assets/NotoSans-Regular.ttf");
+pub(crate) static VANILLA_EXTRACT: &[u8] = include_bytes!("../assets/VanillaExtract.ttf");
+
+/// A registered font: the raw data and its parsed face.
+struct RegisteredFont {
+    data: &'static [u8],
+    face: Face<'static>,
+}
+
+/// Family name to font face lookup with a default fallback.
+pub struct FontRegistry {
+    fonts: HashMap<String, RegisteredFont>,
+    default_family: String,
+    /// Families already reported as missing, so each is logged only once.
+    warned: Mutex<HashSet<String>>,
+}
+
+impl Default for FontRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FontRegistry {
+    /// Create a registry holding the embedded font for every [`FontFamily`],
+    /// with [`FontFamily::default`] as the fallback.
+    pub fn new() -> Self {
+        let mut registry = Self {
+            fonts: HashMap::new(),
+            default_family: FontFamily::default().name().to_string(),
+            warned: Mutex::new(HashSet::new()),
+        };
+        for &family in FontFamily::all() {
+            let data = match family {
+                FontFamily::GelPen => GELPEN_REGULAR,
+                FontFamily::NotoSans => NOTO_SANS,
+                FontFamily::GelPenSerif => GELPEN_SERIF_MEDIUM,
+                FontFamily::VanillaExtract => VANILLA_EXTRACT,
+            };
+            if let Err(err) = registry.register(family.name(), data) {
+                log::error!("Embedded font {:?} failed to parse: {err}", family.name());
+            }
+        }
+        registry
+    }
+
+    /// Register `data` as the font for `family`, replacing any previous one.
+    pub fn register(
+        &mut self,
+        family: impl Into<String>,
+        data: &'static [u8],
+    ) -> Result<(), FaceParsingError> {
+        let face = Face::parse(data, 0)?;
+        self.fonts
+            .insert(family.into(), RegisteredFont { data, face });
+        Ok(())
+    }
+
+    /// Whether `family` has a registered font.
+    pub fn contains(&self, family: &str) -> bool {
+        self.fonts.contains_key(family)
+    }
+
+    /// Font face for `family`.
+    ///
+    /// Unregistered families fall back to the default family, logging a
+    /// warning the first time each one is requested. Returns `None` only if
+    /// the default family is missing too.
+    pub fn face(&self, family: &str) -> Option<&Face<'static>> {
+        if let Some(font) = self.fonts.get(family) {
+            return Some(&font.face);
+        }
+        if let Ok(mut warned) = self.warned.lock() {
+            if warned.insert(family.to_string()) {
+                log::warn!(
+                    "Font family {family:?} is not registered, falling back to {:?}",
+                    self.default_family
+                );
+            }
+        }
+        self.fonts.get(&self.default_family).map(|font| &font.face)
+    }
+
+    /// Raw data of every registered font, for registering with a text
+    /// layout engine.
+    pub fn font_data(&self) -> impl Iterator<Item = &'static [u8]> + '_ {
+        self.fonts.values().map(|font| font.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_families_are_registered() {
+        let registry = FontRegistry::new();
+        for family in FontFamily::all() {
+            assert!(registry.contains(family.name()));
+        }
+        assert_eq!(registry.font_data().count(), FontFamily::all().len());
+    }
+
+    #[test]
+    fn test_missing_family_falls_back_to_default() {
+        let registry = FontRegistry::new();
+        let fallback = registry.face("No Such Font").unwrap();
+        let default = registry.face(FontFamily::default().name()).unwrap();
+        assert_eq!(fallback.number_of_glyphs(), default.number_of_glyphs());
+        assert_eq!(
+            fallback.glyph_index('a'),
+            Face::parse(GELPEN_REGULAR, 0).unwrap().glyph_index('a')
+        );
+    }
+
+    #[test]
+    fn test_register_rejects_invalid_data() {
+        let mut registry = FontRegistry::new();
+        assert!(registry.register("Broken", b"not a font").is_err());
+        assert!(!registry.contains("Broken"));
+    }
+}
@@ -3,7 +3,11 @@
 //! Renderer abstraction and implementations for DrafftInk.
 //! The default implementation uses Vello for GPU-accelerated rendering.
 
+pub mod fonts;
+pub mod pdf;
 mod renderer;
+pub mod stroke;
+pub mod svg;
 pub mod text_editor;
 
 #[cfg(feature = "vello-renderer")]
@@ -12,10 +16,21 @@ mod vello_impl;
 #[cfg(feature = "vello-renderer")]
 pub mod rex_backend;
 
+#[cfg(test)]
+mod rex_mock;
+
+pub use fonts::FontRegistry;
+pub use pdf::{PdfError, export_pdf, export_pdf_single_page};
 pub use renderer::{
-    AngleSnapInfo, GridStyle, RenderContext, Renderer, RendererError, RotationInfo,
+    AngleSnapInfo, BackgroundStyle, DEFAULT_TEXT_LOD_PX, GridStyle, HIT_TOLERANCE_PX,
+    RenderContext, RenderResult, Renderer, RendererError, RotationInfo, export_background,
+    shape_content_hash,
 };
+pub use stroke::build_variable_width_path;
+pub use svg::export_svg;
 pub use text_editor::{TextEditResult, TextEditState, TextKey, TextModifiers};
 
+#[cfg(all(feature = "vello-renderer", not(target_arch = "wasm32")))]
+pub use vello_impl::render_scene_to_rgba;
 #[cfg(feature = "vello-renderer")]
-pub use vello_impl::{PngRenderResult, VelloRenderer};
+pub use vello_impl::{BuildStats, PngRenderResult, VelloRenderer};
@@ -0,0 +1,714 @@
+//! PDF export for canvas documents.
+//!
+//! Like SVG export this needs no GPU, and the PDF is written directly rather
+//! than through a library. Shapes become vector paths from their `to_path()`,
+//! so angular arrows are polylines following the on-screen elbow routing.
+//! Text is real text set in the embedded font of its family (the primary
+//! face; weights are not distinguished), encoded as WinAnsi, so characters
+//! outside Latin-1 print as `?`. One world unit is one point.
+//!
+//! Not yet supported: the hand-drawn sloppiness effect, non-solid fill
+//! patterns, gradient fills (the plain fill color is used), drop shadows,
+//! per-character text colors, images, and math. Images and math are written
+//! as content stream comments.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use drafftink_core::canvas::{Background, CanvasDocument};
+use drafftink_core::shapes::{FontFamily, LineCap, LineJoin, Shape, ShapeStyle, StrokeStyle, Text};
+use kurbo::{Affine, BezPath, PathEl, Point, Rect, Size};
+use peniko::Color;
+use thiserror::Error;
+use ttf_parser::Face;
+
+use crate::fonts::{GELPEN_REGULAR, GELPEN_SERIF_MEDIUM, NOTO_SANS, VANILLA_EXTRACT};
+
+/// Padding around the document content, in points.
+const PADDING: f64 = 20.0;
+
+/// Line height used when laying out multi-line text, relative to font size.
+const LINE_HEIGHT: f64 = 1.2;
+
+/// First and last character codes given widths in embedded fonts.
+const FIRST_CHAR: u8 = 32;
+const LAST_CHAR: u8 = 255;
+
+/// PDF export errors.
+#[derive(Debug, Error)]
+pub enum PdfError {
+    #[error("Invalid page size {}x{}: both sides must be positive and finite", .0.width, .0.height)]
+    InvalidPageSize(Size),
+}
+
+/// Refuse page sizes that would tile into infinitely many pages or none.
+fn check_page_size(page_size: Size) -> Result<(), PdfError> {
+    if page_size.is_finite() && page_size.width > 0.0 && page_size.height > 0.0 {
+        Ok(())
+    } else {
+        Err(PdfError::InvalidPageSize(page_size))
+    }
+}
+
+/// Export `doc` as a PDF, tiling its content into pages of `page_size`.
+///
+/// Content is drawn at its natural size (one world unit per point), so a
+/// document larger than a page spans several, ordered left to right, then
+/// top to bottom. An empty document gives one blank page. Fails if either
+/// side of `page_size` is not positive and finite.
+pub fn export_pdf(doc: &CanvasDocument, page_size: Size) -> Result<Vec<u8>, PdfError> {
+    check_page_size(page_size)?;
+    let bounds = content_bounds(doc, page_size);
+    let cols = (bounds.width() / page_size.width).ceil().max(1.0) as usize;
+    let rows = (bounds.height() / page_size.height).ceil().max(1.0) as usize;
+    let pages = (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (row, col)))
+        .map(|(row, col)| {
+            let x0 = bounds.x0 + col as f64 * page_size.width;
+            let y0 = bounds.y0 + row as f64 * page_size.height;
+            // World y grows down, PDF y grows up
+            Affine::new([1.0, 0.0, 0.0, -1.0, -x0, page_size.height + y0])
+        })
+        .collect::<Vec<_>>();
+    Ok(write_pdf(doc, page_size, &pages))
+}
+
+/// Export `doc` as a single-page PDF, scaling its content to fit and
+/// centering it on a page of `page_size`. Fails on the same page sizes as
+/// [`export_pdf`].
+pub fn export_pdf_single_page(doc: &CanvasDocument, page_size: Size) -> Result<Vec<u8>, PdfError> {
+    check_page_size(page_size)?;
+    let bounds = content_bounds(doc, page_size);
+    let scale = (page_size.width / bounds.width()).min(page_size.height / bounds.height());
+    let x_margin = (page_size.width - scale * bounds.width()) / 2.0;
+    let y_margin = (page_size.height - scale * bounds.height()) / 2.0;
+    let page = Affine::new([
+        scale,
+        0.0,
+        0.0,
+        -scale,
+        x_margin - scale * bounds.x0,
+        page_size.height - y_margin + scale * bounds.y0,
+    ]);
+    Ok(write_pdf(doc, page_size, &[page]))
+}
+
+/// Bounds of the document content plus padding, or a page at the origin
+/// for an empty document.
+fn content_bounds(doc: &CanvasDocument, page_size: Size) -> Rect {
+    CanvasDocument::shapes_bounds(doc.shapes_ordered())
+        .map(|b| b.inflate(PADDING, PADDING))
+        .unwrap_or_else(|| Rect::from_origin_size((0.0, 0.0), page_size))
+}
+
+/// Write a PDF with one page per transform from world to page space.
+fn write_pdf(doc: &CanvasDocument, page_size: Size, pages: &[Affine]) -> Vec<u8> {
+    let mut content = Content::default();
+    let streams: Vec<String> = pages
+        .iter()
+        .map(|&page| {
+            content.page(doc, page, page_size);
+            std::mem::take(&mut content.ops)
+        })
+        .collect();
+
+    let mut pdf = PdfWriter::new();
+    let catalog = pdf.alloc();
+    let page_tree = pdf.alloc();
+    let resources = pdf.alloc();
+
+    let mut kids = String::new();
+    for stream in &streams {
+        let page = pdf.alloc();
+        let contents = pdf.alloc();
+        pdf.object(
+            page,
+            &format!(
+                "<< /Type /Page /Parent {page_tree} 0 R /MediaBox [0 0 {} {}] /Resources {resources} 0 R /Contents {contents} 0 R >>",
+                num(page_size.width),
+                num(page_size.height),
+            ),
+        );
+        pdf.stream(contents, "", stream.as_bytes());
+        let _ = write!(kids, "{page} 0 R ");
+    }
+
+    let mut font_refs = String::new();
+    for (index, &family) in content.fonts.iter().enumerate() {
+        let font = write_font(&mut pdf, family);
+        let _ = write!(font_refs, "/F{index} {font} 0 R ");
+    }
+    let mut states = String::new();
+    for &(op, alpha) in &content.alphas {
+        let key = if op == 'f' { "ca" } else { "CA" };
+        let _ = write!(
+            states,
+            "/{op}{alpha} << /{key} {} >> ",
+            num(alpha as f64 / 255.0)
+        );
+    }
+    pdf.object(
+        resources,
+        &format!("<< /Font << {font_refs}>> /ExtGState << {states}>> >>"),
+    );
+    pdf.object(
+        page_tree,
+        &format!("<< /Type /Pages /Kids [{kids}] /Count {} >>", streams.len()),
+    );
+    pdf.object(
+        catalog,
+        &format!("<< /Type /Catalog /Pages {page_tree} 0 R >>"),
+    );
+    pdf.finish(catalog)
+}
+
+/// Embed the font of `family` as a simple TrueType font. Returns the font
+/// dictionary's object number.
+fn write_font(pdf: &mut PdfWriter, family: FontFamily) -> usize {
+    let data = font_data(family);
+    let face = Face::parse(data, 0).expect("embedded fonts parse");
+    let units = 1000.0 / face.units_per_em() as f64;
+    let scaled = |v: i16| (v as f64 * units).round() as i64;
+
+    let font = pdf.alloc();
+    let descriptor = pdf.alloc();
+    let file = pdf.alloc();
+    let name = family.name().replace(' ', "");
+    let widths: Vec<String> = (FIRST_CHAR..=LAST_CHAR)
+        .map(|code| glyph_width(&face, code).round().to_string())
+        .collect();
+    pdf.object(
+        font,
+        &format!(
+            "<< /Type /Font /Subtype /TrueType /BaseFont /{name} /FirstChar {FIRST_CHAR} /LastChar {LAST_CHAR} /Widths [{}] /FontDescriptor {descriptor} 0 R /Encoding /WinAnsiEncoding >>",
+            widths.join(" "),
+        ),
+    );
+    let bbox = face.global_bounding_box();
+    pdf.object(
+        descriptor,
+        &format!(
+            "<< /Type /FontDescriptor /FontName /{name} /Flags 32 /FontBBox [{} {} {} {}] /ItalicAngle 0 /Ascent {} /Descent {} /CapHeight {} /StemV 80 /FontFile2 {file} 0 R >>",
+            scaled(bbox.x_min),
+            scaled(bbox.y_min),
+            scaled(bbox.x_max),
+            scaled(bbox.y_max),
+            scaled(face.ascender()),
+            scaled(face.descender()),
+            scaled(face.capital_height().unwrap_or(face.ascender())),
+        ),
+    );
+    pdf.stream(file, &format!("/Length1 {}", data.len()), data);
+    font
+}
+
+/// Embedded font data for `family`, as registered by
+/// [`FontRegistry`](crate::FontRegistry).
+fn font_data(family: FontFamily) -> &'static [u8] {
+    match family {
+        FontFamily::GelPen => GELPEN_REGULAR,
+        FontFamily::NotoSans => NOTO_SANS,
+        FontFamily::GelPenSerif => GELPEN_SERIF_MEDIUM,
+        FontFamily::VanillaExtract => VANILLA_EXTRACT,
+    }
+}
+
+/// Advance width of WinAnsi character `code` in thousandths of an em.
+fn glyph_width(face: &Face, code: u8) -> f64 {
+    face.glyph_index(code as char)
+        .and_then(|glyph| face.glyph_hor_advance(glyph))
+        .map_or(0.0, |advance| {
+            advance as f64 * 1000.0 / face.units_per_em() as f64
+        })
+}
+
+/// Width of `line` set in `face` at `font_size`.
+fn line_width(face: &Face, font_size: f64, line: &str) -> f64 {
+    encode(line)
+        .into_iter()
+        .map(|code| glyph_width(face, code))
+        .sum::<f64>()
+        * font_size
+        / 1000.0
+}
+
+/// Encode `text` as WinAnsi bytes. Latin-1 characters map to themselves
+/// (WinAnsi agrees with Latin-1 outside 0x80-0x9F); anything else is `?`.
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c as u32 {
+            code @ (0x20..=0x7e | 0xa0..=0xff) => code as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Page content stream being built, plus the resources it uses.
+#[derive(Default)]
+struct Content {
+    ops: String,
+    /// Font families used, in order of their `/F{index}` resource names.
+    fonts: Vec<FontFamily>,
+    /// Graphics states used: `'f'` or `'s'` for fill or stroke alpha, and
+    /// the alpha value.
+    alphas: BTreeSet<(char, u8)>,
+}
+
+impl Content {
+    /// Draw the document onto a page.
+    fn page(&mut self, doc: &CanvasDocument, page: Affine, page_size: Size) {
+        if let Some(Background::Color(color)) = doc.background {
+            self.set_fill(color.into());
+            let _ = writeln!(
+                self.ops,
+                "0 0 {} {} re f",
+                num(page_size.width),
+                num(page_size.height)
+            );
+        }
+        self.ops.push_str("q\n");
+        self.transform(page);
+        for shape in doc.shapes_ordered() {
+            self.shape(shape);
+        }
+        self.ops.push_str("Q\n");
+    }
+
+    /// Draw one shape.
+    fn shape(&mut self, shape: &Shape) {
+        let rotation = shape.rotation();
+        let rotated = rotation.abs() > 0.001;
+        if rotated {
+            let center = shape.bounds().center().to_vec2();
+            self.ops.push_str("q\n");
+            self.transform(
+                Affine::translate(center) * Affine::rotate(rotation) * Affine::translate(-center),
+            );
+        }
+        match shape {
+            Shape::Group(group) => {
+                for child in group.children() {
+                    self.shape(child);
+                }
+            }
+            Shape::Text(text) => self.text(text),
+            Shape::Line(line) => self.stroke(
+                &shape.to_path(),
+                &line.style,
+                line.stroke_style,
+                (line.line_cap, line.line_join),
+            ),
+            Shape::Arrow(arrow) => {
+                let caps = (arrow.line_cap, arrow.line_join);
+                self.stroke(&arrow.shaft_path(), &arrow.style, arrow.stroke_style, caps);
+                for (head_style, head) in arrow.head_paths() {
+                    if head_style.is_filled() {
+                        self.set_fill(arrow.style.stroke_with_opacity());
+                        self.path(&head);
+                        self.ops.push_str("f\n");
+                    }
+                    self.stroke(&head, &arrow.style, StrokeStyle::Solid, caps);
+                }
+                if let Some(label) = arrow.label.as_deref().filter(|l| !l.is_empty()) {
+                    let mut text = Text::new(Point::ZERO, label.to_string());
+                    text.style.stroke_color = arrow.style.stroke_color;
+                    text.style.opacity = arrow.style.opacity;
+                    // Center the label on its anchor
+                    let anchor = arrow.label_anchor();
+                    let face =
+                        Face::parse(font_data(text.font_family), 0).expect("embedded fonts parse");
+                    let width = line_width(&face, text.font_size, label);
+                    text.position = anchor - kurbo::Vec2::new(width, text.font_size) / 2.0;
+                    self.text(&text);
+                }
+            }
+            Shape::Freehand(_) => self.stroke(
+                &shape.to_path(),
+                shape.style(),
+                StrokeStyle::Solid,
+                (LineCap::Round, LineJoin::Round),
+            ),
+            Shape::StickyNote(note) => {
+                self.fill_and_stroke(&shape.to_path(), &note.background.style);
+                self.text(&note.text);
+            }
+            Shape::Image(_) => self.ops.push_str("% unsupported: image\n"),
+            Shape::Math(_) => self.ops.push_str("% unsupported: math\n"),
+            Shape::Rectangle(_) | Shape::Ellipse(_) => {
+                self.fill_and_stroke(&shape.to_path(), shape.style());
+            }
+        }
+        if rotated {
+            self.ops.push_str("Q\n");
+        }
+    }
+
+    /// Fill `path` (if the style has a fill) and stroke it.
+    fn fill_and_stroke(&mut self, path: &BezPath, style: &ShapeStyle) {
+        self.set_stroke(style, StrokeStyle::Solid, (LineCap::Round, LineJoin::Round));
+        self.path(path);
+        match style.fill_with_opacity() {
+            Some(fill) => {
+                self.set_fill(fill);
+                self.ops.push_str("B\n");
+            }
+            None => self.ops.push_str("S\n"),
+        }
+    }
+
+    /// Stroke `path` without filling it.
+    fn stroke(
+        &mut self,
+        path: &BezPath,
+        style: &ShapeStyle,
+        stroke_style: StrokeStyle,
+        caps: (LineCap, LineJoin),
+    ) {
+        self.set_stroke(style, stroke_style, caps);
+        self.path(path);
+        self.ops.push_str("S\n");
+    }
+
+    /// Draw text, one line per wrapped or explicit line, aligned within its
+    /// wrap width.
+    fn text(&mut self, text: &Text) {
+        let index = self.font_index(text.font_family);
+        let face = Face::parse(font_data(text.font_family), 0).expect("embedded fonts parse");
+        let ascent = face.ascender() as f64 / face.units_per_em() as f64 * text.font_size;
+        self.set_fill(text.style.stroke_with_opacity());
+        let lines = text.approximate_lines();
+        let widths: Vec<f64> = lines
+            .iter()
+            .map(|line| line_width(&face, text.font_size, line))
+            .collect();
+        // Without a wrap width lines align within the widest line
+        let box_width = text
+            .wrap_width
+            .unwrap_or_else(|| widths.iter().copied().fold(0.0, f64::max));
+        let _ = writeln!(self.ops, "BT /F{index} {} Tf", num(text.font_size));
+        for (i, (line, width)) in lines.into_iter().zip(widths).enumerate() {
+            let offset = text.align.line_offset(width, box_width);
+            let x = text.position.x + offset;
+            let y = text.position.y + ascent + i as f64 * text.font_size * LINE_HEIGHT;
+            // Flip glyphs back upright inside the y-down page transform
+            let _ = write!(self.ops, "1 0 0 -1 {} {} Tm (", num(x), num(y));
+            for byte in encode(line) {
+                match byte {
+                    b'(' | b')' | b'\\' => {
+                        self.ops.push('\\');
+                        self.ops.push(byte as char);
+                    }
+                    0x20..=0x7e => self.ops.push(byte as char),
+                    _ => {
+                        let _ = write!(self.ops, "\\{byte:03o}");
+                    }
+                }
+            }
+            self.ops.push_str(") Tj\n");
+        }
+        self.ops.push_str("ET\n");
+    }
+
+    /// Resource index of the font for `family`, adding it if new.
+    fn font_index(&mut self, family: FontFamily) -> usize {
+        match self.fonts.iter().position(|&f| f == family) {
+            Some(index) => index,
+            None => {
+                self.fonts.push(family);
+                self.fonts.len() - 1
+            }
+        }
+    }
+
+    /// Append path construction operators for `path`.
+    fn path(&mut self, path: &BezPath) {
+        let mut current = Point::ZERO;
+        for el in path.elements() {
+            match *el {
+                PathEl::MoveTo(p) => {
+                    let _ = writeln!(self.ops, "{} {} m", num(p.x), num(p.y));
+                    current = p;
+                }
+                PathEl::LineTo(p) => {
+                    let _ = writeln!(self.ops, "{} {} l", num(p.x), num(p.y));
+                    current = p;
+                }
+                PathEl::QuadTo(c, p) => {
+                    // PDF has no quadratic curves; raise to a cubic
+                    let c1 = current + (c - current) * (2.0 / 3.0);
+                    let c2 = p + (c - p) * (2.0 / 3.0);
+                    self.curve(c1, c2, p);
+                    current = p;
+                }
+                PathEl::CurveTo(c1, c2, p) => {
+                    self.curve(c1, c2, p);
+                    current = p;
+                }
+                PathEl::ClosePath => self.ops.push_str("h\n"),
+            }
+        }
+    }
+
+    fn curve(&mut self, c1: Point, c2: Point, p: Point) {
+        let _ = writeln!(
+            self.ops,
+            "{} {} {} {} {} {} c",
+            num(c1.x),
+            num(c1.y),
+            num(c2.x),
+            num(c2.y),
+            num(p.x),
+            num(p.y)
+        );
+    }
+
+    /// Concatenate `affine` onto the current transform.
+    fn transform(&mut self, affine: Affine) {
+        let c = affine.as_coeffs().map(num);
+        let _ = writeln!(
+            self.ops,
+            "{} {} {} {} {} {} cm",
+            c[0], c[1], c[2], c[3], c[4], c[5]
+        );
+    }
+
+    /// Set the fill color and alpha.
+    fn set_fill(&mut self, color: Color) {
+        let rgba = color.to_rgba8();
+        let _ = writeln!(
+            self.ops,
+            "{} {} {} rg /f{} gs",
+            num(rgba.r as f64 / 255.0),
+            num(rgba.g as f64 / 255.0),
+            num(rgba.b as f64 / 255.0),
+            rgba.a
+        );
+        self.alphas.insert(('f', rgba.a));
+    }
+
+    /// Set the stroke color, alpha, width, dashes, caps and joins, with the
+    /// same dash lengths as the Vello renderer.
+    fn set_stroke(
+        &mut self,
+        style: &ShapeStyle,
+        stroke_style: StrokeStyle,
+        (cap, join): (LineCap, LineJoin),
+    ) {
+        let rgba = style.stroke_with_opacity().to_rgba8();
+        let width = style.stroke_width;
+        let cap = match cap {
+            LineCap::Butt => 0,
+            LineCap::Round => 1,
+            LineCap::Square => 2,
+        };
+        let join = match join {
+            LineJoin::Miter => 0,
+            LineJoin::Round => 1,
+            LineJoin::Bevel => 2,
+        };
+        let dashes = match stroke_style.dash_pattern(width) {
+            Some([dash, gap]) => format!("[{} {}] 0 d", num(dash), num(gap)),
+            None => "[] 0 d".to_string(),
+        };
+        let _ = writeln!(
+            self.ops,
+            "{} {} {} RG /s{} gs {} w {cap} J {join} j {dashes}",
+            num(rgba.r as f64 / 255.0),
+            num(rgba.g as f64 / 255.0),
+            num(rgba.b as f64 / 255.0),
+            rgba.a,
+            num(width),
+        );
+        self.alphas.insert(('s', rgba.a));
+    }
+}
+
+/// Format a number for a content stream: at most three decimals, without
+/// trailing zeros.
+fn num(value: f64) -> String {
+    let s = format!("{value:.3}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Low-level PDF file writer: numbered objects and a cross-reference table.
+struct PdfWriter {
+    buf: Vec<u8>,
+    /// Byte offset of each object, indexed by object number minus one.
+    offsets: Vec<Option<usize>>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        // The binary comment marks the file as binary for transfer tools
+        Self {
+            buf: b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec(),
+            offsets: Vec::new(),
+        }
+    }
+
+    /// Reserve an object number.
+    fn alloc(&mut self) -> usize {
+        self.offsets.push(None);
+        self.offsets.len()
+    }
+
+    /// Write object `id` with a dictionary or other direct body.
+    fn object(&mut self, id: usize, body: &str) {
+        self.offsets[id - 1] = Some(self.buf.len());
+        self.buf
+            .extend_from_slice(format!("{id} 0 obj\n{body}\nendobj\n").as_bytes());
+    }
+
+    /// Write stream object `id` with extra dictionary entries `dict`.
+    fn stream(&mut self, id: usize, dict: &str, data: &[u8]) {
+        self.offsets[id - 1] = Some(self.buf.len());
+        self.buf.extend_from_slice(
+            format!("{id} 0 obj\n<< /Length {} {dict} >>\nstream\n", data.len()).as_bytes(),
+        );
+        self.buf.extend_from_slice(data);
+        self.buf.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    /// Write the cross-reference table and trailer.
+    fn finish(mut self, root: usize) -> Vec<u8> {
+        let xref = self.buf.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            let offset = offset.expect("every allocated object is written");
+            let _ = writeln!(table, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {root} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            self.offsets.len() + 1
+        );
+        self.buf.extend_from_slice(table.as_bytes());
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use drafftink_core::shapes::{Arrow, PathStyle, Rectangle};
+
+    /// Number of page objects in `pdf`.
+    fn page_count(pdf: &[u8]) -> usize {
+        String::from_utf8_lossy(pdf)
+            .matches("/Type /Page /Parent")
+            .count()
+    }
+
+    #[test]
+    fn test_exports_intro_document_in_tiles() {
+        let doc = CanvasDocument::from_json(include_str!("../../drafftink-app/assets/intro.json"))
+            .unwrap();
+        let page_size = Size::new(595.0, 842.0);
+        let pdf = export_pdf(&doc, page_size).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+
+        let bounds = CanvasDocument::shapes_bounds(doc.shapes_ordered())
+            .unwrap()
+            .inflate(PADDING, PADDING);
+        let expected =
+            (bounds.width() / page_size.width).ceil() * (bounds.height() / page_size.height).ceil();
+        assert_eq!(page_count(&pdf), expected as usize);
+        assert!(String::from_utf8_lossy(&pdf).contains(&format!("/Count {expected} ")));
+
+        let single = export_pdf_single_page(&doc, page_size).unwrap();
+        assert!(single.starts_with(b"%PDF-"));
+        assert_eq!(page_count(&single), 1);
+    }
+
+    #[test]
+    fn test_tiles_cover_distant_shapes() {
+        let mut doc = CanvasDocument::new();
+        doc.add_shape(Shape::Rectangle(Rectangle::new(
+            Point::new(0.0, 0.0),
+            10.0,
+            10.0,
+        )));
+        doc.add_shape(Shape::Rectangle(Rectangle::new(
+            Point::new(900.0, 0.0),
+            10.0,
+            10.0,
+        )));
+        // 950 wide including padding: two pages across, one down
+        let pdf = export_pdf(&doc, Size::new(500.0, 500.0)).unwrap();
+        assert_eq!(page_count(&pdf), 2);
+        assert_eq!(
+            page_count(&export_pdf(&CanvasDocument::new(), Size::new(500.0, 500.0)).unwrap()),
+            1
+        );
+    }
+
+    #[test]
+    fn test_text_and_elbow_arrows() {
+        let mut doc = CanvasDocument::new();
+        doc.add_shape(Shape::Text(Text::new(
+            Point::new(0.0, 0.0),
+            "a (b) é".to_string(),
+        )));
+        let mut arrow = Arrow::new(Point::new(0.0, 100.0), Point::new(100.0, 200.0));
+        arrow.path_style = PathStyle::Angular;
+        doc.add_shape(Shape::Arrow(arrow));
+        let pdf = export_pdf(&doc, Size::new(500.0, 500.0)).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(text.contains("/Subtype /TrueType /BaseFont /GelPen"));
+        assert!(text.contains("/FontFile2"));
+        assert!(text.contains(r"(a \(b\) \351) Tj"));
+        // The elbow shaft is straight segments only
+        let shaft = text
+            .split("S\n")
+            .find(|ops| ops.contains("RG") && ops.contains(" 100 m"))
+            .unwrap();
+        assert!(shaft.lines().filter(|l| l.ends_with(" l")).count() >= 2);
+        assert!(!shaft.lines().any(|l| l.ends_with(" c")));
+    }
+
+    #[test]
+    fn test_xref_offsets_point_at_objects() {
+        let mut doc = CanvasDocument::new();
+        doc.add_shape(Shape::Rectangle(Rectangle::new(
+            Point::new(0.0, 0.0),
+            10.0,
+            10.0,
+        )));
+        let pdf = export_pdf(&doc, Size::new(100.0, 100.0)).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        let xref = text.rfind("xref\n").unwrap();
+        for (i, entry) in text[xref..]
+            .lines()
+            .skip(3)
+            .take_while(|l| l.ends_with(" n "))
+            .enumerate()
+        {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_rejects_degenerate_page_sizes() {
+        let doc = CanvasDocument::new();
+        for size in [
+            Size::new(0.0, 842.0),
+            Size::new(595.0, -1.0),
+            Size::new(f64::NAN, 842.0),
+            Size::new(f64::INFINITY, 842.0),
+        ] {
+            assert!(matches!(
+                export_pdf(&doc, size),
+                Err(PdfError::InvalidPageSize(_))
+            ));
+            assert!(export_pdf_single_page(&doc, size).is_err());
+        }
+    }
+}
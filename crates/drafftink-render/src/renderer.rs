@@ -1,12 +1,19 @@
 //! Renderer trait abstraction.
 
-use drafftink_core::canvas::Canvas;
-use drafftink_core::shapes::Shape;
+pub use drafftink_core::canvas::HIT_TOLERANCE_PX;
+use drafftink_core::canvas::{Background, Canvas, CanvasDocument};
+pub use drafftink_core::shapes::shape_content_hash;
+use drafftink_core::shapes::{Shape, ShapeId};
 use drafftink_core::snap::SmartGuide;
-use kurbo::{Affine, Rect, Size};
+use kurbo::{Affine, ParamCurveNearest, Point, Rect, Size};
 use peniko::Color;
+use std::collections::HashMap;
 use thiserror::Error;
 
+/// Default on-screen font size, in pixels, below which text is drawn as a
+/// placeholder bar instead of glyphs.
+pub const DEFAULT_TEXT_LOD_PX: f64 = 5.0;
+
 /// Renderer errors.
 #[derive(Debug, Error)]
 pub enum RendererError {
@@ -19,7 +26,6 @@ pub enum RendererError {
 }
 
 /// Result type for renderer operations.
-#[allow(dead_code)]
 pub type RenderResult<T> = Result<T, RendererError>;
 
 /// Grid display style.
@@ -62,6 +68,20 @@ impl GridStyle {
     }
 }
 
+/// Background pattern drawn behind all shapes.
+///
+/// Spacing is in world units, so the pattern pans and zooms with the content.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BackgroundStyle {
+    /// Plain background.
+    #[default]
+    None,
+    /// Dots at grid intersections.
+    Dots { spacing: f64, color: Color },
+    /// Full grid lines.
+    Lines { spacing: f64, color: Color },
+}
+
 /// Angle snap visualization info.
 #[derive(Debug, Clone, Copy)]
 pub struct AngleSnapInfo {
@@ -98,6 +118,8 @@ pub struct RenderContext<'a> {
     pub background_color: Color,
     /// Grid display style.
     pub grid_style: GridStyle,
+    /// Background pattern, drawn before the grid and shapes.
+    pub background_style: BackgroundStyle,
     /// Selection highlight color.
     pub selection_color: Color,
     /// Selection rectangle (marquee) in world coordinates.
@@ -116,6 +138,14 @@ pub struct RenderContext<'a> {
     pub eraser_cursor: Option<(kurbo::Point, f64)>,
     /// Laser pointer position and trail.
     pub laser_pointer: Option<(kurbo::Point, Vec<(kurbo::Point, f64)>)>,
+    /// View transform overriding the canvas camera (e.g. a fit transform).
+    pub view_transform: Option<Affine>,
+    /// On-screen font size in pixels below which text is drawn as a filled
+    /// bar ("greeking"). Zero always draws glyphs.
+    pub text_lod_threshold: f64,
+    /// Time in seconds driving the marching-ants outline around the
+    /// selection bounds. `None` draws no outline.
+    pub marching_ants: Option<f64>,
 }
 
 impl<'a> RenderContext<'a> {
@@ -127,6 +157,7 @@ impl<'a> RenderContext<'a> {
             scale_factor: 1.0,
             background_color: Color::from_rgba8(250, 250, 250, 255),
             grid_style: GridStyle::Lines,
+            background_style: BackgroundStyle::None,
             selection_color: Color::from_rgba8(59, 130, 246, 255), // Blue
             selection_rect: None,
             editing_shape_id: None,
@@ -136,6 +167,9 @@ impl<'a> RenderContext<'a> {
             smart_guides: Vec::new(),
             eraser_cursor: None,
             laser_pointer: None,
+            view_transform: None,
+            text_lod_threshold: DEFAULT_TEXT_LOD_PX,
+            marching_ants: None,
         }
     }
 
@@ -157,6 +191,12 @@ impl<'a> RenderContext<'a> {
         self
     }
 
+    /// Set the background pattern.
+    pub fn with_background_style(mut self, style: BackgroundStyle) -> Self {
+        self.background_style = style;
+        self
+    }
+
     /// Set the selection rectangle.
     pub fn with_selection_rect(mut self, rect: Option<Rect>) -> Self {
         self.selection_rect = rect;
@@ -207,6 +247,118 @@ impl<'a> RenderContext<'a> {
         self.laser_pointer = pointer;
         self
     }
+
+    /// Render through `transform` instead of the canvas camera.
+    ///
+    /// Pass the result of [`Canvas::compute_fit_transform`] to frame the
+    /// content without moving the camera.
+    pub fn with_view_transform(mut self, transform: Affine) -> Self {
+        self.view_transform = Some(transform);
+        self
+    }
+
+    /// Set the on-screen font size below which text is greeked.
+    pub fn with_text_lod_threshold(mut self, px: f64) -> Self {
+        self.text_lod_threshold = px;
+        self
+    }
+
+    /// Draw marching ants around the selection, advanced to `time` seconds.
+    ///
+    /// Pass a steadily increasing time on every frame to animate them.
+    pub fn with_marching_ants(mut self, time: Option<f64>) -> Self {
+        self.marching_ants = time;
+        self
+    }
+
+    /// The world-to-screen transform used for rendering.
+    pub fn view_transform(&self) -> Affine {
+        self.view_transform
+            .unwrap_or_else(|| self.canvas.camera.transform())
+    }
+
+    /// Color the frame is cleared to: the document background if it has
+    /// one, otherwise `background_color`.
+    pub fn clear_color(&self) -> Color {
+        match self.canvas.document.background {
+            Some(Background::Color(color)) => color.into(),
+            Some(Background::Transparent) => Color::TRANSPARENT,
+            None => self.background_color,
+        }
+    }
+
+    /// Find the topmost shape under a point given in screen coordinates.
+    ///
+    /// Filled shapes are hit anywhere inside them; lines and arrows (including
+    /// elbow routes) within half their stroke width plus [`HIT_TOLERANCE_PX`]
+    /// screen pixels of any segment, converted to world units at the current
+    /// zoom. Locked shapes are skipped.
+    pub fn hit_test(&self, point: Point) -> Option<ShapeId> {
+        self.hit_test_with(point, false)
+    }
+
+    /// Like [`hit_test`](Self::hit_test), but also hits locked shapes when
+    /// `include_locked` is set.
+    pub fn hit_test_with(&self, point: Point, include_locked: bool) -> Option<ShapeId> {
+        let transform = self.view_transform();
+        let world = transform.inverse() * point;
+        let tolerance = HIT_TOLERANCE_PX / transform.as_coeffs()[0];
+        let shapes: Vec<&Shape> = self.canvas.document.shapes_ordered().collect();
+        shapes
+            .into_iter()
+            .rev()
+            .filter(|shape| include_locked || !shape.is_locked())
+            .find(|shape| shape_hit(shape, world, tolerance))
+            .map(|shape| shape.id())
+    }
+
+    /// Shapes whose content changed since they were encoded.
+    ///
+    /// `encoded` maps shape IDs to the [`shape_content_hash`] they were last
+    /// encoded with; shapes missing from it are dirty too.
+    pub fn dirty_shapes(&self, encoded: &HashMap<ShapeId, u64>) -> Vec<ShapeId> {
+        self.canvas
+            .document
+            .shapes_ordered()
+            .filter(|shape| encoded.get(&shape.id()) != Some(&shape_content_hash(shape)))
+            .map(|shape| shape.id())
+            .collect()
+    }
+}
+
+/// Test a world-space point against a shape's drawn geometry.
+fn shape_hit(shape: &Shape, point: Point, tolerance: f64) -> bool {
+    // Undo the shape's rotation so the test runs in its unrotated frame.
+    let rotation = shape.rotation();
+    let point = if rotation.abs() > 0.001 {
+        Affine::rotate_about(-rotation, shape.bounds().center()) * point
+    } else {
+        point
+    };
+    match shape {
+        Shape::Line(_) | Shape::Arrow(_) => {
+            let reach = tolerance + shape.style().stroke_width / 2.0;
+            shape
+                .to_path()
+                .segments()
+                .any(|seg| seg.nearest(point, 1e-3).distance_sq <= reach * reach)
+        }
+        Shape::Group(group) => group
+            .children()
+            .iter()
+            .any(|child| shape_hit(child, point, tolerance)),
+        _ => shape.hit_test(point, tolerance),
+    }
+}
+
+/// Background color for exporting `doc`: its own background, or white when
+/// it has none.
+pub fn export_background(doc: &CanvasDocument) -> Color {
+    match doc.background {
+        Some(Background::Color(color)) => color.into(),
+        Some(Background::Transparent) => Color::TRANSPARENT,
+        None => Color::WHITE,
+    }
 }
 
 /// Trait for rendering backends.
@@ -220,7 +372,7 @@ pub trait Renderer: Send + Sync {
 
     /// Get the background color (for clearing).
     fn background_color(&self, ctx: &RenderContext) -> Color {
-        ctx.background_color
+        ctx.clear_color()
     }
 }
 
@@ -236,3 +388,128 @@ pub trait ShapeRenderer {
     /// Render selection handles for a shape.
     fn render_selection_handles(&mut self, bounds: Rect, transform: Affine);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use drafftink_core::shapes::{Arrow, Line, PathStyle, Rectangle};
+
+    fn canvas_with(shapes: Vec<Shape>) -> Canvas {
+        let mut canvas = Canvas::new();
+        canvas.camera.zoom = 2.0;
+        canvas.camera.offset = kurbo::Vec2::new(10.0, 20.0);
+        for shape in shapes {
+            canvas.document.add_shape(shape);
+        }
+        canvas
+    }
+
+    #[test]
+    fn test_hit_test_inside_rect() {
+        let rect = Shape::Rectangle(Rectangle::new(Point::new(100.0, 100.0), 50.0, 50.0));
+        let id = rect.id();
+        let canvas = canvas_with(vec![rect]);
+        let ctx = RenderContext::new(&canvas, Size::new(800.0, 600.0));
+
+        // World (125, 125) is screen (260, 270) at zoom 2 with offset (10, 20).
+        assert_eq!(ctx.hit_test(Point::new(260.0, 270.0)), Some(id));
+        // World (125, 125) in screen space misses once the camera is applied.
+        assert_eq!(ctx.hit_test(Point::new(125.0, 125.0)), None);
+    }
+
+    #[test]
+    fn test_hit_test_on_line() {
+        let line = Shape::Line(Line::new(Point::new(0.0, 0.0), Point::new(100.0, 0.0)));
+        let id = line.id();
+        let canvas = canvas_with(vec![line]);
+        let ctx = RenderContext::new(&canvas, Size::new(800.0, 600.0));
+
+        // World (50, 1) -> screen (110, 22): within the stroke band.
+        assert_eq!(ctx.hit_test(Point::new(110.0, 22.0)), Some(id));
+        // World (50, 20) -> screen (110, 60): too far off the line.
+        assert_eq!(ctx.hit_test(Point::new(110.0, 60.0)), None);
+    }
+
+    #[test]
+    fn test_hit_test_tolerance_is_screen_space() {
+        let line = Shape::Line(Line::new(Point::new(0.0, 0.0), Point::new(100.0, 0.0)));
+        let id = line.id();
+        let canvas = canvas_with(vec![line]);
+        for zoom in [1.0, 0.25] {
+            let ctx = RenderContext::new(&canvas, Size::new(800.0, 600.0))
+                .with_view_transform(Affine::scale(zoom));
+            let on_line = Point::new(50.0 * zoom, 0.0);
+            let near = on_line + kurbo::Vec2::new(0.0, HIT_TOLERANCE_PX);
+            assert_eq!(ctx.hit_test(near), Some(id), "zoom {zoom}");
+            // Past the tolerance and the (zoomed) half stroke width.
+            let far = on_line + kurbo::Vec2::new(0.0, HIT_TOLERANCE_PX + 2.0);
+            assert_eq!(ctx.hit_test(far), None, "zoom {zoom}");
+        }
+    }
+
+    #[test]
+    fn test_hit_test_elbow_arrow_segments() {
+        let mut arrow = Arrow::new(Point::new(0.0, 0.0), Point::new(100.0, 100.0));
+        arrow.path_style = PathStyle::Angular;
+        let shape = Shape::Arrow(arrow);
+        let id = shape.id();
+        let canvas = canvas_with(vec![shape]);
+        let ctx = RenderContext::new(&canvas, Size::new(800.0, 600.0));
+
+        // Every corner of the elbow route is on the arrow.
+        for corner in drafftink_core::elbow::compute_elbow_path(
+            Point::new(0.0, 0.0),
+            Point::new(100.0, 100.0),
+        ) {
+            let screen = canvas.camera.world_to_screen(corner);
+            assert_eq!(ctx.hit_test(screen), Some(id));
+        }
+        // A point on the straight diagonal is off the elbow route.
+        let diagonal = canvas.camera.world_to_screen(Point::new(25.0, 25.0));
+        assert_eq!(ctx.hit_test(diagonal), None);
+    }
+
+    #[test]
+    fn test_hit_test_empty_space_and_z_order() {
+        let back = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 100.0, 100.0));
+        let front = Shape::Rectangle(Rectangle::new(Point::new(50.0, 50.0), 100.0, 100.0));
+        let front_id = front.id();
+        let canvas = canvas_with(vec![back, front]);
+        let ctx = RenderContext::new(&canvas, Size::new(800.0, 600.0));
+
+        let overlap = canvas.camera.world_to_screen(Point::new(75.0, 75.0));
+        assert_eq!(ctx.hit_test(overlap), Some(front_id));
+        let empty = canvas.camera.world_to_screen(Point::new(300.0, 300.0));
+        assert_eq!(ctx.hit_test(empty), None);
+    }
+
+    #[test]
+    fn test_hit_test_skips_locked_shapes() {
+        let back = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 100.0, 100.0));
+        let mut front = Shape::Rectangle(Rectangle::new(Point::new(50.0, 50.0), 100.0, 100.0));
+        front.set_locked(true);
+        let (back_id, front_id) = (back.id(), front.id());
+        let canvas = canvas_with(vec![back, front]);
+        let ctx = RenderContext::new(&canvas, Size::new(800.0, 600.0));
+
+        let overlap = canvas.camera.world_to_screen(Point::new(75.0, 75.0));
+        assert_eq!(ctx.hit_test(overlap), Some(back_id));
+        assert_eq!(ctx.hit_test_with(overlap, true), Some(front_id));
+        let locked_only = canvas.camera.world_to_screen(Point::new(125.0, 125.0));
+        assert_eq!(ctx.hit_test(locked_only), None);
+    }
+
+    #[test]
+    fn test_hit_test_with_fit_transform() {
+        let rect = Shape::Rectangle(Rectangle::new(Point::new(1000.0, 1000.0), 50.0, 50.0));
+        let id = rect.id();
+        let canvas = canvas_with(vec![rect]);
+        let viewport = Size::new(800.0, 600.0);
+        let ctx = RenderContext::new(&canvas, viewport)
+            .with_view_transform(canvas.compute_fit_transform(viewport));
+
+        // The fitted content sits in the middle of the viewport.
+        assert_eq!(ctx.hit_test(Point::new(400.0, 300.0)), Some(id));
+        assert_eq!(ctx.hit_test(Point::new(10.0, 10.0)), None);
+    }
+}
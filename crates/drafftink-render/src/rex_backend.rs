@@ -1,11 +1,12 @@
 //! Vello backend for ReX math rendering with font fallback.
 
-use kurbo::{Affine, BezPath, Point};
+use kurbo::{Affine, BezPath, Point, Rect, RoundedRect, Shape, Stroke};
 use peniko::Color;
 use rex::font::backend::ttf_parser::TtfMathFont;
 use rex::font::common::GlyphId;
 use rex::render::{Backend, Cursor, FontBackend, GraphicsBackend, RGBA};
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use vello::Scene;
 
 /// Map Unicode math alphanumeric symbols to ASCII equivalents.
@@ -40,33 +41,181 @@ fn math_to_ascii(c: char) -> Option<char> {
         0x1D7E2..=0x1D7EB => Some((b'0' + (cp - 0x1D7E2) as u8) as char),
         0x1D7EC..=0x1D7F5 => Some((b'0' + (cp - 0x1D7EC) as u8) as char),
         0x1D7F6..=0x1D7FF => Some((b'0' + (cp - 0x1D7F6) as u8) as char),
+        // Double-struck Capital A-Z (U+1D538-1D551; holes are the letterlike forms below)
+        0x1D538..=0x1D551 => Some((b'A' + (cp - 0x1D538) as u8) as char),
+        // Double-struck Small a-z (U+1D552-1D56B)
+        0x1D552..=0x1D56B => Some((b'a' + (cp - 0x1D552) as u8) as char),
+        // Letterlike double-struck capitals
+        0x2102 => Some('C'),
+        0x210D => Some('H'),
+        0x2115 => Some('N'),
+        0x2119 => Some('P'),
+        0x211A => Some('Q'),
+        0x211D => Some('R'),
+        0x2124 => Some('Z'),
+        // Greek bold, italic, bold italic, sans bold and sans bold italic
+        // (U+1D6A8-1D7C9, five consecutive blocks of 58)
+        0x1D6A8..=0x1D7C9 => math_greek_to_base((cp - 0x1D6A8) % 58),
         _ => None,
     }
 }
 
-/// Vello backend for ReX rendering with primary font fallback.
+/// Map an offset within a math Greek block to its base Greek codepoint.
+fn math_greek_to_base(offset: u32) -> Option<char> {
+    let cp = match offset {
+        // Capital theta symbol sits between Rho and Sigma
+        17 => 0x03F4,
+        // Capital Alpha-Omega (U+0391-03A9; theta symbol fills the U+03A2 hole)
+        0..=24 => 0x0391 + offset,
+        25 => 0x2207, // nabla
+        // Small alpha-omega, including final sigma (U+03B1-03C9)
+        26..=50 => 0x03B1 + (offset - 26),
+        51 => 0x2202, // partial differential
+        52 => 0x03F5, // epsilon symbol
+        53 => 0x03D1, // theta symbol
+        54 => 0x03F0, // kappa symbol
+        55 => 0x03D5, // phi symbol
+        56 => 0x03F1, // rho symbol
+        57 => 0x03D6, // pi symbol
+        _ => return None,
+    };
+    char::from_u32(cp)
+}
+
+/// Default size of text-font glyphs relative to the ReX scale, chosen to
+/// match text tool rendering.
+pub const DEFAULT_PRIMARY_SCALE: f64 = 0.75;
+
+/// How glyphs and rules are painted.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RenderStyle {
+    /// Solid fill in the current color.
+    #[default]
+    Fill,
+    /// Outline of the given width (in backend space) and color.
+    Stroke { width: f64, color: Color },
+}
+
+/// Corner radius of rounded rules, relative to the bar thickness. Half the
+/// thickness gives fully rounded ends.
+pub const ROUNDED_RULE_RADIUS_RATIO: f64 = 0.5;
+
+/// Shape painted for a rule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RuleShape {
+    Sharp(Rect),
+    Rounded(RoundedRect),
+}
+
+/// Fill or stroke `shape`, given in `local` coordinates, under `transform`.
+///
+/// Strokes are applied after `local` so their width is not scaled by it.
+fn paint(
+    scene: &mut Scene,
+    style: RenderStyle,
+    color: Color,
+    transform: Affine,
+    local: Affine,
+    shape: &impl Shape,
+) {
+    match style {
+        RenderStyle::Fill => {
+            scene.fill(
+                vello::peniko::Fill::NonZero,
+                transform * local,
+                color,
+                None,
+                shape,
+            );
+        }
+        RenderStyle::Stroke { width, color } => {
+            let mut path = shape.to_path(0.1);
+            path.apply_affine(local);
+            scene.stroke(&Stroke::new(width), transform, color, None, &path);
+        }
+    }
+}
+
+/// Glyph outlines in font units, keyed by glyph ID and the index of the text
+/// font that supplied them (`None` for the math font). Outlines are transformed
+/// per use, so one entry serves every occurrence of a glyph regardless of
+/// position or scale.
+#[derive(Default)]
+struct GlyphCache {
+    paths: HashMap<(u16, Option<usize>), BezPath>,
+    /// Number of glyphs outlined from the font (cache misses).
+    #[cfg(test)]
+    misses: usize,
+}
+
+impl GlyphCache {
+    /// Get the outline of `gid`, outlining it from `face` on first use.
+    fn get_or_outline(
+        &mut self,
+        gid: u16,
+        source: Option<usize>,
+        face: &ttf_parser::Face<'_>,
+    ) -> Option<&BezPath> {
+        match self.paths.entry((gid, source)) {
+            Entry::Occupied(entry) => Some(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                #[cfg(test)]
+                {
+                    self.misses += 1;
+                }
+                let mut builder = PathBuilder(BezPath::new());
+                face.outline_glyph(ttf_parser::GlyphId(gid), &mut builder)?;
+                Some(entry.insert(builder.0))
+            }
+        }
+    }
+}
+
+/// Vello backend for ReX rendering with text font fallback.
 pub struct VelloBackend<'a, 'f, 'p> {
     scene: &'a mut Scene,
     math_font: &'f TtfMathFont<'f>,
-    primary_font: Option<&'p ttf_parser::Face<'p>>,
+    /// Text fonts tried in order before falling back to the math font.
+    text_fonts: &'p [ttf_parser::Face<'p>],
     /// Maps math font glyph IDs to codepoints for fallback lookup.
     glyph_to_codepoint: HashMap<u16, char>,
+    /// Outlines reused across repeated glyphs in the formula.
+    glyph_cache: GlyphCache,
+    /// Multiplier applied to the ReX-provided scale for text-font glyphs.
+    primary_scale: f64,
+    /// Fill or outline for glyphs and rules.
+    style: RenderStyle,
+    /// Draw rules with rounded ends instead of square ones.
+    rounded_rules: bool,
+    /// Extent of everything painted so far, in transformed space.
+    bounds: Option<Rect>,
+    /// Number of shapes painted with a stroke.
+    #[cfg(test)]
+    strokes: usize,
+    /// Rules painted so far, in transformed space.
+    #[cfg(test)]
+    rules: Vec<Rect>,
+    /// Shapes painted for rules, before the backend transform.
+    #[cfg(test)]
+    rule_shapes: Vec<RuleShape>,
     transform: Affine,
     color_stack: Vec<Color>,
     current_color: Color,
+    /// Log `end_color` calls that have no matching `begin_color`.
+    warn_color_underflow: bool,
 }
 
 impl<'a, 'f, 'p> VelloBackend<'a, 'f, 'p> {
     pub fn new(
         scene: &'a mut Scene,
         math_font: &'f TtfMathFont<'f>,
-        primary_font: Option<&'p ttf_parser::Face<'p>>,
+        text_fonts: &'p [ttf_parser::Face<'p>],
         transform: Affine,
         color: Color,
     ) -> Self {
         // Build reverse map from glyph ID to codepoint
         let mut glyph_to_codepoint = HashMap::new();
-        if primary_font.is_some() {
+        if !text_fonts.is_empty() {
             for subtable in math_font
                 .font()
                 .tables()
@@ -89,13 +238,134 @@ impl<'a, 'f, 'p> VelloBackend<'a, 'f, 'p> {
         Self {
             scene,
             math_font,
-            primary_font,
+            text_fonts,
             glyph_to_codepoint,
+            glyph_cache: GlyphCache::default(),
+            primary_scale: DEFAULT_PRIMARY_SCALE,
+            style: RenderStyle::Fill,
+            rounded_rules: false,
+            bounds: None,
+            #[cfg(test)]
+            strokes: 0,
+            #[cfg(test)]
+            rules: Vec::new(),
+            #[cfg(test)]
+            rule_shapes: Vec::new(),
             transform,
             color_stack: Vec::new(),
             current_color: color,
+            warn_color_underflow: false,
+        }
+    }
+
+    /// Set the multiplier applied to the ReX-provided scale when a glyph is
+    /// drawn from a text font. Tune per font to line up x-heights with
+    /// the surrounding text; defaults to [`DEFAULT_PRIMARY_SCALE`].
+    pub fn with_primary_scale(mut self, primary_scale: f64) -> Self {
+        self.primary_scale = primary_scale;
+        self
+    }
+
+    /// Paint glyphs and rules as outlines instead of fills.
+    pub fn with_render_style(mut self, style: RenderStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Draw rules (fraction bars, overlines, radical vincula) as bars with
+    /// rounded ends, with a corner radius of [`ROUNDED_RULE_RADIUS_RATIO`]
+    /// times their thickness. Off by default.
+    pub fn with_rounded_rules(mut self, enabled: bool) -> Self {
+        self.rounded_rules = enabled;
+        self
+    }
+
+    /// Log a warning when `end_color` is called with no color pushed.
+    ///
+    /// Underflow is still ignored either way; this only helps track down
+    /// formulas whose color commands are unbalanced.
+    pub fn with_color_underflow_warnings(mut self, enabled: bool) -> Self {
+        self.warn_color_underflow = enabled;
+        self
+    }
+
+    /// Number of `begin_color` calls not yet closed by `end_color`.
+    pub fn color_depth(&self) -> usize {
+        self.color_stack.len()
+    }
+
+    /// Bounding box of everything rendered so far, after the backend
+    /// transform. `None` until a glyph or rule has been drawn.
+    pub fn bounds(&self) -> Option<Rect> {
+        self.bounds
+    }
+
+    /// Units per em of the math font.
+    pub fn math_units_per_em(&self) -> u16 {
+        self.math_font.font().units_per_em()
+    }
+
+    /// Ascent of the math font above the baseline, in font units.
+    pub fn math_ascent(&self) -> i16 {
+        self.math_font.font().ascender()
+    }
+
+    /// Descent of the math font below the baseline, in font units. Negative,
+    /// as stored in the font.
+    pub fn math_descent(&self) -> i16 {
+        self.math_font.font().descender()
+    }
+
+    /// World units per math font unit at `font_size`, the ReX layout font
+    /// size.
+    ///
+    /// Multiply [`math_ascent`](Self::math_ascent) and
+    /// [`math_descent`](Self::math_descent) by this to get the distances
+    /// from the formula baseline, e.g. to line it up with a text baseline.
+    pub fn math_scale(&self, font_size: f64) -> f64 {
+        font_size / self.math_units_per_em() as f64
+    }
+
+    /// Grow the rendered bounds by a shape's box given in `local` coordinates.
+    fn record_paint(&mut self, local: Affine, bbox: Rect) {
+        let rect = (self.transform * local).transform_rect_bbox(bbox);
+        self.bounds = Some(self.bounds.map_or(rect, |b| b.union(rect)));
+        #[cfg(test)]
+        if matches!(self.style, RenderStyle::Stroke { .. }) {
+            self.strokes += 1;
+        }
+    }
+
+    /// Shape to paint for a rule covering `rect`.
+    fn rule_shape(&self, rect: Rect) -> RuleShape {
+        if self.rounded_rules {
+            let thickness = rect.width().abs().min(rect.height().abs());
+            RuleShape::Rounded(rect.to_rounded_rect(thickness * ROUNDED_RULE_RADIUS_RATIO))
+        } else {
+            RuleShape::Sharp(rect)
         }
     }
+
+    /// Scale used to draw a text-font glyph for a ReX `scale`.
+    fn primary_glyph_scale(&self, scale: f64) -> f64 {
+        scale * self.primary_scale
+    }
+
+    /// Find the first text font covering math glyph `gid`, returning the
+    /// font's index and its own glyph ID.
+    fn resolve_text_glyph(&self, gid: u16) -> Option<(usize, ttf_parser::GlyphId)> {
+        let &codepoint = self.glyph_to_codepoint.get(&gid)?;
+        // Map math italic/bold Unicode to ASCII for text font lookup
+        let lookup_char = math_to_ascii(codepoint).unwrap_or(codepoint);
+        self.text_fonts
+            .iter()
+            .enumerate()
+            .find_map(|(index, face)| {
+                face.glyph_index(lookup_char)
+                    .or_else(|| face.glyph_index(codepoint))
+                    .map(|text_gid| (index, text_gid))
+            })
+    }
 }
 
 struct PathBuilder(BezPath);
@@ -127,34 +397,36 @@ impl ttf_parser::OutlineBuilder for PathBuilder {
 
 impl<'f, 'p> FontBackend<TtfMathFont<'f>> for VelloBackend<'_, 'f, 'p> {
     fn symbol(&mut self, pos: Cursor, gid: GlyphId, scale: f64, _ctx: &TtfMathFont<'f>) {
-        // Try primary font first if available
-        if let Some(primary) = self.primary_font {
-            if let Some(&codepoint) = self.glyph_to_codepoint.get(&gid.into()) {
-                // Map math italic/bold Unicode to ASCII for primary font lookup
-                let lookup_char = math_to_ascii(codepoint).unwrap_or(codepoint);
-                if let Some(primary_gid) = primary.glyph_index(lookup_char) {
-                    // Use primary font (slightly smaller to match text tool rendering)
-                    let units_per_em = primary.units_per_em() as f64;
-                    let adjusted_scale = scale * 0.75;
-                    let glyph_transform = self.transform
-                        * Affine::translate(kurbo::Vec2::new(pos.x, pos.y))
-                        * Affine::scale_non_uniform(
-                            adjusted_scale / units_per_em,
-                            -adjusted_scale / units_per_em,
-                        );
-
-                    let mut builder = PathBuilder(BezPath::new());
-                    if primary.outline_glyph(primary_gid, &mut builder).is_some() {
-                        self.scene.fill(
-                            vello::peniko::Fill::NonZero,
-                            glyph_transform,
-                            self.current_color,
-                            None,
-                            &builder.0,
-                        );
-                        return;
-                    }
-                }
+        let gid: u16 = gid.into();
+
+        // Try text fonts first, in order
+        if let Some((index, text_gid)) = self.resolve_text_glyph(gid) {
+            let fonts = self.text_fonts;
+            let face = &fonts[index];
+            // Resize to match text tool rendering
+            let units_per_em = face.units_per_em() as f64;
+            let adjusted_scale = self.primary_glyph_scale(scale);
+            let glyph_transform = Affine::translate(kurbo::Vec2::new(pos.x, pos.y))
+                * Affine::scale_non_uniform(
+                    adjusted_scale / units_per_em,
+                    -adjusted_scale / units_per_em,
+                );
+
+            if let Some(path) = self
+                .glyph_cache
+                .get_or_outline(text_gid.0, Some(index), face)
+            {
+                let bbox = path.bounding_box();
+                paint(
+                    self.scene,
+                    self.style,
+                    self.current_color,
+                    self.transform,
+                    glyph_transform,
+                    path,
+                );
+                self.record_paint(glyph_transform, bbox);
+                return;
             }
         }
 
@@ -171,36 +443,52 @@ impl<'f, 'p> FontBackend<TtfMathFont<'f>> for VelloBackend<'_, 'f, 'p> {
             sx as f64, ky as f64, kx as f64, sy as f64, tx as f64, ty as f64,
         ]);
 
-        let glyph_transform = self.transform
-            * Affine::translate(kurbo::Vec2::new(pos.x, pos.y))
+        let glyph_transform = Affine::translate(kurbo::Vec2::new(pos.x, pos.y))
             * Affine::scale_non_uniform(scale, -scale)
             * font_matrix;
 
-        let mut builder = PathBuilder(BezPath::new());
-        self.math_font
-            .font()
-            .outline_glyph(gid.into(), &mut builder);
-
-        self.scene.fill(
-            vello::peniko::Fill::NonZero,
-            glyph_transform,
-            self.current_color,
-            None,
-            &builder.0,
-        );
+        let math_font = self.math_font;
+        if let Some(path) = self.glyph_cache.get_or_outline(gid, None, math_font.font()) {
+            let bbox = path.bounding_box();
+            paint(
+                self.scene,
+                self.style,
+                self.current_color,
+                self.transform,
+                glyph_transform,
+                path,
+            );
+            self.record_paint(glyph_transform, bbox);
+        }
     }
 }
 
 impl GraphicsBackend for VelloBackend<'_, '_, '_> {
     fn rule(&mut self, pos: Cursor, width: f64, height: f64) {
         let rect = kurbo::Rect::new(pos.x, pos.y, pos.x + width, pos.y + height);
-        self.scene.fill(
-            vello::peniko::Fill::NonZero,
-            self.transform,
-            self.current_color,
-            None,
-            &rect,
-        );
+        let shape = self.rule_shape(rect);
+        let (style, color, transform) = (self.style, self.current_color, self.transform);
+        match shape {
+            RuleShape::Sharp(rect) => {
+                paint(self.scene, style, color, transform, Affine::IDENTITY, &rect);
+            }
+            RuleShape::Rounded(rounded) => {
+                paint(
+                    self.scene,
+                    style,
+                    color,
+                    transform,
+                    Affine::IDENTITY,
+                    &rounded,
+                );
+            }
+        }
+        self.record_paint(Affine::IDENTITY, rect);
+        #[cfg(test)]
+        {
+            self.rules.push(self.transform.transform_rect_bbox(rect));
+            self.rule_shapes.push(shape);
+        }
     }
 
     fn begin_color(&mut self, RGBA(r, g, b, a): RGBA) {
@@ -211,8 +499,263 @@ impl GraphicsBackend for VelloBackend<'_, '_, '_> {
     fn end_color(&mut self) {
         if let Some(color) = self.color_stack.pop() {
             self.current_color = color;
+        } else if self.warn_color_underflow {
+            log::warn!("ReX end_color without matching begin_color");
         }
     }
 }
 
 impl<'f, 'p> Backend<TtfMathFont<'f>> for VelloBackend<'_, 'f, 'p> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rex::layout::engine::LayoutBuilder;
+    use rex::render::Renderer as RexRenderer;
+
+    static XITS_MATH: &[u8] = include_bytes!("../assets/rex-xits.otf");
+    static GELPEN: &[u8] = include_bytes!("../assets/GelPen.ttf");
+    static NOTO_SANS: &[u8] = include_bytes!("../assets/NotoSans-Regular.ttf");
+
+    /// Render `latex` and hand the finished backend to `check`.
+    fn with_rendered(
+        latex: &str,
+        text_fonts: &[ttf_parser::Face<'_>],
+        style: RenderStyle,
+        check: impl FnOnce(&VelloBackend<'_, '_, '_>),
+    ) {
+        let math_face = ttf_parser::Face::parse(XITS_MATH, 0).unwrap();
+        let math_font = TtfMathFont::new(math_face).unwrap();
+        let nodes = rex::parser::parse(latex).unwrap();
+        let layout = LayoutBuilder::new(&math_font)
+            .font_size(16.0)
+            .build()
+            .layout(&nodes)
+            .unwrap();
+
+        let mut scene = Scene::new();
+        let mut backend = VelloBackend::new(
+            &mut scene,
+            &math_font,
+            text_fonts,
+            Affine::IDENTITY,
+            Color::BLACK,
+        )
+        .with_render_style(style);
+        RexRenderer::new().render(&layout, &mut backend);
+        check(&backend);
+    }
+
+    #[test]
+    fn test_repeated_glyphs_are_outlined_once() {
+        let fonts = [ttf_parser::Face::parse(NOTO_SANS, 0).unwrap()];
+        with_rendered("xxxxxx + yyyyyy", &fonts, RenderStyle::Fill, |backend| {
+            // x, y and + each outlined a single time.
+            assert_eq!(backend.glyph_cache.misses, 3);
+            assert_eq!(backend.glyph_cache.paths.len(), 3);
+        });
+    }
+
+    #[test]
+    fn test_nested_colors_track_depth() {
+        let math_face = ttf_parser::Face::parse(XITS_MATH, 0).unwrap();
+        let math_font = TtfMathFont::new(math_face).unwrap();
+        let mut scene = Scene::new();
+        let mut backend =
+            VelloBackend::new(&mut scene, &math_font, &[], Affine::IDENTITY, Color::BLACK)
+                .with_color_underflow_warnings(true);
+        assert_eq!(backend.color_depth(), 0);
+
+        backend.begin_color(RGBA(255, 0, 0, 255));
+        backend.begin_color(RGBA(0, 0, 255, 255));
+        assert_eq!(backend.color_depth(), 2);
+        assert_eq!(backend.current_color, Color::from_rgba8(0, 0, 255, 255));
+
+        backend.end_color();
+        assert_eq!(backend.color_depth(), 1);
+        assert_eq!(backend.current_color, Color::from_rgba8(255, 0, 0, 255));
+        backend.end_color();
+        assert_eq!(backend.current_color, Color::BLACK);
+
+        // Underflow is ignored
+        backend.end_color();
+        assert_eq!(backend.color_depth(), 0);
+        assert_eq!(backend.current_color, Color::BLACK);
+    }
+
+    #[test]
+    fn test_primary_scale_multiplies_rex_scale() {
+        let math_face = ttf_parser::Face::parse(XITS_MATH, 0).unwrap();
+        let math_font = TtfMathFont::new(math_face).unwrap();
+        let mut scene = Scene::new();
+
+        let backend =
+            VelloBackend::new(&mut scene, &math_font, &[], Affine::IDENTITY, Color::BLACK);
+        assert_eq!(backend.primary_glyph_scale(20.0), 15.0);
+
+        let backend = backend.with_primary_scale(1.0);
+        assert_eq!(backend.primary_glyph_scale(20.0), 20.0);
+    }
+
+    #[test]
+    fn test_reports_math_font_metrics() {
+        let math_face = ttf_parser::Face::parse(XITS_MATH, 0).unwrap();
+        let math_font = TtfMathFont::new(math_face).unwrap();
+        let mut scene = Scene::new();
+        let backend =
+            VelloBackend::new(&mut scene, &math_font, &[], Affine::IDENTITY, Color::BLACK);
+
+        assert!(backend.math_units_per_em() > 0);
+        assert!(backend.math_ascent() > 0);
+        assert!(backend.math_descent() < 0);
+        let scale = backend.math_scale(16.0);
+        assert_eq!(scale, 16.0 / backend.math_units_per_em() as f64);
+        assert!(backend.math_ascent() as f64 * scale > 0.0);
+    }
+
+    #[test]
+    fn test_falls_through_to_next_text_font() {
+        let math_face = ttf_parser::Face::parse(XITS_MATH, 0).unwrap();
+        let math_font = TtfMathFont::new(math_face).unwrap();
+        // GelPen has no Greek; Noto Sans does.
+        let fonts = [
+            ttf_parser::Face::parse(GELPEN, 0).unwrap(),
+            ttf_parser::Face::parse(NOTO_SANS, 0).unwrap(),
+        ];
+        assert!(fonts[0].glyph_index('α').is_none());
+
+        let mut scene = Scene::new();
+        let backend = VelloBackend::new(
+            &mut scene,
+            &math_font,
+            &fonts,
+            Affine::IDENTITY,
+            Color::BLACK,
+        );
+
+        let alpha = math_font.font().glyph_index('α').unwrap();
+        let (index, gid) = backend.resolve_text_glyph(alpha.0).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(Some(gid), fonts[1].glyph_index('α'));
+
+        let x = math_font.font().glyph_index('x').unwrap();
+        assert_eq!(backend.resolve_text_glyph(x.0).map(|(i, _)| i), Some(0));
+    }
+
+    #[test]
+    fn test_registered_family_supplies_text_glyphs() {
+        let math_face = ttf_parser::Face::parse(XITS_MATH, 0).unwrap();
+        let math_font = TtfMathFont::new(math_face).unwrap();
+        let registry = crate::fonts::FontRegistry::new();
+        let alpha = math_font.font().glyph_index('α').unwrap();
+
+        let resolve = |family: &str| {
+            let face = registry.face(family).unwrap();
+            let mut scene = Scene::new();
+            let backend = VelloBackend::new(
+                &mut scene,
+                &math_font,
+                std::slice::from_ref(face),
+                Affine::IDENTITY,
+                Color::BLACK,
+            );
+            backend.resolve_text_glyph(alpha.0).map(|(_, gid)| gid)
+        };
+
+        // Noto Sans covers Greek, GelPen (the default) does not.
+        let noto = ttf_parser::Face::parse(NOTO_SANS, 0).unwrap();
+        assert_eq!(resolve("Noto Sans"), noto.glyph_index('α'));
+        assert_eq!(resolve("GelPen"), None);
+        assert_eq!(resolve("Unregistered"), None);
+    }
+
+    #[test]
+    fn test_maps_double_struck_and_greek() {
+        assert_eq!(math_to_ascii('\u{1D538}'), Some('A'));
+        assert_eq!(math_to_ascii('\u{1D549}'), Some('R'));
+        assert_eq!(math_to_ascii('\u{211D}'), Some('R'));
+        assert_eq!(math_to_ascii('\u{1D56B}'), Some('z'));
+        // Italic alpha, capital Omega and final sigma
+        assert_eq!(math_to_ascii('\u{1D6FC}'), Some('α'));
+        assert_eq!(math_to_ascii('\u{1D6FA}'), Some('Ω'));
+        assert_eq!(math_to_ascii('\u{1D70D}'), Some('ς'));
+        // Bold capital Sigma after the theta symbol slot
+        assert_eq!(math_to_ascii('\u{1D6BA}'), Some('Σ'));
+        // Existing Latin ranges are unchanged
+        assert_eq!(math_to_ascii('\u{1D44E}'), Some('a'));
+    }
+
+    #[test]
+    fn test_blackboard_r_uses_text_font() {
+        let math_face = ttf_parser::Face::parse(XITS_MATH, 0).unwrap();
+        let math_font = TtfMathFont::new(math_face).unwrap();
+        let fonts = [ttf_parser::Face::parse(NOTO_SANS, 0).unwrap()];
+        let mut scene = Scene::new();
+        let backend = VelloBackend::new(
+            &mut scene,
+            &math_font,
+            &fonts,
+            Affine::IDENTITY,
+            Color::BLACK,
+        );
+
+        let reals = math_font.font().glyph_index('\u{211D}').unwrap();
+        let (index, gid) = backend.resolve_text_glyph(reals.0).unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(Some(gid), fonts[0].glyph_index('R'));
+    }
+
+    #[test]
+    fn test_stroke_style_strokes_glyphs_and_rules() {
+        let stroke = RenderStyle::Stroke {
+            width: 0.5,
+            color: Color::WHITE,
+        };
+        with_rendered("\\frac{a}{b}", &[], stroke, |backend| {
+            // Two glyphs plus the fraction bar.
+            assert_eq!(backend.strokes, 3);
+        });
+        with_rendered("\\frac{a}{b}", &[], RenderStyle::Fill, |backend| {
+            assert_eq!(backend.strokes, 0);
+        });
+    }
+
+    #[test]
+    fn test_rounded_rules_draw_rounded_rects() {
+        with_rendered("\\frac{a}{b}", &[], RenderStyle::Fill, |backend| {
+            assert!(!backend.rule_shapes.is_empty());
+            assert!(
+                backend
+                    .rule_shapes
+                    .iter()
+                    .all(|shape| matches!(shape, RuleShape::Sharp(_)))
+            );
+        });
+
+        let math_face = ttf_parser::Face::parse(XITS_MATH, 0).unwrap();
+        let math_font = TtfMathFont::new(math_face).unwrap();
+        let mut scene = Scene::new();
+        let mut backend =
+            VelloBackend::new(&mut scene, &math_font, &[], Affine::IDENTITY, Color::BLACK)
+                .with_rounded_rules(true);
+        backend.rule(Cursor { x: 10.0, y: 20.0 }, 40.0, 2.0);
+        let RuleShape::Rounded(rounded) = backend.rule_shapes[0] else {
+            panic!("expected a rounded rule");
+        };
+        assert_eq!(rounded.rect(), Rect::new(10.0, 20.0, 50.0, 22.0));
+        assert_eq!(rounded.radii().top_left, 2.0 * ROUNDED_RULE_RADIUS_RATIO);
+        assert_eq!(backend.bounds(), Some(rounded.rect()));
+    }
+
+    #[test]
+    fn test_bounds_enclose_glyphs_and_rules() {
+        with_rendered("\\frac{x+1}{2}", &[], RenderStyle::Fill, |backend| {
+            let bounds = backend.bounds().unwrap();
+            assert!(bounds.width() > 0.0 && bounds.height() > 0.0);
+            assert!(!backend.rules.is_empty());
+            for rule in &backend.rules {
+                assert_eq!(bounds.union(*rule), bounds);
+            }
+        });
+    }
+}
@@ -0,0 +1,175 @@
+//! Recording ReX backend for math layout tests.
+//!
+//! [`MockBackend`] implements the ReX backend traits like
+//! [`VelloBackend`](crate::rex_backend::VelloBackend) but records each call
+//! instead of drawing, so formula layout can be asserted without a GPU, a
+//! `Scene` or text fonts.
+
+use kurbo::Point;
+use rex::font::backend::ttf_parser::TtfMathFont;
+use rex::font::common::GlyphId;
+use rex::render::{Backend, Cursor, FontBackend, GraphicsBackend, RGBA};
+use std::collections::HashMap;
+
+/// A backend call recorded by [`MockBackend`], in ReX layout coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RexCall {
+    /// A math font glyph. `codepoint` is a character the math font maps to
+    /// it, if any; ReX draws letters from the math italic block.
+    Symbol {
+        pos: Point,
+        gid: u16,
+        codepoint: Option<char>,
+        scale: f64,
+    },
+    /// A filled bar, such as a fraction bar.
+    Rule { pos: Point, width: f64, height: f64 },
+    /// A color pushed, as RGBA.
+    BeginColor([u8; 4]),
+    /// The last pushed color popped.
+    EndColor,
+}
+
+/// ReX backend that records calls instead of drawing.
+pub struct MockBackend {
+    /// Maps math font glyph IDs to codepoints.
+    glyph_to_codepoint: HashMap<u16, char>,
+    /// Calls in the order ReX made them.
+    pub calls: Vec<RexCall>,
+}
+
+impl MockBackend {
+    pub fn new(math_font: &TtfMathFont<'_>) -> Self {
+        let mut glyph_to_codepoint = HashMap::new();
+        for subtable in math_font
+            .font()
+            .tables()
+            .cmap
+            .iter()
+            .flat_map(|c| c.subtables)
+        {
+            if subtable.is_unicode() {
+                subtable.codepoints(|cp| {
+                    if let Some(c) = char::from_u32(cp) {
+                        if let Some(gid) = subtable.glyph_index(cp) {
+                            glyph_to_codepoint.insert(gid.0, c);
+                        }
+                    }
+                });
+            }
+        }
+        Self {
+            glyph_to_codepoint,
+            calls: Vec::new(),
+        }
+    }
+}
+
+impl<'f> FontBackend<TtfMathFont<'f>> for MockBackend {
+    fn symbol(&mut self, pos: Cursor, gid: GlyphId, scale: f64, _ctx: &TtfMathFont<'f>) {
+        let gid: u16 = gid.into();
+        self.calls.push(RexCall::Symbol {
+            pos: Point::new(pos.x, pos.y),
+            gid,
+            codepoint: self.glyph_to_codepoint.get(&gid).copied(),
+            scale,
+        });
+    }
+}
+
+impl GraphicsBackend for MockBackend {
+    fn rule(&mut self, pos: Cursor, width: f64, height: f64) {
+        self.calls.push(RexCall::Rule {
+            pos: Point::new(pos.x, pos.y),
+            width,
+            height,
+        });
+    }
+
+    fn begin_color(&mut self, RGBA(r, g, b, a): RGBA) {
+        self.calls.push(RexCall::BeginColor([r, g, b, a]));
+    }
+
+    fn end_color(&mut self) {
+        self.calls.push(RexCall::EndColor);
+    }
+}
+
+impl<'f> Backend<TtfMathFont<'f>> for MockBackend {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rex::layout::engine::LayoutBuilder;
+    use rex::render::Renderer as RexRenderer;
+
+    static XITS_MATH: &[u8] = include_bytes!("../assets/rex-xits.otf");
+
+    /// Lay out and render `latex`, returning the recorded calls.
+    fn record(latex: &str) -> Vec<RexCall> {
+        let math_face = ttf_parser::Face::parse(XITS_MATH, 0).unwrap();
+        let math_font = TtfMathFont::new(math_face).unwrap();
+        let nodes = rex::parser::parse(latex).unwrap();
+        let layout = LayoutBuilder::new(&math_font)
+            .font_size(16.0)
+            .build()
+            .layout(&nodes)
+            .unwrap();
+        let mut backend = MockBackend::new(&math_font);
+        RexRenderer::new().render(&layout, &mut backend);
+        backend.calls
+    }
+
+    #[test]
+    fn test_superscript_is_two_symbols_and_no_rules() {
+        let calls = record("x^2");
+        let [
+            RexCall::Symbol {
+                pos: base_pos,
+                codepoint: base,
+                scale: base_scale,
+                ..
+            },
+            RexCall::Symbol {
+                pos: sup_pos,
+                codepoint: sup,
+                scale: sup_scale,
+                ..
+            },
+        ] = calls.as_slice()
+        else {
+            panic!("expected exactly two symbols, got {calls:?}");
+        };
+        // Math italic x, then an upright 2 raised to its right in script size
+        assert_eq!(*base, Some('\u{1D465}'));
+        assert_eq!(*sup, Some('2'));
+        assert!(sup_scale < base_scale);
+        assert!(sup_pos.x > base_pos.x);
+        assert!(sup_pos.y < base_pos.y);
+    }
+
+    #[test]
+    fn test_fraction_draws_one_rule_between_symbols() {
+        let calls = record("\\frac{a}{b}");
+        let symbols: Vec<Point> = calls
+            .iter()
+            .filter_map(|call| match call {
+                RexCall::Symbol { pos, .. } => Some(*pos),
+                _ => None,
+            })
+            .collect();
+        let rules: Vec<(Point, f64)> = calls
+            .iter()
+            .filter_map(|call| match call {
+                RexCall::Rule { pos, width, .. } => Some((*pos, *width)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(rules.len(), 1);
+        // The bar sits between numerator and denominator
+        let (bar, width) = rules[0];
+        assert!(symbols[0].y < bar.y && bar.y < symbols[1].y);
+        assert!(width > 0.0);
+    }
+}
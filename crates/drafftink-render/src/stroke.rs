@@ -0,0 +1,139 @@
+//! Variable-width stroke outlines.
+//!
+//! Pressure-sensitive freehand strokes are drawn as a filled outline rather
+//! than a stroked path, so the width can change from sample to sample.
+
+use kurbo::{BezPath, Point, Vec2};
+
+/// Longest miter at a corner, as a multiple of the local half width.
+///
+/// Sharper turns are clamped to this length so the inner side of a hairpin
+/// does not shoot far past the stroke and fold the outline over itself.
+const MITER_LIMIT: f64 = 2.0;
+
+/// Build the filled outline of a stroke through `points` whose full width at
+/// each point is given by `widths`.
+///
+/// The outline is the left offset polyline followed by the right one in
+/// reverse, closed with flat ends. Corners are mitered so straight runs keep
+/// their exact width, with the miter clamped at [`MITER_LIMIT`] for acute
+/// turns. Consecutive duplicate points are skipped. If `widths` is shorter
+/// than `points` its last value is repeated; with no widths or fewer than two
+/// distinct points the path is empty.
+pub fn build_variable_width_path(points: &[Point], widths: &[f64]) -> BezPath {
+    let Some(&last_width) = widths.last() else {
+        return BezPath::new();
+    };
+    let mut samples: Vec<(Point, f64)> = Vec::with_capacity(points.len());
+    for (i, &point) in points.iter().enumerate() {
+        if samples
+            .last()
+            .is_some_and(|&(prev, _)| (point - prev).hypot() < f64::EPSILON)
+        {
+            continue;
+        }
+        samples.push((point, widths.get(i).copied().unwrap_or(last_width)));
+    }
+    if samples.len() < 2 {
+        return BezPath::new();
+    }
+
+    // Unit normal of each segment, pointing left of the direction of travel
+    let normals: Vec<Vec2> = samples
+        .windows(2)
+        .map(|pair| {
+            let dir = (pair[1].0 - pair[0].0).normalize();
+            Vec2::new(-dir.y, dir.x)
+        })
+        .collect();
+
+    let mut left = Vec::with_capacity(samples.len());
+    let mut right = Vec::with_capacity(samples.len());
+    for (i, &(point, width)) in samples.iter().enumerate() {
+        let half_width = width / 2.0;
+        let offset = match (i.checked_sub(1).map(|j| normals[j]), normals.get(i)) {
+            (Some(incoming), Some(&outgoing)) => miter(incoming, outgoing) * half_width,
+            (Some(normal), None) | (None, Some(&normal)) => normal * half_width,
+            (None, None) => unreachable!("at least two samples"),
+        };
+        left.push(point + offset);
+        right.push(point - offset);
+    }
+
+    let mut path = BezPath::new();
+    path.move_to(left[0]);
+    for &point in &left[1..] {
+        path.line_to(point);
+    }
+    for &point in right.iter().rev() {
+        path.line_to(point);
+    }
+    path.close_path();
+    path
+}
+
+/// Offset direction at a corner between two segment normals, scaled so the
+/// offset lines stay one half width from both segments, up to the miter limit.
+fn miter(incoming: Vec2, outgoing: Vec2) -> Vec2 {
+    let sum = incoming + outgoing;
+    let len = sum.hypot();
+    if len < f64::EPSILON {
+        // A full reversal has no miter; keep the incoming side.
+        return incoming;
+    }
+    let bisector = sum / len;
+    // Cosine of half the turn angle
+    let cos_half = bisector.dot(incoming);
+    bisector / cos_half.max(1.0 / MITER_LIMIT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kurbo::{Rect, Shape};
+
+    #[test]
+    fn test_constant_width_gives_uniform_ribbon() {
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(100.0, 0.0),
+            Point::new(100.0, 100.0),
+        ];
+        let path = build_variable_width_path(&points, &[4.0; 3]);
+
+        // With mitered corners the area is the centerline length times width.
+        assert!((path.area().abs() - 800.0).abs() < 1e-9);
+        // Both arms are exactly 4 wide, meeting in a square outer corner.
+        assert_eq!(path.bounding_box(), Rect::new(0.0, -2.0, 102.0, 100.0));
+    }
+
+    #[test]
+    fn test_widths_follow_samples() {
+        let points = [Point::new(0.0, 0.0), Point::new(100.0, 0.0)];
+        let path = build_variable_width_path(&points, &[2.0, 10.0]);
+        let bounds = path.bounding_box();
+        assert_eq!(bounds.y0, -5.0);
+        assert_eq!(bounds.y1, 5.0);
+        // Missing widths repeat the last one; no widths means no outline.
+        assert_eq!(
+            build_variable_width_path(&points, &[6.0])
+                .bounding_box()
+                .height(),
+            6.0
+        );
+        assert!(build_variable_width_path(&points, &[]).is_empty());
+        assert!(build_variable_width_path(&points[..1], &[4.0]).is_empty());
+    }
+
+    #[test]
+    fn test_acute_turn_clamps_miter() {
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(100.0, 0.0),
+            Point::new(0.0, 1.0),
+        ];
+        let path = build_variable_width_path(&points, &[4.0; 3]);
+        // An unclamped miter at this hairpin would reach hundreds of units.
+        assert!(path.bounding_box().x1 <= 100.0 + MITER_LIMIT * 2.0 + 1e-9);
+    }
+}
@@ -0,0 +1,408 @@
+//! SVG export for canvas documents.
+//!
+//! Produces vector output without a GPU. Geometry comes from each shape's
+//! `to_path()`, so angular lines and arrows follow the same elbow routing as
+//! on-screen rendering. A document background color becomes a full-size
+//! rectangle; without one (or when transparent) the SVG has no background.
+//!
+//! Not yet supported: the hand-drawn sloppiness effect, non-solid fill
+//! patterns, gradient fills (the plain fill color is used), drop shadows,
+//! per-character text colors, images, and math. Images and math
+//! are emitted as comments so the output stays valid.
+
+use std::fmt::Write;
+
+use drafftink_core::canvas::{Background, CanvasDocument};
+use drafftink_core::shapes::{
+    ElementId, LineCap, LineJoin, Shape, ShapeStyle, ShapeTrait, StrokeStyle, Text, TextAlign,
+};
+use kurbo::{BezPath, Rect, Size};
+use peniko::Color;
+
+/// Padding around the document content in the exported view box.
+const PADDING: f64 = 20.0;
+
+/// Line height used when laying out multi-line text, relative to font size.
+const LINE_HEIGHT: f64 = 1.2;
+
+/// Export `doc` as an SVG document of the given output size.
+///
+/// `ids` limits the export to the given elements, with the same arrow rule as
+/// [`CanvasDocument::export_shapes`]; `None` exports everything. The view box
+/// covers the bounds of the exported shapes plus padding, so the content is
+/// scaled to fit `size`.
+pub fn export_svg(doc: &CanvasDocument, ids: Option<&[ElementId]>, size: Size) -> String {
+    let shapes = doc.export_shapes(ids);
+    let view_box = CanvasDocument::shapes_bounds(shapes.iter().copied())
+        .map(|b| b.inflate(PADDING, PADDING))
+        .unwrap_or_else(|| Rect::from_origin_size((0.0, 0.0), size));
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        size.width,
+        size.height,
+        view_box.x0,
+        view_box.y0,
+        view_box.width(),
+        view_box.height(),
+    );
+    if let Some(Background::Color(color)) = doc.background {
+        let _ = writeln!(
+            out,
+            r#"<rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+            view_box.x0,
+            view_box.y0,
+            view_box.width(),
+            view_box.height(),
+            fill_attrs_color(color.into()),
+        );
+    }
+    for shape in shapes {
+        write_shape(&mut out, shape);
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Append the SVG elements for one shape.
+fn write_shape(out: &mut String, shape: &Shape) {
+    let transform = rotation_attr(shape);
+    match shape {
+        Shape::Group(group) => {
+            out.push_str("<g>\n");
+            for child in group.children() {
+                write_shape(out, child);
+            }
+            out.push_str("</g>\n");
+        }
+        Shape::Text(text) => write_text(out, text, &transform),
+        Shape::Line(line) => {
+            let caps = (line.line_cap, line.line_join);
+            write_stroke(out, &shape.to_path(), &line.style, line.stroke_style, caps);
+        }
+        Shape::Arrow(arrow) => {
+            let caps = (arrow.line_cap, arrow.line_join);
+            write_stroke(
+                out,
+                &arrow.shaft_path(),
+                &arrow.style,
+                arrow.stroke_style,
+                caps,
+            );
+            for (head_style, head) in arrow.head_paths() {
+                if head_style.is_filled() {
+                    let _ = writeln!(
+                        out,
+                        r#"<path d="{}"{}{}/>"#,
+                        head.to_svg(),
+                        fill_attrs_color(arrow.style.stroke_with_opacity()),
+                        stroke_attrs(&arrow.style, StrokeStyle::Solid),
+                    );
+                } else {
+                    write_stroke(out, &head, &arrow.style, StrokeStyle::Solid, caps);
+                }
+            }
+            if let Some(label) = arrow.label.as_deref().filter(|l| !l.is_empty()) {
+                let anchor = arrow.label_anchor();
+                let (color, opacity) = svg_color(arrow.style.stroke_with_opacity());
+                let _ = writeln!(
+                    out,
+                    r#"<text x="{}" y="{}" fill="{}" fill-opacity="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                    anchor.x,
+                    anchor.y,
+                    color,
+                    opacity,
+                    escape(label),
+                );
+            }
+        }
+        Shape::Freehand(_) => {
+            write_stroke(
+                out,
+                &shape.to_path(),
+                shape.style(),
+                StrokeStyle::Solid,
+                (LineCap::Round, LineJoin::Round),
+            );
+        }
+        Shape::Image(_) => out.push_str("<!-- unsupported: image -->\n"),
+        Shape::Math(_) => out.push_str("<!-- unsupported: math -->\n"),
+        Shape::StickyNote(note) => {
+            let _ = writeln!(
+                out,
+                r#"<path d="{}"{}{}/>"#,
+                shape.to_path().to_svg(),
+                fill_attrs(&note.background.style),
+                stroke_attrs(&note.background.style, StrokeStyle::Solid),
+            );
+            write_text(out, &note.text, "");
+        }
+        Shape::Rectangle(_) | Shape::Ellipse(_) => {
+            let style = shape.style();
+            let _ = writeln!(
+                out,
+                r#"<path d="{}"{}{}{}/>"#,
+                shape.to_path().to_svg(),
+                fill_attrs(style),
+                stroke_attrs(style, StrokeStyle::Solid),
+                transform,
+            );
+        }
+    }
+}
+
+/// Append a stroke-only path (lines, arrows, freehand).
+fn write_stroke(
+    out: &mut String,
+    path: &BezPath,
+    style: &ShapeStyle,
+    stroke_style: StrokeStyle,
+    (cap, join): (LineCap, LineJoin),
+) {
+    let cap = match cap {
+        LineCap::Butt => "butt",
+        LineCap::Round => "round",
+        LineCap::Square => "square",
+    };
+    let join = match join {
+        LineJoin::Miter => "miter",
+        LineJoin::Round => "round",
+        LineJoin::Bevel => "bevel",
+    };
+    let _ = writeln!(
+        out,
+        r#"<path d="{}" fill="none"{} stroke-linecap="{cap}" stroke-linejoin="{join}"/>"#,
+        path.to_svg(),
+        stroke_attrs(style, stroke_style),
+    );
+}
+
+/// Append a text element, one `tspan` per line.
+///
+/// Wrapped text is broken with approximate character widths, and aligned
+/// lines are anchored at the matching edge of the wrap width.
+fn write_text(out: &mut String, text: &Text, transform: &str) {
+    let (color, opacity) = svg_color(text.style.stroke_with_opacity());
+    // Without a wrap width lines align within the widest line
+    let box_width = text.wrap_width.unwrap_or_else(|| text.bounds().width());
+    let (x, anchor) = match text.align {
+        TextAlign::Left | TextAlign::Justify => (text.position.x, "start"),
+        TextAlign::Center => (text.position.x + box_width / 2.0, "middle"),
+        TextAlign::Right => (text.position.x + box_width, "end"),
+    };
+    let _ = writeln!(
+        out,
+        r#"<text x="{}" y="{}" font-family="{}" font-size="{}" fill="{}" fill-opacity="{}" text-anchor="{}" dominant-baseline="text-before-edge"{}>"#,
+        x,
+        text.position.y,
+        text.font_family.name(),
+        text.font_size,
+        color,
+        opacity,
+        anchor,
+        transform,
+    );
+    for (i, line) in text.approximate_lines().into_iter().enumerate() {
+        let dy = if i == 0 {
+            0.0
+        } else {
+            text.font_size * LINE_HEIGHT
+        };
+        let _ = writeln!(
+            out,
+            r#"<tspan x="{}" dy="{}">{}</tspan>"#,
+            x,
+            dy,
+            escape(line),
+        );
+    }
+    out.push_str("</text>\n");
+}
+
+/// Fill attributes matching the on-screen solid fill.
+fn fill_attrs(style: &ShapeStyle) -> String {
+    match style.fill_with_opacity() {
+        Some(fill) => fill_attrs_color(fill),
+        None => r#" fill="none""#.to_string(),
+    }
+}
+
+/// Fill attributes for a solid color.
+fn fill_attrs_color(fill: Color) -> String {
+    let (color, opacity) = svg_color(fill);
+    format!(r#" fill="{color}" fill-opacity="{opacity}""#)
+}
+
+/// Stroke attributes, with the same dash lengths as the Vello renderer.
+fn stroke_attrs(style: &ShapeStyle, stroke_style: StrokeStyle) -> String {
+    let (color, opacity) = svg_color(style.stroke_with_opacity());
+    let width = style.stroke_width;
+    let mut attrs =
+        format!(r#" stroke="{color}" stroke-opacity="{opacity}" stroke-width="{width}""#);
+    if let Some([dash, gap]) = stroke_style.dash_pattern(width) {
+        let _ = write!(attrs, r#" stroke-dasharray="{dash} {gap}""#);
+    }
+    attrs
+}
+
+/// Rotation about the shape's center, as the renderer applies it.
+fn rotation_attr(shape: &Shape) -> String {
+    let rotation = shape.rotation();
+    if rotation.abs() > 0.001 {
+        let center = shape.bounds().center();
+        format!(
+            r#" transform="rotate({} {} {})""#,
+            rotation.to_degrees(),
+            center.x,
+            center.y
+        )
+    } else {
+        String::new()
+    }
+}
+
+/// Split a color into an SVG `rgb()` value and an opacity.
+fn svg_color(color: Color) -> (String, f64) {
+    let rgba = color.to_rgba8();
+    (
+        format!("rgb({},{},{})", rgba.r, rgba.g, rgba.b),
+        rgba.a as f64 / 255.0,
+    )
+}
+
+/// Escape text for use in SVG character data.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use drafftink_core::shapes::{Arrow, Rectangle};
+    use kurbo::Point;
+
+    #[test]
+    fn test_exports_intro_document() {
+        let doc = CanvasDocument::from_json(include_str!("../../drafftink-app/assets/intro.json"))
+            .unwrap();
+        let svg = export_svg(&doc, None, Size::new(800.0, 600.0));
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("<path "));
+    }
+
+    #[test]
+    fn test_rectangle_and_text_styles() {
+        let mut doc = CanvasDocument::new();
+        let mut rect = Rectangle::new(Point::new(0.0, 0.0), 100.0, 50.0);
+        rect.style.fill_color = Some(drafftink_core::shapes::SerializableColor::white());
+        doc.add_shape(Shape::Rectangle(rect));
+        doc.add_shape(Shape::Text(Text::new(
+            Point::new(10.0, 10.0),
+            "a < b\nc".to_string(),
+        )));
+
+        let svg = export_svg(&doc, None, Size::new(200.0, 100.0));
+        assert!(svg.contains(r#"fill="rgb(255,255,255)""#));
+        assert!(svg.contains("a &lt; b"));
+        assert_eq!(svg.matches("<tspan").count(), 2);
+    }
+
+    #[test]
+    fn test_wrapped_text_right_aligned() {
+        let mut doc = CanvasDocument::new();
+        let text = Text::new(Point::new(10.0, 10.0), "one two three four".to_string())
+            .with_wrap_width(120.0)
+            .with_align(TextAlign::Right);
+        doc.add_shape(Shape::Text(text));
+
+        let svg = export_svg(&doc, None, Size::new(200.0, 100.0));
+        assert!(svg.contains(r#"x="130" y="10""#));
+        assert!(svg.contains(r#"text-anchor="end""#));
+        assert!(svg.contains(r#"<tspan x="130" dy="0">one two</tspan>"#));
+        assert_eq!(svg.matches("<tspan").count(), 2);
+    }
+
+    #[test]
+    fn test_unwrapped_text_centers_within_widest_line() {
+        let mut doc = CanvasDocument::new();
+        let text = Text::new(Point::new(10.0, 10.0), "ab\nabcdefgh".to_string())
+            .with_align(TextAlign::Center);
+        let center = text.position.x + text.bounds().width() / 2.0;
+        assert!(center > text.position.x);
+        doc.add_shape(Shape::Text(text));
+
+        let svg = export_svg(&doc, None, Size::new(200.0, 100.0));
+        assert!(svg.contains(r#"text-anchor="middle""#));
+        assert!(svg.contains(&format!(r#"<tspan x="{center}" dy="0">ab</tspan>"#)));
+    }
+
+    #[test]
+    fn test_sticky_note_exports_background_and_text() {
+        use drafftink_core::shapes::{StickyNote, StickyNoteColor};
+
+        let mut doc = CanvasDocument::new();
+        doc.add_shape(Shape::StickyNote(
+            StickyNote::new(Point::new(0.0, 0.0), "todo".to_string())
+                .with_color(StickyNoteColor::Pink),
+        ));
+        let svg = export_svg(&doc, None, Size::new(200.0, 200.0));
+        assert!(svg.contains(r#"fill="rgb(251,207,232)""#));
+        assert!(svg.contains(r#"text-anchor="middle""#));
+        assert!(svg.contains(">todo</tspan>"));
+    }
+
+    #[test]
+    fn test_background_rect_only_for_colors() {
+        let mut doc = CanvasDocument::new();
+        doc.add_shape(Shape::Rectangle(Rectangle::new(
+            Point::new(0.0, 0.0),
+            10.0,
+            10.0,
+        )));
+        let size = Size::new(100.0, 100.0);
+        assert!(!export_svg(&doc, None, size).contains("<rect"));
+
+        doc.background = Some(Background::Transparent);
+        assert!(!export_svg(&doc, None, size).contains("<rect"));
+
+        doc.background = Some(Background::Color(
+            drafftink_core::shapes::SerializableColor::new(255, 0, 0, 255),
+        ));
+        assert!(
+            export_svg(&doc, None, size)
+                .contains(r#"<rect x="-20" y="-20" width="50" height="50" fill="rgb(255,0,0)""#)
+        );
+    }
+
+    #[test]
+    fn test_selection_export_bounds_cover_subset() {
+        let mut doc = CanvasDocument::new();
+        let a = Shape::Rectangle(Rectangle::new(Point::new(0.0, 0.0), 10.0, 10.0));
+        let b = Shape::Rectangle(Rectangle::new(Point::new(40.0, 0.0), 10.0, 30.0));
+        let far = Shape::Rectangle(Rectangle::new(Point::new(500.0, 500.0), 10.0, 10.0));
+        let ids = [a.id(), b.id()];
+        let mut arrow = Arrow::new(Point::new(10.0, 5.0), Point::new(500.0, 505.0));
+        arrow.start_binding = Some(a.id());
+        arrow.end_binding = Some(far.id());
+        let dangling = Shape::Arrow(arrow);
+        let dangling_id = dangling.id();
+        doc.add_shape(a);
+        doc.add_shape(b);
+        doc.add_shape(far);
+        doc.add_shape(dangling);
+
+        let svg = export_svg(&doc, Some(&ids), Size::new(100.0, 100.0));
+        assert!(svg.contains(r#"viewBox="-20 -20 90 70""#));
+        assert_eq!(svg.matches("<path ").count(), 2);
+
+        // The arrow is dropped even when selected, since its end is not.
+        let with_arrow = [ids[0], ids[1], dangling_id];
+        let svg = export_svg(&doc, Some(&with_arrow), Size::new(100.0, 100.0));
+        assert_eq!(svg.matches("<path ").count(), 2);
+    }
+}
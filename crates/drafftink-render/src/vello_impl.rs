@@ -1,16 +1,44 @@
 //! Vello-based renderer implementation.
 
-use crate::renderer::{RenderContext, Renderer, ShapeRenderer};
+use crate::fonts::FontRegistry;
+use crate::renderer::{
+    BackgroundStyle, RenderContext, RenderResult, Renderer, RendererError, ShapeRenderer,
+    export_background, shape_content_hash,
+};
 use crate::text_editor::TextEditState;
+use drafftink_core::canvas::{Background, CanvasDocument};
 use drafftink_core::selection::{Handle, HandleKind, get_handles};
-use drafftink_core::shapes::{FillPattern, Shape, ShapeStyle, ShapeTrait, StrokeStyle};
-use kurbo::{Affine, BezPath, PathEl, Point, Rect, Shape as KurboShape, Stroke};
+use drafftink_core::shapes::{
+    ElementId, FillPattern, FontFamily, FontWeight, LineCap, LineJoin, SerializableColor, Shadow,
+    Shape, ShapeId, ShapeStyle, ShapeTrait, StrokeStyle, TextAlign,
+};
+use kurbo::{Affine, BezPath, PathEl, Point, Rect, Shape as KurboShape, Size, Stroke};
 use parley::layout::PositionedLayoutItem;
 use parley::{FontContext, LayoutContext};
 use peniko::{Brush, Color, Fill};
 use roughr::core::{FillStyle, OptionsBuilder};
+use std::time::Duration;
 use vello::Scene;
 
+// Use web_time for WASM compatibility
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// Timing and workload of one `build_scene` call.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BuildStats {
+    /// Wall-clock time spent building the scene.
+    pub duration: Duration,
+    /// Top-level document elements considered (the edited shape excluded).
+    pub elements: usize,
+    /// Fragments encoded because they were new or changed.
+    pub encoded: usize,
+    /// Fragments appended to the scene, cached or freshly encoded.
+    pub drawn: usize,
+}
+
 /// Result of PNG rendering - contains the raw RGBA pixel data and dimensions.
 #[derive(Debug)]
 pub struct PngRenderResult {
@@ -22,23 +50,177 @@ pub struct PngRenderResult {
     pub height: u32,
 }
 
-/// Embedded GelPen fonts (Regular, Light, Heavy variants)
-static GELPEN_REGULAR: &[u8] = include_bytes!("../assets/GelPen.ttf");
+impl PngRenderResult {
+    /// Encode the pixels as a PNG file.
+    pub fn to_png(&self) -> RenderResult<Vec<u8>> {
+        let image = image::RgbaImage::from_raw(self.width, self.height, self.rgba_data.clone())
+            .ok_or_else(|| RendererError::RenderFailed("pixel buffer size mismatch".into()))?;
+        let mut png = Vec::new();
+        image
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .map_err(|e| RendererError::RenderFailed(format!("PNG encoding failed: {e}")))?;
+        Ok(png)
+    }
+}
+
+/// Render a Vello scene into an offscreen texture and read back its pixels.
+///
+/// `base_color` fills everything the scene leaves uncovered; pass a
+/// transparent color for a transparent background.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn render_scene_to_rgba(
+    device: &vello::wgpu::Device,
+    queue: &vello::wgpu::Queue,
+    vello_renderer: &mut vello::Renderer,
+    scene: &Scene,
+    width: u32,
+    height: u32,
+    base_color: Color,
+) -> RenderResult<PngRenderResult> {
+    if width == 0 || height == 0 {
+        return Err(RendererError::RenderFailed("empty image size".into()));
+    }
+
+    // Create offscreen texture for rendering
+    let texture = device.create_texture(&vello::wgpu::TextureDescriptor {
+        label: Some("png export texture"),
+        size: vello::wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: vello::wgpu::TextureDimension::D2,
+        format: vello::wgpu::TextureFormat::Rgba8Unorm,
+        usage: vello::wgpu::TextureUsages::STORAGE_BINDING
+            | vello::wgpu::TextureUsages::COPY_SRC
+            | vello::wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+
+    let texture_view = texture.create_view(&vello::wgpu::TextureViewDescriptor::default());
+
+    // Render the scene
+    let params = vello::RenderParams {
+        base_color,
+        width,
+        height,
+        antialiasing_method: vello::AaConfig::Area,
+    };
+
+    vello_renderer
+        .render_to_texture(device, queue, scene, &texture_view, &params)
+        .map_err(|e| RendererError::RenderFailed(format!("{e:?}")))?;
+
+    // Rows are padded to wgpu's copy alignment, which matters for widths
+    // that are not a multiple of 64 pixels
+    let bytes_per_row = (width * 4).next_multiple_of(vello::wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let buffer_size = (bytes_per_row * height) as u64;
+
+    let readback_buffer = device.create_buffer(&vello::wgpu::BufferDescriptor {
+        label: Some("png readback buffer"),
+        size: buffer_size,
+        usage: vello::wgpu::BufferUsages::COPY_DST | vello::wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    // Copy texture to buffer
+    let mut encoder = device.create_command_encoder(&vello::wgpu::CommandEncoderDescriptor {
+        label: Some("png copy encoder"),
+    });
+
+    encoder.copy_texture_to_buffer(
+        vello::wgpu::TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: vello::wgpu::Origin3d::ZERO,
+            aspect: vello::wgpu::TextureAspect::All,
+        },
+        vello::wgpu::TexelCopyBufferInfo {
+            buffer: &readback_buffer,
+            layout: vello::wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        vello::wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    queue.submit(std::iter::once(encoder.finish()));
+
+    // Map buffer and read pixels
+    let buffer_slice = readback_buffer.slice(..);
+    let (tx, rx) = std::sync::mpsc::channel();
+    buffer_slice.map_async(vello::wgpu::MapMode::Read, move |result| {
+        tx.send(result).ok();
+    });
+
+    // Wait for GPU to finish (blocking - native only)
+    let _ = device.poll(vello::wgpu::PollType::wait_indefinitely());
+
+    match rx.recv() {
+        Ok(Ok(())) => {}
+        _ => {
+            return Err(RendererError::RenderFailed(
+                "failed to map buffer for PNG readback".into(),
+            ));
+        }
+    }
+
+    let data = buffer_slice.get_mapped_range();
+
+    // Remove row padding if any
+    let mut rgba_data = Vec::with_capacity((width * height * 4) as usize);
+    for row in 0..height {
+        let row_start = (row * bytes_per_row) as usize;
+        let row_end = row_start + (width * 4) as usize;
+        rgba_data.extend_from_slice(&data[row_start..row_end]);
+    }
+
+    drop(data);
+    readback_buffer.unmap();
+
+    Ok(PngRenderResult {
+        rgba_data,
+        width,
+        height,
+    })
+}
+
+/// Embedded GelPen weight variants (Regular lives in the font registry)
 static GELPEN_LIGHT: &[u8] = include_bytes!("../assets/GelPenLight.ttf");
 static GELPEN_HEAVY: &[u8] = include_bytes!("../assets/GelPenHeavy.ttf");
-/// Embedded VanillaExtract font
-static VANILLA_EXTRACT: &[u8] = include_bytes!("../assets/VanillaExtract.ttf");
 /// Embedded GelPenSerif fonts for handwritten style
 static GELPEN_SERIF_LIGHT: &[u8] = include_bytes!("../assets/GelPenSerifLight.ttf");
-static GELPEN_SERIF_MEDIUM: &[u8] = include_bytes!("../assets/GelPenSerifMedium.ttf");
 static GELPEN_SERIF_HEAVY: &[u8] = include_bytes!("../assets/GelPenSerifHeavy.ttf");
 /// Embedded XITS Math font for LaTeX rendering
 static XITS_MATH: &[u8] = include_bytes!("../assets/rex-xits.otf");
-/// Embedded Noto Sans for UI elements
-static NOTO_SANS: &[u8] = include_bytes!("../assets/NotoSans-Regular.ttf");
+/// Embedded Noto Sans variants for UI elements
 static NOTO_SANS_BOLD: &[u8] = include_bytes!("../assets/NotoSans-Bold.ttf");
 static NOTO_SANS_ITALIC: &[u8] = include_bytes!("../assets/NotoSans-Italic.ttf");
 
+/// Font size of arrow labels, in world units.
+const ARROW_LABEL_FONT_SIZE: f64 = 16.0;
+/// Padding and corner radius of an arrow label's background box.
+const ARROW_LABEL_PADDING: f64 = 4.0;
+/// Padding around the exported content, in logical pixels.
+const EXPORT_PADDING: f64 = 20.0;
+/// Margin around the document in an overview, in target pixels.
+const OVERVIEW_MARGIN: f64 = 4.0;
+/// Shapes smaller than this in both directions, in target pixels, are left
+/// out of an overview.
+const OVERVIEW_MIN_SIZE: f64 = 0.5;
+/// Dash and gap length of the marching-ants outline, in screen pixels.
+const MARCHING_ANTS_DASH: f64 = 4.0;
+/// Speed the marching ants move along the outline, in screen pixels per second.
+const MARCHING_ANTS_SPEED: f64 = 16.0;
+
 /// Cached text layout data for rendering.
 #[derive(Clone)]
 struct CachedTextLayout {
@@ -62,18 +244,49 @@ pub struct VelloRenderer {
     selection_color: Color,
     /// Font context for text rendering (cached to avoid re-registering fonts).
     font_cx: FontContext,
+    /// Primary face of each font family, shared by text and math.
+    fonts: FontRegistry,
     /// Layout context for text rendering.
     layout_cx: LayoutContext<Brush>,
     /// Current zoom level (for zoom-independent UI elements).
     zoom: f64,
     /// Image cache to avoid re-decoding images every frame.
-    /// Key is the shape ID (as string), value is the decoded peniko ImageData.
-    image_cache: std::collections::HashMap<String, peniko::ImageData>,
+    /// Key is the image content hash, so shapes showing the same image share
+    /// one decoded ImageData (and one GPU upload, as its blob is reused).
+    image_cache: std::collections::HashMap<u64, peniko::ImageData>,
     /// Shape path cache for hand-drawn effects.
     /// Key: (shape_id, seed, stroke_index, roughness_bits, zoom_bucket)
     shape_cache: std::collections::HashMap<(String, u32, u32, u64, i32), BezPath>,
     /// Text layout cache. Key: (shape_id, content_hash)
     text_cache: std::collections::HashMap<(String, u64), CachedTextLayout>,
+    /// Encoded scene fragments per shape, in world coordinates, with the
+    /// content hash each was encoded with.
+    fragments: std::collections::HashMap<ShapeId, (u64, CachedFragment)>,
+    /// Zoom the cached fragments were encoded at.
+    fragment_zoom: f64,
+    /// On-screen font size below which text is greeked (0 = never).
+    text_lod_threshold: f64,
+    /// Fragments replayed from the cache, over the renderer's lifetime.
+    cache_hits: u64,
+    /// Fragments (re-)encoded, over the renderer's lifetime.
+    cache_misses: u64,
+    /// Shapes re-encoded by the last `build_scene`.
+    last_dirty: Vec<ShapeId>,
+    /// Whether `build_scene` records [`BuildStats`].
+    collect_stats: bool,
+    /// Stats of the last `build_scene`, when collection is on.
+    last_build_stats: Option<BuildStats>,
+    /// Set while drawing an overview, which skips shadows and arrow labels.
+    overview: bool,
+}
+
+/// A shape encoded into its own scene so unchanged shapes can be replayed.
+struct CachedFragment {
+    /// World-space bounds, used for culling.
+    bounds: Rect,
+    /// Whether selection handles were encoded.
+    selected: bool,
+    scene: Scene,
 }
 
 impl Default for VelloRenderer {
@@ -118,6 +331,41 @@ impl SimpleRng {
     }
 }
 
+/// Split a path into dash sub-paths for the given stroke style.
+///
+/// Returns `None` for solid strokes. The dash phase is anchored at the path
+/// start, so an unchanged element dashes identically on every redraw.
+fn dash_path(path: &BezPath, stroke_style: StrokeStyle, stroke_width: f64) -> Option<BezPath> {
+    let pattern = stroke_style.dash_pattern(stroke_width)?;
+    Some(kurbo::dash(path.iter(), 0.0, &pattern).collect())
+}
+
+/// Dashes of the marching-ants outline around `bounds` at `time` seconds.
+///
+/// Dash lengths are divided by `zoom` so they stay constant on screen, and the
+/// phase advances with time, wrapping every dash-and-gap period.
+fn marching_ants_path(bounds: Rect, time: f64, zoom: f64) -> BezPath {
+    let period = 2.0 * MARCHING_ANTS_DASH;
+    let phase = (time * MARCHING_ANTS_SPEED).rem_euclid(period) / zoom;
+    let dash = MARCHING_ANTS_DASH / zoom;
+    kurbo::dash(bounds.path_elements(0.1), phase, &[dash, dash]).collect()
+}
+
+/// Stroke of the given width with a line or arrow's cap and join styles.
+fn stroke_params(width: f64, cap: LineCap, join: LineJoin) -> Stroke {
+    let cap = match cap {
+        LineCap::Butt => kurbo::Cap::Butt,
+        LineCap::Round => kurbo::Cap::Round,
+        LineCap::Square => kurbo::Cap::Square,
+    };
+    let join = match join {
+        LineJoin::Miter => kurbo::Join::Miter,
+        LineJoin::Round => kurbo::Join::Round,
+        LineJoin::Bevel => kurbo::Join::Bevel,
+    };
+    Stroke::new(width).with_caps(cap).with_join(join)
+}
+
 /// Apply hand-drawn effect to a path based on roughness level.
 /// This mimics the Excalidraw/rough.js algorithm:
 /// - Endpoints are randomly offset (lines overshoot/undershoot at corners)
@@ -239,6 +487,38 @@ fn apply_hand_drawn_effect(
     result
 }
 
+/// Brush for a shape fill, with gradients laid out in `bounds`.
+fn fill_brush(fill: &drafftink_core::shapes::Fill, bounds: Rect, style: &ShapeStyle) -> Brush {
+    use drafftink_core::shapes::{Fill as ShapeFill, GradientStop};
+
+    let color = |c: SerializableColor| style.apply_opacity(c.into());
+    let stops = |stops: &[GradientStop]| -> Vec<peniko::ColorStop> {
+        stops
+            .iter()
+            .map(|s| peniko::ColorStop::from((s.offset as f32, color(s.color))))
+            .collect()
+    };
+    match fill {
+        ShapeFill::Solid(c) => Brush::Solid(color(*c)),
+        ShapeFill::LinearGradient { stops: s, angle } => {
+            let (start, end) = ShapeFill::linear_endpoints(*angle, bounds);
+            Brush::Gradient(
+                peniko::Gradient::new_linear(start, end).with_stops(stops(s).as_slice()),
+            )
+        }
+        ShapeFill::RadialGradient {
+            stops: s,
+            center,
+            radius,
+        } => {
+            let (center, radius) = ShapeFill::radial_geometry(*center, *radius, bounds);
+            Brush::Gradient(
+                peniko::Gradient::new_radial(center, radius as f32).with_stops(stops(s).as_slice()),
+            )
+        }
+    }
+}
+
 /// Generate fill pattern lines within the given bounds using roughr.
 fn generate_fill_pattern(
     pattern: FillPattern,
@@ -304,61 +584,84 @@ fn generate_fill_pattern(
     path
 }
 
+/// Parley alignment for a text alignment.
+fn parley_alignment(align: TextAlign) -> parley::Alignment {
+    match align {
+        TextAlign::Left => parley::Alignment::Left,
+        TextAlign::Center => parley::Alignment::Center,
+        TextAlign::Right => parley::Alignment::Right,
+        TextAlign::Justify => parley::Alignment::Justify,
+    }
+}
+
+/// Registered font name, weight and italic flag for a text style.
+fn parley_font(family: FontFamily, weight: FontWeight) -> (&'static str, parley::FontWeight, bool) {
+    match (family, weight) {
+        (FontFamily::GelPen, FontWeight::Light) => {
+            ("GelPenLight", parley::FontWeight::NORMAL, false)
+        }
+        (FontFamily::GelPen, FontWeight::Regular) => ("GelPen", parley::FontWeight::NORMAL, false),
+        (FontFamily::GelPen, FontWeight::Heavy) => {
+            ("GelPenHeavy", parley::FontWeight::NORMAL, false)
+        }
+        (FontFamily::NotoSans, FontWeight::Light) => {
+            ("Noto Sans", parley::FontWeight::NORMAL, true)
+        }
+        (FontFamily::NotoSans, FontWeight::Regular) => {
+            ("Noto Sans", parley::FontWeight::NORMAL, false)
+        }
+        (FontFamily::NotoSans, FontWeight::Heavy) => ("Noto Sans", parley::FontWeight::BOLD, false),
+        (FontFamily::GelPenSerif, FontWeight::Light) => {
+            ("GelPenSerifLight", parley::FontWeight::NORMAL, false)
+        }
+        (FontFamily::GelPenSerif, FontWeight::Regular) => {
+            ("GelPenSerif", parley::FontWeight::NORMAL, false)
+        }
+        (FontFamily::GelPenSerif, FontWeight::Heavy) => {
+            ("GelPenSerifHeavy", parley::FontWeight::NORMAL, false)
+        }
+        (FontFamily::VanillaExtract, _) => ("Vanilla Extract", parley::FontWeight::NORMAL, false),
+    }
+}
+
 impl VelloRenderer {
     /// Create a new Vello renderer.
     pub fn new() -> Self {
+        let fonts = FontRegistry::new();
         let mut font_cx = FontContext::new();
-        // Register all font variants
-        font_cx.collection.register_fonts(
-            vello::peniko::Blob::new(std::sync::Arc::new(GELPEN_REGULAR)),
-            None,
-        );
-        font_cx.collection.register_fonts(
-            vello::peniko::Blob::new(std::sync::Arc::new(GELPEN_LIGHT)),
-            None,
-        );
-        font_cx.collection.register_fonts(
-            vello::peniko::Blob::new(std::sync::Arc::new(GELPEN_HEAVY)),
-            None,
-        );
-        font_cx.collection.register_fonts(
-            vello::peniko::Blob::new(std::sync::Arc::new(VANILLA_EXTRACT)),
-            None,
-        );
-        font_cx.collection.register_fonts(
-            vello::peniko::Blob::new(std::sync::Arc::new(GELPEN_SERIF_LIGHT)),
-            None,
-        );
-        font_cx.collection.register_fonts(
-            vello::peniko::Blob::new(std::sync::Arc::new(GELPEN_SERIF_MEDIUM)),
-            None,
-        );
-        font_cx.collection.register_fonts(
-            vello::peniko::Blob::new(std::sync::Arc::new(GELPEN_SERIF_HEAVY)),
-            None,
-        );
-        font_cx.collection.register_fonts(
-            vello::peniko::Blob::new(std::sync::Arc::new(NOTO_SANS)),
-            None,
-        );
-        font_cx.collection.register_fonts(
-            vello::peniko::Blob::new(std::sync::Arc::new(NOTO_SANS_BOLD)),
-            None,
-        );
-        font_cx.collection.register_fonts(
-            vello::peniko::Blob::new(std::sync::Arc::new(NOTO_SANS_ITALIC)),
-            None,
-        );
+        // Register each family's primary face, then the weight variants
+        for data in fonts.font_data().chain([
+            GELPEN_LIGHT,
+            GELPEN_HEAVY,
+            GELPEN_SERIF_LIGHT,
+            GELPEN_SERIF_HEAVY,
+            NOTO_SANS_BOLD,
+            NOTO_SANS_ITALIC,
+        ]) {
+            font_cx
+                .collection
+                .register_fonts(vello::peniko::Blob::new(std::sync::Arc::new(data)), None);
+        }
 
         Self {
             scene: Scene::new(),
             selection_color: Color::from_rgba8(59, 130, 246, 255),
             font_cx,
+            fonts,
             layout_cx: LayoutContext::new(),
             zoom: 1.0,
             image_cache: std::collections::HashMap::new(),
             shape_cache: std::collections::HashMap::new(),
             text_cache: std::collections::HashMap::new(),
+            fragments: std::collections::HashMap::new(),
+            fragment_zoom: 1.0,
+            text_lod_threshold: 0.0,
+            cache_hits: 0,
+            cache_misses: 0,
+            last_dirty: Vec::new(),
+            collect_stats: false,
+            last_build_stats: None,
+            overview: false,
         }
     }
 
@@ -367,6 +670,52 @@ impl VelloRenderer {
         &self.scene
     }
 
+    /// Content hash of each cached shape fragment, for
+    /// [`RenderContext::dirty_shapes`].
+    pub fn fragment_hashes(&self) -> std::collections::HashMap<ShapeId, u64> {
+        self.fragments
+            .iter()
+            .map(|(&id, &(hash, _))| (id, hash))
+            .collect()
+    }
+
+    /// Fragment cache `(hits, misses)` accumulated over all `build_scene`
+    /// calls.
+    ///
+    /// A hit replays a shape's cached fragment; a miss encodes it because it
+    /// is new, its content or selection changed, or the zoom changed.
+    /// Off-screen shapes that are not encoded count as neither.
+    pub fn cache_stats(&self) -> (u64, u64) {
+        (self.cache_hits, self.cache_misses)
+    }
+
+    /// Shapes that were re-encoded by the last `build_scene`.
+    pub fn last_dirty(&self) -> &[ShapeId] {
+        &self.last_dirty
+    }
+
+    /// Turn [`BuildStats`] collection on or off. Off by default, in which
+    /// case `build_scene` does not read the clock.
+    pub fn set_collect_stats(&mut self, enabled: bool) {
+        self.collect_stats = enabled;
+        if !enabled {
+            self.last_build_stats = None;
+        }
+    }
+
+    /// Timing and element counts of the last `build_scene`, or `None` if
+    /// stats collection is off or no scene was built since it was enabled.
+    pub fn last_build_stats(&self) -> Option<BuildStats> {
+        self.last_build_stats
+    }
+
+    /// Encode a shape into a standalone scene in world coordinates.
+    fn encode_fragment(&mut self, shape: &Shape, selected: bool) -> Scene {
+        let outer = std::mem::take(&mut self.scene);
+        self.render_shape(shape, Affine::IDENTITY, selected);
+        std::mem::replace(&mut self.scene, outer)
+    }
+
     /// Take ownership of the scene (resets internal scene).
     pub fn take_scene(&mut self) -> Scene {
         std::mem::take(&mut self.scene)
@@ -377,30 +726,123 @@ impl VelloRenderer {
         (&mut self.font_cx, &mut self.layout_cx)
     }
 
+    /// Measure the size of `content` laid out in the default text font, as
+    /// on-screen text is drawn, wrapping at `max_width` if given.
+    ///
+    /// Empty content measures as one empty line, and a trailing newline adds
+    /// an empty line, so a text box can grow with the caret.
+    pub fn measure_text(&mut self, content: &str, font_size: f64, max_width: Option<f64>) -> Size {
+        use parley::StyleProperty;
+
+        // Parley drops a trailing empty line; lay out a space on it instead
+        let padded;
+        let layout_text = if content.is_empty() || content.ends_with('\n') {
+            padded = format!("{content} ");
+            padded.as_str()
+        } else {
+            content
+        };
+
+        let (font_name, parley_weight, is_italic) =
+            parley_font(FontFamily::default(), FontWeight::default());
+        let mut builder = self
+            .layout_cx
+            .ranged_builder(&mut self.font_cx, layout_text, 1.0, false);
+        builder.push_default(StyleProperty::FontSize(font_size as f32));
+        builder.push_default(StyleProperty::FontWeight(parley_weight));
+        if is_italic {
+            builder.push_default(StyleProperty::FontStyle(parley::FontStyle::Italic));
+        }
+        builder.push_default(StyleProperty::FontStack(parley::FontStack::Single(
+            parley::FontFamily::Named(font_name.into()),
+        )));
+
+        let mut layout = builder.build(layout_text);
+        layout.break_all_lines(max_width.map(|w| w as f32));
+
+        let width = if content.is_empty() {
+            0.0
+        } else {
+            layout.width() as f64
+        };
+        Size::new(width, layout.height() as f64)
+    }
+
+    /// Render the canvas as seen through `ctx` into a PNG of the given size.
+    ///
+    /// Uses the camera from `ctx`, so the image matches the on-screen view.
+    /// The background is [`RenderContext::clear_color`], so a transparent
+    /// document background (or a transparent `ctx.background_color`) gives a
+    /// transparent PNG. A headless GPU device is created for the call.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_to_png(
+        &mut self,
+        ctx: &RenderContext,
+        width: u32,
+        height: u32,
+    ) -> RenderResult<Vec<u8>> {
+        self.build_scene(ctx);
+
+        let mut gpu = vello::util::RenderContext::new();
+        let device_id = pollster::block_on(gpu.device(None))
+            .ok_or_else(|| RendererError::InitFailed("no GPU adapter available".into()))?;
+        let handle = &gpu.devices[device_id];
+        let mut vello_renderer =
+            vello::Renderer::new(&handle.device, vello::RendererOptions::default())
+                .map_err(|e| RendererError::InitFailed(format!("{e:?}")))?;
+
+        render_scene_to_rgba(
+            &handle.device,
+            &handle.queue,
+            &mut vello_renderer,
+            &self.scene,
+            width,
+            height,
+            ctx.clear_color(),
+        )?
+        .to_png()
+    }
+
+    /// Fill the export area with the document's export background, rounded
+    /// up to whole pixels so no partly covered edge is left. Transparent
+    /// backgrounds are left unfilled.
+    fn fill_export_background(&mut self, document: &CanvasDocument, width: f64, height: f64) {
+        let color = export_background(document);
+        if color.components[3] > 0.0 {
+            let rect = Rect::new(0.0, 0.0, width.ceil(), height.ceil());
+            self.scene
+                .fill(Fill::NonZero, Affine::IDENTITY, color, None, &rect);
+        }
+    }
+
     /// Build a scene for export (shapes only, no grid/selection/guides).
     /// Returns the scene and the scaled bounds (for texture dimensions).
     ///
+    /// `ids` limits the export to the given elements, and the output bounds
+    /// then cover only those; see [`CanvasDocument::export_shapes`] for how
+    /// arrows bound to unselected shapes are handled. `None` exports the
+    /// whole document.
+    ///
     /// `scale` is the export resolution multiplier (1 = 1x, 2 = 2x, 3 = 3x).
     pub fn build_export_scene(
         &mut self,
-        document: &drafftink_core::canvas::CanvasDocument,
+        document: &CanvasDocument,
+        ids: Option<&[ElementId]>,
         scale: f64,
     ) -> (Scene, Option<Rect>) {
         self.scene.reset();
         self.zoom = scale;
+        self.text_lod_threshold = 0.0;
 
-        let bounds = document.bounds();
+        let shapes = document.export_shapes(ids);
 
         // If no shapes, return empty scene
-        if bounds.is_none() {
+        let Some(bounds) = CanvasDocument::shapes_bounds(shapes.iter().copied()) else {
             return (std::mem::take(&mut self.scene), None);
-        }
-
-        let bounds = bounds.unwrap();
+        };
 
         // Add padding around the content (in logical pixels)
-        let padding = 20.0;
-        let padded_bounds = bounds.inflate(padding, padding);
+        let padded_bounds = bounds.inflate(EXPORT_PADDING, EXPORT_PADDING);
 
         // Transform: translate to origin, then scale up
         let transform =
@@ -410,18 +852,10 @@ impl VelloRenderer {
         let scaled_width = padded_bounds.width() * scale;
         let scaled_height = padded_bounds.height() * scale;
 
-        // Fill background with white (at scaled size)
-        let bg_rect = Rect::new(0.0, 0.0, scaled_width, scaled_height);
-        self.scene.fill(
-            Fill::NonZero,
-            Affine::IDENTITY,
-            Color::WHITE,
-            None,
-            &bg_rect,
-        );
+        self.fill_export_background(document, scaled_width, scaled_height);
 
-        // Render all shapes with scaled transform
-        for shape in document.shapes_ordered() {
+        // Render shapes with scaled transform
+        for shape in shapes {
             self.render_shape(shape, transform, false);
         }
 
@@ -430,76 +864,61 @@ impl VelloRenderer {
         (std::mem::take(&mut self.scene), Some(scaled_bounds))
     }
 
-    /// Build a scene for exporting selected shapes only.
+    /// Render the whole document scaled to fit `target_size`, for a minimap.
     ///
-    /// `scale` is the export resolution multiplier (1 = 1x, 2 = 2x, 3 = 3x).
-    pub fn build_export_scene_selection(
-        &mut self,
-        document: &drafftink_core::canvas::CanvasDocument,
-        selection: &[drafftink_core::shapes::ShapeId],
-        scale: f64,
-    ) -> (Scene, Option<Rect>) {
+    /// The scene replaces the current one (see [`scene`](Self::scene)) and
+    /// holds the background and shapes only. Text is always drawn as
+    /// placeholder bars, and shadows, arrow labels and shapes smaller than
+    /// half a pixel are left out.
+    ///
+    /// Returns the world-to-target transform, so the UI can draw the camera's
+    /// viewport over the overview and map clicks back to world space with its
+    /// inverse. Returns `None`, with an empty scene, for an empty document.
+    pub fn render_overview(&mut self, ctx: &RenderContext, target_size: Size) -> Option<Affine> {
         self.scene.reset();
-        self.zoom = scale;
-
-        if selection.is_empty() {
-            return (std::mem::take(&mut self.scene), None);
-        }
-
-        // Calculate bounds of selected shapes
-        let mut min_x = f64::MAX;
-        let mut min_y = f64::MAX;
-        let mut max_x = f64::MIN;
-        let mut max_y = f64::MIN;
-
-        let mut shapes_to_render = Vec::new();
-        for &shape_id in selection {
-            if let Some(shape) = document.get_shape(shape_id) {
-                let b = shape.bounds();
-                min_x = min_x.min(b.x0);
-                min_y = min_y.min(b.y0);
-                max_x = max_x.max(b.x1);
-                max_y = max_y.max(b.y1);
-                shapes_to_render.push(shape);
-            }
-        }
-
-        if shapes_to_render.is_empty() {
-            return (std::mem::take(&mut self.scene), None);
-        }
-
-        let bounds = Rect::new(min_x, min_y, max_x, max_y);
-
-        // Add padding around the content (in logical pixels)
-        let padding = 20.0;
-        let padded_bounds = bounds.inflate(padding, padding);
-
-        // Transform: translate to origin, then scale up
-        let transform =
-            Affine::scale(scale) * Affine::translate((-padded_bounds.x0, -padded_bounds.y0));
-
-        // Scaled output dimensions
-        let scaled_width = padded_bounds.width() * scale;
-        let scaled_height = padded_bounds.height() * scale;
+        let document = &ctx.canvas.document;
+        let bounds = document.bounds()?;
 
-        // Fill background with white (at scaled size)
-        let bg_rect = Rect::new(0.0, 0.0, scaled_width, scaled_height);
+        let available = Size::new(
+            (target_size.width - 2.0 * OVERVIEW_MARGIN).max(1.0),
+            (target_size.height - 2.0 * OVERVIEW_MARGIN).max(1.0),
+        );
+        let scale = (available.width / bounds.width().max(f64::EPSILON))
+            .min(available.height / bounds.height().max(f64::EPSILON));
+        let center = bounds.center();
+        let transform = Affine::translate((target_size.width / 2.0, target_size.height / 2.0))
+            * Affine::scale(scale)
+            * Affine::translate((-center.x, -center.y));
+
+        let background = match document.background {
+            Some(Background::Color(color)) => Color::from(color),
+            _ => ctx.background_color,
+        };
         self.scene.fill(
             Fill::NonZero,
             Affine::IDENTITY,
-            Color::WHITE,
+            background,
             None,
-            &bg_rect,
+            &target_size.to_rect(),
         );
 
-        // Render selected shapes with scaled transform
-        for shape in shapes_to_render {
+        // Swap in overview settings, restoring them afterwards so the
+        // fragment cache of the main view stays valid.
+        let zoom = std::mem::replace(&mut self.zoom, scale);
+        let text_lod_threshold = std::mem::replace(&mut self.text_lod_threshold, f64::INFINITY);
+        self.overview = true;
+        for shape in document.shapes_ordered() {
+            let size = shape.bounds().size() * scale;
+            if size.width < OVERVIEW_MIN_SIZE && size.height < OVERVIEW_MIN_SIZE {
+                continue;
+            }
             self.render_shape(shape, transform, false);
         }
+        self.overview = false;
+        self.zoom = zoom;
+        self.text_lod_threshold = text_lod_threshold;
 
-        // Return scaled bounds for texture dimensions
-        let scaled_bounds = Rect::new(0.0, 0.0, scaled_width, scaled_height);
-        (std::mem::take(&mut self.scene), Some(scaled_bounds))
+        Some(transform)
     }
 
     /// Get or compute a cached hand-drawn path.
@@ -542,41 +961,57 @@ impl VelloRenderer {
         let roughness = style.sloppiness.roughness();
         let seed = style.seed;
 
-        // Fill if present
-        if let Some(fill_color) = style.fill_with_opacity() {
+        // Fill if present; gradients override the plain fill color
+        let fill_color = style.fill_with_opacity();
+        if style.fill.is_some() || fill_color.is_some() {
             let fill_path = if roughness > 0.0 {
                 self.get_cached_hand_drawn(shape_id, path, roughness * 0.3, seed, 0)
             } else {
                 path.clone()
             };
 
-            match style.fill_pattern {
-                FillPattern::Solid => {
-                    self.scene
-                        .fill(Fill::NonZero, transform, fill_color, None, &fill_path);
-                }
-                _ => {
-                    // For patterns, first fill with a lighter version of the color as background
-                    let bg_color = Color::from_rgba8(
-                        fill_color.to_rgba8().r,
-                        fill_color.to_rgba8().g,
-                        fill_color.to_rgba8().b,
-                        (fill_color.to_rgba8().a as f32 * 0.15) as u8,
-                    );
-                    self.scene
-                        .fill(Fill::NonZero, transform, bg_color, None, &fill_path);
-
-                    // Then draw the pattern lines clipped to shape
-                    let bounds = path.bounding_box();
-                    let pattern_path =
-                        generate_fill_pattern(style.fill_pattern, bounds, style.stroke_width, seed);
+            if let Some(fill) = &style.fill {
+                let brush = fill_brush(fill, path.bounding_box(), style);
+                self.scene
+                    .fill(Fill::NonZero, transform, &brush, None, &fill_path);
+            } else if let Some(fill_color) = fill_color {
+                match style.fill_pattern {
+                    FillPattern::Solid => {
+                        self.scene
+                            .fill(Fill::NonZero, transform, fill_color, None, &fill_path);
+                    }
+                    _ => {
+                        // For patterns, first fill with a lighter version of the color as background
+                        let bg_color = Color::from_rgba8(
+                            fill_color.to_rgba8().r,
+                            fill_color.to_rgba8().g,
+                            fill_color.to_rgba8().b,
+                            (fill_color.to_rgba8().a as f32 * 0.15) as u8,
+                        );
+                        self.scene
+                            .fill(Fill::NonZero, transform, bg_color, None, &fill_path);
+
+                        // Then draw the pattern lines clipped to shape
+                        let bounds = path.bounding_box();
+                        let pattern_path = generate_fill_pattern(
+                            style.fill_pattern,
+                            bounds,
+                            style.stroke_width,
+                            seed,
+                        );
 
-                    // Clip pattern to shape boundary
-                    self.scene.push_clip_layer(transform, &fill_path);
-                    let pattern_stroke = Stroke::new(style.stroke_width * 0.5);
-                    self.scene
-                        .stroke(&pattern_stroke, transform, fill_color, None, &pattern_path);
-                    self.scene.pop_layer();
+                        // Clip pattern to shape boundary
+                        self.scene.push_clip_layer(transform, &fill_path);
+                        let pattern_stroke = Stroke::new(style.stroke_width * 0.5);
+                        self.scene.stroke(
+                            &pattern_stroke,
+                            transform,
+                            fill_color,
+                            None,
+                            &pattern_path,
+                        );
+                        self.scene.pop_layer();
+                    }
                 }
             }
         }
@@ -618,29 +1053,17 @@ impl VelloRenderer {
         path: &BezPath,
         style: &ShapeStyle,
         stroke_style: StrokeStyle,
+        cap: LineCap,
+        join: LineJoin,
         transform: Affine,
     ) {
         let roughness = style.sloppiness.roughness();
         let seed = style.seed;
-
-        // Create stroke with dash pattern based on stroke_style
-        let mut stroke = Stroke::new(style.stroke_width);
-        match stroke_style {
-            StrokeStyle::Solid => {}
-            StrokeStyle::Dashed => {
-                let dash_len = style.stroke_width * 4.0;
-                let gap_len = style.stroke_width * 2.0;
-                stroke = stroke.with_dashes(0.0, [dash_len, gap_len]);
-            }
-            StrokeStyle::Dotted => {
-                let dot_len = style.stroke_width;
-                let gap_len = style.stroke_width * 2.0;
-                stroke = stroke.with_dashes(0.0, [dot_len, gap_len]);
-            }
-        }
+        let stroke = stroke_params(style.stroke_width, cap, join);
 
         if roughness > 0.0 {
             let path1 = apply_hand_drawn_effect(path, roughness, self.zoom, seed, 0);
+            let path1 = dash_path(&path1, stroke_style, style.stroke_width).unwrap_or(path1);
             self.scene.stroke(
                 &stroke,
                 transform,
@@ -649,6 +1072,7 @@ impl VelloRenderer {
                 &path1,
             );
             let path2 = apply_hand_drawn_effect(path, roughness, self.zoom, seed, 1);
+            let path2 = dash_path(&path2, stroke_style, style.stroke_width).unwrap_or(path2);
             self.scene.stroke(
                 &stroke,
                 transform,
@@ -657,6 +1081,8 @@ impl VelloRenderer {
                 &path2,
             );
         } else {
+            let dashed = dash_path(path, stroke_style, style.stroke_width);
+            let path = dashed.as_ref().unwrap_or(path);
             self.scene
                 .stroke(&stroke, transform, style.stroke_with_opacity(), None, path);
         }
@@ -668,83 +1094,31 @@ impl VelloRenderer {
         freehand: &drafftink_core::shapes::Freehand,
         transform: Affine,
     ) {
-        use kurbo::Vec2;
-
-        if freehand.points.len() < 2 {
-            return;
-        }
-
         let style = &freehand.style;
         let base_size = style.stroke_width * 2.3;
         let thinning = 0.6;
-        let color = style.stroke_with_opacity();
-
-        // Build a filled polygon that represents the variable-width stroke
-        let mut left_points: Vec<Point> = Vec::new();
-        let mut right_points: Vec<Point> = Vec::new();
-
-        for i in 0..freehand.points.len() {
-            let point = freehand.points[i];
-            let pressure = freehand.pressure_at(i);
-            // Apply easing: sin((pressure * π) / 2)
-            let eased_pressure = (pressure * std::f64::consts::PI / 2.0).sin();
-            // Apply thinning: size * (1 - thinning * (1 - pressure))
-            let width = base_size * (1.0 - thinning * (1.0 - eased_pressure));
-
-            // Calculate perpendicular direction
-            let dir = if i == 0 {
-                // First point: use direction to next point
-                let next = freehand.points[i + 1];
-                Vec2::new(next.x - point.x, next.y - point.y)
-            } else if i == freehand.points.len() - 1 {
-                // Last point: use direction from previous point
-                let prev = freehand.points[i - 1];
-                Vec2::new(point.x - prev.x, point.y - prev.y)
-            } else {
-                // Middle points: average of incoming and outgoing directions
-                let prev = freehand.points[i - 1];
-                let next = freehand.points[i + 1];
-                Vec2::new(next.x - prev.x, next.y - prev.y)
-            };
-
-            let len = dir.hypot();
-            if len < f64::EPSILON {
-                continue;
-            }
 
-            // Perpendicular unit vector
-            let perp = Vec2::new(-dir.y / len, dir.x / len);
-            let half_width = width / 2.0;
-
-            left_points.push(Point::new(
-                point.x + perp.x * half_width,
-                point.y + perp.y * half_width,
-            ));
-            right_points.push(Point::new(
-                point.x - perp.x * half_width,
-                point.y - perp.y * half_width,
-            ));
-        }
-
-        if left_points.is_empty() {
+        let widths: Vec<f64> = (0..freehand.points.len())
+            .map(|i| {
+                // Apply easing: sin((pressure * π) / 2)
+                let eased_pressure = (freehand.pressure_at(i) * std::f64::consts::PI / 2.0).sin();
+                // Apply thinning: size * (1 - thinning * (1 - pressure))
+                base_size * (1.0 - thinning * (1.0 - eased_pressure))
+            })
+            .collect();
+        let path = crate::stroke::build_variable_width_path(&freehand.points, &widths);
+        if path.is_empty() {
             return;
         }
 
-        // Build the path: left side forward, right side backward
-        let mut path = BezPath::new();
-        path.move_to(left_points[0]);
-        for point in left_points.iter().skip(1) {
-            path.line_to(*point);
-        }
-        // Connect to right side (reversed)
-        for point in right_points.iter().rev() {
-            path.line_to(*point);
-        }
-        path.close_path();
-
         // Fill the path (no sloppiness - freehand is already hand-drawn)
-        self.scene
-            .fill(Fill::NonZero, transform, color, None, &path);
+        self.scene.fill(
+            Fill::NonZero,
+            transform,
+            style.stroke_with_opacity(),
+            None,
+            &path,
+        );
     }
 
     /// Render a text shape using Parley for proper text layout.
@@ -771,7 +1145,17 @@ impl VelloRenderer {
             return;
         }
 
-        use drafftink_core::shapes::{FontFamily, FontWeight};
+        // Text too small to read on screen: a bar is far cheaper than glyphs
+        if text.font_size * self.zoom < self.text_lod_threshold {
+            self.scene.fill(
+                Fill::NonZero,
+                transform,
+                text.style.stroke_with_opacity(),
+                None,
+                &text.bounds(),
+            );
+            return;
+        }
 
         // Build cache key from content hash
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
@@ -779,6 +1163,8 @@ impl VelloRenderer {
         (text.font_family.clone() as u8).hash(&mut hasher);
         (text.font_weight.clone() as u8).hash(&mut hasher);
         text.font_size.to_bits().hash(&mut hasher);
+        text.wrap_width.map(f64::to_bits).hash(&mut hasher);
+        text.align.hash(&mut hasher);
         text.char_colors.len().hash(&mut hasher);
         for c in &text.char_colors {
             c.is_some().hash(&mut hasher);
@@ -791,6 +1177,8 @@ impl VelloRenderer {
         text.style.stroke_color.r.hash(&mut hasher);
         text.style.stroke_color.g.hash(&mut hasher);
         text.style.stroke_color.b.hash(&mut hasher);
+        text.style.stroke_color.a.hash(&mut hasher);
+        text.style.opacity.to_bits().hash(&mut hasher);
         let cache_key = (text.id().to_string(), hasher.finish());
 
         // Check cache
@@ -816,38 +1204,7 @@ impl VelloRenderer {
         let brush = Brush::Solid(style.stroke_with_opacity());
         let font_size = text.font_size as f32;
 
-        let (font_name, parley_weight, is_italic) = match (&text.font_family, &text.font_weight) {
-            (FontFamily::GelPen, FontWeight::Light) => {
-                ("GelPenLight", parley::FontWeight::NORMAL, false)
-            }
-            (FontFamily::GelPen, FontWeight::Regular) => {
-                ("GelPen", parley::FontWeight::NORMAL, false)
-            }
-            (FontFamily::GelPen, FontWeight::Heavy) => {
-                ("GelPenHeavy", parley::FontWeight::NORMAL, false)
-            }
-            (FontFamily::NotoSans, FontWeight::Light) => {
-                ("Noto Sans", parley::FontWeight::NORMAL, true)
-            }
-            (FontFamily::NotoSans, FontWeight::Regular) => {
-                ("Noto Sans", parley::FontWeight::NORMAL, false)
-            }
-            (FontFamily::NotoSans, FontWeight::Heavy) => {
-                ("Noto Sans", parley::FontWeight::BOLD, false)
-            }
-            (FontFamily::GelPenSerif, FontWeight::Light) => {
-                ("GelPenSerifLight", parley::FontWeight::NORMAL, false)
-            }
-            (FontFamily::GelPenSerif, FontWeight::Regular) => {
-                ("GelPenSerif", parley::FontWeight::NORMAL, false)
-            }
-            (FontFamily::GelPenSerif, FontWeight::Heavy) => {
-                ("GelPenSerifHeavy", parley::FontWeight::NORMAL, false)
-            }
-            (FontFamily::VanillaExtract, _) => {
-                ("Vanilla Extract", parley::FontWeight::NORMAL, false)
-            }
-        };
+        let (font_name, parley_weight, is_italic) = parley_font(text.font_family, text.font_weight);
 
         let mut builder =
             self.layout_cx
@@ -865,7 +1222,7 @@ impl VelloRenderer {
         let mut byte_offset = 0;
         for (char_idx, ch) in text.content.chars().enumerate() {
             if let Some(Some(color)) = text.char_colors.get(char_idx) {
-                let color: peniko::Color = (*color).into();
+                let color = text.style.apply_opacity((*color).into());
                 let span_brush = Brush::Solid(color);
                 let char_len = ch.len_utf8();
                 builder.push(
@@ -876,15 +1233,21 @@ impl VelloRenderer {
             byte_offset += ch.len_utf8();
         }
 
+        // Lines break at word boundaries within the wrap width; words wider
+        // than it overflow
+        let wrap_width = text.wrap_width.map(|w| w as f32);
         let mut layout = builder.build(&text.content);
-        layout.break_all_lines(None);
+        layout.break_all_lines(wrap_width);
         layout.align(
-            None,
-            parley::Alignment::Start,
+            wrap_width,
+            parley_alignment(text.align),
             parley::AlignmentOptions::default(),
         );
 
-        let layout_width = layout.width() as f64;
+        // A wrapped text box spans its wrap width, however short its lines
+        let layout_width = text
+            .wrap_width
+            .map_or(layout.width() as f64, |w| w.max(layout.width() as f64));
         let layout_height = layout.height() as f64;
         text.set_cached_size(layout_width, layout_height);
 
@@ -974,10 +1337,10 @@ impl VelloRenderer {
     fn render_image(&mut self, image: &drafftink_core::shapes::Image, transform: Affine) {
         use std::sync::Arc;
 
-        let id_str = image.id().to_string();
+        let key = image.content_hash();
 
         // Check if we have a cached decoded image
-        let image_data = if let Some(cached) = self.image_cache.get(&id_str) {
+        let image_data = if let Some(cached) = self.image_cache.get(&key) {
             cached.clone()
         } else {
             // Decode the image data
@@ -994,7 +1357,7 @@ impl VelloRenderer {
                         height,
                         alpha_type: peniko::ImageAlphaType::Alpha,
                     };
-                    self.image_cache.insert(id_str.clone(), img_data.clone());
+                    self.image_cache.insert(key, img_data.clone());
                     img_data
                 } else {
                     // Failed to decode - draw placeholder
@@ -1017,7 +1380,8 @@ impl VelloRenderer {
             * Affine::translate((bounds.x0, bounds.y0))
             * Affine::scale_non_uniform(scale_x, scale_y);
 
-        self.scene.draw_image(&image_data.into(), image_transform);
+        let brush = peniko::ImageBrush::from(image_data).with_alpha(image.style.opacity as f32);
+        self.scene.draw_image(&brush, image_transform);
     }
 
     /// Render a placeholder for images that couldn't be loaded.
@@ -1079,8 +1443,9 @@ impl VelloRenderer {
             self.render_math_error(math, transform, "No MATH table");
             return;
         };
-        // Primary font (GelPen) for text glyphs - fallback to math font if unavailable
-        let primary_face = ttf_parser::Face::parse(GELPEN_REGULAR, 0).ok();
+        // Primary font for letters and digits, in the shape's font family;
+        // the math font covers everything else
+        let primary_face = self.fonts.face(math.font_family.name()).cloned();
 
         // Parse LaTeX
         let Ok(parse_nodes) = rex::parser::parse(&math.latex) else {
@@ -1115,7 +1480,7 @@ impl VelloRenderer {
         let mut backend = VelloBackend::new(
             &mut self.scene,
             &math_font,
-            primary_face.as_ref(),
+            primary_face.as_slice(),
             math_transform,
             color,
         );
@@ -1158,49 +1523,16 @@ impl VelloRenderer {
         transform: Affine,
         anchor: Option<Point>,
     ) {
-        use drafftink_core::shapes::{FontFamily as ShapeFontFamily, FontWeight};
-
         let style = &text.style;
         let brush = Brush::Solid(style.stroke_with_opacity());
 
         // Determine font name and parley weight based on family and weight
-        // Use same logic as render_text - all Roboto variants use "Roboto" family with weight
-        let (font_name, parley_weight, is_italic) = match (&text.font_family, &text.font_weight) {
-            (ShapeFontFamily::GelPen, FontWeight::Light) => {
-                ("GelPenLight", parley::FontWeight::NORMAL, false)
-            }
-            (ShapeFontFamily::GelPen, FontWeight::Regular) => {
-                ("GelPen", parley::FontWeight::NORMAL, false)
-            }
-            (ShapeFontFamily::GelPen, FontWeight::Heavy) => {
-                ("GelPenHeavy", parley::FontWeight::NORMAL, false)
-            }
-            (ShapeFontFamily::NotoSans, FontWeight::Light) => {
-                ("Noto Sans", parley::FontWeight::NORMAL, true)
-            }
-            (ShapeFontFamily::NotoSans, FontWeight::Regular) => {
-                ("Noto Sans", parley::FontWeight::NORMAL, false)
-            }
-            (ShapeFontFamily::NotoSans, FontWeight::Heavy) => {
-                ("Noto Sans", parley::FontWeight::BOLD, false)
-            }
-            (ShapeFontFamily::GelPenSerif, FontWeight::Light) => {
-                ("GelPenSerifLight", parley::FontWeight::NORMAL, false)
-            }
-            (ShapeFontFamily::GelPenSerif, FontWeight::Regular) => {
-                ("GelPenSerif", parley::FontWeight::NORMAL, false)
-            }
-            (ShapeFontFamily::GelPenSerif, FontWeight::Heavy) => {
-                ("GelPenSerifHeavy", parley::FontWeight::NORMAL, false)
-            }
-            (ShapeFontFamily::VanillaExtract, _) => {
-                ("Vanilla Extract", parley::FontWeight::NORMAL, false)
-            }
-        };
+        let (font_name, parley_weight, is_italic) = parley_font(text.font_family, text.font_weight);
 
         // Configure the editor styles
         edit_state.set_font_size(text.font_size as f32);
         edit_state.set_brush(brush.clone());
+        edit_state.set_width(text.wrap_width.map(|w| w as f32));
 
         // Set the font family and weight in the editor
         {
@@ -1233,7 +1565,7 @@ impl VelloRenderer {
         let mut byte_offset = 0;
         for (char_idx, ch) in editor_text.chars().enumerate() {
             if let Some(Some(color)) = text.char_colors.get(char_idx) {
-                let color: peniko::Color = (*color).into();
+                let color = text.style.apply_opacity((*color).into());
                 let span_brush = Brush::Solid(color);
                 let char_len = ch.len_utf8();
                 builder.push(
@@ -1245,7 +1577,9 @@ impl VelloRenderer {
         }
 
         let mut styled_layout = builder.build(&editor_text);
-        styled_layout.break_all_lines(None);
+        // Wrap like the rendered text; lines stay left-aligned so they match
+        // the editor's caret geometry
+        styled_layout.break_all_lines(text.wrap_width.map(|w| w as f32));
         styled_layout.align(
             None,
             parley::Alignment::Start,
@@ -1582,16 +1916,29 @@ impl VelloRenderer {
 
 impl Renderer for VelloRenderer {
     fn build_scene(&mut self, ctx: &RenderContext) {
+        let started = self.collect_stats.then(Instant::now);
+        let mut stats = BuildStats::default();
+
         // Clear the scene
         self.scene.reset();
-        self.selection_color = ctx.selection_color;
-        self.zoom = ctx.canvas.camera.zoom;
+        let camera_transform = ctx.view_transform();
+        self.zoom = camera_transform.as_coeffs()[0];
 
-        let camera_transform = ctx.canvas.camera.transform();
+        let viewport = Rect::new(0.0, 0.0, ctx.viewport_size.width, ctx.viewport_size.height);
+        // A document background covers the viewer's base color
+        if let Some(Background::Color(color)) = ctx.canvas.document.background {
+            self.scene.fill(
+                Fill::NonZero,
+                Affine::IDENTITY,
+                Color::from(color),
+                None,
+                &viewport,
+            );
+        }
+        self.render_background(ctx.background_style, viewport, camera_transform);
 
         // Draw grid based on style
         use crate::renderer::GridStyle;
-        let viewport = Rect::new(0.0, 0.0, ctx.viewport_size.width, ctx.viewport_size.height);
         match ctx.grid_style {
             GridStyle::None => {}
             GridStyle::Lines => self.render_grid_lines(viewport, camera_transform, 20.0),
@@ -1603,24 +1950,69 @@ impl Renderer for VelloRenderer {
         }
 
         // Compute world-space viewport for culling
-        let world_viewport = Rect::new(
-            -ctx.canvas.camera.offset.x / ctx.canvas.camera.zoom,
-            -ctx.canvas.camera.offset.y / ctx.canvas.camera.zoom,
-            (-ctx.canvas.camera.offset.x + ctx.viewport_size.width) / ctx.canvas.camera.zoom,
-            (-ctx.canvas.camera.offset.y + ctx.viewport_size.height) / ctx.canvas.camera.zoom,
-        );
+        let world_viewport = camera_transform.inverse().transform_rect_bbox(viewport);
+
+        // Zoom-dependent effects (hand-drawn jitter, handle sizes, text
+        // level of detail) and the selection color are baked into fragments,
+        // so drop them on change.
+        if self.fragment_zoom != self.zoom
+            || self.selection_color != ctx.selection_color
+            || self.text_lod_threshold != ctx.text_lod_threshold
+        {
+            self.fragments.clear();
+            self.fragment_zoom = self.zoom;
+            self.selection_color = ctx.selection_color;
+            self.text_lod_threshold = ctx.text_lod_threshold;
+        }
+        self.last_dirty.clear();
 
-        // Draw all shapes in z-order (skip shape being edited or off-screen)
+        // Draw all shapes in z-order (skip shape being edited or off-screen),
+        // re-encoding only shapes whose content or selection changed.
         for shape in ctx.canvas.document.shapes_ordered() {
-            if ctx.editing_shape_id == Some(shape.id()) {
+            let id = shape.id();
+            if ctx.editing_shape_id == Some(id) {
                 continue;
             }
-            // Viewport culling
-            if !shape.bounds().intersect(world_viewport).is_zero_area() {
-                let is_selected = ctx.canvas.is_selected(shape.id());
-                self.render_shape(shape, camera_transform, is_selected);
+            stats.elements += 1;
+            let is_selected = ctx.canvas.is_selected(id);
+            let hash = shape_content_hash(shape);
+            let stale = self.fragments.get(&id).is_none_or(|(cached, fragment)| {
+                *cached != hash || fragment.selected != is_selected
+            });
+            if stale {
+                let bounds = shape.bounds();
+                // Viewport culling
+                if bounds.intersect(world_viewport).is_zero_area() {
+                    continue;
+                }
+                let scene = self.encode_fragment(shape, is_selected);
+                self.fragments.insert(
+                    id,
+                    (
+                        hash,
+                        CachedFragment {
+                            bounds,
+                            selected: is_selected,
+                            scene,
+                        },
+                    ),
+                );
+                self.last_dirty.push(id);
+                self.cache_misses += 1;
+                stats.encoded += 1;
+            } else {
+                self.cache_hits += 1;
+            }
+            let (_, fragment) = &self.fragments[&id];
+            if !fragment.bounds.intersect(world_viewport).is_zero_area() {
+                self.scene.append(&fragment.scene, Some(camera_transform));
+                stats.drawn += 1;
             }
         }
+        // Evict fragments of shapes that were removed from the document
+        let document = &ctx.canvas.document;
+        self.fragments
+            .retain(|id, _| document.get_shape(*id).is_some());
 
         // Draw preview shape if tool is active
         if let Some(preview) = ctx.canvas.tool_manager.preview_shape() {
@@ -1632,6 +2024,10 @@ impl Renderer for VelloRenderer {
             self.render_selection_rect(rect, camera_transform);
         }
 
+        if let Some(time) = ctx.marching_ants {
+            self.render_marching_ants(ctx, time, camera_transform);
+        }
+
         // Draw smart guides
         if !ctx.smart_guides.is_empty() {
             self.render_smart_guides(&ctx.smart_guides, camera_transform);
@@ -1661,6 +2057,11 @@ impl Renderer for VelloRenderer {
         if let Some((pos, ref trail)) = ctx.laser_pointer {
             self.render_laser_pointer(pos, trail, camera_transform);
         }
+
+        if let Some(started) = started {
+            stats.duration = started.elapsed();
+            self.last_build_stats = Some(stats);
+        }
     }
 }
 
@@ -1714,7 +2115,7 @@ impl VelloRenderer {
 
     /// Render a small text badge (background rect + white text).
     fn render_badge(&mut self, text: &str, center: Point, bg_color: Color, transform: Affine) {
-        use parley::{PositionedLayoutItem, StyleProperty};
+        use parley::StyleProperty;
 
         let font_size = 11.0_f32;
         let padding = 3.0 / self.zoom;
@@ -1750,7 +2151,16 @@ impl VelloRenderer {
         let text_y = center.y - text_height / 2.0;
         let text_transform =
             transform * Affine::translate((text_x, text_y)) * Affine::scale(1.0 / self.zoom);
+        self.draw_layout_glyphs(&layout, text_transform, &Brush::Solid(Color::WHITE));
+    }
 
+    /// Draw the glyphs of a laid-out paragraph with a single brush.
+    fn draw_layout_glyphs(
+        &mut self,
+        layout: &parley::Layout<Brush>,
+        transform: Affine,
+        brush: &Brush,
+    ) {
         for line in layout.lines() {
             for item in line.items() {
                 let PositionedLayoutItem::GlyphRun(glyph_run) = item else {
@@ -1779,9 +2189,9 @@ impl VelloRenderer {
                 if !glyphs.is_empty() {
                     self.scene
                         .draw_glyphs(font)
-                        .brush(&Brush::Solid(Color::WHITE))
+                        .brush(brush)
                         .hint(true)
-                        .transform(text_transform)
+                        .transform(transform)
                         .font_size(font_size)
                         .normalized_coords(run.normalized_coords())
                         .draw(Fill::NonZero, glyphs.into_iter());
@@ -1790,6 +2200,53 @@ impl VelloRenderer {
         }
     }
 
+    /// Render an arrow's label centered on its anchor along the shaft, over
+    /// a rounded box so it stays legible on top of the line.
+    fn render_arrow_label(
+        &mut self,
+        arrow: &drafftink_core::shapes::Arrow,
+        label: &str,
+        transform: Affine,
+    ) {
+        use parley::StyleProperty;
+
+        let brush = Brush::Solid(arrow.style.stroke_with_opacity());
+        let (font_name, parley_weight, is_italic) =
+            parley_font(FontFamily::default(), FontWeight::default());
+        let mut builder = self
+            .layout_cx
+            .ranged_builder(&mut self.font_cx, label, 1.0, false);
+        builder.push_default(StyleProperty::FontSize(ARROW_LABEL_FONT_SIZE as f32));
+        builder.push_default(StyleProperty::Brush(brush.clone()));
+        builder.push_default(StyleProperty::FontWeight(parley_weight));
+        if is_italic {
+            builder.push_default(StyleProperty::FontStyle(parley::FontStyle::Italic));
+        }
+        builder.push_default(StyleProperty::FontStack(parley::FontStack::Single(
+            parley::FontFamily::Named(font_name.into()),
+        )));
+        let mut layout = builder.build(label);
+        layout.break_all_lines(None);
+
+        let size = Size::new(layout.width() as f64, layout.height() as f64);
+        let origin = arrow.label_anchor() - size.to_vec2() / 2.0;
+        let background = Rect::from_origin_size(origin, size)
+            .inflate(ARROW_LABEL_PADDING, ARROW_LABEL_PADDING)
+            .to_rounded_rect(ARROW_LABEL_PADDING);
+        self.scene.fill(
+            Fill::NonZero,
+            transform,
+            Color::from_rgba8(255, 255, 255, 230),
+            None,
+            &background,
+        );
+        self.draw_layout_glyphs(
+            &layout,
+            transform * Affine::translate(origin.to_vec2()),
+            &brush,
+        );
+    }
+
     /// Render smart guide lines (magenta alignment guides like Figma).
     fn render_smart_guides(
         &mut self,
@@ -2103,6 +2560,23 @@ impl VelloRenderer {
             .stroke(&stroke, transform, self.selection_color, None, &path);
     }
 
+    /// Render an animated dashed outline around the combined selection bounds.
+    fn render_marching_ants(&mut self, ctx: &RenderContext, time: f64, transform: Affine) {
+        let document = &ctx.canvas.document;
+        let selected = ctx
+            .canvas
+            .selection
+            .iter()
+            .filter_map(|&id| document.get_shape(id));
+        let Some(bounds) = CanvasDocument::shapes_bounds(selected) else {
+            return;
+        };
+        let stroke = Stroke::new(1.0 / self.zoom);
+        let ants = marching_ants_path(bounds, time, self.zoom);
+        self.scene
+            .stroke(&stroke, transform, self.selection_color, None, &ants);
+    }
+
     /// Render eraser cursor (circle showing eraser radius).
     fn render_eraser_cursor(&mut self, pos: Point, radius: f64, transform: Affine) {
         let circle = kurbo::Circle::new(pos, radius);
@@ -2157,7 +2631,78 @@ impl VelloRenderer {
     }
 }
 
+/// Smallest on-screen spacing between background grid positions, in pixels.
+/// Coarser spacing is used when zoomed out so the pattern stays cheap.
+const MIN_BACKGROUND_SPACING_PX: f64 = 8.0;
+
+/// World-space column and row positions of the background grid that lie
+/// inside the screen-space `viewport`.
+///
+/// The spacing is doubled until it is at least `MIN_BACKGROUND_SPACING_PX`
+/// on screen, so the count depends on the viewport size alone.
+fn background_grid(viewport: Rect, transform: Affine, spacing: f64) -> (Vec<f64>, Vec<f64>) {
+    let scale = transform.as_coeffs()[0];
+    if spacing <= 0.0 || scale <= 0.0 {
+        return (Vec::new(), Vec::new());
+    }
+    let mut spacing = spacing;
+    while spacing * scale < MIN_BACKGROUND_SPACING_PX {
+        spacing *= 2.0;
+    }
+
+    let inv = transform.inverse();
+    let world = Rect::from_points(
+        inv * Point::new(viewport.x0, viewport.y0),
+        inv * Point::new(viewport.x1, viewport.y1),
+    );
+    let positions = |min: f64, max: f64| -> Vec<f64> {
+        let first = (min / spacing).ceil() as i64;
+        let last = (max / spacing).floor() as i64;
+        (first..=last).map(|i| i as f64 * spacing).collect()
+    };
+    (positions(world.x0, world.x1), positions(world.y0, world.y1))
+}
+
 impl VelloRenderer {
+    /// Render the background pattern, limited to the visible viewport.
+    fn render_background(&mut self, style: BackgroundStyle, viewport: Rect, transform: Affine) {
+        let (spacing, color, dots) = match style {
+            BackgroundStyle::None => return,
+            BackgroundStyle::Dots { spacing, color } => (spacing, color, true),
+            BackgroundStyle::Lines { spacing, color } => (spacing, color, false),
+        };
+        let (xs, ys) = background_grid(viewport, transform, spacing);
+        let (Some(&x0), Some(&x1), Some(&y0), Some(&y1)) =
+            (xs.first(), xs.last(), ys.first(), ys.last())
+        else {
+            return;
+        };
+
+        // Batch everything into one path, sized to stay constant on screen
+        let mut path = BezPath::new();
+        if dots {
+            let radius = 1.0 / self.zoom;
+            for &x in &xs {
+                for &y in &ys {
+                    path.extend(kurbo::Circle::new((x, y), radius).path_elements(0.1));
+                }
+            }
+            self.scene
+                .fill(Fill::NonZero, transform, color, None, &path);
+        } else {
+            for &x in &xs {
+                path.move_to((x, y0));
+                path.line_to((x, y1));
+            }
+            for &y in &ys {
+                path.move_to((x0, y));
+                path.line_to((x1, y));
+            }
+            self.scene
+                .stroke(&Stroke::new(1.0 / self.zoom), transform, color, None, &path);
+        }
+    }
+
     /// Calculate grid bounds from viewport and transform.
     fn grid_bounds(
         &self,
@@ -2343,19 +2888,75 @@ impl VelloRenderer {
 }
 
 impl ShapeRenderer for VelloRenderer {
+    /// Draw a blurred copy of the shape's geometry behind it.
+    ///
+    /// Rectangles and images use Vello's blurred rounded rect; other paths get
+    /// an unblurred offset duplicate. The blur is in world units, so it scales
+    /// with the view transform.
+    fn render_shadow(&mut self, shape: &Shape, shadow: &Shadow, transform: Affine) {
+        let color = shape.style().apply_opacity(shadow.color.into());
+        match shape {
+            Shape::Rectangle(rect) => {
+                self.scene.draw_blurred_rounded_rect(
+                    transform,
+                    rect.as_rect(),
+                    color,
+                    rect.effective_corner_radius(),
+                    shadow.blur.max(0.0),
+                );
+            }
+            Shape::StickyNote(note) => {
+                self.scene.draw_blurred_rounded_rect(
+                    transform,
+                    note.background.as_rect(),
+                    color,
+                    note.background.effective_corner_radius(),
+                    shadow.blur.max(0.0),
+                );
+            }
+            Shape::Image(image) => {
+                self.scene.draw_blurred_rounded_rect(
+                    transform,
+                    image.bounds(),
+                    color,
+                    0.0,
+                    shadow.blur.max(0.0),
+                );
+            }
+            Shape::Ellipse(_) => {
+                self.scene
+                    .fill(Fill::NonZero, transform, color, None, &shape.to_path());
+            }
+            Shape::Line(_) | Shape::Arrow(_) | Shape::Freehand(_) => {
+                let stroke = Stroke::new(shape.style().stroke_width)
+                    .with_caps(kurbo::Cap::Round)
+                    .with_join(kurbo::Join::Round);
+                self.scene
+                    .stroke(&stroke, transform, color, None, &shape.to_path());
+            }
+            // Text, math and groups carry no fill geometry of their own.
+            Shape::Text(_) | Shape::Math(_) | Shape::Group(_) => {}
+        }
+    }
+
     fn render_shape(&mut self, shape: &Shape, transform: Affine, selected: bool) {
         // Get rotation and apply rotation transform around shape center
         let rotation = shape.rotation();
-        let shape_transform = if rotation.abs() > 0.001 {
+        let local_rotation = if rotation.abs() > 0.001 {
             let center = shape.bounds().center();
             let center_vec = kurbo::Vec2::new(center.x, center.y);
-            transform
-                * Affine::translate(center_vec)
+            Affine::translate(center_vec)
                 * Affine::rotate(rotation)
                 * Affine::translate(-center_vec)
         } else {
-            transform
+            Affine::IDENTITY
         };
+        let shape_transform = transform * local_rotation;
+
+        if let Some(shadow) = shape.style().shadow.filter(|_| !self.overview) {
+            let shadow_transform = transform * Affine::translate(shadow.offset) * local_rotation;
+            self.render_shadow(shape, &shadow, shadow_transform);
+        }
 
         // Special handling for different shape types
         match shape {
@@ -2374,11 +2975,52 @@ impl ShapeRenderer for VelloRenderer {
             }
             Shape::Line(line) => {
                 let path = shape.to_path();
-                self.render_stroke_only(&path, shape.style(), line.stroke_style, shape_transform);
+                self.render_stroke_only(
+                    &path,
+                    shape.style(),
+                    line.stroke_style,
+                    line.line_cap,
+                    line.line_join,
+                    shape_transform,
+                );
             }
             Shape::Arrow(arrow) => {
-                let path = shape.to_path();
-                self.render_stroke_only(&path, shape.style(), arrow.stroke_style, shape_transform);
+                let path = arrow.shaft_path();
+                self.render_stroke_only(
+                    &path,
+                    shape.style(),
+                    arrow.stroke_style,
+                    arrow.line_cap,
+                    arrow.line_join,
+                    shape_transform,
+                );
+                // Heads are never dashed
+                for (head_style, head) in arrow.head_paths() {
+                    if head_style.is_filled() {
+                        self.scene.fill(
+                            Fill::NonZero,
+                            shape_transform,
+                            arrow.style.stroke_with_opacity(),
+                            None,
+                            &head,
+                        );
+                    }
+                    self.render_stroke_only(
+                        &head,
+                        shape.style(),
+                        StrokeStyle::Solid,
+                        arrow.line_cap,
+                        arrow.line_join,
+                        shape_transform,
+                    );
+                }
+                if let Some(label) = arrow
+                    .label
+                    .as_deref()
+                    .filter(|l| !l.is_empty() && !self.overview)
+                {
+                    self.render_arrow_label(arrow, label, shape_transform);
+                }
             }
             Shape::Freehand(freehand) => {
                 // Freehand with pressure support
@@ -2386,10 +3028,13 @@ impl ShapeRenderer for VelloRenderer {
                     self.render_freehand_with_pressure(freehand, shape_transform);
                 } else {
                     let path = shape.to_path();
+                    // Round ends so ink strokes never end in a hard edge
                     self.render_stroke_only(
                         &path,
                         shape.style(),
                         StrokeStyle::Solid,
+                        LineCap::Round,
+                        LineJoin::Round,
                         shape_transform,
                     );
                 }
@@ -2397,6 +3042,16 @@ impl ShapeRenderer for VelloRenderer {
             Shape::Math(math) => {
                 self.render_math(math, shape_transform);
             }
+            Shape::StickyNote(note) => {
+                let path = shape.to_path();
+                self.render_path_cached(
+                    &shape.id().to_string(),
+                    &path,
+                    shape.style(),
+                    shape_transform,
+                );
+                self.render_text(&note.text, shape_transform);
+            }
             _ => {
                 let path = shape.to_path();
                 self.render_path_cached(
@@ -2457,8 +3112,143 @@ impl VelloRenderer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::renderer::GridStyle;
     use drafftink_core::canvas::Canvas;
-    use drafftink_core::shapes::Rectangle;
+    use drafftink_core::shapes::{Rectangle, Text};
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ::image::RgbaImage::from_pixel(width, height, ::image::Rgba([255, 0, 0, 255]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                ::image::ImageFormat::Png,
+            )
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_image_cache_shared_by_content() {
+        use drafftink_core::shapes::{Image, ImageFormat};
+
+        let mut renderer = VelloRenderer::new();
+        let png = png_bytes(2, 2);
+        let a = Image::new(Point::ZERO, &png, 2, 2, ImageFormat::Png);
+        let b = Image::new(Point::new(10.0, 0.0), &png, 2, 2, ImageFormat::Png);
+        renderer.render_image(&a, Affine::IDENTITY);
+        renderer.render_image(&b, Affine::IDENTITY);
+        renderer.render_image(&a, Affine::IDENTITY);
+        assert_eq!(renderer.image_cache.len(), 1);
+        let blob_id = renderer.image_cache[&a.content_hash()].data.id();
+
+        // Different pixels get their own entry; the shared blob is untouched
+        let c = Image::new(Point::ZERO, &png_bytes(3, 1), 3, 1, ImageFormat::Png);
+        renderer.render_image(&c, Affine::IDENTITY);
+        assert_eq!(renderer.image_cache.len(), 2);
+        assert_eq!(renderer.image_cache[&a.content_hash()].data.id(), blob_id);
+    }
+
+    #[test]
+    fn test_stroke_params_map_cap_and_join() {
+        let stroke = stroke_params(3.0, LineCap::Round, LineJoin::Miter);
+        assert_eq!(stroke.width, 3.0);
+        assert_eq!(stroke.start_cap, kurbo::Cap::Round);
+        assert_eq!(stroke.end_cap, kurbo::Cap::Round);
+        assert_eq!(stroke.join, kurbo::Join::Miter);
+
+        let stroke = stroke_params(1.0, LineCap::Butt, LineJoin::Bevel);
+        assert_eq!(stroke.start_cap, kurbo::Cap::Butt);
+        assert_eq!(stroke.join, kurbo::Join::Bevel);
+    }
+
+    #[test]
+    fn test_marching_ants_phase_follows_time() {
+        let bounds = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let first_start = |path: &BezPath| match path.elements().first() {
+            Some(&PathEl::MoveTo(p)) => p,
+            other => panic!("expected a move, got {other:?}"),
+        };
+
+        let at_rest = marching_ants_path(bounds, 0.0, 1.0);
+        // 0.3 s puts the path start in a gap, so the first dash starts later.
+        let moved = marching_ants_path(bounds, 0.3, 1.0);
+        assert_ne!(first_start(&at_rest), first_start(&moved));
+        // A full dash period later the ants look the same again.
+        let period = 2.0 * MARCHING_ANTS_DASH / MARCHING_ANTS_SPEED;
+        let wrapped = marching_ants_path(bounds, period, 1.0);
+        assert!((first_start(&wrapped) - first_start(&at_rest)).hypot() < 1e-9);
+
+        // Dashes keep their screen length when zoomed in.
+        let zoomed = marching_ants_path(Rect::new(0.0, 0.0, 50.0, 25.0), 0.0, 2.0);
+        let dashes = |path: &BezPath| {
+            path.elements()
+                .iter()
+                .filter(|el| matches!(el, PathEl::MoveTo(_)))
+                .count()
+        };
+        assert_eq!(dashes(&zoomed), dashes(&at_rest));
+    }
+
+    #[test]
+    fn test_overview_fits_document_in_target() {
+        let mut canvas = Canvas::new();
+        canvas.document.add_shape(Shape::Rectangle(Rectangle::new(
+            Point::new(-500.0, 200.0),
+            400.0,
+            100.0,
+        )));
+        canvas.document.add_shape(Shape::Text(Text::new(
+            Point::new(1500.0, 900.0),
+            "far away".to_string(),
+        )));
+        let bounds = canvas.document.bounds().unwrap();
+        let target = Size::new(200.0, 150.0);
+        let ctx = RenderContext::new(&canvas, Size::new(800.0, 600.0));
+
+        let mut renderer = VelloRenderer::new();
+        let transform = renderer.render_overview(&ctx, target).unwrap();
+        let mapped = transform.transform_rect_bbox(bounds);
+        let frame = target.to_rect();
+        assert!(mapped.x0 >= frame.x0 - 1e-9 && mapped.y0 >= frame.y0 - 1e-9);
+        assert!(mapped.x1 <= frame.x1 + 1e-9 && mapped.y1 <= frame.y1 + 1e-9);
+        // Fits the limiting axis edge to edge, minus the margin.
+        assert!((mapped.width() - (target.width - 2.0 * OVERVIEW_MARGIN)).abs() < 1e-6);
+        // Text is greeked, so no glyphs are encoded.
+        assert!(renderer.scene().encoding().resources.glyph_runs.is_empty());
+
+        let empty = Canvas::new();
+        let ctx = RenderContext::new(&empty, Size::new(800.0, 600.0));
+        assert!(renderer.render_overview(&ctx, target).is_none());
+    }
+
+    #[test]
+    fn test_measure_text_lines() {
+        let mut renderer = VelloRenderer::new();
+        let one = renderer.measure_text("Hello", 20.0, None);
+        let two = renderer.measure_text("Hello\nWorld", 20.0, None);
+        assert!(one.width > 0.0 && one.height > 0.0);
+        assert!(
+            (two.height / one.height - 2.0).abs() < 0.1,
+            "{one:?} vs {two:?}"
+        );
+
+        // Empty text is one empty line; a trailing newline adds a line
+        let empty = renderer.measure_text("", 20.0, None);
+        assert_eq!(empty.width, 0.0);
+        assert!((empty.height - one.height).abs() < 0.5);
+        let trailing = renderer.measure_text("Hello\n", 20.0, None);
+        assert!((trailing.height - two.height).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_measure_text_wraps() {
+        let mut renderer = VelloRenderer::new();
+        let text = "the quick brown fox jumps over the lazy dog";
+        let unwrapped = renderer.measure_text(text, 20.0, None);
+        let wrapped = renderer.measure_text(text, 20.0, Some(unwrapped.width / 2.0));
+        assert!(wrapped.width <= unwrapped.width / 2.0 + 1.0);
+        assert!(wrapped.height > unwrapped.height);
+    }
 
     #[test]
     fn test_renderer_creation() {
@@ -2487,4 +3277,314 @@ mod tests {
         let ctx = RenderContext::new(&canvas, kurbo::Size::new(800.0, 600.0));
         renderer.build_scene(&ctx);
     }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_render_to_png_intro() {
+        let mut canvas = Canvas::new();
+        canvas.document = drafftink_core::canvas::CanvasDocument::from_json(include_str!(
+            "../../drafftink-app/assets/intro.json"
+        ))
+        .unwrap();
+        // Odd width exercises readback row padding.
+        let ctx = RenderContext::new(&canvas, kurbo::Size::new(301.0, 203.0));
+
+        let mut renderer = VelloRenderer::new();
+        let png = match renderer.render_to_png(&ctx, 301, 203) {
+            Ok(png) => png,
+            // Headless machines without a GPU adapter cannot run this test.
+            Err(RendererError::InitFailed(_)) => return,
+            Err(e) => panic!("render failed: {e}"),
+        };
+
+        let decoded = image::load_from_memory(&png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (301, 203));
+    }
+
+    #[test]
+    fn test_transparent_background_png_has_zero_alpha() {
+        let mut canvas = Canvas::new();
+        canvas.document.background = Some(Background::Transparent);
+        canvas.document.add_shape(Shape::Rectangle(Rectangle::new(
+            Point::new(100.0, 100.0),
+            20.0,
+            20.0,
+        )));
+        let ctx =
+            RenderContext::new(&canvas, kurbo::Size::new(200.0, 200.0)).with_grid(GridStyle::None);
+        assert_eq!(ctx.clear_color(), Color::TRANSPARENT);
+
+        let mut renderer = VelloRenderer::new();
+        let png = match renderer.render_to_png(&ctx, 200, 200) {
+            Ok(png) => png,
+            // Headless machines without a GPU adapter cannot run this test.
+            Err(RendererError::InitFailed(_)) => return,
+            Err(e) => panic!("render failed: {e}"),
+        };
+
+        let decoded = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(5, 5).0[3], 0);
+    }
+
+    #[test]
+    fn test_linear_gradient_fill_uses_gradient_brush() {
+        use drafftink_core::shapes::{Fill as ShapeFill, GradientStop, Sloppiness};
+
+        let build = |fill: Option<ShapeFill>| {
+            let mut rect = Rectangle::new(Point::new(10.0, 10.0), 100.0, 50.0);
+            rect.style.sloppiness = Sloppiness::Architect;
+            rect.style.fill_color = Some(SerializableColor::white());
+            rect.style.fill = fill;
+            let mut canvas = Canvas::new();
+            canvas.document.add_shape(Shape::Rectangle(rect));
+            let mut renderer = VelloRenderer::new();
+            renderer.build_scene(
+                &RenderContext::new(&canvas, kurbo::Size::new(800.0, 600.0))
+                    .with_grid(GridStyle::None),
+            );
+            renderer.scene().encoding().resources.color_stops.len()
+        };
+
+        assert_eq!(build(None), 0);
+        let gradient = ShapeFill::LinearGradient {
+            stops: vec![
+                GradientStop::new(0.0, SerializableColor::new(255, 0, 0, 255)),
+                GradientStop::new(1.0, SerializableColor::new(0, 0, 255, 255)),
+            ],
+            angle: 0.0,
+        };
+        assert_eq!(build(Some(gradient)), 2);
+    }
+
+    #[test]
+    fn test_shadow_adds_fill_behind_shape() {
+        use drafftink_core::shapes::{Shadow, Sloppiness};
+
+        let build = |shadow: Option<Shadow>| {
+            let mut rect = Rectangle::new(Point::new(10.0, 10.0), 100.0, 50.0);
+            rect.style.sloppiness = Sloppiness::Architect;
+            rect.style.fill_color = Some(SerializableColor::white());
+            rect.style.shadow = shadow;
+            let mut canvas = Canvas::new();
+            canvas.document.add_shape(Shape::Rectangle(rect));
+            let mut renderer = VelloRenderer::new();
+            renderer.build_scene(
+                &RenderContext::new(&canvas, kurbo::Size::new(800.0, 600.0))
+                    .with_grid(GridStyle::None),
+            );
+            renderer.scene().encoding().n_paths
+        };
+
+        assert_eq!(build(Some(Shadow::default())), build(None) + 1);
+    }
+
+    #[test]
+    fn test_arrow_label_draws_box_and_glyphs() {
+        use drafftink_core::shapes::Arrow;
+
+        let build = |label: Option<&str>| {
+            let mut arrow = Arrow::new(Point::new(10.0, 10.0), Point::new(300.0, 10.0));
+            arrow.label = label.map(str::to_string);
+            let mut canvas = Canvas::new();
+            canvas.document.add_shape(Shape::Arrow(arrow));
+            let mut renderer = VelloRenderer::new();
+            renderer.build_scene(
+                &RenderContext::new(&canvas, kurbo::Size::new(800.0, 600.0))
+                    .with_grid(GridStyle::None),
+            );
+            let encoding = renderer.scene().encoding();
+            (encoding.n_paths, encoding.resources.glyph_runs.len())
+        };
+
+        let (plain_paths, plain_runs) = build(None);
+        assert_eq!(plain_runs, 0);
+        let (paths, runs) = build(Some("yes"));
+        assert!(paths > plain_paths);
+        assert!(runs > 0);
+        // An empty label draws nothing
+        assert_eq!(build(Some("")), (plain_paths, plain_runs));
+    }
+
+    #[test]
+    fn test_text_lod_greeks_small_text() {
+        let mut canvas = Canvas::new();
+        canvas.document.add_shape(Shape::Text(Text::new(
+            Point::new(10.0, 10.0),
+            "Hello".to_string(),
+        )));
+        let size = kurbo::Size::new(800.0, 600.0);
+
+        // Default font size at full zoom renders glyphs
+        let mut renderer = VelloRenderer::new();
+        renderer.build_scene(&RenderContext::new(&canvas, size));
+        assert!(!renderer.scene().encoding().resources.glyph_runs.is_empty());
+
+        // Far zoomed out, the text becomes a single filled rect
+        canvas.camera.zoom = 0.1;
+        let mut renderer = VelloRenderer::new();
+        renderer.build_scene(&RenderContext::new(&canvas, size).with_grid(GridStyle::None));
+        let encoding = renderer.scene().encoding();
+        assert!(encoding.resources.glyph_runs.is_empty());
+        assert!(encoding.n_paths >= 1);
+
+        // A zero threshold always draws glyphs
+        renderer.build_scene(
+            &RenderContext::new(&canvas, size)
+                .with_grid(GridStyle::None)
+                .with_text_lod_threshold(0.0),
+        );
+        assert!(!renderer.scene().encoding().resources.glyph_runs.is_empty());
+    }
+
+    #[test]
+    fn test_cache_stats_counts_edits() {
+        let mut renderer = VelloRenderer::new();
+        let mut canvas = Canvas::new();
+        let ids: Vec<_> = (0..3)
+            .map(|i| {
+                let rect = Rectangle::new(Point::new(100.0 * i as f64, 100.0), 50.0, 50.0);
+                let shape = Shape::Rectangle(rect);
+                let id = shape.id();
+                canvas.document.add_shape(shape);
+                id
+            })
+            .collect();
+        let size = kurbo::Size::new(800.0, 600.0);
+
+        renderer.build_scene(&RenderContext::new(&canvas, size));
+        assert_eq!(renderer.cache_stats(), (0, 3));
+        renderer.build_scene(&RenderContext::new(&canvas, size));
+        assert_eq!(renderer.cache_stats(), (3, 3));
+
+        if let Some(Shape::Rectangle(rect)) = canvas.document.get_shape_mut(ids[2]) {
+            rect.width += 5.0;
+        }
+        renderer.build_scene(&RenderContext::new(&canvas, size));
+        assert_eq!(renderer.cache_stats(), (5, 4));
+
+        // Removed shapes are evicted
+        canvas.document.remove_shape(ids[0]);
+        renderer.build_scene(&RenderContext::new(&canvas, size));
+        assert_eq!(renderer.cache_stats(), (7, 4));
+        assert!(!renderer.fragment_hashes().contains_key(&ids[0]));
+    }
+
+    #[test]
+    fn test_build_stats_count_intro_elements() {
+        let document =
+            CanvasDocument::from_json(include_str!("../../drafftink-app/assets/intro.json"))
+                .unwrap();
+        let elements = document.len();
+        let mut canvas = Canvas::with_document(document);
+        canvas.set_viewport_size(800.0, 600.0);
+        canvas.fit_to_content();
+        let ctx = RenderContext::new(&canvas, canvas.viewport_size);
+
+        let mut renderer = VelloRenderer::new();
+        renderer.build_scene(&ctx);
+        assert_eq!(renderer.last_build_stats(), None);
+
+        renderer.set_collect_stats(true);
+        renderer.build_scene(&ctx);
+        let stats = renderer.last_build_stats().unwrap();
+        assert_eq!(stats.elements, elements);
+        assert!(stats.drawn > 0 && stats.drawn <= elements);
+        // Everything was cached by the first build
+        assert_eq!(stats.encoded, 0);
+    }
+
+    #[test]
+    fn test_build_scene_reencodes_only_dirty_shapes() {
+        let mut renderer = VelloRenderer::new();
+        let mut canvas = Canvas::new();
+        let ids: Vec<_> = (0..3)
+            .map(|i| {
+                let rect = Rectangle::new(Point::new(100.0 * i as f64, 100.0), 50.0, 50.0);
+                let shape = Shape::Rectangle(rect);
+                let id = shape.id();
+                canvas.document.add_shape(shape);
+                id
+            })
+            .collect();
+        let size = kurbo::Size::new(800.0, 600.0);
+
+        renderer.build_scene(&RenderContext::new(&canvas, size));
+        assert_eq!(renderer.last_dirty().len(), 3);
+
+        renderer.build_scene(&RenderContext::new(&canvas, size));
+        assert!(renderer.last_dirty().is_empty());
+
+        if let Some(Shape::Rectangle(rect)) = canvas.document.get_shape_mut(ids[1]) {
+            rect.position.x += 10.0;
+        }
+        let ctx = RenderContext::new(&canvas, size);
+        assert_eq!(ctx.dirty_shapes(&renderer.fragment_hashes()), vec![ids[1]]);
+        renderer.build_scene(&ctx);
+        assert_eq!(renderer.last_dirty(), &[ids[1]]);
+
+        // Selection changes re-encode handles without touching content.
+        canvas.select(ids[0]);
+        renderer.build_scene(&RenderContext::new(&canvas, size));
+        assert_eq!(renderer.last_dirty(), &[ids[0]]);
+    }
+
+    #[test]
+    fn test_background_grid_scales_with_viewport() {
+        let transform = Affine::scale(1.0);
+        let small = background_grid(Rect::new(0.0, 0.0, 400.0, 300.0), transform, 20.0);
+        let large = background_grid(Rect::new(0.0, 0.0, 800.0, 600.0), transform, 20.0);
+        assert_eq!((small.0.len(), small.1.len()), (21, 16));
+        assert_eq!((large.0.len(), large.1.len()), (41, 31));
+
+        // Panning shifts positions but keeps them inside the viewport.
+        let panned = background_grid(
+            Rect::new(0.0, 0.0, 400.0, 300.0),
+            Affine::translate((-1005.0, 0.0)),
+            20.0,
+        );
+        assert_eq!(panned.0.first(), Some(&1020.0));
+        assert!(panned.0.iter().all(|&x| (1005.0..=1405.0).contains(&x)));
+
+        // Zoomed far out, spacing is coarsened instead of drawing millions of dots.
+        let far = background_grid(
+            Rect::new(0.0, 0.0, 800.0, 600.0),
+            Affine::scale(0.001),
+            20.0,
+        );
+        assert!(far.0.len() * far.1.len() <= 101 * 76);
+    }
+
+    fn subpath_count(path: &BezPath) -> usize {
+        path.elements()
+            .iter()
+            .filter(|el| matches!(el, PathEl::MoveTo(_)))
+            .count()
+    }
+
+    #[test]
+    fn test_dash_path_splits_line() {
+        let mut path = BezPath::new();
+        path.move_to((0.0, 0.0));
+        path.line_to((100.0, 0.0));
+
+        assert!(dash_path(&path, StrokeStyle::Solid, 2.0).is_none());
+
+        // Dash 8, gap 4: 100 units start 9 dashes.
+        let dashed = dash_path(&path, StrokeStyle::DASHED, 2.0).unwrap();
+        assert_eq!(subpath_count(&dashed), 9);
+        // Stable phase: the first dash starts at the path start.
+        assert_eq!(dashed.elements()[0], PathEl::MoveTo(Point::new(0.0, 0.0)));
+        assert_eq!(dash_path(&path, StrokeStyle::DASHED, 2.0).unwrap(), dashed);
+    }
+
+    #[test]
+    fn test_dash_path_elbow_route() {
+        let mut path = BezPath::new();
+        path.move_to((0.0, 0.0));
+        path.line_to((50.0, 0.0));
+        path.line_to((50.0, 50.0));
+
+        let dashed = dash_path(&path, StrokeStyle::Dotted, 1.0).unwrap();
+        assert!(subpath_count(&dashed) > 20);
+    }
 }
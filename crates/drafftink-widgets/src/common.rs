@@ -1,50 +1,210 @@
 //! Common UI components: buttons and text inputs.
 
-use egui::{Color32, CornerRadius, Stroke, TextEdit, Ui, Vec2};
+use std::ops::RangeInclusive;
+
+use egui::{Align2, Color32, CornerRadius, CursorIcon, FontId, Sense, Stroke, TextEdit, Ui, Vec2};
+
+/// Height of the common buttons; icon buttons are square at this size.
+pub const BUTTON_HEIGHT: f32 = 32.0;
+
+/// Corner radius shared by the common buttons.
+const BUTTON_RADIUS: u8 = 6;
+
+/// Fill of primary buttons and active toggles.
+const PRIMARY_FILL: Color32 = Color32::from_rgb(59, 130, 246);
+
+/// Opacity applied to disabled buttons.
+const DISABLED_OPACITY: f32 = 0.4;
+
+/// Border shown around hovered buttons and inputs.
+const HOVER_STROKE: Stroke = Stroke {
+    width: 1.0,
+    color: Color32::from_gray(180),
+};
 
 /// Primary button (blue background, white text).
 pub fn primary_btn(ui: &mut Ui, text: &str) -> bool {
-    ui.add(
-        egui::Button::new(egui::RichText::new(text).color(Color32::WHITE))
-            .fill(Color32::from_rgb(59, 130, 246))
-            .min_size(Vec2::new(80.0, 32.0))
-            .corner_radius(CornerRadius::same(6)),
+    primary_btn_enabled(ui, text, true)
+}
+
+/// Primary button that is grayed out and never clicks when `enabled` is false.
+pub fn primary_btn_enabled(ui: &mut Ui, text: &str, enabled: bool) -> bool {
+    ui.add_enabled(
+        enabled,
+        egui::Button::new(egui::RichText::new(text).color(fade(Color32::WHITE, enabled)))
+            .fill(fade(PRIMARY_FILL, enabled))
+            .min_size(Vec2::new(80.0, BUTTON_HEIGHT))
+            .corner_radius(CornerRadius::same(BUTTON_RADIUS)),
     )
     .clicked()
 }
 
 /// Secondary button (gray background, gray text).
 pub fn secondary_btn(ui: &mut Ui, text: &str) -> bool {
-    ui.add(
-        egui::Button::new(egui::RichText::new(text).color(Color32::from_gray(100)))
-            .fill(Color32::from_gray(240))
-            .min_size(Vec2::new(80.0, 32.0))
-            .corner_radius(CornerRadius::same(6)),
+    secondary_btn_enabled(ui, text, true)
+}
+
+/// Secondary button that is grayed out and never clicks when `enabled` is false.
+pub fn secondary_btn_enabled(ui: &mut Ui, text: &str, enabled: bool) -> bool {
+    ui.add_enabled(
+        enabled,
+        egui::Button::new(egui::RichText::new(text).color(fade(Color32::from_gray(100), enabled)))
+            .fill(fade(Color32::from_gray(240), enabled))
+            .min_size(Vec2::new(80.0, BUTTON_HEIGHT))
+            .corner_radius(CornerRadius::same(BUTTON_RADIUS)),
     )
     .clicked()
 }
 
+/// Reduce a color's opacity for disabled widgets.
+fn fade(color: Color32, enabled: bool) -> Color32 {
+    if enabled {
+        color
+    } else {
+        color.gamma_multiply(DISABLED_OPACITY)
+    }
+}
+
+/// Toggle button: primary style when on, secondary style when off.
+///
+/// Flips `state` on click and returns whether it changed this frame. The
+/// new state is painted in the same frame.
+pub fn toggle_btn(ui: &mut Ui, text: &str, state: &mut bool) -> bool {
+    let font_id = FontId::proportional(14.0);
+    let galley =
+        ui.painter()
+            .layout_no_wrap(text.to_string(), font_id.clone(), Color32::PLACEHOLDER);
+    let size = Vec2::new((galley.size().x + 16.0).max(80.0), BUTTON_HEIGHT);
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+
+    let changed = response.clicked();
+    if changed {
+        *state = !*state;
+    }
+
+    if ui.is_rect_visible(rect) {
+        let (fill, text_color) = if *state {
+            (PRIMARY_FILL, Color32::WHITE)
+        } else {
+            (Color32::from_gray(240), Color32::from_gray(100))
+        };
+        let radius = CornerRadius::same(BUTTON_RADIUS);
+        ui.painter().rect_filled(rect, radius, fill);
+        if response.hovered() {
+            ui.painter()
+                .rect_stroke(rect, radius, HOVER_STROKE, egui::StrokeKind::Inside);
+        }
+        ui.painter().text(
+            rect.center(),
+            Align2::CENTER_CENTER,
+            text,
+            font_id,
+            text_color,
+        );
+    }
+
+    response.on_hover_cursor(CursorIcon::PointingHand);
+    changed
+}
+
 /// Default button (frameless close button).
 pub fn default_btn(ui: &mut Ui, text: &str) -> bool {
-    ui.add(
+    default_btn_enabled(ui, text, true)
+}
+
+/// Default button that is grayed out and never clicks when `enabled` is false.
+pub fn default_btn_enabled(ui: &mut Ui, text: &str, enabled: bool) -> bool {
+    ui.add_enabled(
+        enabled,
         egui::Button::new(
             egui::RichText::new(text)
                 .size(16.0)
-                .color(Color32::from_gray(100)),
+                .color(fade(Color32::from_gray(100), enabled)),
         )
         .frame(false),
     )
     .clicked()
 }
 
+/// Icon button (square, frameless until hovered) with a hover tooltip.
+pub fn icon_btn(ui: &mut Ui, icon: &str, tooltip: &str) -> bool {
+    icon_btn_enabled(ui, icon, tooltip, true)
+}
+
+/// Icon button that is grayed out and never clicks when `enabled` is false.
+pub fn icon_btn_enabled(ui: &mut Ui, icon: &str, tooltip: &str, enabled: bool) -> bool {
+    let sense = if enabled {
+        Sense::click()
+    } else {
+        Sense::hover()
+    };
+    let (rect, response) = ui.allocate_exact_size(Vec2::splat(BUTTON_HEIGHT), sense);
+
+    if ui.is_rect_visible(rect) {
+        let radius = CornerRadius::same(BUTTON_RADIUS);
+        if enabled && response.hovered() {
+            ui.painter()
+                .rect_filled(rect, radius, Color32::from_gray(240));
+            ui.painter()
+                .rect_stroke(rect, radius, HOVER_STROKE, egui::StrokeKind::Inside);
+        }
+        ui.painter().text(
+            rect.center(),
+            Align2::CENTER_CENTER,
+            icon,
+            FontId::proportional(16.0),
+            fade(Color32::from_gray(100), enabled),
+        );
+    }
+
+    let clicked = enabled && response.clicked();
+    let response = response.on_hover_text(tooltip);
+    if enabled {
+        response.on_hover_cursor(CursorIcon::PointingHand);
+    }
+    clicked
+}
+
+/// Row of preset color swatches followed by a color picker button.
+///
+/// Clicking a preset sets `color` directly; the matching preset is outlined
+/// in the accent blue. Returns true when the color changed.
+pub fn color_swatch(ui: &mut Ui, color: &mut Color32, presets: &[Color32]) -> bool {
+    ui.horizontal(|ui| {
+        let mut changed = false;
+        for &preset in presets {
+            let (rect, response) =
+                ui.allocate_exact_size(Vec2::splat(BUTTON_HEIGHT), Sense::click());
+            if response.clicked() && *color != preset {
+                *color = preset;
+                changed = true;
+            }
+
+            if ui.is_rect_visible(rect) {
+                let radius = CornerRadius::same(BUTTON_RADIUS);
+                let stroke = if *color == preset {
+                    Stroke::new(2.0, PRIMARY_FILL)
+                } else if response.hovered() {
+                    HOVER_STROKE
+                } else {
+                    Stroke::new(1.0, Color32::from_gray(220))
+                };
+                ui.painter().rect_filled(rect.shrink(2.0), radius, preset);
+                ui.painter()
+                    .rect_stroke(rect, radius, stroke, egui::StrokeKind::Inside);
+            }
+            response.on_hover_cursor(CursorIcon::PointingHand);
+        }
+        changed |= ui.color_edit_button_srgba(color).changed();
+        changed
+    })
+    .inner
+}
+
 /// Single-line text input with modern styling.
 pub fn input_text(ui: &mut Ui, text: &mut String, width: f32, hint: &str) -> egui::Response {
     ui.scope(|ui| {
-        ui.visuals_mut().widgets.inactive.bg_stroke = Stroke::new(1.0, Color32::from_gray(220));
-        ui.visuals_mut().widgets.hovered.bg_stroke = Stroke::new(1.0, Color32::from_gray(180));
-        ui.visuals_mut().widgets.active.bg_stroke =
-            Stroke::new(1.0, Color32::from_rgb(59, 130, 246));
-
+        style_input(ui);
         ui.add(
             TextEdit::singleline(text)
                 .desired_width(width)
@@ -56,3 +216,204 @@ pub fn input_text(ui: &mut Ui, text: &mut String, width: f32, hint: &str) -> egu
     })
     .inner
 }
+
+/// Multi-line text input styled like [`input_text`], sized to `rows` lines.
+///
+/// Enter inserts a newline rather than committing the edit.
+pub fn input_text_multiline(
+    ui: &mut Ui,
+    text: &mut String,
+    width: f32,
+    rows: usize,
+    hint: &str,
+) -> egui::Response {
+    ui.scope(|ui| {
+        style_input(ui);
+        ui.add(
+            TextEdit::multiline(text)
+                .desired_width(width)
+                .desired_rows(rows)
+                .text_color(Color32::from_gray(30))
+                .background_color(Color32::WHITE)
+                .hint_text(hint)
+                .frame(true),
+        )
+    })
+    .inner
+}
+
+/// Numeric input clamped to `range`.
+///
+/// `value` follows every parsable edit; the typed text is kept while the
+/// field has focus and replaced by the last valid value once it loses focus.
+pub fn number_input(
+    ui: &mut Ui,
+    value: &mut f64,
+    width: f32,
+    range: RangeInclusive<f64>,
+) -> egui::Response {
+    let id = ui.next_auto_id();
+    let mut text = ui
+        .data_mut(|d| d.get_temp::<String>(id))
+        .unwrap_or_else(|| value.to_string());
+
+    let response = ui
+        .scope(|ui| {
+            style_input(ui);
+            ui.add(
+                TextEdit::singleline(&mut text)
+                    .id(id)
+                    .desired_width(width)
+                    .text_color(Color32::from_gray(30))
+                    .background_color(Color32::WHITE)
+                    .frame(true),
+            )
+        })
+        .inner;
+
+    if response.changed() {
+        if let Some(parsed) = text.trim().parse::<f64>().ok().filter(|v| v.is_finite()) {
+            *value = parsed.clamp(*range.start(), *range.end());
+        }
+    }
+
+    if response.has_focus() {
+        ui.data_mut(|d| d.insert_temp(id, text));
+    } else {
+        ui.data_mut(|d| d.remove::<String>(id));
+    }
+    response
+}
+
+/// Border styling shared by the text inputs.
+fn style_input(ui: &mut Ui) {
+    let widgets = &mut ui.visuals_mut().widgets;
+    widgets.inactive.bg_stroke = Stroke::new(1.0, Color32::from_gray(220));
+    widgets.hovered.bg_stroke = HOVER_STROKE;
+    widgets.active.bg_stroke = Stroke::new(1.0, PRIMARY_FILL);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Context, Event, Modifiers, PointerButton, Pos2, RawInput};
+
+    /// A point inside the first widget of a central panel.
+    const INSIDE: Pos2 = Pos2::new(16.0, 16.0);
+
+    /// Run one frame per entry of `frames`, feeding its events and returning
+    /// what `add` produced in each frame.
+    fn run_frames<R>(frames: Vec<Vec<Event>>, mut add: impl FnMut(&mut Ui) -> R) -> Vec<R> {
+        let ctx = Context::default();
+        frames
+            .into_iter()
+            .map(|events| {
+                let mut out = None;
+                let _ = ctx.run(
+                    RawInput {
+                        events,
+                        ..Default::default()
+                    },
+                    |ctx| {
+                        egui::CentralPanel::default().show(ctx, |ui| out = Some(add(ui)));
+                    },
+                );
+                out.expect("panel ran")
+            })
+            .collect()
+    }
+
+    fn button(pressed: bool) -> Event {
+        Event::PointerButton {
+            pos: INSIDE,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    fn enter() -> Event {
+        Event::Key {
+            key: egui::Key::Enter,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    /// Frames that lay out the UI, press inside the first widget, then release.
+    fn click() -> Vec<Vec<Event>> {
+        vec![
+            vec![],
+            vec![Event::PointerMoved(INSIDE), button(true)],
+            vec![button(false)],
+        ]
+    }
+
+    #[test]
+    fn test_icon_btn_reports_click() {
+        let clicks = run_frames(click(), |ui| icon_btn(ui, "+", "Add"));
+        assert_eq!(clicks, vec![false, false, true]);
+    }
+
+    #[test]
+    fn test_toggle_btn_flips_state() {
+        let mut state = false;
+        let changes = run_frames(click(), |ui| toggle_btn(ui, "Snap", &mut state));
+        assert_eq!(changes, vec![false, false, true]);
+        assert!(state);
+    }
+
+    #[test]
+    fn test_number_input_clamps_on_commit() {
+        let mut value = 5.0;
+        let mut frames = click();
+        frames.push(vec![Event::Text("999".into())]);
+        frames.push(vec![enter()]);
+        frames.push(vec![]);
+
+        let focus = run_frames(frames, |ui| {
+            number_input(ui, &mut value, 80.0, 0.0..=100.0).has_focus()
+        });
+        assert_eq!(focus.last(), Some(&false));
+        assert_eq!(value, 100.0);
+    }
+
+    #[test]
+    fn test_color_swatch_selects_preset() {
+        let mut color = Color32::WHITE;
+        let presets = [Color32::RED, Color32::GREEN, Color32::BLUE];
+        let changes = run_frames(click(), |ui| color_swatch(ui, &mut color, &presets));
+        assert_eq!(changes, vec![false, false, true]);
+        assert_eq!(color, Color32::RED);
+    }
+
+    #[test]
+    fn test_disabled_buttons_never_click() {
+        let clicks = run_frames(click(), |ui| primary_btn_enabled(ui, "Export", false));
+        assert_eq!(clicks, vec![false; 3]);
+        let clicks = run_frames(click(), |ui| icon_btn_enabled(ui, "+", "Add", false));
+        assert_eq!(clicks, vec![false; 3]);
+        let clicks = run_frames(click(), |ui| primary_btn_enabled(ui, "Export", true));
+        assert_eq!(clicks, vec![false, false, true]);
+    }
+
+    #[test]
+    fn test_multiline_input_keeps_newlines() {
+        let mut text = String::new();
+        let mut frames = click();
+        frames.push(vec![
+            Event::Text("first".into()),
+            enter(),
+            Event::Text("second".into()),
+        ]);
+        frames.push(vec![]);
+
+        let focus = run_frames(frames, |ui| {
+            input_text_multiline(ui, &mut text, 200.0, 4, "").has_focus()
+        });
+        assert_eq!(focus.last(), Some(&true));
+        assert_eq!(text, "first\nsecond");
+    }
+}
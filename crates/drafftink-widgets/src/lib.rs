@@ -22,7 +22,11 @@ pub use colors::{
     NoColorSwatch, SHADE_LABELS, SelectionStyle, TAILWIND_COLORS, TailwindColor, TailwindPalette,
     colors_match, hue_to_rgb, parse_css_color,
 };
-pub use common::{default_btn, input_text, primary_btn, secondary_btn};
+pub use common::{
+    BUTTON_HEIGHT, color_swatch, default_btn, default_btn_enabled, icon_btn, icon_btn_enabled,
+    input_text, input_text_multiline, number_input, primary_btn, primary_btn_enabled,
+    secondary_btn, secondary_btn_enabled, toggle_btn,
+};
 pub use layout::{section_label, separator, vertical_separator};
 pub use menu::{menu_item, menu_item_enabled, menu_separator, panel_frame, toolbar_frame};
 